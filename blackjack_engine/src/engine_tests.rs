@@ -4,11 +4,20 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::gizmos::{BlackjackGizmo, TransformGizmo, TransformGizmoMode};
 use crate::graph::serialization::SerializedBjkGraph;
-use crate::graph::{BjkGraph, BjkNodeId};
-use crate::graph_interpreter::run_graph;
+use crate::graph::{
+    BjkGraph, BjkNodeId, BjkSubgraph, BlackjackValue, NodeDefinition, SUBGRAPH_OP_NAME,
+};
+use crate::graph_interpreter::{
+    run_graph, run_graph_cached, Cancelled, ExternalParameter, ExternalParameterValues, GizmoState,
+    NodeOutputCache, ProgressSink,
+};
+use crate::lua_engine::lua_stdlib::StdLuaFileIo;
 use crate::lua_engine::{LuaRuntime, ProgramResult, RenderableThing};
 use crate::prelude::*;
+use crate::sync::RefCounted;
+use slotmap::SecondaryMap;
 
 /// Looks for the first node with no outgoing parameters and assumes it to be
 /// the graph's final node. Comment nodes are ignored because examples typically
@@ -41,10 +50,11 @@ struct Example {
 
 fn run_example(example: &Example, rt: &LuaRuntime) -> ProgramResult {
     let bjk_data = std::fs::read_to_string(example.path).unwrap();
-    let (rt_data, _, _) = SerializedBjkGraph::load_from_string(&bjk_data)
+    let (mut rt_data, _, _) = SerializedBjkGraph::load_from_string(&bjk_data)
         .unwrap()
         .into_runtime()
         .unwrap();
+    rt_data.fill_missing_inputs(&rt.node_definitions);
     run_graph(
         &rt.lua,
         &rt_data.graph,
@@ -52,13 +62,77 @@ fn run_example(example: &Example, rt: &LuaRuntime) -> ProgramResult {
         rt_data.external_parameters.unwrap(),
         &rt.node_definitions,
         None,
+        None,
     )
     .unwrap()
 }
 
+/// Adds a node running `op_name` to `graph`, with all the inputs and outputs
+/// from its node definition. Inputs are set to their default values.
+fn add_node(
+    rt: &LuaRuntime,
+    graph: &mut BjkGraph,
+    params: &mut ExternalParameterValues,
+    op_name: &str,
+) -> BjkNodeId {
+    let node_def = rt.node_definitions.node_def(op_name).unwrap();
+    let node_id = graph.add_node(op_name, node_def.returns.clone());
+    for input in &node_def.inputs {
+        graph
+            .add_input(node_id, &input.name, input.data_type, None)
+            .unwrap();
+        params.0.insert(
+            ExternalParameter::new(node_id, input.name.clone()),
+            input.default_value(),
+        );
+    }
+    for output in &node_def.outputs {
+        graph
+            .add_output(node_id, &output.name, output.data_type)
+            .unwrap();
+    }
+    node_id
+}
+
+/// Groups the `nodes` of `graph` into a subgraph, and adds a subgraph node
+/// running it to `outer`. The node's ports are the subgraph's inputs and
+/// outputs.
+fn add_group_node(
+    outer: &mut BjkGraph,
+    graph: &BjkGraph,
+    params: &ExternalParameterValues,
+    nodes: &[BjkNodeId],
+) -> BjkNodeId {
+    let (subgraph, _) = BjkSubgraph::from_nodes(graph, params, nodes).unwrap();
+    let group = outer.add_node(SUBGRAPH_OP_NAME, subgraph.return_value());
+    for port in &subgraph.inputs {
+        outer
+            .add_input(group, &port.name, port.data_type, None)
+            .unwrap();
+    }
+    for port in &subgraph.outputs {
+        outer.add_output(group, &port.name, port.data_type).unwrap();
+    }
+    outer.nodes[group].subgraph = Some(RefCounted::new(subgraph));
+    group
+}
+
+/// Runs the Lua `code`, which may add new nodes to the node library, and
+/// reloads the node definitions of `rt`.
+fn add_lua_nodes(rt: &LuaRuntime, code: &str) {
+    rt.lua.load(code).exec().unwrap();
+    let nodes = rt
+        .lua
+        .load("require('node_library').nodes")
+        .eval::<mlua::Table>()
+        .unwrap();
+    rt.node_definitions
+        .update(NodeDefinition::load_nodes_from_table(nodes).unwrap());
+}
+
 #[test]
 pub fn test_examples_folder() {
-    let lua_runtime = LuaRuntime::initialize_with_std("../blackjack_lua".into()).unwrap();
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
 
     let examples = &[
         Example {
@@ -93,3 +167,688 @@ pub fn test_examples_folder() {
         }
     }
 }
+
+#[test]
+pub fn test_output_cache() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let bjk_data = std::fs::read_to_string("../examples/tp_cutter.bjk").unwrap();
+    let (mut rt_data, _, _) = SerializedBjkGraph::load_from_string(&bjk_data)
+        .unwrap()
+        .into_runtime()
+        .unwrap();
+    rt_data.fill_missing_inputs(&lua_runtime.node_definitions);
+    let target = infer_target_node(&rt_data.graph);
+    let params = rt_data.external_parameters.unwrap();
+
+    let mut cache = NodeOutputCache::default();
+    let mut num_faces = vec![];
+    // The second run reuses the outputs of the first one. Running twice makes
+    // sure cached outputs are not consumed when producing the renderable.
+    for _ in 0..2 {
+        let result = run_graph_cached(
+            &lua_runtime.lua,
+            &rt_data.graph,
+            target,
+            params.clone(),
+            &lua_runtime.node_definitions,
+            None,
+            &mut cache,
+            None,
+        )
+        .unwrap();
+        if let Some(RenderableThing::HalfEdgeMesh(h)) = result.renderable {
+            num_faces.push(h.read_connectivity().num_faces());
+        } else {
+            panic!("Expected a mesh")
+        }
+    }
+    assert_eq!(num_faces, vec![170, 170]);
+    assert!(!cache.is_empty());
+}
+
+#[test]
+pub fn test_output_cache_with_gizmos() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let make_box = add_node(&lua_runtime, &mut graph, &mut params, "MakeBox");
+    let subdivide = add_node(&lua_runtime, &mut graph, &mut params, "Subdivide");
+    graph
+        .add_connection(make_box, "out_mesh", subdivide, "mesh")
+        .unwrap();
+
+    let mut cache = NodeOutputCache::default();
+    let mut run = |params: ExternalParameterValues, gizmo: Option<BlackjackGizmo>| {
+        let mut gizmos_state = SecondaryMap::new();
+        gizmos_state.insert(
+            make_box,
+            GizmoState {
+                gizmos_changed: gizmo.is_some(),
+                active_gizmos: gizmo.map(|gizmo| vec![gizmo]),
+            },
+        );
+        let result = run_graph_cached(
+            &lua_runtime.lua,
+            &graph,
+            subdivide,
+            params,
+            &lua_runtime.node_definitions,
+            Some(gizmos_state),
+            &mut cache,
+            None,
+        )
+        .unwrap();
+        match result.renderable {
+            Some(RenderableThing::HalfEdgeMesh(mesh)) => (
+                crate::mesh::halfedge::edit_ops::bounding_box(&mesh),
+                result.updated_values,
+            ),
+            _ => panic!("Expected a mesh"),
+        }
+    };
+
+    let ((min, max), params) = run(params, None);
+    assert!(((min + max) * 0.5).abs_diff_eq(Vec3::ZERO, 1e-5));
+
+    // Dragging the box's gizmo moves its origin. The subdivided mesh can't
+    // come from the cache, even though the origin is only updated once the
+    // box node runs.
+    let dragged = BlackjackGizmo::Transform(TransformGizmo {
+        translation: Vec3::new(5.0, 0.0, 0.0),
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+        pre_translation: Vec3::ZERO,
+        pre_rotation: Quat::IDENTITY,
+        pre_scale: Vec3::ONE,
+        translation_enabled: true,
+        rotation_enabled: false,
+        scale_enabled: false,
+        gizmo_mode: TransformGizmoMode::Translate,
+    });
+    let ((min, max), _) = run(params, Some(dragged));
+    assert!(((min + max) * 0.5).abs_diff_eq(Vec3::new(5.0, 0.0, 0.0), 1e-5));
+}
+
+#[test]
+pub fn test_progress_sink() {
+    struct CancelAfter {
+        max_nodes: usize,
+        started: std::cell::RefCell<Vec<String>>,
+    }
+    impl ProgressSink for CancelAfter {
+        fn node_started(&self, _node_id: BjkNodeId, op_name: &str) {
+            self.started.borrow_mut().push(op_name.to_owned());
+        }
+        fn is_cancelled(&self) -> bool {
+            self.started.borrow().len() >= self.max_nodes
+        }
+    }
+
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let bjk_data = std::fs::read_to_string("../examples/tp_cutter.bjk").unwrap();
+    let (mut rt_data, _, _) = SerializedBjkGraph::load_from_string(&bjk_data)
+        .unwrap()
+        .into_runtime()
+        .unwrap();
+    rt_data.fill_missing_inputs(&lua_runtime.node_definitions);
+    let target = infer_target_node(&rt_data.graph);
+    let params = rt_data.external_parameters.unwrap();
+
+    let run = |sink: &CancelAfter| {
+        run_graph(
+            &lua_runtime.lua,
+            &rt_data.graph,
+            target,
+            params.clone(),
+            &lua_runtime.node_definitions,
+            None,
+            Some(sink),
+        )
+    };
+
+    // Every node that runs is reported, the target one last.
+    let sink = CancelAfter {
+        max_nodes: usize::MAX,
+        started: Default::default(),
+    };
+    run(&sink).unwrap();
+    let started = sink.started.into_inner();
+    assert!(started.len() > 2);
+    assert_eq!(
+        started.last().unwrap(),
+        &rt_data.graph.nodes[target].op_name
+    );
+
+    let sink = CancelAfter {
+        max_nodes: 2,
+        started: Default::default(),
+    };
+    let err = run(&sink).err().unwrap();
+    assert!(err.is::<Cancelled>());
+    assert_eq!(sink.started.borrow().len(), 2);
+}
+
+#[test]
+pub fn test_run_jack() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let (mesh, _) = crate::run_jack(&lua_runtime, "../examples/box.bjk", &HashMap::new()).unwrap();
+    assert_eq!(mesh.read_connectivity().num_vertices(), 8);
+    assert!(lua_runtime.log_receiver.try_recv().is_err());
+
+    // Promote the parameters of the box, and override its size.
+    let bjk_data = std::fs::read_to_string("../examples/box.bjk").unwrap();
+    let promoted = bjk_data.replacen("promoted: None", "promoted: Some(\"origin\")", 1);
+    let promoted = promoted.replacen("promoted: None", "promoted: Some(\"size\")", 1);
+    let path = std::env::temp_dir().join(format!(
+        "blackjack_test_run_jack_{}.bjk",
+        std::process::id()
+    ));
+    std::fs::write(&path, promoted).unwrap();
+
+    let params = HashMap::from([("size".to_string(), BlackjackValue::Vector(Vec3::splat(2.0)))]);
+    let (mesh, _) = crate::run_jack(&lua_runtime, &path, &params).unwrap();
+    let (min, max) = edit_ops::bounding_box(&mesh);
+    assert_eq!(max - min, Vec3::splat(2.0));
+
+    let params = HashMap::from([("missing".to_string(), BlackjackValue::Scalar(1.0))]);
+    assert!(crate::run_jack(&lua_runtime, &path, &params).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn test_run_jack_graph_log() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    add_lua_nodes(
+        &lua_runtime,
+        r#"
+        local P = require("params")
+        require("node_library"):addNodes({
+            LoggedBox = {
+                label = "Logged box",
+                op = function(inputs)
+                    print("making a box")
+                    if inputs.fail then
+                        error("no box today")
+                    end
+                    return { out_mesh = Primitives.cube(vector(0, 0, 0), vector(1, 1, 1)) }
+                end,
+                inputs = { P.bool("fail", false) },
+                outputs = { P.mesh("out_mesh") },
+                returns = "out_mesh",
+            },
+        })
+    "#,
+    );
+
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let node = add_node(&lua_runtime, &mut graph, &mut params, "LoggedBox");
+    graph.default_node = Some(node);
+
+    let (_, log_lines) = crate::run_jack_graph(&lua_runtime, &graph, params.clone()).unwrap();
+    assert!(log_lines.len() == 1 && log_lines[0].contains("making a box"));
+
+    // On failure, the logged lines are attached to the error.
+    params.0.insert(
+        ExternalParameter::new(node, "fail".into()),
+        BlackjackValue::Bool(true),
+    );
+    let err = crate::run_jack_graph(&lua_runtime, &graph, params).unwrap_err();
+    let err = format!("{err:?}");
+    assert!(err.contains("making a box") && err.contains("no box today"));
+    assert!(lua_runtime.log_receiver.try_recv().is_err());
+}
+
+#[test]
+pub fn test_deprecated_point_grid_alias() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let count_vertices = |code: &str| {
+        let mesh = lua_runtime
+            .lua
+            .load(code)
+            .eval::<mlua::AnyUserData>()
+            .unwrap();
+        let mesh = mesh.borrow::<HalfEdgeMesh>().unwrap();
+        let conn = mesh.read_connectivity();
+        conn.num_vertices()
+    };
+    assert_eq!(
+        count_vertices("return Primitives.grid(2, 3, 1.0, 1.0)"),
+        count_vertices("return Primitives.point_grid(2, 3, 1.0, 1.0)")
+    );
+    assert_eq!(
+        count_vertices(
+            "return Primitives.grid(vector(0, 0, 0), vector(0, 1, 0), vector(1, 1, 0), 2, 2)"
+        ),
+        9
+    );
+}
+
+#[test]
+pub fn test_optional_op_arguments() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let run = |code: &str| lua_runtime.lua.load(code).eval::<mlua::AnyUserData>();
+
+    let mesh = run(r#"
+        local mesh = Primitives.cube(vector(0, 0, 0), vector(1, 1, 1))
+        Ops.set_smooth_normals(mesh)
+        return mesh
+    "#)
+    .unwrap();
+    assert!(mesh
+        .borrow::<HalfEdgeMesh>()
+        .unwrap()
+        .read_vertex_normals()
+        .is_some());
+    assert!(run(r#"
+        local mesh = Primitives.cube(vector(0, 0, 0), vector(1, 1, 1))
+        Ops.set_smooth_normals(mesh, "Nonsense")
+        return mesh
+    "#)
+    .is_err());
+
+    let mesh = run(r#"
+        local mesh = Primitives.cube(vector(0, 0, 0), vector(1, 1, 1))
+        Ops.set_full_range_uvs(mesh)
+        Ops.project_uvs(mesh, "Planar", vector(0, 1, 0), vector(1, 1, 1), "uv_lightmap")
+        return mesh
+    "#)
+    .unwrap();
+    let mesh = mesh.borrow::<HalfEdgeMesh>().unwrap();
+    assert!(mesh.read_uv_channel(UvChannel::Main).is_some());
+    assert!(mesh.read_uv_channel(UvChannel::Lightmap).is_some());
+}
+
+#[test]
+pub fn test_iter_channel_while_borrowed() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let lua = &lua_runtime.lua;
+    let mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+    lua.globals().set("mesh", mesh).unwrap();
+    let next: mlua::Function = lua
+        .load(r#"return mesh:iter_channel(Types.VERTEX_ID, Types.VEC3, "position")"#)
+        .eval()
+        .unwrap();
+
+    // Advancing the iterator while the channel is being written to is an
+    // error, not a panic.
+    let mesh: mlua::AnyUserData = lua.globals().get("mesh").unwrap();
+    {
+        let mesh = mesh.borrow::<HalfEdgeMesh>().unwrap();
+        let _positions = mesh.write_positions();
+        assert!(next.call::<_, (mlua::Value, mlua::Value)>(()).is_err());
+    }
+    let (_, position) = next.call::<_, (mlua::Value, mlua::Value)>(()).unwrap();
+    assert!(!matches!(position, mlua::Value::Nil));
+}
+
+#[test]
+pub fn test_measure_empty_selections() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let make_box = add_node(&lua_runtime, &mut graph, &mut params, "MakeBox");
+    let measure = add_node(&lua_runtime, &mut graph, &mut params, "Measure");
+    graph
+        .add_connection(make_box, "out_mesh", measure, "mesh")
+        .unwrap();
+
+    // With gizmos enabled, the default empty selections show no measure
+    // instead of failing.
+    let mut gizmos_state = SecondaryMap::new();
+    gizmos_state.insert(
+        measure,
+        GizmoState {
+            active_gizmos: None,
+            gizmos_changed: false,
+        },
+    );
+    let result = run_graph(
+        &lua_runtime.lua,
+        &graph,
+        measure,
+        params,
+        &lua_runtime.node_definitions,
+        Some(gizmos_state),
+        None,
+    )
+    .unwrap();
+    assert!(matches!(
+        result.updated_gizmos.unwrap()[measure][..],
+        [BlackjackGizmo::None]
+    ));
+}
+
+#[test]
+pub fn test_op_timeout() {
+    let lua_runtime = LuaRuntime::initialize_custom(
+        StdLuaFileIo {
+            search_folders: vec!["../blackjack_lua".into()],
+        },
+        Some(std::time::Duration::from_millis(100)),
+    )
+    .unwrap();
+    add_lua_nodes(
+        &lua_runtime,
+        r#"
+        local P = require("params")
+        require("node_library"):addNodes({
+            InfiniteLoop = {
+                label = "Infinite loop",
+                op = function(inputs)
+                    while true do end
+                end,
+                inputs = {},
+                outputs = { P.mesh("out_mesh") },
+                returns = "out_mesh",
+            },
+        })
+    "#,
+    );
+
+    let run = |op_name: &str| {
+        let mut graph = BjkGraph::new();
+        let mut params = ExternalParameterValues::default();
+        let node = add_node(&lua_runtime, &mut graph, &mut params, op_name);
+        run_graph(
+            &lua_runtime.lua,
+            &graph,
+            node,
+            params,
+            &lua_runtime.node_definitions,
+            None,
+            None,
+        )
+    };
+
+    // The infinite loop is aborted, and the runtime can still run other ops.
+    let err = run("InfiniteLoop").err().unwrap();
+    assert!(format!("{err:?}").contains("timed out"));
+    assert!(run("MakeBox").is_ok());
+}
+
+#[test]
+pub fn test_expression_inputs() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    add_lua_nodes(
+        &lua_runtime,
+        r#"
+        local P = require("params")
+        require("node_library"):addNodes({
+            ScaledBox = {
+                label = "Scaled box",
+                op = function(inputs)
+                    local size = vector(inputs.width, inputs.height, 1)
+                    return { out_mesh = Primitives.cube(vector(0, 0, 0), size) }
+                end,
+                inputs = {
+                    P.scalar("width", { default = 1.0 }),
+                    P.scalar("height", { default = 1.0 }),
+                },
+                outputs = { P.mesh("out_mesh") },
+                returns = "out_mesh",
+            },
+        })
+    "#,
+    );
+
+    // Runs the node with a width of 2, and the `height` given by `expr`.
+    let run = |expr: &str| -> Result<Vec3> {
+        let mut graph = BjkGraph::new();
+        let mut params = ExternalParameterValues::default();
+        let node = add_node(&lua_runtime, &mut graph, &mut params, "ScaledBox");
+        params.0.insert(
+            ExternalParameter::new(node, "width".into()),
+            BlackjackValue::Scalar(2.0),
+        );
+        params.0.insert(
+            ExternalParameter::new(node, "height".into()),
+            BlackjackValue::Expression(expr.into()),
+        );
+        let result = run_graph(
+            &lua_runtime.lua,
+            &graph,
+            node,
+            params,
+            &lua_runtime.node_definitions,
+            None,
+            None,
+        )?;
+        match result.renderable {
+            Some(RenderableThing::HalfEdgeMesh(mesh)) => {
+                let (min, max) = edit_ops::bounding_box(&mesh);
+                Ok(max - min)
+            }
+            _ => bail!("Expected a mesh"),
+        }
+    };
+
+    // Expressions can reference the other inputs of the node, and use the
+    // math library.
+    assert_eq!(run("width * 3").unwrap(), Vec3::new(2.0, 6.0, 1.0));
+    assert_eq!(run("max(width, 4)").unwrap(), Vec3::new(2.0, 4.0, 1.0));
+
+    let err = format!("{:?}", run("width *").err().unwrap());
+    assert!(err.contains("Could not evaluate the expression 'width *' for parameter 'height'"));
+
+    // The sandbox has no access to the io and os libraries.
+    assert!(run("os.time()").is_err());
+    assert!(run("#io.open('Cargo.toml'):read('*a')").is_err());
+    // Nor to the string library, through the methods of string values.
+    assert!(run("#('x'):rep(3)").is_err());
+    assert!(run("#(width .. ''):upper()").is_err());
+    // Which is restored for the node code afterwards.
+    let lua = &lua_runtime.lua;
+    assert_eq!(lua.load("('x'):rep(3)").eval::<String>().unwrap(), "xxx");
+}
+
+#[test]
+pub fn test_lazy_inputs() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    add_lua_nodes(
+        &lua_runtime,
+        r#"
+        local P = require("params")
+        local branch = function(name)
+            return {
+                label = name,
+                op = function(inputs)
+                    BRANCHES_RUN[name] = true
+                    return { out_mesh = Primitives.cube(vector(0, 0, 0), vector(1, 1, 1)) }
+                end,
+                inputs = {},
+                outputs = { P.mesh("out_mesh") },
+                returns = "out_mesh",
+            }
+        end
+        require("node_library"):addNodes({
+            TrueBranch = branch("TrueBranch"),
+            FalseBranch = branch("FalseBranch"),
+        })
+    "#,
+    );
+
+    // Runs a Switch node with the given `condition`, and returns the
+    // branches that ran.
+    let run = |condition: bool| {
+        let mut graph = BjkGraph::new();
+        let mut params = ExternalParameterValues::default();
+        let true_branch = add_node(&lua_runtime, &mut graph, &mut params, "TrueBranch");
+        let false_branch = add_node(&lua_runtime, &mut graph, &mut params, "FalseBranch");
+        let switch = add_node(&lua_runtime, &mut graph, &mut params, "Switch");
+        graph
+            .add_connection(true_branch, "out_mesh", switch, "if_true")
+            .unwrap();
+        graph
+            .add_connection(false_branch, "out_mesh", switch, "if_false")
+            .unwrap();
+        params.0.insert(
+            ExternalParameter::new(switch, "condition".into()),
+            BlackjackValue::Bool(condition),
+        );
+
+        let branches_run = lua_runtime.lua.create_table().unwrap();
+        lua_runtime
+            .lua
+            .globals()
+            .set("BRANCHES_RUN", branches_run.clone())
+            .unwrap();
+        let result = run_graph(
+            &lua_runtime.lua,
+            &graph,
+            switch,
+            params,
+            &lua_runtime.node_definitions,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            result.renderable,
+            Some(RenderableThing::HalfEdgeMesh(_))
+        ));
+        branches_run
+            .pairs::<String, bool>()
+            .map(|pair| pair.unwrap().0)
+            .collect_vec()
+    };
+
+    assert_eq!(run(true), vec!["TrueBranch".to_string()]);
+    assert_eq!(run(false), vec!["FalseBranch".to_string()]);
+}
+
+#[test]
+pub fn test_subgraph_matches_ungrouped() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let run = |graph: &BjkGraph, params: &ExternalParameterValues, target: BjkNodeId| {
+        let result = run_graph(
+            &lua_runtime.lua,
+            graph,
+            target,
+            params.clone(),
+            &lua_runtime.node_definitions,
+            None,
+            None,
+        )
+        .unwrap();
+        match result.renderable {
+            Some(RenderableThing::HalfEdgeMesh(mesh)) => {
+                let positions = mesh.read_positions();
+                let conn = mesh.read_connectivity();
+                conn.iter_vertices()
+                    .map(|(v, _)| positions[v].to_array())
+                    .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+                    .collect_vec()
+            }
+            _ => panic!("Expected a mesh"),
+        }
+    };
+
+    // Box -> Subdivide -> Transform
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let make_box = add_node(&lua_runtime, &mut graph, &mut params, "MakeBox");
+    let subdivide = add_node(&lua_runtime, &mut graph, &mut params, "Subdivide");
+    let transform = add_node(&lua_runtime, &mut graph, &mut params, "Transform");
+    graph
+        .add_connection(make_box, "out_mesh", subdivide, "mesh")
+        .unwrap();
+    graph
+        .add_connection(subdivide, "out_mesh", transform, "mesh")
+        .unwrap();
+    params.0.insert(
+        ExternalParameter::new(transform, "translate".into()),
+        BlackjackValue::Vector(Vec3::new(1.0, 2.0, 3.0)),
+    );
+    graph.default_node = Some(transform);
+    let ungrouped = run(&graph, &params, transform);
+
+    // Box -> [Subdivide -> Transform]
+    let mut outer = BjkGraph::new();
+    let mut outer_params = ExternalParameterValues::default();
+    let outer_box = add_node(&lua_runtime, &mut outer, &mut outer_params, "MakeBox");
+    let group = add_group_node(&mut outer, &graph, &params, &[subdivide, transform]);
+    outer
+        .add_connection(outer_box, "out_mesh", group, "mesh")
+        .unwrap();
+    let grouped = run(&outer, &outer_params, group);
+
+    assert_eq!(ungrouped.len(), 26);
+    assert_eq!(grouped, ungrouped);
+}
+
+#[test]
+pub fn test_progress_sink_subgraph() {
+    struct Record(std::cell::RefCell<Vec<BjkNodeId>>);
+    impl ProgressSink for Record {
+        fn node_started(&self, node_id: BjkNodeId, _op_name: &str) {
+            self.0.borrow_mut().push(node_id);
+        }
+    }
+
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let make_box = add_node(&lua_runtime, &mut graph, &mut params, "MakeBox");
+    let subdivide = add_node(&lua_runtime, &mut graph, &mut params, "Subdivide");
+    graph
+        .add_connection(make_box, "out_mesh", subdivide, "mesh")
+        .unwrap();
+
+    // Box -> [Subdivide]
+    let mut outer = BjkGraph::new();
+    let mut outer_params = ExternalParameterValues::default();
+    let outer_box = add_node(&lua_runtime, &mut outer, &mut outer_params, "MakeBox");
+    let group = add_group_node(&mut outer, &graph, &params, &[subdivide]);
+    outer
+        .add_connection(outer_box, "out_mesh", group, "mesh")
+        .unwrap();
+
+    // Only the nodes of the outer graph are reported.
+    let sink = Record(Default::default());
+    run_graph(
+        &lua_runtime.lua,
+        &outer,
+        group,
+        outer_params,
+        &lua_runtime.node_definitions,
+        None,
+        Some(&sink),
+    )
+    .unwrap();
+    assert_eq!(sink.0.into_inner(), vec![outer_box, group]);
+}
+
+#[test]
+pub fn test_max_subgraph_depth() {
+    let lua_runtime = LuaRuntime::initialize_with_std(vec!["../blackjack_lua".into()]).unwrap();
+    let mut graph = BjkGraph::new();
+    let mut params = ExternalParameterValues::default();
+    let mut node = add_node(&lua_runtime, &mut graph, &mut params, "MakeBox");
+
+    // Wraps the box in a subgraph node, many times.
+    let run = |graph: &BjkGraph, node: BjkNodeId| {
+        run_graph(
+            &lua_runtime.lua,
+            graph,
+            node,
+            Default::default(),
+            &lua_runtime.node_definitions,
+            None,
+            None,
+        )
+    };
+    for depth in 1..=100 {
+        graph.default_node = Some(node);
+        let mut outer = BjkGraph::new();
+        node = add_group_node(&mut outer, &graph, &params, &[node]);
+        graph = outer;
+        params = Default::default();
+        if depth == 10 {
+            assert!(run(&graph, node).is_ok());
+        }
+    }
+
+    let err = run(&graph, node).err().unwrap();
+    assert!(format!("{err:?}").contains("Subgraphs can't be nested more than"));
+}
@@ -193,9 +193,67 @@ mod tr_gizmo {
     }
 }
 
+/// A gizmo that shows measurements between points in the viewport. It
+/// reports the distance between points `a` and `b` and, when a third point `c`
+/// is given, the angle formed at `b` by the segments towards `a` and `c`.
+///
+/// This gizmo is read-only: it doesn't let the user modify any parameters.
+#[derive(Debug, Copy, Clone)]
+pub struct MeasureGizmo {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Option<Vec3>,
+}
+
+#[blackjack_macros::blackjack_lua_module]
+mod measure_gizmo {
+    use crate::lua_engine::lua_stdlib::LVec3;
+
+    use super::*;
+
+    /// Constructs a new measure gizmo showing the distance between `a` and `b`.
+    #[lua(under = "MeasureGizmo")]
+    fn distance(a: LVec3, b: LVec3) -> MeasureGizmo {
+        MeasureGizmo {
+            a: a.0,
+            b: b.0,
+            c: None,
+        }
+    }
+
+    /// Constructs a new measure gizmo showing the distances from `b` to `a`
+    /// and `c`, and the angle between both segments at `b`.
+    #[lua(under = "MeasureGizmo")]
+    fn angle(a: LVec3, b: LVec3, c: LVec3) -> MeasureGizmo {
+        MeasureGizmo {
+            a: a.0,
+            b: b.0,
+            c: Some(c.0),
+        }
+    }
+
+    #[lua_impl]
+    impl MeasureGizmo {
+        /// Returns the distance between the first two points of this gizmo.
+        #[lua]
+        pub fn length(&self) -> f32 {
+            self.a.distance(self.b)
+        }
+
+        /// Returns the angle at the second point of this gizmo, in degrees, or
+        /// nil when the gizmo only measures a distance.
+        #[lua]
+        pub fn angle_degrees(&self) -> Option<f32> {
+            self.c
+                .map(|c| (self.a - self.b).angle_between(c - self.b).to_degrees())
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum BlackjackGizmo {
     Transform(TransformGizmo),
+    Measure(MeasureGizmo),
     // This special value is sometimes returned by the UI to indicate a gizmo
     // wasn't initialized. No gizmo should be rendered for this value.
     None,
@@ -212,9 +270,16 @@ impl<'lua> FromLua<'lua> for BlackjackGizmo {
             };
         }
 
-        if let mlua::Value::UserData(x) = lua_value {
-            // NOTE: Add more cases here:
-            gizmo_type!(x, TransformGizmo, Transform);
+        match lua_value {
+            mlua::Value::UserData(x) => {
+                // NOTE: Add more cases here:
+                gizmo_type!(x, TransformGizmo, Transform);
+                gizmo_type!(x, MeasureGizmo, Measure);
+            }
+            // Same as in ToLua, nil represents the special "None" gizmo. Nodes
+            // can return it when they have nothing to show.
+            mlua::Value::Nil => return Ok(BlackjackGizmo::None),
+            _ => {}
         }
         mlua::Result::Err(mlua::Error::FromLuaConversionError {
             from: "Value",
@@ -230,6 +295,7 @@ impl<'lua> ToLua<'lua> for BlackjackGizmo {
     fn to_lua(self, lua: &'lua Lua) -> mlua::Result<mlua::Value<'lua>> {
         match self {
             BlackjackGizmo::Transform(t) => t.to_lua(lua),
+            BlackjackGizmo::Measure(m) => m.to_lua(lua),
             // The special gizmo value "None" is encoded as nil. Lua functions
             // know that the nil value represents an uninitialized gizmo.
             BlackjackGizmo::None => Ok(mlua::Value::Nil),
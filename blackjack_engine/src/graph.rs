@@ -4,16 +4,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell};
 use std::collections::BTreeMap;
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::graph_interpreter::{ExternalParameter, ExternalParameterValues, MAX_LOOP_ITERATIONS};
 use crate::prelude::*;
+use crate::sync::RefCounted;
 use crate::{lua_engine::lua_stdlib::LVec3, mesh::halfedge::selection::SelectionExpression};
 use anyhow::{anyhow, Result};
 use mlua::{FromLua, Table, ToLua};
-use slotmap::SlotMap;
+use slotmap::{SecondaryMap, SlotMap};
 
 /// The core `bjk` file format
 pub mod serialization;
@@ -22,7 +24,7 @@ pub struct LuaExpression(pub String);
 
 /// A node has inputs (dependencies) that need to be met. A dependency can be
 /// met in three different ways.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DependencyKind {
     /// Taking the value of an external parameter, from the inputs to the graph
     /// function itself.
@@ -43,6 +45,7 @@ pub enum DataType {
     Mesh,
     String,
     HeightMap,
+    Bool,
 }
 
 impl DataType {
@@ -50,7 +53,11 @@ impl DataType {
     pub fn can_be_enabled(&self) -> bool {
         match self {
             DataType::Mesh | DataType::HeightMap => true,
-            DataType::Vector | DataType::Scalar | DataType::Selection | DataType::String => false,
+            DataType::Vector
+            | DataType::Scalar
+            | DataType::Selection
+            | DataType::String
+            | DataType::Bool => false,
         }
     }
 
@@ -58,21 +65,30 @@ impl DataType {
     pub fn is_valid_value(&self, value: &BlackjackValue) -> bool {
         match self {
             DataType::Vector => matches!(value, BlackjackValue::Vector(_)),
-            DataType::Scalar => matches!(value, BlackjackValue::Scalar(_)),
+            DataType::Scalar => matches!(
+                value,
+                BlackjackValue::Scalar(_) | BlackjackValue::Expression(_)
+            ),
             DataType::Selection => matches!(value, BlackjackValue::Selection(_, _)),
             DataType::String => matches!(value, BlackjackValue::String(_)),
             DataType::Mesh => matches!(value, BlackjackValue::None),
             DataType::HeightMap => matches!(value, BlackjackValue::None),
+            DataType::Bool => matches!(value, BlackjackValue::Bool(_)),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BlackjackValue {
     Vector(glam::Vec3),
     Scalar(f32),
     String(String),
     Selection(String, Option<SelectionExpression>),
+    Bool(bool),
+    /// A scalar computed from an expression, like `radius * 4`. Expressions
+    /// can refer to the other inputs of the same node, and are evaluated by
+    /// the graph interpreter right before the node runs.
+    Expression(String),
     None,
 }
 
@@ -83,6 +99,10 @@ impl<'lua> ToLua<'lua> for BlackjackValue {
             BlackjackValue::Scalar(s) => Ok(s.cast_to_lua(lua)),
             BlackjackValue::String(s) => s.to_lua(lua),
             BlackjackValue::Selection(_, sel) => sel.to_lua(lua),
+            BlackjackValue::Bool(b) => b.to_lua(lua),
+            BlackjackValue::Expression(e) => Err(mlua::Error::RuntimeError(format!(
+                "The expression '{e}' should be evaluated before passing it to Lua"
+            ))),
             BlackjackValue::None => Ok(mlua::Value::Nil),
         }
     }
@@ -93,6 +113,7 @@ impl<'lua> FromLua<'lua> for BlackjackValue {
         let type_name = lua_value.type_name();
         match lua_value {
             mlua::Value::Nil => return Ok(BlackjackValue::None),
+            mlua::Value::Boolean(b) => return Ok(BlackjackValue::Bool(b)),
             mlua::Value::Integer(i) => return Ok(BlackjackValue::Scalar(i as f32)),
             mlua::Value::Number(n) => return Ok(BlackjackValue::Scalar(n as f32)),
             mlua::Value::Vector(x, y, z) => {
@@ -117,7 +138,7 @@ impl<'lua> FromLua<'lua> for BlackjackValue {
 
 /// An input parameter in the graph. Inputs represent data dependencies that
 /// need to be met before executing a node.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InputParameter {
     pub name: String,
     pub data_type: DataType,
@@ -126,14 +147,14 @@ pub struct InputParameter {
 
 /// An output parameter. Outputs are pieces of data produced by a node, which
 /// can be used to feed into another nodes as inputs.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Output {
     pub name: String,
     pub data_type: DataType,
 }
 
 /// A node in the blackjack graph
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BjkNode {
     pub op_name: String,
     /// When this node is the target of a graph, this stores the name of the
@@ -141,6 +162,275 @@ pub struct BjkNode {
     pub return_value: Option<String>,
     pub inputs: Vec<InputParameter>,
     pub outputs: Vec<Output>,
+    /// Set for subgraph nodes, which run the embedded graph instead of an op
+    /// from the node library. Their `op_name` is `SUBGRAPH_OP_NAME`.
+    pub subgraph: Option<RefCounted<BjkSubgraph>>,
+}
+
+impl BjkNode {
+    /// Returns the op name and parameter name of the node input that
+    /// `param_name` ends up feeding. For regular nodes, this is the input
+    /// itself. For subgraph nodes, this is the input of the inner node the
+    /// parameter is exposed from. Use this to find the input's definition.
+    pub fn resolve_input<'a>(&'a self, param_name: &'a str) -> Option<(&'a str, &'a str)> {
+        match &self.subgraph {
+            Some(subgraph) => subgraph.resolve_input(param_name),
+            None => Some((self.op_name.as_str(), param_name)),
+        }
+    }
+
+    /// Returns the definition of the input `param_name`, as found in the node
+    /// library. Subgraph inputs use the definition of the input they are
+    /// exposed from, and the iteration count of loops has a fixed definition.
+    pub fn input_def(
+        &self,
+        param_name: &str,
+        node_definitions: &NodeDefinitions,
+    ) -> Option<InputDefinition> {
+        match &self.subgraph {
+            Some(subgraph) => subgraph.input_def(param_name, node_definitions),
+            None => node_definitions
+                .node_def(&self.op_name)?
+                .inputs
+                .iter()
+                .find(|input| input.name == param_name)
+                .cloned(),
+        }
+    }
+}
+
+/// The op name used by subgraph nodes. There is no node definition for it.
+pub const SUBGRAPH_OP_NAME: &str = "Subgraph";
+
+/// Links a parameter of a subgraph node with a parameter of one of the nodes
+/// inside the subgraph.
+#[derive(Debug, Clone)]
+pub struct SubgraphPort {
+    /// The name of the parameter in the subgraph node.
+    pub name: String,
+    pub data_type: DataType,
+    /// The node inside the subgraph.
+    pub node: BjkNodeId,
+    /// The name of the parameter in the inner node.
+    pub param_name: String,
+}
+
+/// The name of the input with the number of iterations of a loop node.
+pub const LOOP_ITERATIONS_INPUT: &str = "iterations";
+
+/// Returns the definition for the iteration count input of loop nodes.
+pub fn loop_iterations_def() -> InputDefinition {
+    InputDefinition {
+        name: LOOP_ITERATIONS_INPUT.into(),
+        data_type: DataType::Scalar,
+        config: InputValueConfig::Scalar {
+            default: 1.0,
+            min: Some(0.0),
+            max: Some(MAX_LOOP_ITERATIONS as f32),
+            soft_min: None,
+            soft_max: Some(10.0),
+            num_decimals: Some(0),
+        },
+    }
+}
+
+/// Turns a subgraph node into a loop. Loops run their subgraph as many times
+/// as their iteration count input says. The mesh produced by each iteration at
+/// the `output` port is fed to the `input` port of the next one.
+#[derive(Debug, Clone)]
+pub struct SubgraphFeedback {
+    /// The name of the mesh input port receiving the previous iteration's mesh.
+    pub input: String,
+    /// The name of the mesh output port fed back to the next iteration.
+    pub output: String,
+}
+
+/// A graph embedded in a subgraph node. Subgraphs let users collapse a group
+/// of nodes into a single node. The inputs of the subgraph node are fed to
+/// some inputs of the inner nodes, and some of the outputs of the inner nodes
+/// are exposed as outputs of the subgraph node.
+#[derive(Debug, Clone, Default)]
+pub struct BjkSubgraph {
+    pub graph: BjkGraph,
+    /// The values for the parameters of the inner nodes that are not exposed
+    /// as inputs of the subgraph node.
+    pub external_parameters: ExternalParameterValues,
+    pub inputs: Vec<SubgraphPort>,
+    pub outputs: Vec<SubgraphPort>,
+    /// Set when the subgraph node is a loop.
+    pub feedback: Option<SubgraphFeedback>,
+}
+
+impl BjkSubgraph {
+    /// Builds a subgraph out of the given `nodes` of `graph`. The nodes are
+    /// copied into the subgraph. Inputs connected to nodes outside the group
+    /// become inputs of the subgraph, and outputs used by nodes outside the
+    /// group become its outputs. The return value of the graph's default node
+    /// is also exposed when that node is part of the group.
+    ///
+    /// Returns the subgraph, and the mapping from the ids of the grouped nodes
+    /// to the ids of their copies inside the subgraph.
+    pub fn from_nodes(
+        graph: &BjkGraph,
+        external_parameters: &ExternalParameterValues,
+        nodes: &[BjkNodeId],
+    ) -> Result<(Self, SecondaryMap<BjkNodeId, BjkNodeId>)> {
+        if nodes.is_empty() {
+            bail!("Cannot group an empty selection");
+        }
+
+        let mut subgraph = BjkSubgraph::default();
+        let mut id_map = SecondaryMap::new();
+        for &node_id in nodes {
+            let node = graph
+                .nodes
+                .get(node_id)
+                .ok_or_else(|| anyhow!("Node {node_id:?} does not exist"))?;
+            id_map.insert(node_id, subgraph.graph.nodes.insert(node.clone()));
+        }
+
+        for &node_id in nodes {
+            let inner_id = id_map[node_id];
+            let inner_node = &mut subgraph.graph.nodes[inner_id];
+            for input in &mut inner_node.inputs {
+                match &mut input.kind {
+                    DependencyKind::Connection { node, .. } => {
+                        if let Some(inner_src) = id_map.get(*node) {
+                            *node = *inner_src;
+                        } else {
+                            input.kind = DependencyKind::External { promoted: None };
+                            let name = unique_port_name(&subgraph.inputs, &input.name);
+                            subgraph.inputs.push(SubgraphPort {
+                                name,
+                                data_type: input.data_type,
+                                node: inner_id,
+                                param_name: input.name.clone(),
+                            });
+                        }
+                    }
+                    DependencyKind::External { promoted } => {
+                        // Promoted parameters can't be reached from outside
+                        // the subgraph, so they're no longer promoted.
+                        *promoted = None;
+                        let outer_param = ExternalParameter::new(node_id, input.name.clone());
+                        if let Some(value) = external_parameters.0.get(&outer_param) {
+                            subgraph.external_parameters.0.insert(
+                                ExternalParameter::new(inner_id, input.name.clone()),
+                                value.clone(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        for &node_id in nodes {
+            let node = &graph.nodes[node_id];
+            for output in &node.outputs {
+                let used_outside = graph.nodes.iter().any(|(other_id, other)| {
+                    !id_map.contains_key(other_id)
+                        && other.inputs.iter().any(|input| {
+                            matches!(
+                                &input.kind,
+                                DependencyKind::Connection { node, param_name }
+                                    if *node == node_id && *param_name == output.name
+                            )
+                        })
+                });
+                let is_returned = graph.default_node == Some(node_id)
+                    && node.return_value.as_deref() == Some(output.name.as_str());
+                if used_outside || is_returned {
+                    let name = unique_port_name(&subgraph.outputs, &output.name);
+                    subgraph.outputs.push(SubgraphPort {
+                        name,
+                        data_type: output.data_type,
+                        node: id_map[node_id],
+                        param_name: output.name.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok((subgraph, id_map))
+    }
+
+    /// The output of the subgraph node that should be displayed when the node
+    /// is active. This is the first output that can be rendered, if any.
+    pub fn return_value(&self) -> Option<String> {
+        self.outputs
+            .iter()
+            .find(|port| port.data_type.can_be_enabled())
+            .map(|port| port.name.clone())
+    }
+
+    /// Same as `BjkNode::resolve_input`, for the input of a subgraph node
+    /// named `name`. Nested subgraphs are followed until a regular node is
+    /// found.
+    pub fn resolve_input(&self, name: &str) -> Option<(&str, &str)> {
+        let port = self.inputs.iter().find(|port| port.name == name)?;
+        self.graph
+            .nodes
+            .get(port.node)?
+            .resolve_input(&port.param_name)
+    }
+
+    /// Same as `BjkNode::input_def`, for the input of a subgraph node named
+    /// `name`.
+    pub fn input_def(
+        &self,
+        name: &str,
+        node_definitions: &NodeDefinitions,
+    ) -> Option<InputDefinition> {
+        if self.feedback.is_some() && name == LOOP_ITERATIONS_INPUT {
+            return Some(loop_iterations_def());
+        }
+        let port = self.inputs.iter().find(|port| port.name == name)?;
+        self.graph
+            .nodes
+            .get(port.node)?
+            .input_def(&port.param_name, node_definitions)
+    }
+
+    /// Turns this subgraph into a loop. The first mesh output is fed back to
+    /// the first mesh input on each iteration. An input port using the name
+    /// of the iteration count input is renamed.
+    pub fn make_loop(&mut self) -> Result<()> {
+        if let Some(idx) = self
+            .inputs
+            .iter()
+            .position(|port| port.name == LOOP_ITERATIONS_INPUT)
+        {
+            self.inputs[idx].name = unique_port_name(&self.inputs, LOOP_ITERATIONS_INPUT);
+        }
+        let input = self
+            .inputs
+            .iter()
+            .find(|port| port.data_type == DataType::Mesh)
+            .ok_or_else(|| anyhow!("A loop needs a mesh coming from outside the group"))?;
+        let output = self
+            .outputs
+            .iter()
+            .find(|port| port.data_type == DataType::Mesh)
+            .ok_or_else(|| anyhow!("A loop needs a mesh output used outside the group"))?;
+        self.feedback = Some(SubgraphFeedback {
+            input: input.name.clone(),
+            output: output.name.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Returns `name`, or `name` followed by a number when there's already a port
+/// with that name.
+fn unique_port_name(ports: &[SubgraphPort], name: &str) -> String {
+    let is_taken = |candidate: &str| ports.iter().any(|port| port.name == candidate);
+    if !is_taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|i| format!("{name}_{i}"))
+        .find(|candidate| !is_taken(candidate))
+        .expect("Infinite iterator")
 }
 
 slotmap::new_key_type! { pub struct BjkNodeId; }
@@ -154,11 +444,14 @@ impl BjkNodeId {
 /// blackjack procedural asset, or 'Jack'. Graphs describe a computation to be
 /// performed by applying transformations (nodes) over data (input/output
 /// parameters).
-#[derive(Default)]
+#[derive(Debug, Clone, Default)]
 pub struct BjkGraph {
     pub nodes: SlotMap<BjkNodeId, BjkNode>,
     /// When the graph is run, this is the node that will be executed by default.
     pub default_node: Option<BjkNodeId>,
+    /// Named selections, stored as their selection expression. Any selection
+    /// parameter in the graph can refer to them as `set("name")`.
+    pub selection_sets: BTreeMap<String, String>,
 }
 
 /// Represents a fragment of a `BjkGraph`. Snippets can be taken out of a graph
@@ -223,6 +516,9 @@ pub enum InputValueConfig {
         default_text: String,
     },
     LuaString {},
+    Bool {
+        default: bool,
+    },
     None,
 }
 
@@ -248,6 +544,7 @@ impl DataType {
             DataType::String => BlackjackValue::String("".into()),
             DataType::Mesh => BlackjackValue::None,
             DataType::HeightMap => BlackjackValue::None,
+            DataType::Bool => BlackjackValue::Bool(false),
         }
     }
 }
@@ -297,6 +594,7 @@ impl InputDefinition {
             }
             (DataType::String, InputValueConfig::LuaString {}) => default_string(),
             (DataType::HeightMap, InputValueConfig::None) => BlackjackValue::None,
+            (DataType::Bool, InputValueConfig::Bool { default }) => BlackjackValue::Bool(*default),
 
             // Fallback: When config is not valud, return some valid value
             (data_type, _) => data_type.default_value(),
@@ -332,6 +630,11 @@ pub struct NodeDefinition {
     pub executable: bool,
     /// This node has an available interactive gizmo.
     pub has_gizmo: bool,
+    /// The inputs of this node that are only computed when the node needs
+    /// them. Before the node runs, its `select_lazy_inputs` function receives
+    /// the other inputs, and returns the names of the lazy inputs to compute.
+    /// Lazy inputs that are not selected are nil in the node's `op`.
+    pub lazy_inputs: Vec<String>,
 }
 
 #[derive(Default)]
@@ -346,19 +649,27 @@ pub struct NodeDefinitionsInner(BTreeMap<String, NodeDefinition>);
 #[derive(Default)]
 pub struct NodeDefinitions {
     pub inner: Rc<RefCell<NodeDefinitionsInner>>,
+    /// Incremented every time the definitions are updated. Used to detect
+    /// when data computed with older definitions becomes stale.
+    generation: Rc<Cell<u64>>,
 }
 
 impl NodeDefinitions {
     pub fn new(inner: NodeDefinitionsInner) -> Self {
         Self {
             inner: Rc::new(RefCell::new(inner)),
+            generation: Default::default(),
         }
     }
     pub fn share(&self) -> Self {
         Self {
             inner: Rc::clone(&self.inner),
+            generation: Rc::clone(&self.generation),
         }
     }
+    pub fn generation(&self) -> u64 {
+        self.generation.get()
+    }
     pub fn node_names(&self) -> Vec<String> {
         self.inner.borrow().0.keys().cloned().collect()
     }
@@ -372,6 +683,7 @@ impl NodeDefinitions {
     }
     pub fn update(&self, new_data: NodeDefinitionsInner) {
         *self.inner.borrow_mut() = new_data;
+        self.generation.set(self.generation.get() + 1);
     }
 }
 
@@ -388,6 +700,7 @@ fn data_type_from_str(s: &str) -> Result<DataType> {
         "file" => Ok(DataType::String),
         "string" => Ok(DataType::String),
         "lua_string" => Ok(DataType::String),
+        "bool" => Ok(DataType::Bool),
         _ => Err(anyhow!("Invalid datatype in node definition {:?}", s)),
     }
 }
@@ -414,6 +727,9 @@ impl InputDefinition {
             },
             DataType::Mesh => InputValueConfig::None,
             DataType::HeightMap => InputValueConfig::None,
+            DataType::Bool => InputValueConfig::Bool {
+                default: table.get::<_, Option<bool>>("default")?.unwrap_or(false),
+            },
             DataType::String if type_str == "enum" => InputValueConfig::Enum {
                 values: table
                     .get::<_, Table>("values")?
@@ -482,6 +798,9 @@ impl NodeDefinition {
             returns: table.get::<_, Option<String>>("returns")?,
             executable: table.get::<_, Option<bool>>("executable")?.unwrap_or(false),
             has_gizmo: table.get::<_, mlua::Value>("gizmos")? != mlua::Value::Nil,
+            lazy_inputs: table
+                .get::<_, Option<Vec<String>>>("lazy_inputs")?
+                .unwrap_or_default(),
         })
     }
 
@@ -505,6 +824,7 @@ impl BjkGraph {
         Self {
             nodes: Default::default(),
             default_node: None,
+            selection_sets: Default::default(),
         }
     }
     /// Adds a new empty node to the graph
@@ -514,6 +834,7 @@ impl BjkGraph {
             return_value,
             inputs: vec![],
             outputs: vec![],
+            subgraph: None,
         })
     }
 
@@ -5,8 +5,8 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader, BufWriter, Write},
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufWriter, Write},
     path::Path,
 };
 
@@ -19,14 +19,15 @@ use slotmap::{SecondaryMap, SlotMap};
 use crate::{
     graph_interpreter::{ExternalParameter, ExternalParameterValues},
     prelude::selection::SelectionExpression,
+    sync::RefCounted,
 };
 
 use super::{
-    BjkGraph, BjkNode, BjkNodeId, BjkSnippet, BlackjackValue, DataType, DependencyKind,
-    InputParameter, Output,
+    BjkGraph, BjkNode, BjkNodeId, BjkSnippet, BjkSubgraph, BlackjackValue, DataType,
+    DependencyKind, InputParameter, NodeDefinitions, Output, SubgraphFeedback, SubgraphPort,
 };
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct SerializationVersion {
     pub major: u32,
     pub minor: u32,
@@ -42,6 +43,16 @@ impl SerializationVersion {
         }
     }
 
+    /// The version assumed for files saved before the version header was
+    /// introduced. Loading them runs every migration.
+    pub fn oldest() -> Self {
+        Self {
+            major: 0,
+            minor: 0,
+            patch: 0,
+        }
+    }
+
     pub fn to_writer(&self, mut w: impl Write) -> Result<()> {
         // Serde made it very inconvenient to deserialize the version field
         // before attempting to deserialize the whole RON file. A pragmatic
@@ -63,7 +74,7 @@ impl SerializationVersion {
         let mut header_line = String::new();
         r.read_line(&mut header_line)?;
 
-        let header = header_line.trim_end_matches('\n').split(' ').collect_vec();
+        let header = header_line.trim_end().split(' ').collect_vec();
         match header.as_slice() {
             &[_, header_str, major_str, minor_str, patch_str] => {
                 if header_str != "BLACKJACK_VERSION_HEADER" {
@@ -88,6 +99,23 @@ impl SerializationVersion {
     }
 }
 
+/// A node parameter that was renamed in the node library. Files saved with a
+/// version older than `since` still refer to the parameter as `old_name`.
+pub struct ParamRename {
+    pub since: SerializationVersion,
+    pub op_name: &'static str,
+    pub old_name: &'static str,
+    pub new_name: &'static str,
+}
+
+/// The list of parameter renames, in chronological order. When renaming a
+/// parameter of an existing node, bump `SerializationVersion::latest` and add
+/// an entry here, so that files saved with older versions keep loading.
+///
+/// Parameters that are added to a node don't need an entry here. Those are
+/// filled with their default value by `RuntimeData::fill_missing_inputs`.
+pub const PARAM_RENAMES: &[ParamRename] = &[];
+
 #[derive(Serialize, Deserialize)]
 pub enum SerializedDependencyKind {
     External { promoted: Option<String> },
@@ -113,6 +141,35 @@ pub struct SerializedBjkNode {
     pub return_value: Option<String>,
     pub inputs: Vec<SerializedInput>,
     pub outputs: Vec<SerializedOutput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subgraph: Option<Box<SerializedBjkSubgraph>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SerializedSubgraphPort {
+    pub name: String,
+    pub data_type: String,
+    pub node_idx: usize,
+    pub param_name: String,
+}
+
+/// The graph embedded in a subgraph node. Node indices are local to the
+/// subgraph's `nodes`.
+#[derive(Serialize, Deserialize)]
+pub struct SerializedBjkSubgraph {
+    pub nodes: Vec<SerializedBjkNode>,
+    pub external_parameters: SerializedExternalParameters,
+    pub inputs: Vec<SerializedSubgraphPort>,
+    pub outputs: Vec<SerializedSubgraphPort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<SerializedSubgraphFeedback>,
+}
+
+/// The ports connected between iterations of a loop node.
+#[derive(Serialize, Deserialize)]
+pub struct SerializedSubgraphFeedback {
+    pub input: String,
+    pub output: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -137,6 +194,8 @@ pub enum SerializedBlackjackValue {
     Scalar(f32),
     String(String),
     Selection(String),
+    Bool(bool),
+    Expression(String),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -150,6 +209,8 @@ pub struct SerializedBjkGraph {
     pub default_node: Option<usize>,
     pub ui_data: Option<SerializedUiData>,
     pub external_parameters: Option<SerializedExternalParameters>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selection_sets: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -234,6 +295,7 @@ impl SerializedBjkGraph {
         let BjkGraph {
             nodes,
             default_node,
+            selection_sets,
         } = graph;
 
         let mut serialized_nodes = vec![];
@@ -251,6 +313,7 @@ impl SerializedBjkGraph {
                     None
                 },
                 ui_data: None,
+                selection_sets,
             },
             mappings,
         ))
@@ -355,6 +418,8 @@ impl SerializedBlackjackValue {
             BlackjackValue::Scalar(s) => Some(Self::Scalar(s)),
             BlackjackValue::String(s) => Some(Self::String(s)),
             BlackjackValue::Selection(s, _) => Some(Self::Selection(s)),
+            BlackjackValue::Bool(b) => Some(Self::Bool(b)),
+            BlackjackValue::Expression(e) => Some(Self::Expression(e)),
             BlackjackValue::None => None,
         }
     }
@@ -367,6 +432,7 @@ impl SerializedBjkNode {
             return_value,
             inputs,
             outputs,
+            subgraph,
         } = node;
 
         let inputs = inputs
@@ -383,6 +449,47 @@ impl SerializedBjkNode {
             return_value: return_value.clone(),
             inputs,
             outputs,
+            subgraph: subgraph
+                .as_ref()
+                .map(|subgraph| SerializedBjkSubgraph::from_runtime(subgraph).map(Box::new))
+                .transpose()?,
+        })
+    }
+}
+
+impl SerializedBjkSubgraph {
+    fn from_runtime(subgraph: &BjkSubgraph) -> Result<Self> {
+        let mappings = IdMappings::from_nodes(&subgraph.graph.nodes);
+        let nodes = subgraph
+            .graph
+            .nodes
+            .values()
+            .map(|node| SerializedBjkNode::from_runtime_data(node, &mappings))
+            .collect::<Result<Vec<_>>>()?;
+        let port = |port: &SubgraphPort| -> Result<SerializedSubgraphPort> {
+            Ok(SerializedSubgraphPort {
+                name: port.name.clone(),
+                data_type: serialize_data_type(port.data_type),
+                node_idx: mappings.get_idx(port.node)?,
+                param_name: port.param_name.clone(),
+            })
+        };
+
+        Ok(Self {
+            nodes,
+            external_parameters: SerializedExternalParameters::from_runtime(
+                subgraph.external_parameters.clone(),
+                &mappings,
+            )?,
+            inputs: subgraph.inputs.iter().map(port).collect::<Result<_>>()?,
+            outputs: subgraph.outputs.iter().map(port).collect::<Result<_>>()?,
+            feedback: subgraph
+                .feedback
+                .as_ref()
+                .map(|feedback| SerializedSubgraphFeedback {
+                    input: feedback.input.clone(),
+                    output: feedback.output.clone(),
+                }),
         })
     }
 }
@@ -395,6 +502,7 @@ fn serialize_data_type(data_type: DataType) -> String {
         super::DataType::Mesh => "BJK_MESH",
         super::DataType::String => "BJK_STRING",
         super::DataType::HeightMap => "BJK_HEIGHTMAP",
+        super::DataType::Bool => "BJK_BOOL",
     }
     .to_owned()
 }
@@ -456,6 +564,8 @@ impl IdMappings {
                 return_value: node.return_value.clone(),
                 inputs: vec![],
                 outputs: vec![],
+                // Filled by `fill_runtime`
+                subgraph: None,
             });
 
             mappings.idx_to_id.push(node_id);
@@ -467,12 +577,50 @@ impl IdMappings {
 
 impl SerializedBjkGraph {
     pub fn load_from_file(path: impl AsRef<Path>) -> Result<SerializedBjkGraph> {
-        let reader = BufReader::new(std::fs::File::open(path)?);
-        Ok(ron::de::from_reader(reader)?)
+        Self::load_from_string(&std::fs::read_to_string(path)?)
     }
 
+    /// Loads a graph from the contents of a `.bjk` file. Files saved with an
+    /// older version are migrated to the latest version of the format. Files
+    /// without a version header are plain RON from before the header existed,
+    /// and are treated as the oldest version.
     pub fn load_from_string(s: &str) -> Result<SerializedBjkGraph> {
-        Ok(ron::de::from_str(s)?)
+        Self::load_from_string_with_renames(s, PARAM_RENAMES)
+    }
+
+    fn load_from_string_with_renames(
+        s: &str,
+        renames: &[ParamRename],
+    ) -> Result<SerializedBjkGraph> {
+        let has_header = s
+            .lines()
+            .next()
+            .map_or(false, |line| line.contains("BLACKJACK_VERSION_HEADER"));
+        let version = if has_header {
+            SerializationVersion::from_reader(s.as_bytes())?
+        } else {
+            SerializationVersion::oldest()
+        };
+        if version > SerializationVersion::latest() {
+            bail!(
+                "This file was saved with a newer version of Blackjack \
+                 (format {}.{}.{}) and cannot be loaded.",
+                version.major,
+                version.minor,
+                version.patch
+            );
+        }
+        let mut graph: SerializedBjkGraph = ron::de::from_str(s)?;
+        graph.migrate(version, renames);
+        Ok(graph)
+    }
+
+    /// Upgrades a graph stored with the given `version` by applying all the
+    /// `renames` that happened after it.
+    fn migrate(&mut self, version: SerializationVersion, renames: &[ParamRename]) {
+        for rename in renames.iter().filter(|r| version < r.since) {
+            apply_rename(&mut self.nodes, self.external_parameters.as_mut(), rename);
+        }
     }
 
     pub fn into_runtime(self) -> Result<(RuntimeData, Option<SerializedUiData>, IdMappings)> {
@@ -489,6 +637,7 @@ impl SerializedBjkGraph {
                 graph: BjkGraph {
                     nodes: rt_nodes,
                     default_node: self.default_node.and_then(|x| mappings.get_id(x).ok()),
+                    selection_sets: self.selection_sets,
                 },
                 external_parameters: if let Some(e) = self.external_parameters {
                     Some(e.into_runtime(&mappings)?)
@@ -502,6 +651,135 @@ impl SerializedBjkGraph {
     }
 }
 
+/// Applies a parameter rename to the given nodes, and to the nodes of any
+/// subgraphs inside them.
+fn apply_rename(
+    nodes: &mut [SerializedBjkNode],
+    external_parameters: Option<&mut SerializedExternalParameters>,
+    rename: &ParamRename,
+) {
+    let renamed_nodes = nodes
+        .iter()
+        .map(|node| node.op_name == rename.op_name)
+        .collect_vec();
+
+    for node in nodes.iter_mut() {
+        if node.op_name == rename.op_name {
+            for input in &mut node.inputs {
+                if input.name == rename.old_name {
+                    input.name = rename.new_name.into();
+                }
+            }
+            for output in &mut node.outputs {
+                if output.name == rename.old_name {
+                    output.name = rename.new_name.into();
+                }
+            }
+            if node.return_value.as_deref() == Some(rename.old_name) {
+                node.return_value = Some(rename.new_name.into());
+            }
+        }
+
+        // Connections refer to the output names of other nodes.
+        for input in &mut node.inputs {
+            if let SerializedDependencyKind::Conection {
+                node_idx,
+                param_name,
+            } = &mut input.kind
+            {
+                if renamed_nodes.get(*node_idx).copied().unwrap_or(false)
+                    && *param_name == rename.old_name
+                {
+                    *param_name = rename.new_name.into();
+                }
+            }
+        }
+
+        if let Some(subgraph) = &mut node.subgraph {
+            apply_rename(
+                &mut subgraph.nodes,
+                Some(&mut subgraph.external_parameters),
+                rename,
+            );
+        }
+    }
+
+    if let Some(external_parameters) = external_parameters {
+        external_parameters.param_values = std::mem::take(&mut external_parameters.param_values)
+            .into_iter()
+            .map(|(mut loc, value)| {
+                if renamed_nodes.get(loc.node_idx).copied().unwrap_or(false)
+                    && loc.param_name == rename.old_name
+                {
+                    loc.param_name = rename.new_name.into();
+                }
+                (loc, value)
+            })
+            .collect();
+    }
+}
+
+impl RuntimeData {
+    /// Adds any inputs that are present in the node definitions, but missing
+    /// from the loaded nodes. This happens when a node gains new parameters
+    /// after a file was saved. The new inputs get their default value.
+    pub fn fill_missing_inputs(&mut self, node_definitions: &NodeDefinitions) {
+        fill_missing_graph_inputs(
+            &mut self.graph,
+            self.external_parameters.as_mut(),
+            node_definitions,
+        );
+    }
+}
+
+fn fill_missing_graph_inputs(
+    graph: &mut BjkGraph,
+    mut external_parameters: Option<&mut ExternalParameterValues>,
+    node_definitions: &NodeDefinitions,
+) {
+    for (node_id, node) in &mut graph.nodes {
+        // The inputs of subgraph nodes come from the nodes inside them.
+        if let Some(subgraph) = &mut node.subgraph {
+            let subgraph = RefCounted::make_mut(subgraph);
+            fill_missing_graph_inputs(
+                &mut subgraph.graph,
+                Some(&mut subgraph.external_parameters),
+                node_definitions,
+            );
+            continue;
+        }
+
+        let node_def = match node_definitions.node_def(&node.op_name) {
+            Some(node_def) => node_def,
+            // Unknown nodes are reported elsewhere.
+            None => continue,
+        };
+
+        for (i, input_def) in node_def.inputs.iter().enumerate() {
+            if node.inputs.iter().any(|input| input.name == input_def.name) {
+                continue;
+            }
+            node.inputs.insert(
+                i.min(node.inputs.len()),
+                InputParameter {
+                    name: input_def.name.clone(),
+                    data_type: input_def.data_type,
+                    kind: DependencyKind::External { promoted: None },
+                },
+            );
+            if let Some(external_parameters) = external_parameters.as_deref_mut() {
+                external_parameters.0.insert(
+                    ExternalParameter {
+                        node_id,
+                        param_name: input_def.name.clone(),
+                    },
+                    input_def.default_value(),
+                );
+            }
+        }
+    }
+}
+
 impl SerializedBjkNode {
     pub fn fill_runtime(self, rt_node: &mut BjkNode, mappings: &IdMappings) -> Result<()> {
         for input in self.inputs {
@@ -537,10 +815,47 @@ impl SerializedBjkNode {
                 println!("[WARNING] Unkown data type: {}", &output.data_type)
             }
         }
+
+        if let Some(subgraph) = self.subgraph {
+            rt_node.subgraph = Some(RefCounted::new(subgraph.into_runtime()?));
+        }
         Ok(())
     }
 }
 
+impl SerializedBjkSubgraph {
+    fn into_runtime(self) -> Result<BjkSubgraph> {
+        let (mappings, mut rt_nodes) = IdMappings::from_serialized_graph(&self.nodes)?;
+        for (node, node_id) in self.nodes.into_iter().zip(&mappings.idx_to_id) {
+            node.fill_runtime(&mut rt_nodes[*node_id], &mappings)?;
+        }
+        let port = |port: SerializedSubgraphPort| -> Result<SubgraphPort> {
+            Ok(SubgraphPort {
+                data_type: deserialize_data_type(&port.data_type)
+                    .ok_or_else(|| anyhow!("Unknown data type: {}", &port.data_type))?,
+                name: port.name,
+                node: mappings.get_id(port.node_idx)?,
+                param_name: port.param_name,
+            })
+        };
+
+        Ok(BjkSubgraph {
+            external_parameters: self.external_parameters.into_runtime(&mappings)?,
+            inputs: self.inputs.into_iter().map(port).collect::<Result<_>>()?,
+            outputs: self.outputs.into_iter().map(port).collect::<Result<_>>()?,
+            feedback: self.feedback.map(|feedback| SubgraphFeedback {
+                input: feedback.input,
+                output: feedback.output,
+            }),
+            graph: BjkGraph {
+                nodes: rt_nodes,
+                default_node: None,
+                selection_sets: Default::default(),
+            },
+        })
+    }
+}
+
 impl SerializedBjkSnippet {
     pub fn load_from_string(s: &str) -> Result<SerializedBjkSnippet> {
         Ok(ron::de::from_str(s)?)
@@ -578,6 +893,7 @@ fn deserialize_data_type(data_type_str: &str) -> Option<DataType> {
         "BJK_MESH" => Some(super::DataType::Mesh),
         "BJK_STRING" => Some(super::DataType::String),
         "BJK_HEIGHTMAP" => Some(super::DataType::HeightMap),
+        "BJK_BOOL" => Some(super::DataType::Bool),
         _ => None,
     }
     .to_owned()
@@ -602,6 +918,10 @@ impl SerializedExternalParameters {
                                 let expr = SelectionExpression::parse(&x).ok();
                                 BlackjackValue::Selection(x, expr)
                             }
+                            SerializedBlackjackValue::Bool(x) => BlackjackValue::Bool(x),
+                            SerializedBlackjackValue::Expression(x) => {
+                                BlackjackValue::Expression(x)
+                            }
                         },
                     ))
                 })
@@ -615,6 +935,9 @@ mod tests {
     use std::{fs::File, io::BufReader};
 
     use super::*;
+    use crate::graph::{
+        InputDefinition, InputValueConfig, NodeDefinition, NodeDefinitionsInner, SUBGRAPH_OP_NAME,
+    };
 
     /// Test reading the serialization version header, plus some data from a
     /// file, and confirms the information can be read back without loss.
@@ -645,4 +968,451 @@ mod tests {
         assert_eq!(version, new_version);
         assert_eq!(data, new_data);
     }
+
+    fn input(name: &str, kind: SerializedDependencyKind) -> SerializedInput {
+        SerializedInput {
+            name: name.into(),
+            data_type: "BJK_SCALAR".into(),
+            kind,
+        }
+    }
+
+    /// Tests that renamed parameters are upgraded when loading a file stored
+    /// with an older version, including connections and external parameters.
+    #[test]
+    pub fn test_migrate_renamed_params() {
+        let mut graph = SerializedBjkGraph {
+            nodes: vec![
+                SerializedBjkNode {
+                    op_name: "MakeBox".into(),
+                    return_value: Some("out_mesh".into()),
+                    inputs: vec![input(
+                        "size",
+                        SerializedDependencyKind::External { promoted: None },
+                    )],
+                    outputs: vec![SerializedOutput {
+                        name: "out_mesh".into(),
+                        data_type: "BJK_MESH".into(),
+                    }],
+                    subgraph: None,
+                },
+                SerializedBjkNode {
+                    op_name: "Other".into(),
+                    return_value: None,
+                    inputs: vec![input(
+                        "out_mesh",
+                        SerializedDependencyKind::Conection {
+                            node_idx: 0,
+                            param_name: "out_mesh".into(),
+                        },
+                    )],
+                    outputs: vec![],
+                    subgraph: None,
+                },
+            ],
+            default_node: None,
+            ui_data: None,
+            selection_sets: Default::default(),
+            external_parameters: Some(SerializedExternalParameters {
+                param_values: [(
+                    SerializedParamLocation {
+                        node_idx: 0,
+                        param_name: "size".into(),
+                    },
+                    SerializedBlackjackValue::Scalar(1.0),
+                )]
+                .into_iter()
+                .collect(),
+            }),
+        };
+
+        let since = SerializationVersion {
+            major: 0,
+            minor: 2,
+            patch: 0,
+        };
+        let renames = [
+            ParamRename {
+                since,
+                op_name: "MakeBox",
+                old_name: "size",
+                new_name: "extents",
+            },
+            ParamRename {
+                since,
+                op_name: "MakeBox",
+                old_name: "out_mesh",
+                new_name: "mesh",
+            },
+        ];
+
+        // Files that already have the new names are left untouched.
+        graph.migrate(since, &renames);
+        assert_eq!(graph.nodes[0].inputs[0].name, "size");
+
+        graph.migrate(SerializationVersion::latest(), &renames);
+        assert_eq!(graph.nodes[0].inputs[0].name, "extents");
+        assert_eq!(graph.nodes[0].outputs[0].name, "mesh");
+        assert_eq!(graph.nodes[0].return_value.as_deref(), Some("mesh"));
+        // Only the connection's source parameter refers to the renamed node.
+        assert_eq!(graph.nodes[1].inputs[0].name, "out_mesh");
+        assert!(matches!(
+            &graph.nodes[1].inputs[0].kind,
+            SerializedDependencyKind::Conection { param_name, .. } if param_name == "mesh"
+        ));
+        assert!(graph
+            .external_parameters
+            .unwrap()
+            .param_values
+            .contains_key(&SerializedParamLocation {
+                node_idx: 0,
+                param_name: "extents".into(),
+            }));
+    }
+
+    /// Tests that files from before the version header existed still load,
+    /// running every migration.
+    #[test]
+    pub fn test_load_headerless() {
+        let with_header = std::fs::read_to_string("../examples/box.bjk").unwrap();
+        let (header, headerless) = with_header.split_once('\n').unwrap();
+        assert!(header.contains("BLACKJACK_VERSION_HEADER"));
+
+        let renames = [ParamRename {
+            since: SerializationVersion::latest(),
+            op_name: "MakeBox",
+            old_name: "size",
+            new_name: "extents",
+        }];
+        let input_names = |graph: &SerializedBjkGraph| {
+            graph.nodes[0]
+                .inputs
+                .iter()
+                .map(|input| input.name.clone())
+                .collect_vec()
+        };
+
+        let graph =
+            SerializedBjkGraph::load_from_string_with_renames(&with_header, &renames).unwrap();
+        assert_eq!(input_names(&graph), ["origin", "size"]);
+
+        let graph =
+            SerializedBjkGraph::load_from_string_with_renames(headerless, &renames).unwrap();
+        assert_eq!(input_names(&graph), ["origin", "extents"]);
+        SerializedBjkGraph::load_from_string(headerless)
+            .unwrap()
+            .into_runtime()
+            .unwrap();
+    }
+
+    /// Tests that inputs added to a node definition after a file was saved
+    /// are filled with their default value when loading.
+    #[test]
+    pub fn test_fill_missing_inputs() {
+        let graph = SerializedBjkGraph {
+            nodes: vec![SerializedBjkNode {
+                op_name: "MakeCircle".into(),
+                return_value: None,
+                inputs: vec![input(
+                    "radius",
+                    SerializedDependencyKind::External { promoted: None },
+                )],
+                outputs: vec![],
+                subgraph: None,
+            }],
+            default_node: None,
+            ui_data: None,
+            selection_sets: Default::default(),
+            external_parameters: Some(SerializedExternalParameters {
+                param_values: HashMap::new(),
+            }),
+        };
+
+        let scalar = |name: &str, default: f32| InputDefinition {
+            name: name.into(),
+            data_type: DataType::Scalar,
+            config: InputValueConfig::Scalar {
+                default,
+                min: None,
+                max: None,
+                soft_min: None,
+                soft_max: None,
+                num_decimals: None,
+            },
+        };
+        let node_definitions = NodeDefinitions::new(NodeDefinitionsInner(
+            [(
+                "MakeCircle".to_string(),
+                NodeDefinition {
+                    op_name: "MakeCircle".into(),
+                    label: "Circle".into(),
+                    inputs: vec![scalar("radius", 1.0), scalar("num_vertices", 8.0)],
+                    outputs: vec![],
+                    returns: None,
+                    executable: false,
+                    has_gizmo: false,
+                    lazy_inputs: vec![],
+                },
+            )]
+            .into_iter()
+            .collect(),
+        ));
+
+        let (mut runtime, _, mappings) = graph.into_runtime().unwrap();
+        runtime.fill_missing_inputs(&node_definitions);
+
+        let node_id = mappings.get_id(0).unwrap();
+        let node = &runtime.graph.nodes[node_id];
+        assert_eq!(
+            node.inputs.iter().map(|i| i.name.as_str()).collect_vec(),
+            ["radius", "num_vertices"]
+        );
+        let value = &runtime.external_parameters.unwrap().0[&ExternalParameter {
+            node_id,
+            param_name: "num_vertices".into(),
+        }];
+        assert!(matches!(value, BlackjackValue::Scalar(x) if *x == 8.0));
+    }
+
+    /// Tests that copying a subset of the nodes keeps the connections between
+    /// them, but turns connections to nodes outside the selection into
+    /// external parameters, and that pasting the result creates fresh ids.
+    #[test]
+    pub fn test_snippet_projection() {
+        let mut graph = BjkGraph::new();
+        let add = |graph: &mut BjkGraph, op_name: &str| {
+            let node = graph.add_node(op_name, None);
+            graph
+                .add_input(node, "in_mesh", DataType::Mesh, None)
+                .unwrap();
+            graph.add_output(node, "out_mesh", DataType::Mesh).unwrap();
+            node
+        };
+        let a = add(&mut graph, "A");
+        let b = add(&mut graph, "B");
+        let c = add(&mut graph, "C");
+        graph.add_connection(a, "out_mesh", b, "in_mesh").unwrap();
+        graph.add_connection(b, "out_mesh", c, "in_mesh").unwrap();
+
+        let mut params = ExternalParameterValues::default();
+        params.0.insert(
+            ExternalParameter::new(a, "in_mesh".into()),
+            BlackjackValue::Scalar(1.0),
+        );
+
+        let (mut snippet, mappings) =
+            SerializedBjkSnippet::from_runtime(graph, params, &[b, c]).unwrap();
+        assert_eq!(snippet.nodes.len(), 2);
+        let b_idx = mappings.get_idx(b).unwrap();
+        let c_idx = mappings.get_idx(c).unwrap();
+        assert!(matches!(
+            snippet.nodes[b_idx].inputs[0].kind,
+            SerializedDependencyKind::External { promoted: None }
+        ));
+        assert!(matches!(
+            &snippet.nodes[c_idx].inputs[0].kind,
+            SerializedDependencyKind::Conection { node_idx, .. } if *node_idx == b_idx
+        ));
+        // The parameters of nodes that were not copied are not stored.
+        assert!(snippet
+            .external_parameters
+            .as_ref()
+            .unwrap()
+            .param_values
+            .is_empty());
+
+        snippet.set_node_relative_positions(vec![glam::Vec2::ZERO, glam::Vec2::X]);
+        let contents = snippet.into_string().unwrap();
+        let (rt_data, positions, new_mappings) = SerializedBjkSnippet::load_from_string(&contents)
+            .unwrap()
+            .into_runtime()
+            .unwrap();
+        assert_eq!(positions.unwrap().len(), 2);
+        let new_b = new_mappings.get_id(b_idx).unwrap();
+        let new_c = new_mappings.get_id(c_idx).unwrap();
+        assert!(matches!(
+            &rt_data.snippet.nodes[new_c].inputs[0].kind,
+            DependencyKind::Connection { node, .. } if *node == new_b
+        ));
+    }
+
+    /// Tests that selection sets survive a save and load, and that graphs
+    /// without sets are stored as before.
+    #[test]
+    pub fn test_selection_sets_roundtrip() {
+        let roundtrip = |graph: BjkGraph| {
+            let (serialized, _) = SerializedBjkGraph::from_runtime(RuntimeData {
+                graph,
+                external_parameters: None,
+            })
+            .unwrap();
+            let mut w = Vec::new();
+            SerializationVersion::latest().to_writer(&mut w).unwrap();
+            ron::ser::to_writer(&mut w, &serialized).unwrap();
+            let contents = String::from_utf8(w).unwrap();
+            let (runtime, _, _) = SerializedBjkGraph::load_from_string(&contents)
+                .unwrap()
+                .into_runtime()
+                .unwrap();
+            (contents, runtime.graph)
+        };
+
+        let (contents, loaded) = roundtrip(BjkGraph::new());
+        assert!(!contents.contains("selection_sets"));
+        assert!(loaded.selection_sets.is_empty());
+
+        let mut graph = BjkGraph::new();
+        graph
+            .selection_sets
+            .insert("my_loop".into(), "0..4 or @rim".into());
+        let (_, loaded) = roundtrip(graph);
+        assert_eq!(
+            loaded.selection_sets.get("my_loop").map(|s| s.as_str()),
+            Some("0..4 or @rim")
+        );
+    }
+
+    /// Tests that grouping nodes into a subgraph exposes the connections that
+    /// cross the group boundary, and that subgraphs survive a save and load.
+    #[test]
+    pub fn test_subgraph_roundtrip() {
+        let mut graph = BjkGraph::new();
+        let add = |graph: &mut BjkGraph, op_name: &str| {
+            let node = graph.add_node(op_name, Some("out_mesh".into()));
+            graph
+                .add_input(node, "in_mesh", DataType::Mesh, None)
+                .unwrap();
+            graph
+                .add_input(node, "amount", DataType::Scalar, None)
+                .unwrap();
+            graph.add_output(node, "out_mesh", DataType::Mesh).unwrap();
+            node
+        };
+        let a = add(&mut graph, "A");
+        let b = add(&mut graph, "B");
+        let c = add(&mut graph, "C");
+        graph.add_connection(a, "out_mesh", b, "in_mesh").unwrap();
+        graph.add_connection(b, "out_mesh", c, "in_mesh").unwrap();
+        graph.default_node = Some(c);
+
+        let mut params = ExternalParameterValues::default();
+        params.0.insert(
+            ExternalParameter::new(b, "amount".into()),
+            BlackjackValue::Scalar(2.0),
+        );
+
+        let (subgraph, id_map) = BjkSubgraph::from_nodes(&graph, &params, &[b, c]).unwrap();
+        assert_eq!(subgraph.graph.nodes.len(), 2);
+        assert_eq!(
+            subgraph
+                .inputs
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect_vec(),
+            ["in_mesh"]
+        );
+        assert_eq!(subgraph.inputs[0].node, id_map[b]);
+        // The output of the default node is exposed, since it's displayed.
+        assert_eq!(
+            subgraph
+                .outputs
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect_vec(),
+            ["out_mesh"]
+        );
+        assert_eq!(subgraph.outputs[0].node, id_map[c]);
+        assert_eq!(subgraph.return_value().as_deref(), Some("out_mesh"));
+        assert!(matches!(
+            subgraph.external_parameters.0[&ExternalParameter::new(id_map[b], "amount".into())],
+            BlackjackValue::Scalar(x) if x == 2.0
+        ));
+
+        let mut outer = BjkGraph::new();
+        let group = outer.add_node(SUBGRAPH_OP_NAME, subgraph.return_value());
+        outer
+            .add_input(group, "in_mesh", DataType::Mesh, None)
+            .unwrap();
+        outer.add_output(group, "out_mesh", DataType::Mesh).unwrap();
+        outer.nodes[group].subgraph = Some(RefCounted::new(subgraph));
+
+        let (serialized, _) = SerializedBjkGraph::from_runtime(RuntimeData {
+            graph: outer,
+            external_parameters: None,
+        })
+        .unwrap();
+        let (runtime, _, mappings) = serialized.into_runtime().unwrap();
+        let node = &runtime.graph.nodes[mappings.get_id(0).unwrap()];
+        let subgraph = node.subgraph.as_ref().unwrap();
+        assert_eq!(subgraph.graph.nodes.len(), 2);
+        assert_eq!(node.resolve_input("in_mesh"), Some(("B", "in_mesh")));
+        let (inner_id, inner_node) = subgraph
+            .graph
+            .nodes
+            .iter()
+            .find(|(_, n)| n.op_name == "C")
+            .unwrap();
+        assert_eq!(subgraph.outputs[0].node, inner_id);
+        assert!(matches!(
+            &inner_node.inputs[0].kind,
+            DependencyKind::Connection { node, .. } if subgraph.graph.nodes[*node].op_name == "B"
+        ));
+    }
+
+    #[test]
+    pub fn test_loop_subgraph() {
+        let mut graph = BjkGraph::new();
+        let source = graph.add_node("Source", Some("out_mesh".into()));
+        graph
+            .add_output(source, "out_mesh", DataType::Mesh)
+            .unwrap();
+        graph.add_output(source, "count", DataType::Scalar).unwrap();
+        let body = graph.add_node("Body", Some("out_mesh".into()));
+        graph
+            .add_input(body, "in_mesh", DataType::Mesh, None)
+            .unwrap();
+        graph
+            .add_input(body, "iterations", DataType::Scalar, None)
+            .unwrap();
+        graph.add_output(body, "out_mesh", DataType::Mesh).unwrap();
+        graph
+            .add_connection(source, "out_mesh", body, "in_mesh")
+            .unwrap();
+        graph
+            .add_connection(source, "count", body, "iterations")
+            .unwrap();
+
+        // Nothing uses the output of the body, so there's no mesh to feed back.
+        let (mut subgraph, _) =
+            BjkSubgraph::from_nodes(&graph, &Default::default(), &[body]).unwrap();
+        assert!(subgraph.make_loop().is_err());
+
+        graph.default_node = Some(body);
+        let (mut subgraph, _) =
+            BjkSubgraph::from_nodes(&graph, &Default::default(), &[body]).unwrap();
+        subgraph.make_loop().unwrap();
+        // The port named like the iteration count input is renamed.
+        assert_eq!(
+            subgraph
+                .inputs
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect_vec(),
+            ["in_mesh", "iterations_2"]
+        );
+
+        let mut outer = BjkGraph::new();
+        let node = outer.add_node(SUBGRAPH_OP_NAME, subgraph.return_value());
+        outer.nodes[node].subgraph = Some(RefCounted::new(subgraph));
+        let (serialized, _) = SerializedBjkGraph::from_runtime(RuntimeData {
+            graph: outer,
+            external_parameters: None,
+        })
+        .unwrap();
+        let (runtime, _, mappings) = serialized.into_runtime().unwrap();
+        let node = &runtime.graph.nodes[mappings.get_id(0).unwrap()];
+        let feedback = node.subgraph.as_ref().unwrap().feedback.clone().unwrap();
+        assert_eq!(feedback.input, "in_mesh");
+        assert_eq!(feedback.output, "out_mesh");
+    }
 }
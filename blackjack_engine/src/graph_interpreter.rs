@@ -4,12 +4,23 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::collections::{hash_map::DefaultHasher, BTreeMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
 use mlua::{Table, ToLua};
 use slotmap::SecondaryMap;
 
 use crate::gizmos::BlackjackGizmo;
-use crate::graph::{BjkGraph, BjkNodeId, BlackjackValue, NodeDefinitions};
-use crate::lua_engine::{ProgramResult, RenderableThing};
+use crate::graph::serialization::SerializedBjkGraph;
+use crate::graph::{
+    BjkGraph, BjkNodeId, BjkSubgraph, BlackjackValue, InputParameter, NodeDefinitions,
+    LOOP_ITERATIONS_INPUT,
+};
+use crate::lua_engine::{
+    format_lua_error, with_op_timeout, LuaNodeErrorContext, LuaRuntime, ProgramResult,
+    RenderableThing,
+};
 use crate::prelude::*;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -30,6 +41,55 @@ impl ExternalParameter {
 #[derive(Debug, Default, Clone)]
 pub struct ExternalParameterValues(pub HashMap<ExternalParameter, BlackjackValue>);
 
+impl ExternalParameterValues {
+    /// Returns the parameters in the order they were declared: Parameters are
+    /// grouped by node, following the order of the nodes in `graph`, and the
+    /// parameters of each node follow the order of the node's inputs, which
+    /// is the same as in its node definition. Unlike iterating the inner map,
+    /// this order is stable across runs.
+    pub fn iter_in_declaration_order<'a>(
+        &'a self,
+        graph: &'a BjkGraph,
+    ) -> impl Iterator<Item = (&'a ExternalParameter, &'a BlackjackValue)> + 'a {
+        graph.nodes.iter().flat_map(move |(node_id, node)| {
+            node.inputs.iter().filter_map(move |input| {
+                self.0
+                    .get_key_value(&ExternalParameter::new(node_id, input.name.clone()))
+            })
+        })
+    }
+}
+
+/// Receives progress updates while a graph runs, and lets the caller stop the
+/// run early. Integrations can use this to give feedback to the user when a
+/// graph takes a long time to run.
+pub trait ProgressSink {
+    /// Called right before the `op` function of a node runs. Nodes whose
+    /// outputs are reused from a `NodeOutputCache` are not reported.
+    ///
+    /// Only nodes of the graph being run are reported. A subgraph node is
+    /// reported once, right before its inner graph runs, and the nodes inside
+    /// it are not.
+    fn node_started(&self, node_id: BjkNodeId, op_name: &str);
+
+    /// Checked before running each node, including the nodes inside subgraphs.
+    /// When this returns true, the run stops with a `Cancelled` error. A node
+    /// whose `op` is already running is not interrupted.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// The error returned when a graph run is stopped by its `ProgressSink`.
+#[derive(Copy, Clone, Debug)]
+pub struct Cancelled;
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The graph execution was cancelled")
+    }
+}
+impl std::error::Error for Cancelled {}
+
 pub struct InterpreterContext<'a, 'lua> {
     outputs_cache: HashMap<BjkNodeId, mlua::Table<'lua>>,
     /// The values for all the external parameters. Mutable reference because
@@ -41,6 +101,153 @@ pub struct InterpreterContext<'a, 'lua> {
     /// Stores the gizmo outputs for each node. This is not filled if
     /// gizmo_state is None.
     gizmo_outputs: &'a mut SecondaryMap<BjkNodeId, Vec<BlackjackGizmo>>,
+    /// The outputs stored by previous runs of the graph. When not present,
+    /// every node runs.
+    output_cache: Option<&'a mut NodeOutputCache>,
+    /// Memoized results of `node_hash`. Only filled when `output_cache` is
+    /// present.
+    node_hashes: HashMap<BjkNodeId, Option<u64>>,
+    /// When running the graph inside a subgraph node, the values of the inner
+    /// parameters that are exposed as inputs of the subgraph node. These take
+    /// precedence over `external_param_values`.
+    subgraph_inputs: HashMap<ExternalParameter, mlua::Value<'lua>>,
+    /// How many subgraph nodes deep the graph being run is.
+    subgraph_depth: usize,
+    /// The selection sets of the graph being run. Nodes inside subgraphs use
+    /// the sets of the outermost graph.
+    selection_sets: &'a BTreeMap<String, String>,
+    /// Reports the nodes being run, and allows cancelling the run.
+    progress: Option<&'a dyn ProgressSink>,
+}
+
+/// The maximum nesting of subgraph nodes. Subgraphs own their inner graph, so
+/// a subgraph can't contain itself, but a malformed file could still describe
+/// a huge nesting. This stops the interpreter before it overflows the stack.
+const MAX_SUBGRAPH_DEPTH: usize = 64;
+
+/// The maximum number of iterations of a loop node. Each iteration runs all
+/// the nodes in the loop, so a bigger count would freeze the application
+/// instead of failing with an error.
+pub const MAX_LOOP_ITERATIONS: u32 = 1000;
+
+/// Stores the outputs of nodes across runs of a graph, so that only the nodes
+/// affected by a change need to run again.
+///
+/// Outputs are stored next to a hash of the node's op name and inputs. For
+/// connected inputs, the hash of the node at the other end is used, so a
+/// change in any parameter invalidates all the nodes downstream of it. This
+/// assumes node `op` functions always return the same outputs for the same
+/// inputs, and never modify their inputs in place.
+#[derive(Default)]
+pub struct NodeOutputCache {
+    entries: HashMap<BjkNodeId, (u64, mlua::RegistryKey)>,
+    /// The generation of the `NodeDefinitions` the entries were computed
+    /// with. When node definitions are reloaded, all entries become stale.
+    definitions_generation: u64,
+    /// The selection sets of the graph the entries were computed with. Node
+    /// hashes don't include the contents of the sets, so all entries become
+    /// stale when they change.
+    selection_sets: BTreeMap<String, String>,
+}
+
+impl NodeOutputCache {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn hash_value(value: &BlackjackValue, hasher: &mut impl Hasher) {
+    std::mem::discriminant(value).hash(hasher);
+    match value {
+        BlackjackValue::Vector(v) => v.to_array().map(f32::to_bits).hash(hasher),
+        BlackjackValue::Scalar(s) => s.to_bits().hash(hasher),
+        BlackjackValue::String(s) => s.hash(hasher),
+        // The expression is parsed from the string, so there's no need to hash
+        // it separately.
+        BlackjackValue::Selection(s, _) => s.hash(hasher),
+        BlackjackValue::Bool(b) => b.hash(hasher),
+        BlackjackValue::Expression(e) => e.hash(hasher),
+        BlackjackValue::None => {}
+    }
+}
+
+/// Returns a hash identifying the outputs of a node: Its op name, the values
+/// of its external parameters and, for its connections, the hash of the
+/// connected nodes.
+///
+/// Returns `None` when the node can't be cached, because it or one of the
+/// nodes it depends on `runs_gizmos`. Gizmos can update the parameters of
+/// their node when it runs, after the hash of the nodes below it is computed.
+fn node_hash(
+    graph: &BjkGraph,
+    external_param_values: &ExternalParameterValues,
+    runs_gizmos: &dyn Fn(BjkNodeId) -> bool,
+    memo: &mut HashMap<BjkNodeId, Option<u64>>,
+    node_id: BjkNodeId,
+) -> Option<u64> {
+    if let Some(hash) = memo.get(&node_id) {
+        return *hash;
+    }
+    let hash = (|| {
+        if runs_gizmos(node_id) {
+            return None;
+        }
+        let node = &graph.nodes[node_id];
+        let mut hasher = DefaultHasher::new();
+        node.op_name.hash(&mut hasher);
+        // The outputs of a subgraph node depend on the nodes inside it. Their
+        // exposed inputs are hashed as missing values, but that's fine because
+        // the inputs of the subgraph node are hashed below.
+        if let Some(subgraph) = &node.subgraph {
+            if let Some(feedback) = &subgraph.feedback {
+                feedback.input.hash(&mut hasher);
+                feedback.output.hash(&mut hasher);
+            }
+            let mut inner_memo = HashMap::new();
+            for port in &subgraph.outputs {
+                port.name.hash(&mut hasher);
+                port.param_name.hash(&mut hasher);
+                node_hash(
+                    &subgraph.graph,
+                    &subgraph.external_parameters,
+                    &|_| false,
+                    &mut inner_memo,
+                    port.node,
+                )?
+                .hash(&mut hasher);
+            }
+        }
+        for input in &node.inputs {
+            input.name.hash(&mut hasher);
+            match &input.kind {
+                crate::graph::DependencyKind::Connection { node, param_name } => {
+                    node_hash(graph, external_param_values, runs_gizmos, memo, *node)?
+                        .hash(&mut hasher);
+                    param_name.hash(&mut hasher);
+                }
+                crate::graph::DependencyKind::External { .. } => {
+                    let ext = ExternalParameter::new(node_id, input.name.clone());
+                    // A missing value is reported as an error when running the
+                    // node, here it's enough to hash something.
+                    match external_param_values.0.get(&ext) {
+                        Some(value) => hash_value(value, &mut hasher),
+                        None => hash_value(&BlackjackValue::None, &mut hasher),
+                    }
+                }
+            }
+        }
+        Some(hasher.finish())
+    })();
+    memo.insert(node_id, hash);
+    hash
 }
 
 #[derive(Clone, Debug, Default)]
@@ -49,13 +256,171 @@ pub struct GizmoState {
     pub gizmos_changed: bool,
 }
 
+/// Runs `graph` up to `target_node`, and returns the result of that node.
+///
+/// When a `progress` sink is given, it is notified of each node that runs and
+/// can cancel the run. See [`ProgressSink`].
 pub fn run_graph(
+    lua: &mlua::Lua,
+    graph: &BjkGraph,
+    target_node: BjkNodeId,
+    external_param_values: ExternalParameterValues,
+    node_definitions: &NodeDefinitions,
+    gizmos_state: Option<SecondaryMap<BjkNodeId, GizmoState>>,
+    progress: Option<&dyn ProgressSink>,
+) -> Result<ProgramResult> {
+    run_graph_inner(
+        lua,
+        graph,
+        target_node,
+        external_param_values,
+        node_definitions,
+        gizmos_state,
+        None,
+        progress,
+    )
+}
+
+/// Loads the `.bjk` file at `path` and runs its graph up to its default node,
+/// returning the generated mesh. This allows using Blackjack as a library,
+/// e.g. to bake meshes from a build script, without any UI.
+///
+/// The `params` override the values of the graph's promoted parameters, using
+/// their promoted names as keys. Other parameters keep the values stored in
+/// the file.
+///
+/// Along with the mesh, returns the lines logged by the graph's Lua code. See
+/// [`run_jack_graph`].
+pub fn run_jack(
+    lua_runtime: &LuaRuntime,
+    path: impl AsRef<Path>,
+    params: &HashMap<String, BlackjackValue>,
+) -> Result<(HalfEdgeMesh, Vec<String>)> {
+    let path = path.as_ref();
+    let (mut rt_data, _, _) = SerializedBjkGraph::load_from_file(path)
+        .and_then(|graph| graph.into_runtime())
+        .with_context(|| format!("Could not load jack file at {}", path.display()))?;
+    rt_data.fill_missing_inputs(&lua_runtime.node_definitions);
+
+    let graph = rt_data.graph;
+    let mut param_values = rt_data
+        .external_parameters
+        .ok_or_else(|| anyhow!("No external parameters found in jack file."))?;
+
+    for (name, value) in params {
+        let param = graph
+            .nodes
+            .iter()
+            .find_map(|(node_id, node)| {
+                node.inputs
+                    .iter()
+                    .find(|input| match &input.kind {
+                        crate::graph::DependencyKind::External { promoted } => {
+                            promoted.as_ref() == Some(name)
+                        }
+                        crate::graph::DependencyKind::Connection { .. } => false,
+                    })
+                    .map(|input| ExternalParameter::new(node_id, input.name.clone()))
+            })
+            .ok_or_else(|| anyhow!("This jack has no parameter named '{name}'"))?;
+        param_values.0.insert(param, value.clone());
+    }
+
+    run_jack_graph(lua_runtime, &graph, param_values)
+}
+
+/// Runs an already loaded jack `graph` up to its default node, with the given
+/// parameter values, and returns the generated mesh.
+///
+/// Along with the mesh, returns the lines logged by the graph's Lua code
+/// while it ran. When running the graph fails, the logged lines are attached
+/// to the error as context instead. Either way, the runtime's `log_receiver`
+/// is drained, so the log doesn't grow when calling this repeatedly.
+pub fn run_jack_graph(
+    lua_runtime: &LuaRuntime,
+    graph: &BjkGraph,
+    params: ExternalParameterValues,
+) -> Result<(HalfEdgeMesh, Vec<String>)> {
+    let target_node = graph
+        .default_node
+        .ok_or_else(|| anyhow!("Default node not set for this jack file."))?;
+
+    let result = run_graph(
+        &lua_runtime.lua,
+        graph,
+        target_node,
+        params,
+        &lua_runtime.node_definitions,
+        None,
+        None,
+    );
+    let log_lines: Vec<String> = lua_runtime.log_receiver.try_iter().collect();
+    let result = result.and_then(|result| match result.renderable {
+        Some(RenderableThing::HalfEdgeMesh(mesh)) => Ok(mesh),
+        _ => bail!("The default node of this jack does not produce a mesh."),
+    });
+    match result {
+        Ok(mesh) => Ok((mesh, log_lines)),
+        Err(err) if log_lines.is_empty() => Err(err),
+        Err(err) => Err(err.context(format!(
+            "The jack failed to run. Log output:\n{}",
+            log_lines.join("\n")
+        ))),
+    }
+}
+
+/// Same as `run_graph`, but nodes whose inputs didn't change since a previous
+/// run with the same `output_cache` are not run again, and their previous
+/// outputs are used instead. See [`NodeOutputCache`].
+///
+/// Nodes running gizmos, and the nodes that depend on them, are always run.
+/// Nodes with side effects (e.g. exporting a file) should not be run this
+/// way.
+pub fn run_graph_cached(
+    lua: &mlua::Lua,
+    graph: &BjkGraph,
+    target_node: BjkNodeId,
+    external_param_values: ExternalParameterValues,
+    node_definitions: &NodeDefinitions,
+    gizmos_state: Option<SecondaryMap<BjkNodeId, GizmoState>>,
+    output_cache: &mut NodeOutputCache,
+    progress: Option<&dyn ProgressSink>,
+) -> Result<ProgramResult> {
+    if output_cache.definitions_generation != node_definitions.generation() {
+        output_cache.clear();
+        output_cache.definitions_generation = node_definitions.generation();
+    }
+    if output_cache.selection_sets != graph.selection_sets {
+        output_cache.clear();
+        output_cache.selection_sets = graph.selection_sets.clone();
+    }
+    output_cache
+        .entries
+        .retain(|node_id, _| graph.nodes.contains_key(*node_id));
+    // Frees the registry slots of the entries that were just dropped.
+    lua.expire_registry_values();
+
+    run_graph_inner(
+        lua,
+        graph,
+        target_node,
+        external_param_values,
+        node_definitions,
+        gizmos_state,
+        Some(output_cache),
+        progress,
+    )
+}
+
+fn run_graph_inner(
     lua: &mlua::Lua,
     graph: &BjkGraph,
     target_node: BjkNodeId,
     mut external_param_values: ExternalParameterValues,
     node_definitions: &NodeDefinitions,
     gizmos_state: Option<SecondaryMap<BjkNodeId, GizmoState>>,
+    output_cache: Option<&mut NodeOutputCache>,
+    progress: Option<&dyn ProgressSink>,
 ) -> Result<ProgramResult> {
     let gizmos_enabled = gizmos_state.is_some();
 
@@ -66,6 +431,12 @@ pub fn run_graph(
         node_definitions,
         gizmo_state: gizmos_state,
         gizmo_outputs: &mut gizmo_outputs,
+        output_cache,
+        node_hashes: Default::default(),
+        subgraph_inputs: Default::default(),
+        subgraph_depth: 0,
+        selection_sets: &graph.selection_sets,
+        progress,
     };
 
     // Ensure the outputs cache is populated.
@@ -76,9 +447,14 @@ pub fn run_graph(
             .outputs_cache
             .get(&target_node)
             .expect("Final node should be in the outputs cache");
-        Some(RenderableThing::from_lua_value(
-            output.get(return_value.as_str())?,
-        )?)
+        let value = output.get(return_value.as_str())?;
+        // Cached outputs may be used again in later runs, so the renderable
+        // can't be taken out of them.
+        if context.output_cache.is_some() {
+            Some(RenderableThing::clone_from_lua_value(value)?)
+        } else {
+            Some(RenderableThing::from_lua_value(value)?)
+        }
     } else {
         None
     };
@@ -101,59 +477,77 @@ pub fn run_node<'lua>(
     node_id: BjkNodeId,
 ) -> Result<()> {
     let node = &graph.nodes[node_id];
+    if let Some(subgraph) = &node.subgraph {
+        return run_subgraph(lua, graph, ctx, node_id, subgraph);
+    }
+
     let op_name = &node.op_name;
     let node_def = ctx
         .node_definitions
         .node_def(op_name)
         .ok_or_else(|| anyhow!("Node definition not found for {op_name}"))?;
 
-    // Stores the arguments that will be sent to this node's `op` fn
-    let mut input_map = lua.create_table()?;
-
-    // Used to allow the gizmo input function to update a node's parameters.
-    // This is None when gizmos don't run to optimize performance
-    let mut referenced_external_params = if ctx.gizmo_state.is_some() {
-        Some(Vec::<ExternalParameter>::new())
+    // Reuse the outputs from a previous run when the inputs didn't change.
+    // Nodes running gizmos are not cached, because gizmos need to run again,
+    // and neither are the nodes that depend on them.
+    let node_definitions = ctx.node_definitions;
+    let gizmo_state = &ctx.gizmo_state;
+    let runs_gizmos = |id: BjkNodeId| {
+        gizmo_state
+            .as_ref()
+            .map(|s| s.contains_key(id))
+            .unwrap_or(false)
+            && node_definitions
+                .node_def(&graph.nodes[id].op_name)
+                .map(|def| def.has_gizmo)
+                .unwrap_or(false)
+    };
+    let cache_hash = if ctx.output_cache.is_some() {
+        node_hash(
+            graph,
+            ctx.external_param_values,
+            &runs_gizmos,
+            &mut ctx.node_hashes,
+            node_id,
+        )
     } else {
         None
     };
-
-    // Compute the values for dependent nodes and populate the output cache.
-    for input in &node.inputs {
-        match &input.kind {
-            crate::graph::DependencyKind::Connection { node, param_name } => {
-                // Make sure the value is there by running the node.
-                let cached_output_map = if let Some(cached) = ctx.outputs_cache.get(node) {
-                    cached
-                } else {
-                    run_node(lua, graph, ctx, *node)?;
-                    ctx.outputs_cache
-                        .get(node)
-                        .expect("Cache should be populated after calling run_node.")
-                };
-
-                input_map.set(
-                    input.name.as_str(),
-                    cached_output_map.get::<_, mlua::Value>(param_name.as_str())?,
-                )?;
-            }
-            crate::graph::DependencyKind::External { promoted: _ } => {
-                let ext = ExternalParameter::new(node_id, input.name.clone());
-                let val = ctx.external_param_values.0.get(&ext).ok_or_else(|| {
-                    anyhow!(
-                        "Could not retrieve external parameter named '{}' from node {}",
-                        &input.name,
-                        node_id.display_id(),
-                    )
-                })?;
-                input_map.set(input.name.as_str(), val.clone().to_lua(lua)?)?;
-                if let Some(m) = &mut referenced_external_params {
-                    m.push(ext);
-                }
+    if let (Some(hash), Some(cache)) = (cache_hash, &ctx.output_cache) {
+        if let Some((cached_hash, key)) = cache.entries.get(&node_id) {
+            if *cached_hash == hash {
+                let outputs: mlua::Table = lua.registry_value(key)?;
+                ctx.outputs_cache.insert(node_id, outputs);
+                return Ok(());
             }
         }
     }
 
+    // Stores the arguments that will be sent to this node's `op` fn. This
+    // computes the values for dependent nodes and populates the output cache.
+    let mut input_map = node_inputs(lua, graph, ctx, node_id)?;
+
+    // Used to allow the gizmo input function to update a node's parameters.
+    // This is None when gizmos don't run to optimize performance. Parameters
+    // given by an expression, or by a selection that references a selection
+    // set, can't be modified by gizmos.
+    let referenced_external_params = if ctx.gizmo_state.is_some() {
+        Some(
+            node.inputs
+                .iter()
+                .filter(|input| matches!(input.kind, crate::graph::DependencyKind::External { .. }))
+                .map(|input| ExternalParameter::new(node_id, input.name.clone()))
+                .filter(|ext| match ctx.external_param_values.0.get(ext) {
+                    Some(BlackjackValue::Expression(_)) => false,
+                    Some(BlackjackValue::Selection(_, Some(expr))) => !expr.has_sets(),
+                    _ => true,
+                })
+                .collect_vec(),
+        )
+    } else {
+        None
+    };
+
     // This special value is injected into the inputs to signal nodes that the
     // gizmos are being processed. This is useful to let nodes optimize out
     // parts of the computation when they're running on a game engine.
@@ -265,7 +659,8 @@ pub fn run_node<'lua>(
                 .call::<_, Table>((input_map, input_gizmo))
                 .map_err(|err| {
                     anyhow!(
-                        "A node's update_params gizmo callback should return an updated parameter list as a table. {err}"
+                        "A node's update_params gizmo callback should return an updated parameter list as a table. {}",
+                        format_lua_error(&err)
                     )
                 })?;
             input_map = new_input_map;
@@ -294,10 +689,13 @@ pub fn run_node<'lua>(
     }
 
     // Run node 'op'
+    report_node_started(ctx, node_id, op_name)?;
     let op_fn: mlua::Function = node_table
         .get("op")
         .map_err(|err| anyhow!("Node should always have an 'op'. {err}"))?;
-    let outputs = match op_fn.call(input_map.clone())? {
+    let outputs = match with_op_timeout(lua, || op_fn.call(input_map.clone()))
+        .node_context(&node_def.label, op_name)?
+    {
         mlua::Value::Table(t) => t,
         other => {
             bail!("A node's `op` function should always return a table, got {other:?}");
@@ -305,6 +703,10 @@ pub fn run_node<'lua>(
     };
 
     ctx.outputs_cache.insert(node_id, outputs.clone());
+    if let (Some(hash), Some(cache)) = (cache_hash, &mut ctx.output_cache) {
+        let key = lua.create_registry_value(outputs.clone())?;
+        cache.entries.insert(node_id, (hash, key));
+    }
 
     // Run post-gizmo
     for (gz_descr, enabled) in gizmo_descriptors.iter_mut().zip(&enabled_gizmos) {
@@ -322,7 +724,10 @@ pub fn run_node<'lua>(
                     .update_gizmos_fn
                     .call::<_, BlackjackGizmo>((input_map.clone(), gizmo, outputs.clone()))
                     .map_err(|err| {
-                        anyhow!("A node's gizmo outputs function should return a new gizmo. {err}")
+                        anyhow!(
+                            "A node's gizmo outputs function should return a new gizmo. {}",
+                            format_lua_error(&err)
+                        )
                     })
             })
             .transpose()?;
@@ -338,3 +743,294 @@ pub fn run_node<'lua>(
 
     Ok(())
 }
+
+/// Builds the table with the input values of a node, which is passed to its
+/// `op` function. Inputs given by an expression are evaluated last, so the
+/// expressions can refer to the values of the other inputs.
+///
+/// The lazy inputs of the node (see `NodeDefinition::lazy_inputs`) come after
+/// that, and only when the node selects them. The nodes connected to the lazy
+/// inputs that are not selected don't run.
+fn node_inputs<'lua>(
+    lua: &'lua mlua::Lua,
+    graph: &BjkGraph,
+    ctx: &mut InterpreterContext<'_, 'lua>,
+    node_id: BjkNodeId,
+) -> Result<Table<'lua>> {
+    let node = &graph.nodes[node_id];
+    let (label, lazy_inputs) = match ctx.node_definitions.node_def(&node.op_name) {
+        Some(node_def) => (node_def.label.clone(), node_def.lazy_inputs.clone()),
+        None => (node.op_name.clone(), vec![]),
+    };
+    let input_map = lua.create_table()?;
+    let mut expressions = vec![];
+    for input in &node.inputs {
+        if lazy_inputs.contains(&input.name) {
+            continue;
+        }
+        let ext = ExternalParameter::new(node_id, input.name.clone());
+        let expression = match (&input.kind, ctx.external_param_values.0.get(&ext)) {
+            (
+                crate::graph::DependencyKind::External { .. },
+                Some(BlackjackValue::Expression(expr)),
+            ) => Some(expr.clone()),
+            _ => None,
+        };
+        if let Some(expr) = expression {
+            expressions.push((input.name.as_str(), expr));
+        } else {
+            input_map.set(
+                input.name.as_str(),
+                input_value(lua, graph, ctx, node_id, input)?,
+            )?;
+        }
+    }
+
+    for (name, expr) in expressions {
+        let value = eval_expression(lua, &expr, &input_map).map_err(|err| {
+            anyhow!(
+                "Could not evaluate the expression '{expr}' for parameter '{name}' of node {}: {}",
+                node_id.display_id(),
+                format_lua_error(&err)
+            )
+        })?;
+        input_map.set(name, value)?;
+    }
+
+    if !lazy_inputs.is_empty() {
+        let selected = select_lazy_inputs(lua, &node.op_name, &input_map)
+            .node_context(&label, &node.op_name)?
+            .unwrap_or_else(|| lazy_inputs.clone());
+        for input in &node.inputs {
+            if lazy_inputs.contains(&input.name) && selected.contains(&input.name) {
+                input_map.set(
+                    input.name.as_str(),
+                    input_value(lua, graph, ctx, node_id, input)?,
+                )?;
+            }
+        }
+    }
+
+    Ok(input_map)
+}
+
+/// Calls the `select_lazy_inputs` function of the node with the given
+/// `op_name`, which receives the node's other `inputs` and returns the names
+/// of the lazy inputs the node needs. Returns `None` when the node has no such
+/// function, in which case all its lazy inputs are needed.
+fn select_lazy_inputs(
+    lua: &mlua::Lua,
+    op_name: &str,
+    inputs: &Table,
+) -> mlua::Result<Option<Vec<String>>> {
+    let node_table = lua
+        .load(&(format!("require('node_library'):getNode('{op_name}')")))
+        .eval::<Table>()?;
+    match node_table.get::<_, Option<mlua::Function>>("select_lazy_inputs")? {
+        Some(select_fn) => with_op_timeout(lua, || select_fn.call(inputs.clone())).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Evaluates the expression of a parameter. Expressions are Lua expressions,
+/// which run in a sandbox where only the `inputs` of the node and the
+/// functions in Lua's `math` library are available.
+///
+/// Strings share a single metatable, which gives access to the `string`
+/// library through method calls, like `("x"):rep(1e9)`, even from inside the
+/// sandbox. Its `__index` is removed while the expression runs.
+fn eval_expression(lua: &mlua::Lua, expr: &str, inputs: &Table) -> mlua::Result<f32> {
+    let env = lua.create_table()?;
+    let math: Table = lua.globals().get("math")?;
+    for pair in math.pairs::<mlua::Value, mlua::Value>() {
+        let (k, v) = pair?;
+        env.set(k, v)?;
+    }
+    for pair in inputs.clone().pairs::<mlua::Value, mlua::Value>() {
+        let (k, v) = pair?;
+        env.set(k, v)?;
+    }
+
+    let getmetatable: mlua::Function = lua.globals().get("getmetatable")?;
+    let string_mt: Table = getmetatable.call("")?;
+    let string_index: mlua::Value = string_mt.raw_get("__index")?;
+    string_mt.raw_set("__index", mlua::Value::Nil)?;
+    let result = with_op_timeout(lua, || {
+        lua.load(&format!("return {expr}"))
+            .set_name("expression")?
+            .set_environment(env)?
+            .eval()
+    });
+    string_mt.raw_set("__index", string_index)?;
+    result
+}
+
+/// Returns the value for an input of a node. For connected inputs, this runs
+/// the node at the other end of the connection when it hasn't run yet.
+fn input_value<'lua>(
+    lua: &'lua mlua::Lua,
+    graph: &BjkGraph,
+    ctx: &mut InterpreterContext<'_, 'lua>,
+    node_id: BjkNodeId,
+    input: &InputParameter,
+) -> Result<mlua::Value<'lua>> {
+    match &input.kind {
+        crate::graph::DependencyKind::Connection { node, param_name } => {
+            // Make sure the value is there by running the node.
+            let cached_output_map = if let Some(cached) = ctx.outputs_cache.get(node) {
+                cached
+            } else {
+                run_node(lua, graph, ctx, *node)?;
+                ctx.outputs_cache
+                    .get(node)
+                    .expect("Cache should be populated after calling run_node.")
+            };
+            Ok(cached_output_map.get::<_, mlua::Value>(param_name.as_str())?)
+        }
+        crate::graph::DependencyKind::External { promoted: _ } => {
+            let ext = ExternalParameter::new(node_id, input.name.clone());
+            if let Some(value) = ctx.subgraph_inputs.get(&ext) {
+                return Ok(value.clone());
+            }
+            let val = ctx.external_param_values.0.get(&ext).ok_or_else(|| {
+                anyhow!(
+                    "Could not retrieve external parameter named '{}' from node {}",
+                    &input.name,
+                    node_id.display_id(),
+                )
+            })?;
+            if let BlackjackValue::Selection(_, Some(expr)) = val {
+                if expr.has_sets() {
+                    let expanded = expr.expand_sets(ctx.selection_sets).map_err(|err| {
+                        anyhow!(
+                            "In parameter '{}' of node {}: {err}",
+                            &input.name,
+                            node_id.display_id(),
+                        )
+                    })?;
+                    return Ok(expanded.to_lua(lua)?);
+                }
+            }
+            Ok(val.clone().to_lua(lua)?)
+        }
+    }
+}
+
+/// Notifies the `ProgressSink` of `ctx`, if any, that a node is about to run.
+/// Returns a `Cancelled` error when the run should stop instead. Nodes inside
+/// subgraphs only check for cancellation, since their ids are not part of the
+/// graph being run.
+fn report_node_started(
+    ctx: &InterpreterContext<'_, '_>,
+    node_id: BjkNodeId,
+    op_name: &str,
+) -> Result<()> {
+    if let Some(progress) = ctx.progress {
+        if progress.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        if ctx.subgraph_depth == 0 {
+            progress.node_started(node_id, op_name);
+        }
+    }
+    Ok(())
+}
+
+/// Runs a subgraph node, by running the graph inside it with the node's
+/// inputs. The subgraph's outputs are stored in the outputs cache like the
+/// outputs of any other node.
+///
+/// Loop nodes run the graph once per iteration, feeding the mesh produced by
+/// each iteration as the input mesh of the next one. The outputs of the node
+/// are the outputs of the last iteration.
+///
+/// Gizmos are not available for the nodes inside a subgraph, and those nodes
+/// are not stored in the `NodeOutputCache`.
+fn run_subgraph<'lua>(
+    lua: &'lua mlua::Lua,
+    graph: &BjkGraph,
+    ctx: &mut InterpreterContext<'_, 'lua>,
+    node_id: BjkNodeId,
+    subgraph: &BjkSubgraph,
+) -> Result<()> {
+    if ctx.subgraph_depth >= MAX_SUBGRAPH_DEPTH {
+        bail!("Subgraphs can't be nested more than {MAX_SUBGRAPH_DEPTH} levels deep.");
+    }
+
+    let inputs = node_inputs(lua, graph, ctx, node_id)?;
+    let mut subgraph_inputs = HashMap::new();
+    for port in &subgraph.inputs {
+        subgraph_inputs.insert(
+            ExternalParameter::new(port.node, port.param_name.clone()),
+            inputs.get::<_, mlua::Value>(port.name.as_str())?,
+        );
+    }
+
+    let iterations = match &subgraph.feedback {
+        Some(_) => {
+            let iterations: f32 = inputs.get(LOOP_ITERATIONS_INPUT)?;
+            if !(0.0..=MAX_LOOP_ITERATIONS as f32).contains(&iterations) {
+                bail!(
+                    "The number of iterations of a loop must be between 0 and \
+                    {MAX_LOOP_ITERATIONS}, but it was {iterations}."
+                );
+            }
+            iterations.round() as u32
+        }
+        None => 1,
+    };
+
+    report_node_started(ctx, node_id, &graph.nodes[node_id].op_name)?;
+
+    let mut outputs = lua.create_table()?;
+    // With no iterations, the mesh passes through the loop unchanged.
+    if let Some(feedback) = &subgraph.feedback {
+        outputs.set(
+            feedback.output.as_str(),
+            inputs.get::<_, mlua::Value>(feedback.input.as_str())?,
+        )?;
+    }
+
+    for _ in 0..iterations {
+        let mut external_param_values = subgraph.external_parameters.clone();
+        let mut gizmo_outputs = Default::default();
+        let mut inner_ctx = InterpreterContext {
+            outputs_cache: Default::default(),
+            external_param_values: &mut external_param_values,
+            node_definitions: ctx.node_definitions,
+            gizmo_state: None,
+            gizmo_outputs: &mut gizmo_outputs,
+            output_cache: None,
+            node_hashes: Default::default(),
+            subgraph_inputs: subgraph_inputs.clone(),
+            subgraph_depth: ctx.subgraph_depth + 1,
+            selection_sets: ctx.selection_sets,
+            progress: ctx.progress,
+        };
+
+        outputs = lua.create_table()?;
+        for port in &subgraph.outputs {
+            if !inner_ctx.outputs_cache.contains_key(&port.node) {
+                run_node(lua, &subgraph.graph, &mut inner_ctx, port.node)?;
+            }
+            let value = inner_ctx.outputs_cache[&port.node]
+                .get::<_, mlua::Value>(port.param_name.as_str())?;
+            outputs.set(port.name.as_str(), value)?;
+        }
+
+        if let Some(feedback) = &subgraph.feedback {
+            let port = subgraph
+                .inputs
+                .iter()
+                .find(|port| port.name == feedback.input)
+                .ok_or_else(|| anyhow!("The loop input '{}' does not exist", feedback.input))?;
+            subgraph_inputs.insert(
+                ExternalParameter::new(port.node, port.param_name.clone()),
+                outputs.get::<_, mlua::Value>(feedback.output.as_str())?,
+            );
+        }
+    }
+
+    ctx.outputs_cache.insert(node_id, outputs);
+    Ok(())
+}
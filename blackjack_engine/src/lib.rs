@@ -22,6 +22,10 @@ pub mod graph;
 /// High level interpreter of blackjack graphs.
 pub mod graph_interpreter;
 
+/// Runs a `.bjk` file, or an already loaded jack graph, without a UI. See
+/// [`graph_interpreter::run_jack`].
+pub use graph_interpreter::{run_jack, run_jack_graph};
+
 /// Gizmos allow visual modifications of a node's parameters.
 pub mod gizmos;
 
@@ -5,11 +5,14 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::{
+    cell::Cell,
+    collections::VecDeque,
+    rc::Rc,
     sync::{
-        mpsc::{self, Receiver},
+        mpsc::{self, Receiver, Sender},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -43,6 +46,39 @@ impl<T> ToLuaError<T> for Result<T, TraversalError> {
     }
 }
 
+/// Formats a Lua error in a way that's suitable to show to users. Errors coming
+/// from Rust callbacks are unwrapped, so the original message is shown first,
+/// followed by the Lua traceback. Source locations are reported as
+/// `file:line:`, since chunks loaded from a `LuaSourceFile` are named after
+/// their path.
+pub fn format_lua_error(err: &mlua::Error) -> String {
+    match err {
+        mlua::Error::CallbackError { traceback, cause } => {
+            format!("{}\n{traceback}", format_lua_error(cause))
+        }
+        mlua::Error::RuntimeError(msg) => msg.clone(),
+        mlua::Error::SyntaxError { message, .. } => message.clone(),
+        err => err.to_string(),
+    }
+}
+
+/// Adds context to errors produced while running a node's Lua code, so users
+/// can tell which node failed and where in the Lua source it happened.
+pub trait LuaNodeErrorContext<T> {
+    fn node_context(self, label: &str, op_name: &str) -> Result<T>;
+}
+
+impl<T> LuaNodeErrorContext<T> for mlua::Result<T> {
+    fn node_context(self, label: &str, op_name: &str) -> Result<T> {
+        self.map_err(|err| {
+            anyhow!(
+                "Error in node '{label}' ({op_name}):\n{}",
+                format_lua_error(&err)
+            )
+        })
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 pub enum RenderableThing {
     HalfEdgeMesh(HalfEdgeMesh),
@@ -63,6 +99,22 @@ impl RenderableThing {
             }
         }
     }
+
+    /// Same as `from_lua_value`, but the renderable is cloned instead of
+    /// taken, so the Lua value remains usable afterwards.
+    pub fn clone_from_lua_value(renderable: mlua::Value<'_>) -> Result<Self> {
+        match renderable {
+            mlua::Value::UserData(renderable) if renderable.is::<HalfEdgeMesh>() => Ok(
+                RenderableThing::HalfEdgeMesh(renderable.borrow::<HalfEdgeMesh>()?.clone()),
+            ),
+            mlua::Value::UserData(renderable) if renderable.is::<HeightMap>() => Ok(
+                RenderableThing::HeightMap(renderable.borrow::<HeightMap>()?.clone()),
+            ),
+            _ => {
+                bail!("Object {renderable:?} is not a thing we can render.")
+            }
+        }
+    }
 }
 
 /// The result of an invocation to a lua program.
@@ -83,27 +135,136 @@ pub struct LuaFileWatcher {
     pub watcher_channel: Receiver<notify::DebouncedEvent>,
 }
 
+/// A bounded buffer of log lines. When the buffer is full, the oldest lines
+/// are discarded to make room for new ones.
+pub struct LogBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> + '_ {
+        self.lines.iter().map(|l| l.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+impl Extend<String> for LogBuffer {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, iter: I) {
+        for line in iter {
+            self.push(line);
+        }
+    }
+}
+
+/// The default maximum time a node's `op` function can run before it's aborted.
+pub const DEFAULT_OP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Guards against node `op` functions that never return, like user code with
+/// an infinite loop. This is stored as app data in the Lua state, and checked
+/// periodically by Luau's interrupt callback while Lua code runs.
+#[derive(Clone)]
+struct OpTimeout {
+    timeout: Duration,
+    deadline: Rc<Cell<Option<Instant>>>,
+}
+
+impl OpTimeout {
+    /// Sets up the interrupt callback on the `lua` state, so that any code
+    /// running under `with_op_timeout` is aborted after `timeout`.
+    fn install(lua: &Lua, timeout: Duration) {
+        let op_timeout = OpTimeout {
+            timeout,
+            deadline: Rc::new(Cell::new(None)),
+        };
+        let deadline = op_timeout.deadline.clone();
+        lua.set_interrupt(move || match deadline.get() {
+            Some(deadline) if Instant::now() > deadline => {
+                // The deadline is not cleared, so the error is raised again if
+                // the aborted code catches it with `pcall`.
+                Err(mlua::Error::RuntimeError(format!(
+                    "Execution timed out after {} ms. Is there an infinite loop?",
+                    timeout.as_millis()
+                )))
+            }
+            _ => Ok(mlua::VmState::Continue),
+        });
+        lua.set_app_data(op_timeout);
+    }
+}
+
+/// Runs `f`, aborting any Lua code it runs with an error when it takes longer
+/// than the timeout configured for this Lua state. When no timeout was
+/// configured, `f` is run without a time limit.
+pub fn with_op_timeout<T>(lua: &Lua, f: impl FnOnce() -> T) -> T {
+    let op_timeout = lua.app_data_ref::<OpTimeout>().map(|t| t.clone());
+    if let Some(op_timeout) = &op_timeout {
+        op_timeout
+            .deadline
+            .set(Some(Instant::now() + op_timeout.timeout));
+    }
+    let result = f();
+    if let Some(op_timeout) = &op_timeout {
+        op_timeout.deadline.set(None);
+    }
+    result
+}
+
 pub struct LuaRuntime {
     pub lua: Lua,
     pub node_definitions: NodeDefinitions,
     pub file_watcher: Option<LuaFileWatcher>,
     pub lua_io: Arc<dyn LuaFileIo + 'static>,
+    /// Receives the lines logged by Lua code, using either `print` or
+    /// `Blackjack.log`. Integrations should drain this periodically (e.g. into
+    /// a `LogBuffer`) and display the lines to the user.
+    pub log_receiver: Receiver<String>,
+    log_sender: Sender<String>,
 }
 
 impl LuaRuntime {
     /// Initializes and returns the Blackjack Lua runtime. This function will
-    /// use the `std::fs` API to load Lua source files. Some integrations may
-    /// prefer to use other file reading methods with `initialize_custom`.
-    pub fn initialize_with_std(node_libraries_path: String) -> anyhow::Result<LuaRuntime> {
-        Self::initialize_custom(StdLuaFileIo {
-            base_folder: node_libraries_path,
-        })
+    /// use the `std::fs` API to load Lua source files from the given
+    /// `search_folders`, in order. Some integrations may prefer to use other
+    /// file reading methods with `initialize_custom`.
+    ///
+    /// Node `op` functions are aborted after running for `DEFAULT_OP_TIMEOUT`.
+    pub fn initialize_with_std(search_folders: Vec<String>) -> anyhow::Result<LuaRuntime> {
+        Self::initialize_custom(StdLuaFileIo { search_folders }, Some(DEFAULT_OP_TIMEOUT))
     }
 
-    pub fn initialize_custom(lua_io: impl LuaFileIo + 'static) -> anyhow::Result<LuaRuntime> {
+    /// Initializes and returns the Blackjack Lua runtime, using `lua_io` to
+    /// load Lua source files. When `op_timeout` is set, running a node's `op`
+    /// function for longer than that returns an error instead of blocking
+    /// forever.
+    pub fn initialize_custom(
+        lua_io: impl LuaFileIo + 'static,
+        op_timeout: Option<Duration>,
+    ) -> anyhow::Result<LuaRuntime> {
         let lua = Lua::new();
+        if let Some(timeout) = op_timeout {
+            OpTimeout::install(&lua, timeout);
+        }
         let lua_io = Arc::new(lua_io);
-        lua_stdlib::load_lua_bindings(&lua, lua_io.clone())?;
+        let (log_sender, log_receiver) = mpsc::channel();
+        lua_stdlib::load_lua_bindings(&lua, lua_io.clone(), log_sender.clone())?;
         let node_definitions = NodeDefinitions::new(load_node_definitions(&lua, lua_io.as_ref())?);
 
         Ok(LuaRuntime {
@@ -111,13 +272,21 @@ impl LuaRuntime {
             node_definitions,
             file_watcher: None,
             lua_io,
+            log_receiver,
+            log_sender,
         })
     }
 
     pub fn start_file_watcher(&mut self) -> Result<()> {
         let (tx, rx) = mpsc::channel();
         let mut watcher = notify::watcher(tx, Duration::from_secs(1))?;
-        watcher.watch(self.lua_io.base_folder(), notify::RecursiveMode::Recursive)?;
+        for folder in self.lua_io.search_folders() {
+            // Missing folders are not an error: They may be user folders
+            // that haven't been created yet.
+            if std::path::Path::new(folder).exists() {
+                watcher.watch(folder, notify::RecursiveMode::Recursive)?;
+            }
+        }
         self.file_watcher = Some(LuaFileWatcher {
             watcher,
             watcher_channel: rx,
@@ -138,7 +307,7 @@ impl LuaRuntime {
                 | DebouncedEvent::Write(_)
                 | DebouncedEvent::Remove(_)
                 | DebouncedEvent::Rename(_, _) => {
-                    println!("Reloading Lua scripts...");
+                    let _ = self.log_sender.send("Reloading Lua scripts...".into());
                     // Reset the _LOADED table to clear any required libraries
                     // from the cache. This will trigger reloading of libraries
                     // when the hot reloaded code first requires them,
@@ -29,8 +29,12 @@ end
 BlackjackUtils.parse_ch_val = function(s)
     if s == "f32" then
         return Types.F32
+    elseif s == "i32" then
+        return Types.I32
     elseif s == "Vec3" then
         return Types.VEC3
+    elseif s == "String" then
+        return Types.STRING
     end
 end
 
@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::sync::Arc;
+use std::sync::{mpsc::Sender, Arc};
 
 use mlua::{FromLua, Lua, Table, ToLua, UserData};
 
@@ -23,6 +23,7 @@ pub mod lua_require_io;
 pub use lua_require_io::*;
 
 mod lua_core_library;
+pub use lua_core_library::load_heightmap_image;
 
 pub mod lua_documentation;
 
@@ -39,9 +40,14 @@ pub struct LuaDocstringData {
 }
 inventory::collect!(LuaDocstringData);
 
-/// Loads all blackjack Rust function wrappers to the Lua API
-pub fn load_lua_bindings(lua: &Lua, lua_io: Arc<dyn LuaFileIo + 'static>) -> anyhow::Result<()> {
-    lua_core_library::load(lua, lua_io)?;
+/// Loads all blackjack Rust function wrappers to the Lua API. Lines printed
+/// from Lua code are sent through `log_sender`.
+pub fn load_lua_bindings(
+    lua: &Lua,
+    lua_io: Arc<dyn LuaFileIo + 'static>,
+    log_sender: Sender<String>,
+) -> anyhow::Result<()> {
+    lua_core_library::load(lua, lua_io, log_sender)?;
 
     // This collects functions from all over the codebase. Any module annotated
     // with `#[blackjack_macros::blackjack_lua_module]` is inspected and may
@@ -51,5 +57,32 @@ pub fn load_lua_bindings(lua: &Lua, lua_io: Arc<dyn LuaFileIo + 'static>) -> any
         (register_fn.f)(lua).expect("Failed to register Lua API");
     }
 
+    lua.load(DEPRECATED_ALIASES)
+        .set_name("deprecated_aliases")?
+        .exec()?;
+
     Ok(())
 }
+
+/// Keeps old Lua API names working after they were renamed or repurposed.
+///
+/// - `Primitives.grid(x, y, spacing_x, spacing_y)` built a point cloud, which
+///   is now `Primitives.point_grid`. Calls starting with a number are
+///   forwarded there.
+const DEPRECATED_ALIASES: &str = r#"
+local grid = Primitives.grid
+local warned_grid = false
+Primitives.grid = function(first, ...)
+    if type(first) ~= "number" then
+        return grid(first, ...)
+    end
+    if not warned_grid then
+        warned_grid = true
+        Blackjack.log(
+            "Primitives.grid(x, y, spacing_x, spacing_y) is deprecated. "
+                .. "Use Primitives.point_grid instead."
+        )
+    end
+    return Primitives.point_grid(first, ...)
+end
+"#;
@@ -4,15 +4,47 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::sync::Arc;
+use std::sync::{mpsc::Sender, Arc};
 
 use mlua::Value;
 
+use crate::mesh::heightmap::HeightMap;
+
 use super::*;
 
-pub fn load(lua: &Lua, lua_io: Arc<dyn LuaFileIo + 'static>) -> anyhow::Result<()> {
+/// Converts a list of Lua values to a single line of text, separated by tabs,
+/// the same way Lua's `print` does.
+fn values_to_line(lua: &Lua, values: mlua::MultiValue) -> mlua::Result<String> {
+    let tostring: mlua::Function = lua.globals().get("tostring")?;
+    Ok(values
+        .into_iter()
+        .map(|v| tostring.call::<_, String>(v))
+        .collect::<mlua::Result<Vec<_>>>()?
+        .join("\t"))
+}
+
+pub fn load(
+    lua: &Lua,
+    lua_io: Arc<dyn LuaFileIo + 'static>,
+    log_sender: Sender<String>,
+) -> anyhow::Result<()> {
     let globals = lua.globals();
 
+    // Lua's `print` writes to stdout, which users of an integration will
+    // typically not see. Instead, both `print` and `Blackjack.log` send lines
+    // to the runtime's log channel.
+    let log_fn = lua.create_function(move |lua, values: mlua::MultiValue| {
+        // If the receiving end was dropped, nobody is listening to the log, so
+        // the line can be safely discarded.
+        let _ = log_sender.send(values_to_line(lua, values)?);
+        Ok(())
+    })?;
+    globals.set("print", log_fn.clone())?;
+    if !globals.contains_key("Blackjack")? {
+        globals.set("Blackjack", lua.create_table()?)?;
+    }
+    globals.get::<_, Table>("Blackjack")?.set("log", log_fn)?;
+
     // The _LOADED table stores things loaded by the `require` function
     globals.set("_LOADED", lua.create_table()?)?;
 
@@ -71,9 +103,55 @@ pub fn load(lua: &Lua, lua_io: Arc<dyn LuaFileIo + 'static>) -> anyhow::Result<(
     Ok(())
 }
 
+/// Loads the image at `path` as a heightmap. The image is converted to
+/// grayscale, and each pixel's luminance is stored as a height in the [0, 1]
+/// range.
+pub fn load_heightmap_image(path: &str) -> anyhow::Result<HeightMap> {
+    let image = image::open(path)
+        .map_err(|err| anyhow::anyhow!("Could not load image at {path}: {err}"))?
+        .into_luma16();
+    Ok(HeightMap::from_shape_fn(
+        image.width() as usize,
+        image.height() as usize,
+        |(x, y)| image.get_pixel(x as u32, y as u32).0[0] as f32 / u16::MAX as f32,
+    ))
+}
+
+/// A seeded pseudo-random number generator, based on the SplitMix64
+/// algorithm. The same seed always produces the same sequence of numbers,
+/// regardless of platform, so nodes using it generate reproducible results.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed number in the [0, 1) range.
+    pub fn next_f32(&mut self) -> f32 {
+        // The 24 most significant bits fit exactly in an f32's mantissa.
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+}
+
 #[blackjack_macros::blackjack_lua_module]
 mod lua_module {
-    use anyhow::Result;
+    use super::{HeightMap, Rng};
+    use crate::lua_engine::lua_stdlib::LVec3;
+    use crate::mesh::halfedge::{HalfEdgeMesh, VertexId};
+    use anyhow::{bail, Result};
+    use glam::Vec3;
 
     /// Read the contents of the file at `path` and return as a string. Will
     /// fail if the path does not exist, or the user does not have correct
@@ -90,4 +168,68 @@ mod lua_module {
         std::fs::write(path, contents)?;
         Ok(())
     }
+
+    /// Loads the grayscale image at `path` as a heightmap, with heights in
+    /// the [0, 1] range. Colored images are converted to grayscale first.
+    #[lua(under = "Io")]
+    pub fn load_heightmap(path: String) -> Result<HeightMap> {
+        super::load_heightmap_image(&path)
+    }
+
+    /// Returns a new random number generator, initialized with the given
+    /// `seed`. Generators created with the same seed always return the same
+    /// sequence of values.
+    #[lua(under = "Blackjack")]
+    pub fn rng(seed: i64) -> Rng {
+        Rng::new(seed as u64)
+    }
+
+    /// Returns the position of the vertex with `vertex_id` in `mesh`, as
+    /// stored in its `position` channel.
+    #[lua(under = "Blackjack")]
+    pub fn get_position(mesh: &HalfEdgeMesh, vertex_id: VertexId) -> Result<LVec3> {
+        if !mesh.read_connectivity().vertex_exists(vertex_id) {
+            bail!("Vertex {vertex_id:?} does not exist in this mesh.");
+        }
+        Ok(LVec3(mesh.read_positions()[vertex_id]))
+    }
+
+    /// Moves the vertex with `vertex_id` in `mesh` to `pos`, by writing to its
+    /// `position` channel. Useful to write custom deformers that move each
+    /// vertex individually.
+    #[lua(under = "Blackjack")]
+    pub fn set_position(mesh: &mut HalfEdgeMesh, vertex_id: VertexId, pos: LVec3) -> Result<()> {
+        if !mesh.read_connectivity().vertex_exists(vertex_id) {
+            bail!("Vertex {vertex_id:?} does not exist in this mesh.");
+        }
+        mesh.write_positions()[vertex_id] = pos.0;
+        Ok(())
+    }
+
+    #[lua_impl]
+    impl Rng {
+        /// Returns a random number in the [`min`, `max`) range.
+        #[lua]
+        pub fn float(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next_f32()
+        }
+
+        /// Returns a random integer in the [`min`, `max`] range, both ends
+        /// included.
+        #[lua]
+        pub fn int(&mut self, min: i64, max: i64) -> Result<i64> {
+            if min > max {
+                anyhow::bail!("Invalid range [{min}, {max}]. Min is greater than max.");
+            }
+            let range = (max as i128 - min as i128 + 1) as u128;
+            Ok((min as i128 + (self.next_u64() as u128 % range) as i128) as i64)
+        }
+
+        /// Returns a vector with each component being a random number in the
+        /// [0, 1) range.
+        #[lua(map = "LVec3(x)")]
+        pub fn vec3(&mut self) -> Vec3 {
+            Vec3::new(self.next_f32(), self.next_f32(), self.next_f32())
+        }
+    }
 }
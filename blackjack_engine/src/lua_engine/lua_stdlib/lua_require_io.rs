@@ -4,7 +4,10 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::{borrow::Cow, path::PathBuf};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
 
 use crate::graph::{NodeDefinition, NodeDefinitionsInner};
 
@@ -19,7 +22,9 @@ impl<'lua> mlua::AsChunk<'lua> for LuaSourceFile {
     }
 
     fn name(&self) -> std::option::Option<std::string::String> {
-        Some(self.name.clone())
+        // The '@' prefix tells Lua this chunk comes from a file, so error
+        // messages and tracebacks are reported as `path:line:`
+        Some(format!("@{}", self.name))
     }
 }
 
@@ -32,13 +37,13 @@ impl<'lua> mlua::AsChunk<'lua> for LuaSourceFile {
 /// Rust's standard io functions, you can use the `StdLuaFileIo` default
 /// implementation which uses the Rust standard library.
 pub trait LuaFileIo {
-    /// Returns the path of the base folder this FileIo is watching. This is
-    /// used in blackjack_ui for hot reloading. Other integrations may use it in
-    /// different ways.
-    fn base_folder(&self) -> &str;
+    /// Returns the paths of the folders this FileIo is loading Lua code from.
+    /// This is used in blackjack_ui for hot reloading. Other integrations may
+    /// use it in different ways.
+    fn search_folders(&self) -> &[String];
 
     /// Returns an iterator over the paths of all the blackjack initialization
-    /// scripts on the lua folder.
+    /// scripts on the lua folders.
     ///
     /// The calling code does not care about the format of the returned paths.
     /// The values should be valid to call the `load_file_absolute` function,
@@ -55,19 +60,73 @@ pub trait LuaFileIo {
     fn load_file_require(&self, path: &str) -> anyhow::Result<LuaSourceFile>;
 }
 
+/// The environment variable with extra folders to load Lua node libraries
+/// from, separated like the `PATH` variable of the current platform.
+pub const LIBRARY_PATH_ENV_VAR: &str = "BLACKJACK_LIBRARY_PATH";
+
+/// Returns the extra node library folders set by the user in the
+/// [`LIBRARY_PATH_ENV_VAR`] environment variable, if any.
+pub fn library_folders_from_env() -> Vec<String> {
+    std::env::var_os(LIBRARY_PATH_ENV_VAR)
+        .map(|paths| {
+            std::env::split_paths(&paths)
+                .filter(|p| !p.as_os_str().is_empty())
+                .map(|p| p.display().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A `LuaFileIo` using the Rust standard library to load files from one or
+/// more search folders.
+///
+/// Each search folder may follow the same layout as `$BLACKJACK_LUA`, with a
+/// `run` subfolder for node definitions and a `lib` subfolder for libraries
+/// that can be loaded with `require`. Folders without those subfolders are
+/// used directly for both purposes, so a user can keep a flat folder with
+/// their custom nodes. In that case, only the files at the top of the folder
+/// are run, so the libraries in a `lib` subfolder are not run as nodes. When
+/// several folders contain a library with the same name, the first one in
+/// `search_folders` is used.
 pub struct StdLuaFileIo {
-    pub base_folder: String,
+    pub search_folders: Vec<String>,
+}
+
+impl StdLuaFileIo {
+    /// Returns `folder/subfolder` when it exists, or `folder` otherwise.
+    fn subfolder_or_root(folder: &str, subfolder: &str) -> PathBuf {
+        let path = PathBuf::from(folder).join(subfolder);
+        if path.is_dir() {
+            path
+        } else {
+            PathBuf::from(folder)
+        }
+    }
 }
 
 impl LuaFileIo for StdLuaFileIo {
-    fn base_folder(&self) -> &str {
-        &self.base_folder
+    fn search_folders(&self) -> &[String] {
+        &self.search_folders
     }
 
     fn find_run_files(&self) -> Box<dyn Iterator<Item = String>> {
-        let run_path = PathBuf::from(&self.base_folder).join("run");
-        Box::new(
+        let run_paths = self
+            .search_folders
+            .iter()
+            .map(|folder| {
+                let run_path = Self::subfolder_or_root(folder, "run");
+                // With a flat layout, subfolders may contain anything.
+                let max_depth = if run_path == Path::new(folder) {
+                    1
+                } else {
+                    usize::MAX
+                };
+                (run_path, max_depth)
+            })
+            .collect::<Vec<_>>();
+        Box::new(run_paths.into_iter().flat_map(|(run_path, max_depth)| {
             walkdir::WalkDir::new(run_path)
+                .max_depth(max_depth)
                 .follow_links(true)
                 .into_iter()
                 .filter_map(|e| e.ok())
@@ -78,8 +137,8 @@ impl LuaFileIo for StdLuaFileIo {
                             .map(|s| s.ends_with(".lua"))
                             .unwrap_or(false)
                 })
-                .filter_map(|e| e.path().to_str().map(|x| x.to_owned())),
-        )
+                .filter_map(|e| e.path().to_str().map(|x| x.to_owned()))
+        }))
     }
 
     fn load_file_absolute(&self, path: &str) -> anyhow::Result<LuaSourceFile> {
@@ -90,14 +149,22 @@ impl LuaFileIo for StdLuaFileIo {
     }
 
     fn load_file_require(&self, path: &str) -> anyhow::Result<LuaSourceFile> {
-        let mut path = PathBuf::from(&self.base_folder).join("lib").join(path);
-        path.set_extension("lua");
-        Ok(LuaSourceFile {
-            contents: std::fs::read_to_string(&path).map_err(|err| {
-                anyhow::anyhow!("Error loading file {}. Cause: {err}", path.display())
-            })?,
-            name: path.display().to_string(),
-        })
+        for folder in &self.search_folders {
+            let mut file_path = Self::subfolder_or_root(folder, "lib").join(path);
+            file_path.set_extension("lua");
+            if file_path.is_file() {
+                return Ok(LuaSourceFile {
+                    contents: std::fs::read_to_string(&file_path).map_err(|err| {
+                        anyhow::anyhow!("Error loading file {}. Cause: {err}", file_path.display())
+                    })?,
+                    name: file_path.display().to_string(),
+                });
+            }
+        }
+        anyhow::bail!(
+            "Could not find library '{path}' in any of the search folders: {:?}",
+            self.search_folders
+        )
     }
 }
 
@@ -118,3 +185,32 @@ pub fn load_node_definitions(
         .get::<_, mlua::Table>("nodes")?;
     NodeDefinition::load_nodes_from_table(table)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_library_folder() {
+        let folder = std::env::temp_dir().join(format!(
+            "blackjack_test_flat_library_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(folder.join("lib")).unwrap();
+        std::fs::write(folder.join("my_nodes.lua"), "").unwrap();
+        std::fs::write(folder.join("lib").join("helpers.lua"), "").unwrap();
+
+        let io = StdLuaFileIo {
+            search_folders: vec![folder.display().to_string()],
+        };
+        // Libraries are found with `require`, but they don't run as nodes.
+        let run_files = io.find_run_files().collect::<Vec<_>>();
+        assert_eq!(
+            run_files,
+            vec![folder.join("my_nodes.lua").display().to_string()]
+        );
+        assert!(io.load_file_require("helpers").is_ok());
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -8,6 +8,7 @@ use blackjack_commons::utils::transmute_vec;
 use noise::NoiseFn;
 
 use super::*;
+use crate::prelude::HalfEdgeMesh;
 
 #[derive(Debug)]
 #[repr(transparent)]
@@ -43,6 +44,29 @@ impl LVec3 {
     }
 }
 
+/// A list of meshes. From Lua, this can be passed either as a single mesh or
+/// as a sequence table of meshes.
+#[derive(Debug)]
+pub struct LMeshList(pub Vec<HalfEdgeMesh>);
+impl<'lua> FromLua<'lua> for LMeshList {
+    fn from_lua(lua_value: mlua::Value<'lua>, _lua: &'lua Lua) -> mlua::Result<Self> {
+        match lua_value {
+            mlua::Value::UserData(ud) if ud.is::<HalfEdgeMesh>() => {
+                Ok(LMeshList(vec![ud.borrow::<HalfEdgeMesh>()?.clone()]))
+            }
+            mlua::Value::Table(table) => table
+                .sequence_values::<HalfEdgeMesh>()
+                .collect::<mlua::Result<Vec<_>>>()
+                .map(LMeshList),
+            _ => Err(mlua::Error::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "HalfEdgeMesh list",
+                message: Some("Expected a mesh or a table of meshes".into()),
+            }),
+        }
+    }
+}
+
 /// Vertex ids cross the Rust<->Lua boundary a lot, so we can't pay the price of
 /// boxing that a `UserData` requires. Instead we use LightUserData by casting
 /// the slotmap key to u64, and then to a pointer.
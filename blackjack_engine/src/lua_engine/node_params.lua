@@ -59,6 +59,11 @@ Params.lua_str = function(name)
     return { name = name, type = "lua_string" }
 end
 
+--- A boolean parameter, with a given `default` value. Shown as a checkbox.
+Params.bool = function(name, default)
+    return { name = name, default = default or false, type = "bool" }
+end
+
 --- Another special string parameter, which lets the user select among a given
 --- set of pre-defined `values`. The `selected` parameter may be used to
 --- optionally provide the index of the default selection.
@@ -10,3 +10,7 @@ pub mod halfedge;
 /// A heightmap data structure. A different mesh representation based on
 /// heightmaps. Supports different operations.
 pub mod heightmap;
+
+/// Signed distance fields, built from primitive shapes and booleans, which
+/// can be converted to halfedge meshes.
+pub mod sdf;
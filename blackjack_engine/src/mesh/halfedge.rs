@@ -37,6 +37,12 @@ pub mod edit_ops;
 /// Import / Export of HalfEdgeMesh data structure to Wavefront OBJ files
 pub mod wavefront_obj;
 
+/// Export of HalfEdgeMesh data structure to glTF 2.0 files
+pub mod gltf;
+
+/// Export of HalfEdgeMesh data structure to STL files, for 3d printing
+pub mod stl;
+
 /// A compact halfedge graph specifically optimized for some operations
 pub mod compact_mesh;
 
@@ -52,6 +58,10 @@ pub mod halfedge_lua_api;
 pub mod channels;
 pub use channels::*;
 
+/// A cached spatial index over vertex positions, for nearest-neighbor queries
+pub mod spatial_index;
+pub use spatial_index::VertexPos;
+
 use self::mappings::MeshMapping;
 
 /// HalfEdge meshes are a type of linked list. This means it is sometimes
@@ -139,6 +149,15 @@ pub struct MeshConnectivity {
     faces: SlotMap<FaceId, Face>,
     halfedges: SlotMap<HalfEdgeId, HalfEdge>,
 
+    /// The order in which each element was added to the mesh. Slotmaps reuse
+    /// the slots of removed elements, so their iteration order depends on the
+    /// history of operations. This is used to provide a stable order instead.
+    /// See `iter_vertices_ordered` and friends.
+    vertex_order: SecondaryMap<VertexId, u64>,
+    face_order: SecondaryMap<FaceId, u64>,
+    halfedge_order: SecondaryMap<HalfEdgeId, u64>,
+    next_order: u64,
+
     debug_edges: HashMap<HalfEdgeId, DebugMark>,
     debug_vertices: HashMap<VertexId, DebugMark>,
 }
@@ -159,6 +178,9 @@ pub struct HalfEdgeMesh {
     pub channels: MeshChannels,
     default_channels: DefaultChannels,
     pub gen_config: MeshGenerationConfig,
+    /// Lazily built spatial index over vertex positions. See
+    /// `vertex_spatial_index`.
+    spatial_index: InteriorMutable<Option<spatial_index::CachedSpatialIndex>>,
 }
 
 #[cfg(feature = "sync")]
@@ -169,6 +191,7 @@ impl Clone for HalfEdgeMesh {
             channels: self.channels.clone(),
             default_channels: self.default_channels.clone(),
             gen_config: self.gen_config.clone(),
+            spatial_index: InteriorMutable::new(self.spatial_index.borrow().clone()),
         }
     }
 }
@@ -370,7 +393,9 @@ impl MeshConnectivity {
         &'a self,
         channel: &'a Channel<VertexId, T>,
     ) -> impl Iterator<Item = (VertexId, &Vertex, T)> + 'a {
-        self.vertices.iter().map(|(id, v)| (id, v, channel[id]))
+        self.vertices
+            .iter()
+            .map(|(id, v)| (id, v, channel[id].clone()))
     }
 
     pub fn iter_faces(&self) -> impl Iterator<Item = (FaceId, &Face)> {
@@ -381,18 +406,53 @@ impl MeshConnectivity {
         &'a self,
         channel: &'a Channel<FaceId, T>,
     ) -> impl Iterator<Item = (FaceId, &Face, T)> + 'a {
-        self.faces.iter().map(|(id, v)| (id, v, channel[id]))
+        self.faces
+            .iter()
+            .map(|(id, v)| (id, v, channel[id].clone()))
     }
 
     pub fn iter_halfedges(&self) -> impl Iterator<Item = (HalfEdgeId, &HalfEdge)> {
         self.halfedges.iter()
     }
 
+    /// Like `iter_vertices`, but yields the vertices in the order they were
+    /// added to the mesh. This order does not depend on which slots were
+    /// freed by previous operations, so it should be used when the output
+    /// needs to be reproducible, like when exporting to a file.
+    pub fn iter_vertices_ordered(&self) -> impl Iterator<Item = (VertexId, &Vertex)> {
+        self.vertices
+            .iter()
+            .sorted_by_key(|(v, _)| self.vertex_order[*v])
+    }
+
+    /// Like `iter_faces`, but in a stable order. See `iter_vertices_ordered`.
+    pub fn iter_faces_ordered(&self) -> impl Iterator<Item = (FaceId, &Face)> {
+        self.faces
+            .iter()
+            .sorted_by_key(|(f, _)| self.face_order[*f])
+    }
+
+    /// Like `iter_halfedges`, but in a stable order. See
+    /// `iter_vertices_ordered`.
+    pub fn iter_halfedges_ordered(&self) -> impl Iterator<Item = (HalfEdgeId, &HalfEdge)> {
+        self.halfedges
+            .iter()
+            .sorted_by_key(|(h, _)| self.halfedge_order[*h])
+    }
+
+    /// Returns the next value for the insertion order of a new element.
+    fn next_order(&mut self) -> u64 {
+        self.next_order += 1;
+        self.next_order
+    }
+
     pub fn iter_halfedges_with_channel<'a, T: ChannelValue>(
         &'a self,
         channel: &'a Channel<HalfEdgeId, T>,
     ) -> impl Iterator<Item = (HalfEdgeId, &HalfEdge, T)> + 'a {
-        self.halfedges.iter().map(|(id, v)| (id, v, channel[id]))
+        self.halfedges
+            .iter()
+            .map(|(id, v)| (id, v, channel[id].clone()))
     }
 
     /// Adds a new vertex to the mesh, disconnected from everything else. Returns its handle.
@@ -402,7 +462,7 @@ impl MeshConnectivity {
         position: Vec3,
         halfedge: Option<HalfEdgeId>,
     ) -> VertexId {
-        let v = self.vertices.insert(Vertex { halfedge });
+        let v = self.alloc_vertex_raw(halfedge);
         positions[v] = position;
         v
     }
@@ -411,24 +471,32 @@ impl MeshConnectivity {
     /// Returns its handle. Unlike `alloc_vertex`, this function does not set
     /// the vertex position, implicitly leaving it at zero.
     fn alloc_vertex_raw(&mut self, halfedge: Option<HalfEdgeId>) -> VertexId {
-        self.vertices.insert(Vertex { halfedge })
+        let v = self.vertices.insert(Vertex { halfedge });
+        let order = self.next_order();
+        self.vertex_order.insert(v, order);
+        v
     }
 
     /// Adds a new face to the mesh, disconnected from everything else. Returns its handle.
     fn alloc_face(&mut self, halfedge: Option<HalfEdgeId>) -> FaceId {
-        self.faces.insert(Face { halfedge })
+        let f = self.faces.insert(Face { halfedge });
+        let order = self.next_order();
+        self.face_order.insert(f, order);
+        f
     }
 
     /// Removes a face from the mesh. This does not attempt to preserve mesh
     /// connectivity and should only be used as part of internal operations.
     fn remove_face(&mut self, face: FaceId) {
         self.faces.remove(face);
+        self.face_order.remove(face);
     }
 
     /// Removes a halfedge from the mesh. This does not attempt to preserve mesh
     /// connectivity and should only be used as part of internal operations.
     fn remove_halfedge(&mut self, halfedge: HalfEdgeId) {
         self.halfedges.remove(halfedge);
+        self.halfedge_order.remove(halfedge);
         self.debug_edges.remove(&halfedge);
     }
 
@@ -436,12 +504,16 @@ impl MeshConnectivity {
     /// connectivity and should only be used as part of internal operations.
     fn remove_vertex(&mut self, vertex: VertexId) {
         self.vertices.remove(vertex);
+        self.vertex_order.remove(vertex);
         self.debug_vertices.remove(&vertex);
     }
 
     /// Adds a new vertex to the mesh, disconnected from everything else. Returns its handle.
     fn alloc_halfedge(&mut self, halfedge: HalfEdge) -> HalfEdgeId {
-        self.halfedges.insert(halfedge)
+        let h = self.halfedges.insert(halfedge);
+        let order = self.next_order();
+        self.halfedge_order.insert(h, order);
+        h
     }
 
     pub fn vertex_debug_mark(&self, vertex: VertexId) -> Option<DebugMark> {
@@ -526,6 +598,7 @@ impl HalfEdgeMesh {
             default_channels,
             connectivity: InteriorMutable::new(MeshConnectivity::new()),
             gen_config: MeshGenerationConfig::default(),
+            spatial_index: InteriorMutable::new(None),
         }
     }
 
@@ -574,14 +647,46 @@ impl HalfEdgeMesh {
         })
     }
 
+    pub fn read_halfedge_normals(&self) -> Option<BorrowedRef<'_, Channel<HalfEdgeId, Vec3>>> {
+        self.default_channels.halfedge_normals.map(|ch_id| {
+            self.channels
+                .read_channel(ch_id)
+                .expect("Could not read halfedge normals")
+        })
+    }
+
     pub fn read_uvs(&self) -> Option<BorrowedRef<'_, Channel<HalfEdgeId, Vec3>>> {
-        self.default_channels.uvs.map(|ch_id| {
+        self.read_uv_channel(UvChannel::Main)
+    }
+
+    /// Returns the channel storing the given UV set, if the mesh has it.
+    pub fn read_uv_channel(
+        &self,
+        uv_ch: UvChannel,
+    ) -> Option<BorrowedRef<'_, Channel<HalfEdgeId, Vec3>>> {
+        self.default_channels.uv_channel(uv_ch).map(|ch_id| {
             self.channels
                 .read_channel(ch_id)
                 .expect("Could not read uvs")
         })
     }
 
+    /// Returns the `color` vertex channel, if the mesh has one. Colors are
+    /// stored as RGB values in the [0, 1] range.
+    pub fn read_vertex_colors(&self) -> Option<BorrowedRef<'_, Channel<VertexId, Vec3>>> {
+        self.channels
+            .read_channel_by_name::<VertexId, Vec3>("color")
+            .ok()
+    }
+
+    /// Returns the `material` face channel, with the material index of each
+    /// face, as written by `edit_ops::set_material`.
+    pub fn read_face_materials(&self) -> Option<BorrowedRef<'_, Channel<FaceId, i32>>> {
+        self.channels
+            .read_channel_by_name::<FaceId, i32>("material")
+            .ok()
+    }
+
     pub fn write_positions(&self) -> MutableRef<'_, Positions> {
         self.channels
             .write_channel(self.default_channels.position)
@@ -747,13 +852,13 @@ impl HalfEdgeMesh {
 
         // On a first pass, we reserve new vertices, faces and halfedges without
         // setting any of their pointers and store their ids in a mapping.
-        for (vertex_id, _vertex) in b_conn.iter_vertices() {
+        for (vertex_id, _vertex) in b_conn.iter_vertices_ordered() {
             vmap.insert(vertex_id, a_conn.alloc_vertex_raw(None));
         }
-        for (face_id, _) in b_conn.iter_faces() {
+        for (face_id, _) in b_conn.iter_faces_ordered() {
             fmap.insert(face_id, a_conn.alloc_face(None));
         }
-        for (halfedge_id, _) in b_conn.iter_halfedges() {
+        for (halfedge_id, _) in b_conn.iter_halfedges_ordered() {
             hmap.insert(
                 halfedge_id,
                 a_conn.alloc_halfedge(HalfEdge {
@@ -830,6 +935,62 @@ impl HalfEdgeMesh {
 
         self.channels.merge_with(&mesh_b.channels, get_ids, id_map)
     }
+
+    /// Merges this halfedge mesh with another one, like `merge_with`, and
+    /// then joins them at the seam: Each boundary vertex of `mesh_b` is welded
+    /// into the closest boundary vertex of this mesh, as long as they are
+    /// closer than `threshold`. Returns the number of welded vertices.
+    ///
+    /// Vertices are only welded across the two meshes, never within the same
+    /// one. When welding would produce non-manifold geometry, this fails and
+    /// the mesh is left unmodified.
+    pub fn merge_welded(&mut self, mesh_b: &HalfEdgeMesh, threshold: f32) -> Result<usize> {
+        let boundary_vertices = |mesh: &HalfEdgeMesh| {
+            mesh.read_connectivity()
+                .iter_halfedges()
+                .filter(|(_, h)| h.face.is_none())
+                .filter_map(|(_, h)| h.vertex)
+                .collect::<HashSet<_>>()
+        };
+        let own_vertices = self
+            .read_connectivity()
+            .iter_vertices()
+            .map(|(v, _)| v)
+            .collect::<HashSet<_>>();
+
+        let mut merged = self.clone();
+        merged.merge_with(mesh_b);
+
+        let mut welded_into = HashMap::<VertexId, VertexId>::new();
+        {
+            let positions = merged.read_positions();
+            let (own_boundary, other_boundary): (Vec<_>, Vec<_>) = boundary_vertices(&merged)
+                .into_iter()
+                .partition(|v| own_vertices.contains(v));
+            let tree = rstar::RTree::bulk_load(
+                own_boundary
+                    .iter()
+                    .map(|&vertex| VertexPos {
+                        vertex,
+                        pos: positions[vertex],
+                    })
+                    .collect_vec(),
+            );
+            let threshold2 = threshold.max(0.0).powi(2);
+            for v in other_boundary {
+                let pos = positions[v];
+                if let Some(nearest) = tree.nearest_neighbor(&pos.to_array()) {
+                    if nearest.pos.distance_squared(pos) <= threshold2 {
+                        welded_into.insert(v, nearest.vertex);
+                    }
+                }
+            }
+        }
+
+        let num_welds = edit_ops::weld_vertices(&mut merged, &welded_into)?;
+        *self = merged;
+        Ok(num_welds)
+    }
 }
 
 impl Default for HalfEdgeMesh {
@@ -63,16 +63,30 @@ impl Introspect for f32 {
     }
 }
 
+impl Introspect for i32 {
+    fn introspect(&self) -> String {
+        format!("{self: >6}")
+    }
+}
+
 impl Introspect for bool {
     fn introspect(&self) -> String {
         format!("{self: >6.3}")
     }
 }
 
+impl Introspect for String {
+    fn introspect(&self) -> String {
+        self.clone()
+    }
+}
+
 /// The value of a channel is the data that is associated to a specific key.
-/// Values can be scalars (f32) or vectors (Vec3).
+/// Values can be scalars (f32), integers (i32), booleans, vectors (Vec3) or
+/// strings. Strings are the only values that are not `Copy`, so generic code
+/// needs to clone values out of a channel.
 pub trait ChannelValue:
-    Default + Debug + Clone + Copy + Sized + FromToLua + Introspect + MaybeSync + 'static
+    Default + Debug + Clone + Sized + FromToLua + Introspect + MaybeSync + 'static
 {
     fn value_type() -> ChannelValueType;
     fn name() -> &'static str;
@@ -93,7 +107,9 @@ macro_rules! impl_channel_value {
 }
 impl_channel_value!(Vec3);
 impl_channel_value!(f32);
+impl_channel_value!(i32);
 impl_channel_value!(bool);
+impl_channel_value!(String);
 
 /// The `FromLua` and `ToLua` traits have a lifetime parameter which is
 /// unnecessary for the channel keys and values. We introduce this new trait
@@ -133,7 +149,9 @@ macro_rules! impl_from_to_lua {
 }
 impl_from_to_lua!(wrapped Vec3 LVec3);
 impl_from_to_lua!(flat f32);
+impl_from_to_lua!(flat i32);
 impl_from_to_lua!(flat bool);
+impl_from_to_lua!(flat String);
 impl_from_to_lua!(flat VertexId);
 impl_from_to_lua!(flat FaceId);
 impl_from_to_lua!(flat HalfEdgeId);
@@ -149,7 +167,7 @@ pub enum ChannelKeyType { VertexId, FaceId, HalfEdgeId }
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-pub enum ChannelValueType { Vec3, f32, bool }
+pub enum ChannelValueType { Vec3, f32, i32, bool, String }
 
 /// A channel represents a set of data that is associated over all the elements
 /// of a mesh. For instance, the well-known `position` channel of a mesh, is a
@@ -237,11 +255,48 @@ pub struct DefaultChannels {
     pub position: ChannelId<VertexId, Vec3>,
     pub vertex_normals: Option<ChannelId<VertexId, Vec3>>,
     pub face_normals: Option<ChannelId<FaceId, Vec3>>,
+    /// Per-corner normals, stored in the outgoing halfedges like the UVs. When
+    /// present, these take precedence over the vertex and face normals. They
+    /// allow mixing smooth and sharp edges in the same mesh.
+    pub halfedge_normals: Option<ChannelId<HalfEdgeId, Vec3>>,
     /// There are no Vec2 channels. Uvs simply use the first two coordinates.
     /// You can store different UVs for every face a vertex belongs to. We use
     /// the outgoing halfedges to represent this relation and store UVs in them
     /// instead.
     pub uvs: Option<ChannelId<HalfEdgeId, Vec3>>,
+    /// The secondary UV set. Stored like `uvs`. See [`UvChannel::Lightmap`].
+    pub lightmap_uvs: Option<ChannelId<HalfEdgeId, Vec3>>,
+}
+
+/// The UV sets a mesh can have. Each of them is stored in its own halfedge
+/// channel, and exported as a separate set of texture coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UvChannel {
+    /// The main UVs, used for texturing. Stored in the `uv` channel.
+    Main,
+    /// A secondary UV set, stored in the `uv_lightmap` channel. Typically,
+    /// these UVs don't overlap, so they can be used to bake lightmaps.
+    Lightmap,
+}
+
+impl UvChannel {
+    pub const ALL: [UvChannel; 2] = [UvChannel::Main, UvChannel::Lightmap];
+
+    /// The name of the halfedge channel storing this UV set.
+    pub fn channel_name(self) -> &'static str {
+        match self {
+            UvChannel::Main => "uv",
+            UvChannel::Lightmap => "uv_lightmap",
+        }
+    }
+
+    /// Returns the UV set stored in the channel with the given `name`.
+    pub fn from_channel_name(name: &str) -> Result<Self> {
+        UvChannel::ALL
+            .into_iter()
+            .find(|uv_ch| uv_ch.channel_name() == name)
+            .ok_or_else(|| anyhow!("Invalid UV channel: {name}"))
+    }
 }
 
 impl<K: ChannelKey, V: ChannelValue> std::ops::Index<K> for Channel<K, V> {
@@ -322,6 +377,14 @@ pub trait DynChannel: Any + Debug {
     where
         'lua: 'a;
 
+    /// Returns the key with the given `ffi` representation and its value,
+    /// converted to Lua values.
+    fn get_pair_lua<'lua>(
+        &self,
+        lua: &'lua mlua::Lua,
+        ffi: u64,
+    ) -> (mlua::Value<'lua>, mlua::Value<'lua>);
+
     /// Returns this channel as a Lua table (sequence). When Lua code wants to
     /// modify a full channel, it is generally faster to convert the channel to
     /// a table, let Lua manipulate it freely and then set it back using the
@@ -369,6 +432,15 @@ pub trait DynChannel: Any + Debug {
         get_ids: &dyn Fn(ChannelKeyType) -> Rc<Vec<slotmap::KeyData>>,
         id_map: &dyn Fn(ChannelKeyType, slotmap::KeyData) -> slotmap::KeyData,
     );
+
+    /// For each `(from, to)` pair of keys, given in their `ffi`
+    /// representation, copies the value at `from` to `to`. All the values are
+    /// read before writing any of them, so the pairs may overlap.
+    fn copy_values_dyn(&mut self, pairs: &[(u64, u64)]);
+
+    /// Same as `copy_values_dyn`, but the values are read from `other`. This
+    /// method will panic if both channels are not of the same type.
+    fn copy_values_from_dyn(&mut self, other: &dyn DynChannel, pairs: &[(u64, u64)]);
 }
 impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
     fn as_any(&self) -> &dyn Any {
@@ -388,7 +460,7 @@ impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
         'lua: 'a,
     {
         let key: K = K::cast_from_lua(key, lua)?;
-        Ok(self[key].cast_to_lua(lua))
+        Ok(self[key].clone().cast_to_lua(lua))
     }
 
     fn set_lua<'a, 'lua>(
@@ -405,13 +477,25 @@ impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
         Ok(())
     }
 
+    fn get_pair_lua<'lua>(
+        &self,
+        lua: &'lua mlua::Lua,
+        ffi: u64,
+    ) -> (mlua::Value<'lua>, mlua::Value<'lua>) {
+        let k = K::cast_from_ffi(ffi);
+        (k.cast_to_lua(lua), self[k].clone().cast_to_lua(lua))
+    }
+
     fn to_seq_table<'lua>(
         &self,
         keys: Box<dyn Iterator<Item = u64> + '_>,
         lua: &'lua mlua::Lua,
     ) -> mlua::Table<'lua> {
-        lua.create_sequence_from(keys.map(K::cast_from_ffi).map(|k| self[k].cast_to_lua(lua)))
-            .unwrap()
+        lua.create_sequence_from(
+            keys.map(K::cast_from_ffi)
+                .map(|k| self[k].clone().cast_to_lua(lua)),
+        )
+        .unwrap()
     }
 
     fn set_from_seq_table<'lua>(
@@ -436,7 +520,7 @@ impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
     ) -> mlua::Table<'lua> {
         lua.create_table_from(
             keys.map(K::cast_from_ffi)
-                .map(|k| (k.cast_to_lua(lua), self[k].cast_to_lua(lua))),
+                .map(|k| (k.cast_to_lua(lua), self[k].clone().cast_to_lua(lua))),
         )
         .unwrap()
     }
@@ -466,7 +550,7 @@ impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
             for id in get_ids(K::key_type()).iter_cpy() {
                 let k_self = K::cast_from_ffi(id_map(K::key_type(), id).as_ffi());
                 let k_other = K::cast_from_ffi(id.as_ffi());
-                self[k_self] = other[k_other];
+                self[k_self] = other[k_other].clone();
             }
         } else {
             panic!(
@@ -474,6 +558,28 @@ impl<K: ChannelKey, V: ChannelValue> DynChannel for Channel<K, V> {
             )
         }
     }
+
+    fn copy_values_dyn(&mut self, pairs: &[(u64, u64)]) {
+        let values = pairs
+            .iter()
+            .map(|(from, _)| self[K::cast_from_ffi(*from)].clone())
+            .collect::<Vec<_>>();
+        for ((_, to), value) in pairs.iter().zip(values) {
+            self[K::cast_from_ffi(*to)] = value;
+        }
+    }
+
+    fn copy_values_from_dyn(&mut self, other: &dyn DynChannel, pairs: &[(u64, u64)]) {
+        if let Some(other) = other.as_any().downcast_ref::<Self>() {
+            for (from, to) in pairs {
+                self[K::cast_from_ffi(*to)] = other[K::cast_from_ffi(*from)].clone();
+            }
+        } else {
+            panic!(
+                "Tried to copy values between channels with different types. This should never happen."
+            )
+        }
+    }
 }
 
 impl<K: ChannelKey, V: ChannelValue> ChannelGroup<K, V> {
@@ -516,6 +622,37 @@ impl<K: ChannelKey, V: ChannelValue> ChannelGroup<K, V> {
         .into_inner())
     }
 
+    /// Copies the contents of the channel named `from` into the channel named
+    /// `to`, creating it if it doesn't exist. Any previous contents of `to` are
+    /// overwritten. Returns the id of the `to` channel.
+    pub fn copy_channel(&mut self, from: &str, to: &str) -> Result<ChannelId<K, V>> {
+        let src_id = self
+            .channel_id(from)
+            .ok_or_else(|| anyhow!("Channel named {from} does not exist"))?;
+        let contents: Channel<K, V> = self.read_channel(src_id)?.clone();
+        let dst_id = self.ensure_channel(to);
+        *self.write_channel(dst_id)? = contents;
+        Ok(dst_id)
+    }
+
+    /// Renames the channel named `old` to `new`. The channel id stays the same.
+    /// Returns an error if `old` doesn't exist or `new` is already taken.
+    pub fn rename_channel(&mut self, old: &str, new: &str) -> Result<()> {
+        if !self.channel_names.contains_left(old) {
+            bail!("Channel named {old} does not exist");
+        }
+        if old == new {
+            return Ok(());
+        }
+        if self.channel_names.contains_left(new) {
+            bail!("The channel named {new} already exists in mesh");
+        }
+        if let Some((_, id)) = self.channel_names.remove_by_left(old) {
+            self.channel_names.insert(new.into(), id);
+        }
+        Ok(())
+    }
+
     /// Returns the channel id for a channel with given `name`, or `None` if it
     /// doesn't exist.
     pub fn channel_id(&self, name: &str) -> Option<ChannelId<K, V>> {
@@ -572,6 +709,13 @@ pub trait DynChannelGroup: Any + Debug + dyn_clone::DynClone + MaybeSync {
     fn channel_rc_dyn(&self, raw_id: RawChannelId) -> RefCounted<InteriorMutable<dyn DynChannel>>;
     /// Returns the names of the channels present in this group
     fn channel_names(&self) -> Box<dyn Iterator<Item = &str> + '_>;
+    /// Same as `copy_channel`, but with erased types.
+    fn copy_channel_dyn(&mut self, from: &str, to: &str) -> Result<RawChannelId>;
+    /// Same as `rename_channel`, but with erased types.
+    fn rename_channel_dyn(&mut self, old: &str, new: &str) -> Result<()>;
+    /// Same as `remove_channel`, but with erased types. Takes the channel
+    /// `name` instead of its id.
+    fn remove_channel_dyn(&mut self, name: &str) -> Result<()>;
 }
 
 impl<K: ChannelKey, V: ChannelValue> Clone for ChannelGroup<K, V> {
@@ -606,10 +750,7 @@ impl<K: ChannelKey, V: ChannelValue> DynChannelGroup for ChannelGroup<K, V> {
             let ch = self.read_channel(*id).unwrap();
             result.insert(
                 name.into(),
-                keys.iter()
-                    .map(|k| ch[K::from(*k)])
-                    .map(|x| x.introspect())
-                    .collect(),
+                keys.iter().map(|k| ch[K::from(*k)].introspect()).collect(),
             );
         }
         result
@@ -649,6 +790,22 @@ impl<K: ChannelKey, V: ChannelValue> DynChannelGroup for ChannelGroup<K, V> {
     fn channel_names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
         Box::new(self.channel_names.iter().map(|(l, _)| l.as_str()))
     }
+
+    fn copy_channel_dyn(&mut self, from: &str, to: &str) -> Result<RawChannelId> {
+        Ok(self.copy_channel(from, to)?.raw)
+    }
+
+    fn rename_channel_dyn(&mut self, old: &str, new: &str) -> Result<()> {
+        self.rename_channel(old, new)
+    }
+
+    fn remove_channel_dyn(&mut self, name: &str) -> Result<()> {
+        let id = self
+            .channel_id(name)
+            .ok_or_else(|| anyhow!("Channel named {name} does not exist"))?;
+        self.remove_channel(id)?;
+        Ok(())
+    }
 }
 
 impl MeshChannels {
@@ -722,6 +879,24 @@ impl MeshChannels {
         self.group_mut()?.remove_channel(ch_id)
     }
 
+    /// Calls `copy_channel` for the channel group with key and value type
+    pub fn copy_channel<K: ChannelKey, V: ChannelValue>(
+        &mut self,
+        from: &str,
+        to: &str,
+    ) -> Result<ChannelId<K, V>> {
+        self.group_mut()?.copy_channel(from, to)
+    }
+
+    /// Calls `rename_channel` for the channel group with key and value type
+    pub fn rename_channel<K: ChannelKey, V: ChannelValue>(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> Result<()> {
+        self.group_mut()?.rename_channel(old, new)
+    }
+
     /// Calls `read_channel` for the channel group with key and value type
     pub fn read_channel<K: ChannelKey, V: ChannelValue>(
         &self,
@@ -791,13 +966,19 @@ impl MeshChannels {
         do_match! {
             VertexId, Vec3;
             VertexId, f32;
+            VertexId, i32;
             VertexId, bool;
+            VertexId, String;
             FaceId, Vec3;
             FaceId, f32;
+            FaceId, i32;
             FaceId, bool;
+            FaceId, String;
             HalfEdgeId, Vec3;
             HalfEdgeId, f32;
-            HalfEdgeId, bool
+            HalfEdgeId, i32;
+            HalfEdgeId, bool;
+            HalfEdgeId, String
         }
     }
 
@@ -813,6 +994,51 @@ impl MeshChannels {
         group.ensure_channel_dyn(name)
     }
 
+    /// Calls `copy_channel` for a group with dynamic key and value types given
+    /// at runtime.
+    pub fn copy_channel_dyn(
+        &mut self,
+        kty: ChannelKeyType,
+        vty: ChannelValueType,
+        from: &str,
+        to: &str,
+    ) -> Result<RawChannelId> {
+        self.channels
+            .get_mut(&(kty, vty))
+            .ok_or_else(|| anyhow!("Channel named {from} does not exist"))?
+            .copy_channel_dyn(from, to)
+    }
+
+    /// Calls `rename_channel` for a group with dynamic key and value types
+    /// given at runtime.
+    pub fn rename_channel_dyn(
+        &mut self,
+        kty: ChannelKeyType,
+        vty: ChannelValueType,
+        old: &str,
+        new: &str,
+    ) -> Result<()> {
+        self.channels
+            .get_mut(&(kty, vty))
+            .ok_or_else(|| anyhow!("Channel named {old} does not exist"))?
+            .rename_channel_dyn(old, new)
+    }
+
+    /// Removes the channel with given `name` from the group with dynamic key
+    /// and value types given at runtime. Returns an error if the channel
+    /// doesn't exist or is still referenced somewhere else.
+    pub fn remove_channel_by_name(
+        &mut self,
+        kty: ChannelKeyType,
+        vty: ChannelValueType,
+        name: &str,
+    ) -> Result<()> {
+        self.channels
+            .get_mut(&(kty, vty))
+            .ok_or_else(|| anyhow!("Channel named {name} does not exist"))?
+            .remove_channel_dyn(name)
+    }
+
     /// Calls `read_channel` for a group with dynamic key and value
     /// types given at runtime.
     pub fn dyn_read_channel(
@@ -923,6 +1149,15 @@ impl MeshChannels {
         self.group().ok()?.channel_name(ch_id)
     }
 
+    /// Returns the names of all the channels with the given key and value
+    /// types.
+    pub fn channel_names<K: ChannelKey, V: ChannelValue>(&self) -> impl Iterator<Item = &str> + '_ {
+        self.group::<K, V>()
+            .ok()
+            .into_iter()
+            .flat_map(|group| group.channel_names.iter().map(|(name, _)| name.as_str()))
+    }
+
     /// Used to inspect the contents of this `MeshChannels`, for UI display
     pub fn introspect(
         &self,
@@ -958,6 +1193,62 @@ impl MeshChannels {
         }
     }
 
+    /// For each `(from, to)` pair of keys, copies the value at `from` to `to`
+    /// in every channel keyed by `K`. This is used by operations that create
+    /// new elements, so they inherit the data of existing ones.
+    pub fn copy_values<K: ChannelKey>(&self, pairs: &[(K, K)]) {
+        let pairs = pairs
+            .iter()
+            .map(|(from, to)| (from.data().as_ffi(), to.data().as_ffi()))
+            .collect::<Vec<_>>();
+        for ((kty, _), group) in self.channels.iter() {
+            if *kty != K::key_type() {
+                continue;
+            }
+            for ch_name in group.channel_names() {
+                let id = group
+                    .channel_id_dyn(ch_name)
+                    .expect("We know it exists because we're iterating the channel names");
+                group.write_channel_dyn(id).copy_values_dyn(&pairs);
+            }
+        }
+    }
+
+    /// Same as `copy_values`, but the values are read from the channels of
+    /// `other`, which may belong to a different mesh, and written to the
+    /// channels with the same name and type in `self`. Missing channels are
+    /// created. Channels for which `skip` returns true are ignored.
+    pub fn copy_values_from<K: ChannelKey>(
+        &mut self,
+        other: &Self,
+        pairs: &[(K, K)],
+        skip: impl Fn(ChannelValueType, &str) -> bool,
+    ) {
+        let pairs = pairs
+            .iter()
+            .map(|(from, to)| (from.data().as_ffi(), to.data().as_ffi()))
+            .collect::<Vec<_>>();
+        for ((kty, vty), other_group) in other.channels.iter() {
+            if *kty != K::key_type() {
+                continue;
+            }
+            let self_group = self.ensure_group_dyn(*kty, *vty);
+            for ch_name in other_group.channel_names() {
+                if skip(*vty, ch_name) {
+                    continue;
+                }
+                let other_id = other_group
+                    .channel_id_dyn(ch_name)
+                    .expect("We know it exists because we're iterating the channel names");
+                let self_id = self_group.ensure_channel_dyn(ch_name);
+                let other_ch = other_group.read_channel_dyn(other_id);
+                self_group
+                    .write_channel_dyn(self_id)
+                    .copy_values_from_dyn(other_ch.deref(), &pairs);
+            }
+        }
+    }
+
     /// Sets a channel directly, by name. If the channel doesn't exist, it is
     /// created, otherwise its contents are dropped and the new channel data is
     /// used. Returns the id of the channel that was created.
@@ -981,7 +1272,25 @@ impl DefaultChannels {
             position,
             vertex_normals: None,
             face_normals: None,
+            halfedge_normals: None,
             uvs: None,
+            lightmap_uvs: None,
+        }
+    }
+
+    /// Returns the id of the channel storing the given UV set, if any.
+    pub fn uv_channel(&self, uv_ch: UvChannel) -> Option<ChannelId<HalfEdgeId, Vec3>> {
+        match uv_ch {
+            UvChannel::Main => self.uvs,
+            UvChannel::Lightmap => self.lightmap_uvs,
+        }
+    }
+
+    /// Sets the id of the channel storing the given UV set.
+    pub fn set_uv_channel(&mut self, uv_ch: UvChannel, ch_id: Option<ChannelId<HalfEdgeId, Vec3>>) {
+        match uv_ch {
+            UvChannel::Main => self.uvs = ch_id,
+            UvChannel::Lightmap => self.lightmap_uvs = ch_id,
         }
     }
 }
@@ -1104,6 +1413,111 @@ mod test {
         drop(dyn_pos);
     }
 
+    #[test]
+    pub fn test_i32_channel() {
+        let mut faces: slotmap::SlotMap<FaceId, ()> = slotmap::SlotMap::with_key();
+        let f1 = faces.insert(());
+
+        let mut mesh_channels = MeshChannels::default();
+
+        // Integer channels can store values that can't be exactly represented
+        // as an f32
+        let raw_id =
+            mesh_channels.ensure_channel_dyn(ChannelKeyType::FaceId, ChannelValueType::i32, "id");
+        let id = ChannelId::<FaceId, i32>::new(raw_id);
+        mesh_channels.write_channel(id).unwrap()[f1] = 16_777_217;
+        assert_eq!(mesh_channels.read_channel(id).unwrap()[f1], 16_777_217);
+
+        use slotmap::Key;
+        let introspected = mesh_channels.introspect(move |k| match k {
+            ChannelKeyType::FaceId => Rc::new(vec![f1.data()]),
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            &introspected[&(ChannelKeyType::FaceId, ChannelValueType::i32)]["id"],
+            &["16777217"]
+        );
+    }
+
+    #[test]
+    pub fn test_string_channel() {
+        let mut faces: slotmap::SlotMap<FaceId, ()> = slotmap::SlotMap::with_key();
+        let f1 = faces.insert(());
+        let f2 = faces.insert(());
+
+        let mut mesh_channels = MeshChannels::default();
+        let names = mesh_channels.ensure_channel::<FaceId, String>("name");
+        mesh_channels.write_channel(names).unwrap()[f1] = "roof".into();
+
+        // Strings are introspected as-is, and unset values are empty
+        use slotmap::Key;
+        let introspected = mesh_channels.introspect(move |k| match k {
+            ChannelKeyType::FaceId => Rc::new(vec![f1.data(), f2.data()]),
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            &introspected[&(ChannelKeyType::FaceId, ChannelValueType::String)]["name"],
+            &["roof", ""]
+        );
+
+        let lua = Lua::new();
+        let dyn_names = mesh_channels
+            .dyn_read_channel_by_name(ChannelKeyType::FaceId, ChannelValueType::String, "name")
+            .unwrap();
+        match dyn_names.get_lua(&lua, f1.cast_to_lua(&lua)).unwrap() {
+            mlua::Value::String(s) if s == "roof" => {}
+            _ => panic!("Expected the string 'roof'"),
+        }
+    }
+
+    #[test]
+    pub fn test_copy_rename_channel() {
+        let mut vertices: slotmap::SlotMap<VertexId, ()> = slotmap::SlotMap::with_key();
+        let v1 = vertices.insert(());
+
+        let mut mesh_channels = MeshChannels::default();
+        let position = mesh_channels
+            .create_channel::<VertexId, Vec3>("position")
+            .unwrap();
+        mesh_channels.write_channel(position).unwrap()[v1] = Vec3::X;
+
+        // Copies are independent from the original channel
+        let rest = mesh_channels
+            .copy_channel::<VertexId, Vec3>("position", "rest_position")
+            .unwrap();
+        mesh_channels.write_channel(position).unwrap()[v1] = Vec3::Y;
+        assert_eq!(mesh_channels.read_channel(rest).unwrap()[v1], Vec3::X);
+
+        // Renaming keeps the same channel id
+        mesh_channels
+            .rename_channel::<VertexId, Vec3>("rest_position", "rest")
+            .unwrap();
+        assert_eq!(
+            mesh_channels.channel_id::<VertexId, Vec3>("rest"),
+            Some(rest)
+        );
+        assert!(mesh_channels
+            .channel_id::<VertexId, Vec3>("rest_position")
+            .is_none());
+
+        // Renaming to an existing name or from a missing one is an error
+        assert!(mesh_channels
+            .rename_channel::<VertexId, Vec3>("rest", "position")
+            .is_err());
+        assert!(mesh_channels
+            .rename_channel_dyn(ChannelKeyType::VertexId, ChannelValueType::f32, "a", "b")
+            .is_err());
+
+        // Channels can be removed by name
+        mesh_channels
+            .remove_channel_by_name(ChannelKeyType::VertexId, ChannelValueType::Vec3, "rest")
+            .unwrap();
+        assert!(mesh_channels.channel_id::<VertexId, Vec3>("rest").is_none());
+        assert!(mesh_channels
+            .remove_channel_by_name(ChannelKeyType::VertexId, ChannelValueType::Vec3, "rest")
+            .is_err());
+    }
+
     #[test]
     pub fn test_ensure_channel() {
         let mut mesh_channels = MeshChannels::default();
@@ -41,9 +41,283 @@ pub struct CompactMesh<const Subdivided: bool> {
     pub edge: Vec<u32>,
     pub face: Vec<u32>,
     pub vertex_positions: Vec<Vec3>,
+    /// The crease sharpness of each edge, following the OpenSubdiv semantics:
+    /// Edges with sharpness `s` are kept sharp for the next `s` subdivision
+    /// iterations, and fractional sharpness blends between the sharp and
+    /// smooth rules. Empty when the mesh has no creases.
+    pub edge_sharpness: Vec<f32>,
+    /// Whether each edge is marked as sharp. Sharp edges are subdivided as
+    /// infinitely sharp creases, and the edges they are split into stay
+    /// marked. Empty when the mesh has no sharp edges.
+    pub edge_sharp: Vec<bool>,
+    /// The rest of the mesh channels, which are carried through subdivision.
+    pub channels: CompactChannels,
     pub counts: MeshCounts,
 }
 
+/// The values of a mesh channel, stored in the same order as the vertices,
+/// halfedges or faces of a [`CompactMesh`].
+#[derive(Clone, Debug)]
+pub enum CompactValues {
+    Vec3(Vec<Vec3>),
+    F32(Vec<f32>),
+    I32(Vec<i32>),
+    Bool(Vec<bool>),
+    String(Vec<String>),
+}
+
+/// Applies `$f` to the vector inside a [`CompactValues`], returning a new
+/// [`CompactValues`] of the same variant.
+macro_rules! map_compact_values {
+    ($values:expr, $f:expr) => {
+        match $values {
+            CompactValues::Vec3(v) => CompactValues::Vec3($f(v)),
+            CompactValues::F32(v) => CompactValues::F32($f(v)),
+            CompactValues::I32(v) => CompactValues::I32($f(v)),
+            CompactValues::Bool(v) => CompactValues::Bool($f(v)),
+            CompactValues::String(v) => CompactValues::String($f(v)),
+        }
+    };
+}
+
+/// The channels of a mesh, other than positions, normals, creases and sharp
+/// edges, stored by name for each key type. Normals are not stored because
+/// they would no longer be valid after subdivision.
+#[derive(Clone, Debug, Default)]
+pub struct CompactChannels {
+    pub vertex: Vec<(String, CompactValues)>,
+    pub halfedge: Vec<(String, CompactValues)>,
+    pub face: Vec<(String, CompactValues)>,
+    /// The names of the halfedge channels used as the mesh UV sets.
+    pub uvs: Vec<(UvChannel, String)>,
+}
+
+/// Channel values that can be carried through subdivision.
+trait SubdivisionValue: ChannelValue {
+    /// Returns the value for a new element created from `values`. Numeric
+    /// values are averaged, while the rest take the first value.
+    fn combine(values: &[&Self]) -> Self;
+}
+
+macro_rules! impl_subdivision_value {
+    (average $t:ty) => {
+        impl SubdivisionValue for $t {
+            fn combine(values: &[&Self]) -> Self {
+                values.iter().fold(<$t>::default(), |acc, x| acc + **x) / values.len() as f32
+            }
+        }
+    };
+    (first $t:ty) => {
+        impl SubdivisionValue for $t {
+            fn combine(values: &[&Self]) -> Self {
+                values.first().map(|x| (*x).clone()).unwrap_or_default()
+            }
+        }
+    };
+}
+impl_subdivision_value!(average Vec3);
+impl_subdivision_value!(average f32);
+impl_subdivision_value!(first i32);
+impl_subdivision_value!(first bool);
+impl_subdivision_value!(first String);
+
+impl CompactChannels {
+    /// Reads the channels of `mesh`. The id slices contain the ids for each
+    /// index in the compact mesh.
+    fn from_halfedge(
+        mesh: &HalfEdgeMesh,
+        v_ids: &[VertexId],
+        h_ids: &[HalfEdgeId],
+        f_ids: &[FaceId],
+    ) -> Result<Self> {
+        fn read_values<K: ChannelKey, V: ChannelValue>(
+            channels: &MeshChannels,
+            name: &str,
+            ids: &[K],
+        ) -> Result<Vec<V>> {
+            let ch = channels.read_channel_by_name::<K, V>(name)?;
+            Ok(ids.iter().map(|id| ch[*id].clone()).collect())
+        }
+
+        let channels = &mesh.channels;
+        let default_ch = &mesh.default_channels;
+        let vertex_normals = default_ch
+            .vertex_normals
+            .and_then(|ch_id| channels.channel_name(ch_id));
+        let face_normals = default_ch
+            .face_normals
+            .and_then(|ch_id| channels.channel_name(ch_id));
+        let halfedge_normals = default_ch
+            .halfedge_normals
+            .and_then(|ch_id| channels.channel_name(ch_id));
+        let position = channels.channel_name(default_ch.position);
+
+        let skip_vertex = |vty: ChannelValueType, name: &str| {
+            vty == ChannelValueType::Vec3
+                && (Some(name) == position || Some(name) == vertex_normals)
+        };
+        let skip_halfedge = |vty: ChannelValueType, name: &str| {
+            (vty == ChannelValueType::f32 && name == CREASE_CHANNEL)
+                || (vty == ChannelValueType::bool && name == SHARP_CHANNEL)
+                || (vty == ChannelValueType::Vec3 && Some(name) == halfedge_normals)
+        };
+        let skip_face = |vty: ChannelValueType, name: &str| {
+            vty == ChannelValueType::Vec3 && Some(name) == face_normals
+        };
+
+        let mut result = Self {
+            uvs: UvChannel::ALL
+                .iter()
+                .filter_map(|uv_ch| {
+                    let ch_id = default_ch.uv_channel(*uv_ch)?;
+                    Some((*uv_ch, channels.channel_name(ch_id)?.to_owned()))
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        macro_rules! read_channels {
+            ($list:ident, $ids:expr, $key:ty, $skip:ident, $($value:ty => $variant:ident),*) => {$(
+                for name in channels.channel_names::<$key, $value>() {
+                    if !$skip(<$value>::value_type(), name) {
+                        let values = read_values::<$key, $value>(channels, name, $ids)?;
+                        result.$list.push((name.to_owned(), CompactValues::$variant(values)));
+                    }
+                }
+            )*};
+        }
+        read_channels!(
+            vertex, v_ids, VertexId, skip_vertex,
+            Vec3 => Vec3, f32 => F32, i32 => I32, bool => Bool, String => String
+        );
+        read_channels!(
+            halfedge, h_ids, HalfEdgeId, skip_halfedge,
+            Vec3 => Vec3, f32 => F32, i32 => I32, bool => Bool, String => String
+        );
+        read_channels!(
+            face, f_ids, FaceId, skip_face,
+            Vec3 => Vec3, f32 => F32, i32 => I32, bool => Bool, String => String
+        );
+
+        Ok(result)
+    }
+
+    /// Writes the channels into `mesh`. The id slices contain the ids for each
+    /// index in the compact mesh.
+    fn write_to(
+        &self,
+        mesh: &mut HalfEdgeMesh,
+        v_ids: &[VertexId],
+        h_ids: &[HalfEdgeId],
+        f_ids: &[FaceId],
+    ) {
+        fn write_values<K: ChannelKey, V: ChannelValue>(
+            mesh: &mut HalfEdgeMesh,
+            name: &str,
+            ids: &[K],
+            values: &[V],
+        ) {
+            let ch_id = mesh.channels.ensure_channel::<K, V>(name);
+            let mut ch = mesh
+                .channels
+                .write_channel(ch_id)
+                .expect("Channel was just created");
+            for (id, value) in ids.iter().zip(values) {
+                ch[*id] = value.clone();
+            }
+        }
+
+        macro_rules! write_channels {
+            ($list:ident, $ids:expr) => {
+                for (name, values) in &self.$list {
+                    match values {
+                        CompactValues::Vec3(v) => write_values(mesh, name, $ids, v),
+                        CompactValues::F32(v) => write_values(mesh, name, $ids, v),
+                        CompactValues::I32(v) => write_values(mesh, name, $ids, v),
+                        CompactValues::Bool(v) => write_values(mesh, name, $ids, v),
+                        CompactValues::String(v) => write_values(mesh, name, $ids, v),
+                    }
+                }
+            };
+        }
+        write_channels!(vertex, v_ids);
+        write_channels!(halfedge, h_ids);
+        write_channels!(face, f_ids);
+
+        for (uv_ch, name) in &self.uvs {
+            let ch_id = mesh.channels.channel_id::<HalfEdgeId, Vec3>(name);
+            mesh.default_channels.set_uv_channel(*uv_ch, ch_id);
+        }
+    }
+}
+
+/// The name of the halfedge channel storing crease weights.
+pub const CREASE_CHANNEL: &str = "crease";
+
+/// The name of the halfedge channel marking sharp edges.
+pub const SHARP_CHANNEL: &str = "sharp";
+
+/// Converts a crease weight, as stored in the crease channel, into an edge
+/// sharpness. The mapping is `w / (1 - w)`, so weights grow continuously into
+/// the number of subdivision levels the edge stays sharp for: A weight of 0.5
+/// gives a sharpness of 1.0, a weight of 0.9 gives a sharpness of 9.0, and a
+/// weight of 1.0 represents an infinitely sharp edge.
+fn weight_to_sharpness(weight: f32) -> f32 {
+    let weight = weight.max(0.0);
+    if weight >= 1.0 {
+        f32::INFINITY
+    } else {
+        weight / (1.0 - weight)
+    }
+}
+
+/// Converts an edge sharpness back into a crease weight. This is the inverse
+/// of `weight_to_sharpness`.
+fn sharpness_to_weight(sharpness: f32) -> f32 {
+    if sharpness.is_infinite() {
+        1.0
+    } else {
+        sharpness / (1.0 + sharpness)
+    }
+}
+
+/// Accumulated crease information for a vertex, used to pick the subdivision
+/// rule for that vertex.
+#[derive(Clone, Copy, Default)]
+struct VertexCreases {
+    /// Number of creased edges around the vertex.
+    count: u32,
+    /// Sum of the sharpness of the creased edges around the vertex.
+    sharpness_sum: f32,
+    /// Sum of the positions at the other end of the creased edges.
+    neighbor_sum: Vec3,
+}
+
+impl VertexCreases {
+    /// Returns the position given by the crease or corner rules for a vertex at
+    /// `position`, and the weight this position should be blended with when
+    /// combined with the smooth rule.
+    fn sharp_rule(&self, position: Vec3) -> (Vec3, f32) {
+        match self.count {
+            0 | 1 => (position, 0.0),
+            2 => (
+                (6.0 * position + self.neighbor_sum) / 8.0,
+                (self.sharpness_sum / 2.0).min(1.0),
+            ),
+            n => (position, (self.sharpness_sum / n as f32).min(1.0)),
+        }
+    }
+
+    /// Combines the creases of two sets of edges around the same vertex.
+    fn merged(&self, other: &VertexCreases) -> VertexCreases {
+        VertexCreases {
+            count: self.count + other.count,
+            sharpness_sum: self.sharpness_sum + other.sharpness_sum,
+            neighbor_sum: self.neighbor_sum + other.neighbor_sum,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MeshCounts {
     /// The number of vertices
@@ -177,6 +451,53 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
             .map(|(v_id, _)| positions[v_id])
             .collect();
 
+        // --- Edge creases ---
+
+        let mut edge_sharpness = vec![];
+        if let Ok(creases) = mesh
+            .channels
+            .read_channel_by_name::<HalfEdgeId, f32>(CREASE_CHANNEL)
+        {
+            edge_sharpness = vec![0.0; edge_id_counter as usize];
+            for (h_id, _) in h_id_to_idx.iter() {
+                // Either halfedge of the pair can be used to set the crease.
+                let twin = conn.at_halfedge(h_id).twin().try_end()?;
+                let weight = creases[h_id].max(creases[twin]);
+                let sharpness = &mut edge_sharpness[h_id_to_edge[h_id] as usize];
+                *sharpness = sharpness.max(weight_to_sharpness(weight));
+            }
+            if edge_sharpness.iter().all(|s| *s == 0.0) {
+                edge_sharpness.clear();
+            }
+        }
+
+        let mut edge_sharp = vec![];
+        if let Ok(sharp) = mesh
+            .channels
+            .read_channel_by_name::<HalfEdgeId, bool>(SHARP_CHANNEL)
+        {
+            edge_sharp = vec![false; edge_id_counter as usize];
+            for (h_id, _) in h_id_to_idx.iter() {
+                // Either halfedge of the pair can be marked.
+                let twin = conn.at_halfedge(h_id).twin().try_end()?;
+                if sharp[h_id] || sharp[twin] {
+                    edge_sharp[h_id_to_edge[h_id] as usize] = true;
+                }
+            }
+            if !edge_sharp.contains(&true) {
+                edge_sharp.clear();
+            }
+        }
+
+        // --- Other channels ---
+
+        let channels = CompactChannels::from_halfedge(
+            mesh,
+            &v_id_to_idx.keys().collect_vec(),
+            &h_id_to_idx.keys().collect_vec(),
+            &f_id_to_idx.keys().collect_vec(),
+        )?;
+
         Ok(CompactMesh {
             twin,
             next,
@@ -185,6 +506,9 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
             edge,
             face,
             vertex_positions,
+            edge_sharpness,
+            edge_sharp,
+            channels,
             counts: MeshCounts {
                 num_halfedges,
                 num_vertices,
@@ -198,7 +522,7 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
 
     #[profiling::function]
     pub fn to_halfedge(&self) -> HalfEdgeMesh {
-        let mesh = HalfEdgeMesh::new();
+        let mut mesh = HalfEdgeMesh::new();
         let mut conn = mesh.write_connectivity();
         let mut positions = mesh.write_positions();
 
@@ -248,6 +572,51 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
 
         drop(conn);
         drop(positions);
+
+        self.channels
+            .write_to(&mut mesh, &v_idx_to_id, &h_idx_to_id, &f_idx_to_id);
+
+        // Store the creases that are still active, so they can be used if the
+        // mesh is subdivided again.
+        if !self.edge_sharpness.is_empty() {
+            let ch_id = mesh
+                .channels
+                .ensure_channel::<HalfEdgeId, f32>(CREASE_CHANNEL);
+            let conn = mesh.read_connectivity();
+            let mut creases = mesh
+                .channels
+                .write_channel(ch_id)
+                .expect("Channel was just created");
+            for (h, h_id) in h_idx_to_id.iter().enumerate() {
+                let weight = sharpness_to_weight(self.edge_sharpness[self.edge[h] as usize]);
+                if weight > 0.0 {
+                    creases[*h_id] = weight;
+                    if let Some(twin) = conn[*h_id].twin {
+                        creases[twin] = weight;
+                    }
+                }
+            }
+        }
+
+        if !self.edge_sharp.is_empty() {
+            let ch_id = mesh
+                .channels
+                .ensure_channel::<HalfEdgeId, bool>(SHARP_CHANNEL);
+            let conn = mesh.read_connectivity();
+            let mut sharp = mesh
+                .channels
+                .write_channel(ch_id)
+                .expect("Channel was just created");
+            for (h, h_id) in h_idx_to_id.iter().enumerate() {
+                if self.edge_sharp[self.edge[h] as usize] {
+                    sharp[*h_id] = true;
+                    if let Some(twin) = conn[*h_id].twin {
+                        sharp[twin] = true;
+                    }
+                }
+            }
+        }
+
         mesh
     }
 
@@ -301,6 +670,105 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
         };
     }
 
+    /// Returns the halfedges in the face of `h`, starting at `h`.
+    fn face_halfedges(&self, h: usize) -> SVec<usize> {
+        let mut halfedges = SVec::new();
+        let mut hh = h;
+        loop {
+            halfedges.push(hh);
+            hh = self.get_next(hh);
+            if hh == h || halfedges.len() > MAX_LOOP_ITERATIONS {
+                break;
+            }
+        }
+        halfedges
+    }
+
+    /// Computes the values of a vertex channel after subdivision. Original
+    /// vertices keep their value, and the new face and edge points combine
+    /// the values of the vertices in their face or edge.
+    fn subdivide_vertex_values<V: SubdivisionValue>(&self, values: &[V]) -> Vec<V> {
+        let v_d = self.counts.num_vertices;
+        let f_d = self.counts.num_faces;
+        let mut new_values = vec![V::default(); self.counts.subdiv().num_vertices];
+        new_values[..v_d].clone_from_slice(values);
+
+        let mut visited_faces = vec![false; f_d];
+        for h in 0..self.counts.num_halfedges {
+            let face = self.get_face(h);
+            if !visited_faces[face] {
+                visited_faces[face] = true;
+                let corners = self
+                    .face_halfedges(h)
+                    .iter()
+                    .map(|hh| &values[self.vert[*hh] as usize])
+                    .collect::<SVec<_>>();
+                new_values[v_d + face] = V::combine(&corners);
+            }
+
+            let v = self.vert[h] as usize;
+            let v_end = self.vert[self.get_next(h)] as usize;
+            new_values[v_d + f_d + self.edge[h] as usize] =
+                V::combine(&[&values[v], &values[v_end]]);
+        }
+        new_values
+    }
+
+    /// Computes the values of a halfedge channel after subdivision. Halfedge
+    /// values are attached to the face corner at their source vertex, so the
+    /// four halfedges spawning from `h` interpolate between the corners of
+    /// the original face.
+    fn subdivide_halfedge_values<V: SubdivisionValue>(&self, values: &[V]) -> Vec<V> {
+        (0..self.counts.num_halfedges)
+            .flat_map(|h| {
+                let next = self.get_next(h);
+                let prev = self.get_prev(h);
+                let corners = self
+                    .face_halfedges(h)
+                    .iter()
+                    .map(|hh| &values[*hh])
+                    .collect::<SVec<_>>();
+                [
+                    values[h].clone(),
+                    V::combine(&[&values[h], &values[next]]),
+                    V::combine(&corners),
+                    V::combine(&[&values[prev], &values[h]]),
+                ]
+            })
+            .collect()
+    }
+
+    /// Computes the values of a face channel after subdivision. Each new face
+    /// keeps the value of the face it was created from.
+    fn subdivide_face_values<V: SubdivisionValue>(&self, values: &[V]) -> Vec<V> {
+        (0..self.counts.num_halfedges)
+            .map(|h| values[self.get_face(h)].clone())
+            .collect()
+    }
+
+    /// Computes the channels of the mesh after subdivision.
+    fn subdivide_channels(&self) -> CompactChannels {
+        macro_rules! subdivide_list {
+            ($list:ident, $subdivide_fn:ident) => {
+                self.channels
+                    .$list
+                    .iter()
+                    .map(|(name, values)| {
+                        let values =
+                            map_compact_values!(values, |v: &Vec<_>| self.$subdivide_fn(v));
+                        (name.clone(), values)
+                    })
+                    .collect()
+            };
+        }
+        CompactChannels {
+            vertex: subdivide_list!(vertex, subdivide_vertex_values),
+            halfedge: subdivide_list!(halfedge, subdivide_halfedge_values),
+            face: subdivide_list!(face, subdivide_face_values),
+            uvs: self.channels.uvs.clone(),
+        }
+    }
+
     /// Returns the next of a given halfedge h. This will use an analytical
     /// expression if the mesh has been subdivided at least once.
     pub fn get_next(&self, h: usize) -> usize {
@@ -344,6 +812,10 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
     ///
     /// If `catmull_clark` is set to true, smooth subdivision using the Catmull
     /// Clark algorithm is performed, otherwise linear subdivision is performed.
+    ///
+    /// Linear subdivision splits each n-sided face into n quads, by adding a
+    /// vertex at the centroid of each face and the midpoint of each edge. No
+    /// existing vertex is moved, so the shape of the mesh is kept exactly.
     #[profiling::function]
     pub fn subdivide(&self, catmull_clark: bool) -> CompactMesh<true> {
         use rayon::prelude::*;
@@ -410,6 +882,65 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
             }
         };
 
+        // Crease information is only relevant for Catmull-Clark subdivision.
+        let has_creases =
+            catmull_clark && !(self.edge_sharpness.is_empty() && self.edge_sharp.is_empty());
+        let sharpness = |h: usize| {
+            let e = self.edge[h] as usize;
+            if !has_creases {
+                0.0
+            } else if self.edge_sharp.get(e).copied().unwrap_or(false) {
+                f32::INFINITY
+            } else {
+                self.edge_sharpness.get(e).copied().unwrap_or(0.0)
+            }
+        };
+
+        let mut vertex_creases = Vec::new();
+        if has_creases {
+            vertex_creases = vec![VertexCreases::default(); self.counts.num_vertices];
+            for h in 0..self.counts.num_halfedges {
+                // Visit each interior edge once, from the halfedge with the
+                // lowest index. Boundary edges are counted separately below.
+                let first_of_pair = self.twin[h]
+                    .map(|twin_h| h < twin_h.get() as usize)
+                    .unwrap_or(false);
+                let s = sharpness(h);
+                if first_of_pair && s > 0.0 {
+                    let v = self.vert[h] as usize;
+                    let v_end = self.vert[self.get_next(h)] as usize;
+                    for (a, b) in [(v, v_end), (v_end, v)] {
+                        vertex_creases[a].count += 1;
+                        vertex_creases[a].sharpness_sum += s;
+                        vertex_creases[a].neighbor_sum += self.vertex_positions[b];
+                    }
+                }
+            }
+        }
+
+        // Boundary edges are treated as infinitely sharp creases, so boundary
+        // vertices are only influenced by their neighbors along the boundary.
+        // Vertices with a single face, like the corners of a grid, are kept
+        // in place.
+        let mut boundary_creases = Vec::new();
+        let mut vertex_num_faces = Vec::new();
+        if catmull_clark {
+            boundary_creases = vec![VertexCreases::default(); self.counts.num_vertices];
+            vertex_num_faces = vec![0u32; self.counts.num_vertices];
+            for h in 0..self.counts.num_halfedges {
+                let v = self.vert[h] as usize;
+                vertex_num_faces[v] += 1;
+                if self.twin[h].is_none() {
+                    let v_end = self.vert[self.get_next(h)] as usize;
+                    for (a, b) in [(v, v_end), (v_end, v)] {
+                        boundary_creases[a].count += 1;
+                        boundary_creases[a].sharpness_sum += f32::INFINITY;
+                        boundary_creases[a].neighbor_sum += self.vertex_positions[b];
+                    }
+                }
+            }
+        }
+
         let mut valences = Vec::new();
         (0..self.counts.num_halfedges)
             .into_par_iter()
@@ -458,9 +989,19 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
                     // NOTE: Same rationale as above for relaxed ordering. The
                     // vertices in `i` are not being iterated in this loop, so the
                     // load() does not read a value that changes during this loop
-                    let inc = (self.vertex_positions[v]
+                    let mut inc = (self.vertex_positions[v]
                         + new_vertex_positions[i].load(Ordering::Relaxed))
                         / 4.0;
+
+                    // Creased edges blend towards the midpoint. Each of the two
+                    // halfedges in the edge contributes half of it.
+                    let s = sharpness(h);
+                    if s > 0.0 {
+                        let v_end = self.vert[self.get_next(h)] as usize;
+                        let midpoint =
+                            (self.vertex_positions[v] + self.vertex_positions[v_end]) / 2.0;
+                        inc = inc.lerp(midpoint / 2.0, s.min(1.0));
+                    }
                     new_vertex_positions[j].fetch_add(inc, Ordering::Relaxed)
                 } else {
                     let v_end = self.vert[self.get_next(h)] as usize;
@@ -483,12 +1024,50 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
                     let j =
                         self.counts.num_vertices + self.counts.num_faces + self.edge[h] as usize;
 
-                    let inc = (4.0 * new_vertex_positions[j].load(Ordering::Relaxed)
-                        - new_vertex_positions[i].load(Ordering::Relaxed)
+                    let face_point = new_vertex_positions[i].load(Ordering::Relaxed);
+                    let edge_point = if sharpness(h) > 0.0 {
+                        // The smooth rule needs the smooth edge point, but the
+                        // one stored at `j` was moved by the crease.
+                        let twin_h = self.twin[h].unwrap().get() as usize;
+                        let twin_face_point = new_vertex_positions
+                            [self.counts.num_vertices + self.get_face(twin_h)]
+                        .load(Ordering::Relaxed);
+                        let v_end = self.vert[self.get_next(h)] as usize;
+                        (self.vertex_positions[v]
+                            + self.vertex_positions[v_end]
+                            + face_point
+                            + twin_face_point)
+                            / 4.0
+                    } else {
+                        new_vertex_positions[j].load(Ordering::Relaxed)
+                    };
+
+                    let mut inc = (4.0 * edge_point - face_point
                         + (n - 3.0) * self.vertex_positions[v])
                         / (n * n);
 
+                    // Creased vertices blend towards the crease or corner
+                    // rules. Each of the `n` halfedges around the vertex
+                    // contributes a fraction of the result.
+                    if has_creases {
+                        let (sharp, weight) =
+                            vertex_creases[v].sharp_rule(self.vertex_positions[v]);
+                        if weight > 0.0 {
+                            inc = inc.lerp(sharp / n, weight);
+                        }
+                    }
+
                     new_vertex_positions[v].fetch_add(inc, Ordering::Relaxed);
+                } else if catmull_clark && vertex_num_faces[v] > 1 {
+                    // Boundary vertex. Uses the crease rule along the
+                    // boundary, or the corner rule when there are other
+                    // creased edges around the vertex.
+                    let creases = match vertex_creases.get(v) {
+                        Some(interior) => boundary_creases[v].merged(interior),
+                        None => boundary_creases[v],
+                    };
+                    let (pos, _) = creases.sharp_rule(self.vertex_positions[v]);
+                    new_vertex_positions[v].store(pos, Ordering::Relaxed);
                 } else {
                     new_vertex_positions[v].store(self.vertex_positions[v], Ordering::Relaxed);
                 }
@@ -498,6 +1077,31 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
         let new_vertex_positions =
             unsafe { transmute_vec::<AtomicVec3, Vec3>(new_vertex_positions) };
 
+        // Each edge is split in two, and the sharpness of the new edges is
+        // decreased by one. Edges inside the faces are never creased.
+        let mut new_edge_sharpness = vec![];
+        if has_creases {
+            new_edge_sharpness = (0..new_counts.num_edges)
+                .map(|e| {
+                    // NOTE: Only the first `2 * num_edges` new edges come from
+                    // splitting an old edge, the rest are outside this range.
+                    self.edge_sharpness
+                        .get(e / 2)
+                        .map(|s| (s - 1.0).max(0.0))
+                        .unwrap_or(0.0)
+                })
+                .collect();
+            if new_edge_sharpness.iter().all(|s| *s == 0.0) {
+                new_edge_sharpness.clear();
+            }
+        }
+        let mut new_edge_sharp = vec![];
+        if !self.edge_sharp.is_empty() {
+            new_edge_sharp = (0..new_counts.num_edges)
+                .map(|e| self.edge_sharp.get(e / 2).copied().unwrap_or(false))
+                .collect();
+        }
+
         CompactMesh {
             twin: new_twin,
             // NOTE: Empty vecs represent analytically computed properties
@@ -507,6 +1111,9 @@ impl<const Subdivided: bool> CompactMesh<Subdivided> {
             edge: new_edge,
             face: vec![],
             vertex_positions: new_vertex_positions,
+            edge_sharpness: new_edge_sharpness,
+            edge_sharp: new_edge_sharp,
+            channels: self.subdivide_channels(),
             counts: new_counts,
         }
     }
@@ -554,6 +1161,7 @@ impl AtomicVec3 {
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::prelude::selection::SelectionExpression;
     #[test]
     pub fn mesh_counts_test() {
         // Results empirically validated by subdividing several meshes in
@@ -648,4 +1256,224 @@ pub mod test {
             ]
         );
     }
+
+    #[test]
+    pub fn test_creases() {
+        let subdivide = |mesh: &HalfEdgeMesh| {
+            CompactMesh::<false>::from_halfedge(mesh)
+                .unwrap()
+                .subdivide_multi(3, true)
+                .to_halfedge()
+        };
+        let on_box_surface = |mesh: &HalfEdgeMesh| {
+            mesh.read_positions()
+                .iter()
+                .all(|(_, p)| (p.abs().max_element() - 0.5).abs() < 1e-5)
+        };
+
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        assert!(!on_box_surface(&subdivide(&mesh)));
+
+        // When all edges are sharp, the box keeps its shape.
+        edit_ops::set_crease(&mut mesh, &SelectionExpression::All, 1.0).unwrap();
+        let creased = subdivide(&mesh);
+        assert!(on_box_surface(&creased));
+        assert!(creased
+            .channels
+            .read_channel_by_name::<HalfEdgeId, f32>(CREASE_CHANNEL)
+            .unwrap()
+            .iter()
+            .any(|(_, w)| *w == 1.0));
+    }
+
+    #[test]
+    pub fn test_crease_weight_mapping() {
+        // The mapping is continuous, and weights close to 1.0 keep the edges
+        // sharp for many subdivision levels.
+        assert_eq!(weight_to_sharpness(0.0), 0.0);
+        assert_eq!(weight_to_sharpness(0.5), 1.0);
+        assert!(weight_to_sharpness(0.99) > 90.0);
+        assert_eq!(weight_to_sharpness(1.0), f32::INFINITY);
+        for weight in [0.0, 0.25, 0.5, 0.9, 0.99, 1.0] {
+            let roundtrip = sharpness_to_weight(weight_to_sharpness(weight));
+            assert!((roundtrip - weight).abs() < 1e-5);
+        }
+
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        edit_ops::set_crease(&mut mesh, &SelectionExpression::All, 0.99).unwrap();
+        let subdivided = CompactMesh::<false>::from_halfedge(&mesh)
+            .unwrap()
+            .subdivide_multi(3, true)
+            .to_halfedge();
+        assert!(subdivided
+            .read_positions()
+            .iter()
+            .all(|(_, p)| (p.abs().max_element() - 0.5).abs() < 1e-5));
+    }
+
+    #[test]
+    pub fn test_sharp_edges() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        edit_ops::mark_sharp(&mut mesh, &SelectionExpression::All, true).unwrap();
+        let subdivided = CompactMesh::<false>::from_halfedge(&mesh)
+            .unwrap()
+            .subdivide_multi(3, true)
+            .to_halfedge();
+
+        // Sharp edges behave like infinitely sharp creases, and the mark is
+        // kept on the subdivided edges.
+        assert!(subdivided
+            .read_positions()
+            .iter()
+            .all(|(_, p)| (p.abs().max_element() - 0.5).abs() < 1e-5));
+        assert!(subdivided
+            .channels
+            .read_channel_by_name::<HalfEdgeId, bool>(SHARP_CHANNEL)
+            .unwrap()
+            .iter()
+            .any(|(_, sharp)| *sharp));
+    }
+
+    #[test]
+    pub fn test_boundary_rules() {
+        let subdivide = |mesh: &HalfEdgeMesh| {
+            CompactMesh::<false>::from_halfedge(mesh)
+                .unwrap()
+                .subdivide_multi(2, true)
+                .to_halfedge()
+        };
+
+        // The outer edge of a flat grid stays straight, and its corners are
+        // kept in place.
+        let grid = primitives::Grid::build(Vec3::ZERO, Vec3::Y, Vec2::ONE * 2.0, 3, 3).unwrap();
+        let subdivided = subdivide(&grid);
+        let positions = subdivided.read_positions();
+        let (_, max) = positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), (_, p)| (min.min(*p), max.max(*p)),
+        );
+        assert!((max - Vec3::new(1.0, 0.0, 1.0)).abs().max_element() < 1e-5);
+        let on_edge = positions
+            .iter()
+            .filter(|(_, p)| (p.x - 1.0).abs() < 1e-5)
+            .count();
+        assert_eq!(on_edge, 3 * 4 + 1);
+        assert!(positions
+            .iter()
+            .any(|(_, p)| (*p - Vec3::new(1.0, 0.0, 1.0)).length() < 1e-5));
+
+        // On a curved boundary, boundary vertices are smoothed only by their
+        // boundary neighbors: (6 * v + a + b) / 8
+        let mut points = (0..8)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::TAU / 8.0;
+                Vec3::new(angle.cos(), 0.0, angle.sin())
+            })
+            .collect_vec();
+        points.push(Vec3::ZERO);
+        let triangles = (0..8u32).map(|i| [8, (i + 1) % 8, i]).collect_vec();
+        let disk = HalfEdgeMesh::build_from_polygons(&points, &triangles).unwrap();
+        let subdivided = CompactMesh::<false>::from_halfedge(&disk)
+            .unwrap()
+            .subdivide(true)
+            .to_halfedge();
+        let expected = (6.0 * points[0] + points[1] + points[7]) / 8.0;
+        assert!(subdivided
+            .read_positions()
+            .iter()
+            .any(|(_, p)| (*p - expected).length() < 1e-5));
+
+        // A boundary vertex with an extra creased edge becomes a corner, and
+        // is kept in place.
+        let mut creased_disk = HalfEdgeMesh::build_from_polygons(&points, &triangles).unwrap();
+        let ch_id = creased_disk
+            .channels
+            .ensure_channel::<HalfEdgeId, f32>(CREASE_CHANNEL);
+        {
+            let conn = creased_disk.read_connectivity();
+            let positions = creased_disk.read_positions();
+            let mut creases = creased_disk.channels.write_channel(ch_id).unwrap();
+            for (h, _) in conn.iter_halfedges() {
+                let (src, dst) = conn.at_halfedge(h).src_dst_pair().unwrap();
+                let is_spoke = [positions[src], positions[dst]] == [points[8], points[0]]
+                    || [positions[src], positions[dst]] == [points[0], points[8]];
+                if is_spoke {
+                    creases[h] = 1.0;
+                }
+            }
+        }
+        let subdivided = CompactMesh::<false>::from_halfedge(&creased_disk)
+            .unwrap()
+            .subdivide(true)
+            .to_halfedge();
+        let positions = subdivided.read_positions();
+        assert!(positions
+            .iter()
+            .any(|(_, p)| (*p - points[0]).length() < 1e-5));
+        assert!(!positions
+            .iter()
+            .any(|(_, p)| (*p - expected).length() < 1e-5));
+        assert!(!subdivided
+            .read_positions()
+            .iter()
+            .any(|(_, p)| (*p - points[0]).length() < 1e-5));
+    }
+
+    #[test]
+    pub fn test_linear_subdivision() {
+        let mesh = primitives::Icosahedron::build(Vec3::ZERO, 1.0).unwrap();
+        let original = mesh.read_positions().iter().map(|(_, p)| *p).collect_vec();
+        let subdivided = CompactMesh::<false>::from_halfedge(&mesh)
+            .unwrap()
+            .subdivide(false)
+            .to_halfedge();
+
+        // Each triangle is split into three quads.
+        let conn = subdivided.read_connectivity();
+        assert_eq!(conn.num_faces(), 3 * 20);
+        assert!(conn
+            .iter_faces()
+            .all(|(f, _)| conn.face_vertices(f).len() == 4));
+
+        // The original vertices are kept in place.
+        let positions = subdivided.read_positions();
+        for p in original {
+            assert!(positions.iter().any(|(_, q)| (p - *q).length() < 1e-5));
+        }
+    }
+
+    #[test]
+    pub fn test_subdivide_channels() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        edit_ops::set_full_range_uvs(&mut mesh, UvChannel::Main).unwrap();
+        edit_ops::set_full_range_uvs(&mut mesh, UvChannel::Lightmap).unwrap();
+        edit_ops::set_material(&mut mesh, &SelectionExpression::All, 2).unwrap();
+
+        let subdivided = CompactMesh::<false>::from_halfedge(&mesh)
+            .unwrap()
+            .subdivide_multi(2, true)
+            .to_halfedge();
+        let conn = subdivided.read_connectivity();
+
+        // New UVs are interpolated from the corners of the original faces, so
+        // they stay within the full range, and the face centers are at 0.5
+        let uvs = subdivided.read_uvs().expect("UVs should be preserved");
+        assert!(subdivided.read_uv_channel(UvChannel::Lightmap).is_some());
+        let face_uvs = conn
+            .iter_halfedges()
+            .filter(|(_, h)| h.face.is_some())
+            .map(|(h, _)| uvs[h])
+            .collect_vec();
+        assert_eq!(face_uvs.len(), 6 * 16 * 4);
+        assert!(face_uvs
+            .iter()
+            .all(|uv| (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y)));
+        assert!(face_uvs.iter().any(|uv| *uv == Vec3::new(0.5, 0.5, 0.0)));
+
+        let materials = subdivided
+            .channels
+            .read_channel_by_name::<FaceId, i32>("material")
+            .unwrap();
+        assert!(conn.iter_faces().all(|(f, _)| materials[f] == 2));
+    }
 }
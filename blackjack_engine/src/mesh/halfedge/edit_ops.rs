@@ -5,7 +5,8 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
     f32::consts::PI,
 };
 
@@ -21,6 +22,16 @@ use super::selection::SelectionExpression;
 /// Just a place where commented-out code goes to die
 pub mod deprecated;
 
+/// BSP-tree based constructive solid geometry, used by `boolean`
+mod csg;
+
+/// Quickhull implementation, used by `convex_hull`
+mod quickhull;
+
+/// Sampled distance fields and surface nets, used by `remesh_voxel` and
+/// `isosurface`
+mod surface_nets;
+
 /// Removes `h_l` and its twin `h_r`, merging their respective faces together.
 /// The face on the L side will be kept, and the R side removed. Both sides of
 /// the edge that will be dissolved need to be on a face. Boundary halfedges are
@@ -220,6 +231,140 @@ pub fn cut_face(
     Ok(h_v_w)
 }
 
+/// Walks the edge ring that starts at halfedge `h0`, going through the face
+/// of `h0` and then jumping to the opposite edge on every quad. Returns the
+/// ring's halfedges, oriented so that the face between two consecutive
+/// halfedges is the face of the first one, and whether the ring is closed.
+///
+/// The `visited` set is used to detect rings that cross themselves.
+fn walk_edge_ring(
+    mesh: &MeshConnectivity,
+    h0: HalfEdgeId,
+    visited: &mut HashSet<FaceId>,
+) -> Result<(Vec<HalfEdgeId>, bool)> {
+    let mut ring = vec![h0];
+    let mut h = h0;
+    loop {
+        let face = match mesh.at_halfedge(h).face().try_end() {
+            Ok(face) => face,
+            // Reached the boundary of the mesh
+            Err(_) => return Ok((ring, false)),
+        };
+        if !visited.insert(face) {
+            bail!("loop_cut: The edge ring crosses itself. Loop cuts are only supported on simple rings")
+        }
+        let num_sides = mesh.face_edges(face).len();
+        if num_sides != 4 {
+            bail!(
+                "loop_cut: The edge ring reached a face with {num_sides} sides. \
+                 The direction of the ring is only defined for quads"
+            )
+        }
+        let opposite = mesh.at_halfedge(h).next().next().try_end()?;
+        h = mesh.at_halfedge(opposite).twin().try_end()?;
+        if h == h0 {
+            return Ok((ring, true));
+        }
+        ring.push(h);
+    }
+}
+
+/// Returns the edge ring containing `start_edge`, oriented like it, and
+/// whether the ring is closed. See `loop_cut`.
+fn edge_ring(conn: &MeshConnectivity, start_edge: HalfEdgeId) -> Result<(Vec<HalfEdgeId>, bool)> {
+    let mut visited = HashSet::new();
+    let (mut ring, closed) = walk_edge_ring(conn, start_edge, &mut visited)?;
+    if !closed {
+        // The ring is open. Walk the other way around too, and prepend that
+        // half of the ring, reoriented to match the forward walk.
+        let start_twin = conn.at_halfedge(start_edge).twin().try_end()?;
+        let (backward, _) = walk_edge_ring(conn, start_twin, &mut visited)?;
+        let mut full_ring = backward
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|h| conn.at_halfedge(*h).twin().try_end())
+            .collect::<Result<Vec<_>, _>>()?;
+        full_ring.extend(ring);
+        ring = full_ring;
+    }
+    Ok((ring, closed))
+}
+
+/// Performs a loop cut on the edge ring containing `start_edge`. Every edge
+/// of the ring is divided in `cuts` equally spaced points, and the new
+/// vertices are connected across the quads of the ring, creating `cuts`
+/// parallel edge loops.
+///
+/// The ring is followed in both directions starting at `start_edge`, until
+/// it either closes or reaches a boundary. This operation fails without
+/// modifying the mesh if the ring goes through a face that is not a quad,
+/// since the ring direction would be ambiguous.
+pub fn loop_cut(mesh: &mut HalfEdgeMesh, start_edge: HalfEdgeId, cuts: usize) -> Result<()> {
+    let mut conn = mesh.write_connectivity();
+    let mut positions = mesh.write_positions();
+
+    if cuts == 0 {
+        return Ok(());
+    }
+
+    let (ring, closed) = edge_ring(&conn, start_edge)?;
+
+    // --- Divide the edges ---
+    // For each edge in the ring, the new vertices, ordered starting from the
+    // source of the ring halfedge.
+    let mut ring_vertices = Vec::with_capacity(ring.len());
+    for h in ring.iter_cpy() {
+        let mut vertices = SVec::new();
+        for i in 0..cuts {
+            // The `h` halfedge is kept on the second half of the edge after
+            // dividing, so we keep splitting what remains of the edge.
+            let remaining_segments = (cuts - i + 1) as f32;
+            vertices.push(divide_edge(
+                &mut conn,
+                &mut positions,
+                h,
+                1.0 / remaining_segments,
+            )?);
+        }
+        ring_vertices.push(vertices);
+    }
+
+    // --- Connect the new vertices ---
+    if closed {
+        ring_vertices.push(ring_vertices[0].clone());
+    }
+    for (vs, ws) in ring_vertices.iter().tuple_windows() {
+        for (v, w) in vs.iter_cpy().zip(ws.iter_cpy()) {
+            cut_face(&mut conn, v, w)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Performs a loop cut on the edge ring of each edge in `edges`, like
+/// `loop_cut`. Each ring is only cut once, even when `edges` contains both
+/// halfedges of an edge, or several edges of the same ring.
+pub fn loop_cut_edges(mesh: &mut HalfEdgeMesh, edges: &[HalfEdgeId], cuts: usize) -> Result<()> {
+    let mut cut_edges = HashSet::new();
+    for edge in edges.iter_cpy() {
+        if cut_edges.contains(&edge) {
+            continue;
+        }
+        {
+            let conn = mesh.read_connectivity();
+            let (ring, _) = edge_ring(&conn, edge)?;
+            for h in ring {
+                cut_edges.insert(h);
+                cut_edges.insert(conn.at_halfedge(h).twin().try_end()?);
+            }
+        }
+        loop_cut(mesh, edge, cuts)?;
+    }
+    Ok(())
+}
+
 pub fn dissolve_vertex(mesh: &mut halfedge::MeshConnectivity, v: VertexId) -> Result<FaceId> {
     let outgoing = mesh.at_vertex(v).outgoing_halfedges()?;
 
@@ -647,7 +792,17 @@ pub fn bevel_edges(
     amount: f32,
 ) -> Result<()> {
     let beveled_edges = bevel_edges_connectivity(mesh, positions, halfedges)?;
+    move_beveled_vertices(mesh, positions, &beveled_edges, amount)
+}
 
+/// Moves the vertices of the `beveled_edges`, as returned by
+/// `bevel_edges_connectivity`, a given `amount` distance away from each other.
+fn move_beveled_vertices(
+    mesh: &mut MeshConnectivity,
+    positions: &mut Positions,
+    beveled_edges: &BTreeSet<HalfEdgeId>,
+    amount: f32,
+) -> Result<()> {
     // --- Adjust vertex positions ---
 
     // Movement of vertices in a bevel can be modelled as a set of pulls. For
@@ -656,7 +811,7 @@ pub fn bevel_edges(
     // depending on their location of the halfedge (head, tail resp.). The final
     // move direction of a vertice is the sum of all its pulls.
     let mut move_ops = HashMap::<VertexId, HashSet<Vec3Ord>>::new();
-    for h in beveled_edges {
+    for &h in beveled_edges {
         mesh.add_debug_halfedge(h, DebugMark::green("bvl"));
 
         if mesh.at_halfedge(h).is_boundary()? {
@@ -688,6 +843,110 @@ pub fn bevel_edges(
     Ok(())
 }
 
+/// Returns the point at parameter `t` of a bevel profile going from `p0` to
+/// `p1`. The `corner` is the position of the original vertex the bevel was
+/// created from. A `profile` of 0 gives a straight line, and 1 gives a
+/// circular arc, tangent to the lines from `corner` to both endpoints.
+fn bevel_profile_point(p0: Vec3, p1: Vec3, corner: Vec3, t: f32, profile: f32) -> Vec3 {
+    let linear = p0.lerp(p1, t);
+    // A rational quadratic bezier curve with the corner as control point. The
+    // weight is chosen so that the curve is a circular arc when both
+    // endpoints are at the same distance from the corner.
+    let cos_angle = (p0 - corner)
+        .normalize_or_zero()
+        .dot((p1 - corner).normalize_or_zero())
+        .clamp(-1.0, 1.0);
+    let weight = ((1.0 - cos_angle) * 0.5).sqrt();
+    let (b0, b1, b2) = ((1.0 - t).powi(2), 2.0 * weight * t * (1.0 - t), t.powi(2));
+    let arc = (p0 * b0 + corner * b1 + p1 * b2) / (b0 + b1 + b2);
+    linear.lerp(arc, profile.clamp(0.0, 1.0))
+}
+
+/// Same as `bevel_edges`, but each edge gets replaced by `segments` faces,
+/// forming a strip. The `profile` parameter, from 0 to 1, controls the
+/// roundness of the bevel: 0 gives a flat bevel, and 1 a round one.
+///
+/// When `segments` is 1, this is the same as `bevel_edges`.
+pub fn bevel_edges_segments(
+    mesh: &mut MeshConnectivity,
+    positions: &mut Positions,
+    halfedges: &[HalfEdgeId],
+    amount: f32,
+    segments: usize,
+    profile: f32,
+) -> Result<()> {
+    if segments <= 1 {
+        return bevel_edges(mesh, positions, halfedges, amount);
+    }
+
+    let beveled_edges = bevel_edges_connectivity(mesh, positions, halfedges)?;
+    // Before moving the vertices, all the new vertices coming from a vertex of
+    // the original mesh are at the position of that vertex. We use this later
+    // to find the corners of the bevel profiles.
+    let corners = positions.clone();
+    move_beveled_vertices(mesh, positions, &beveled_edges, amount)?;
+
+    // Each bevel face goes across two edges that go from one side of the bevel
+    // to the other. These are divided, and the new vertices are connected to
+    // form the strip. Edges shared by two bevel faces are divided only once.
+    // Stores the vertices created for each halfedge, from its src to its dst.
+    let mut divided = HashMap::<HalfEdgeId, SVec<VertexId>>::new();
+    let mut divide_across_edge = |mesh: &mut MeshConnectivity,
+                                  positions: &mut Positions,
+                                  h: HalfEdgeId|
+     -> Result<SVec<VertexId>> {
+        let twin = mesh.at_halfedge(h).twin().try_end()?;
+        if let Some(vertices) = divided.get(&twin) {
+            return Ok(vertices.iter_cpy().rev().collect());
+        }
+        if let Some(vertices) = divided.get(&h) {
+            return Ok(vertices.clone());
+        }
+        let (src, dst) = mesh.at_halfedge(h).src_dst_pair()?;
+        let (p0, p1, corner) = (positions[src], positions[dst], corners[src]);
+        let mut vertices = SVec::new();
+        for i in 1..segments {
+            // The `h` halfedge is kept on the second half of the edge after
+            // dividing, so we keep splitting what remains of the edge.
+            let x = divide_edge(mesh, positions, h, 1.0 / (segments - i + 1) as f32)?;
+            positions[x] = bevel_profile_point(p0, p1, corner, i as f32 / segments as f32, profile);
+            vertices.push(x);
+        }
+        divided.insert(h, vertices.clone());
+        Ok(vertices)
+    };
+
+    // Each beveled edge has both its halfedges in `beveled_edges`, so we need
+    // to make sure every bevel face is only handled once. All faces are
+    // collected before making any changes to the mesh.
+    let mut visited_faces = HashSet::new();
+    let mut bevel_faces = vec![];
+    for h in beveled_edges {
+        // The bevel face is on the other side of the beveled halfedges.
+        let g = mesh.at_halfedge(h).twin().try_end()?;
+        let face = match mesh.at_halfedge(g).face().try_end() {
+            Ok(face) => face,
+            Err(_) => continue,
+        };
+        if mesh.face_edges(face).len() == 4 && visited_faces.insert(face) {
+            bevel_faces.push((
+                mesh.at_halfedge(g).next().try_end()?,
+                mesh.at_halfedge(g).previous().try_end()?,
+            ));
+        }
+    }
+
+    for (across_1, across_2) in bevel_faces {
+        let vertices_1 = divide_across_edge(mesh, positions, across_1)?;
+        let vertices_2 = divide_across_edge(mesh, positions, across_2)?;
+        for (v, w) in vertices_1.iter_cpy().zip(vertices_2.iter_cpy().rev()) {
+            cut_face(mesh, v, w)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Extrudes the given set of faces. Faces that are connected by at least one
 /// edge will be connected after the extrude.
 pub fn extrude_faces(
@@ -742,6 +1001,149 @@ pub fn extrude_faces(
     Ok(())
 }
 
+/// Extrudes each of the given faces separately, along its own normal. Unlike
+/// `extrude_faces`, faces that share an edge are not extruded as a region:
+/// each face gets its own walls, even against other extruded faces.
+pub fn extrude_faces_individual(
+    mesh: &mut MeshConnectivity,
+    positions: &mut Positions,
+    faces: &[FaceId],
+    amount: f32,
+) -> Result<()> {
+    // NOTE: Extruding a face doesn't change the ids or the positions of the
+    // faces around it, so each face can be extruded as its own region.
+    for face in faces.iter_cpy() {
+        extrude_faces(mesh, positions, &[face], amount)?;
+    }
+    Ok(())
+}
+
+/// Extrudes the given boundary `edges`, creating a new quad for each one. The
+/// new quads connect each edge with a copy of it, moved by `offset`. Edges
+/// that are not in the boundary of the mesh are ignored.
+///
+/// Contiguous edges share the new vertices, so extruding a chain of edges
+/// produces a connected strip of quads.
+///
+/// Unlike `MeshConnectivity::extrude_edge`, which is meant to be used while
+/// building a mesh from scratch, this works on a mesh with complete
+/// connectivity, so the boundary of the mesh is rebuilt after extruding.
+pub fn extrude_edges(mesh: &mut HalfEdgeMesh, edges: &[HalfEdgeId], offset: Vec3) -> Result<()> {
+    let mut conn = mesh.write_connectivity();
+    let mut positions = mesh.write_positions();
+
+    // The selection may contain either halfedge of each edge. We want the one
+    // in the boundary, which will become part of the new quad.
+    let mut boundary_halfedges = vec![];
+    let mut seen = HashSet::new();
+    for h in edges.iter_cpy() {
+        let t = conn.at_halfedge(h).twin().try_end()?;
+        let b = match (conn[h].face, conn[t].face) {
+            (None, Some(_)) => h,
+            (Some(_), None) => t,
+            _ => continue,
+        };
+        if seen.insert(b) {
+            boundary_halfedges.push(b);
+        }
+    }
+    if boundary_halfedges.is_empty() {
+        return Ok(());
+    }
+
+    // --- Create the new vertices and quads ---
+    let mut new_vertices = HashMap::<VertexId, VertexId>::new();
+    let mut new_halfedges = HashMap::<(VertexId, VertexId), HalfEdgeId>::new();
+    for b in boundary_halfedges.iter_cpy() {
+        let (u, w) = conn.at_halfedge(b).src_dst_pair()?;
+        let [u2, w2] = [u, w].map(|x| {
+            *new_vertices.entry(x).or_insert_with(|| {
+                let pos = positions[x] + offset;
+                conn.alloc_vertex(&mut positions, pos, None)
+            })
+        });
+
+        let face = conn.alloc_face(Some(b));
+        let loop_vertices = [u, w, w2, u2];
+        let mut alloc_halfedge = |v: VertexId| {
+            conn.alloc_halfedge(HalfEdge {
+                vertex: Some(v),
+                ..Default::default()
+            })
+        };
+        let loop_halfedges = [b, alloc_halfedge(w), alloc_halfedge(w2), alloc_halfedge(u2)];
+        for (i, h) in loop_halfedges.iter_cpy().enumerate() {
+            conn[h].face = Some(face);
+            conn[h].next = Some(loop_halfedges[(i + 1) % 4]);
+            if i > 0 {
+                new_halfedges.insert((loop_vertices[i], loop_vertices[(i + 1) % 4]), h);
+            }
+        }
+        conn[w2].halfedge = Some(loop_halfedges[2]);
+        conn[u2].halfedge.get_or_insert(loop_halfedges[3]);
+    }
+
+    // --- Fix connectivity ---
+
+    // Quads from contiguous edges share the edges between them.
+    for (&(x, y), &h) in &new_halfedges {
+        conn[h].twin = new_halfedges.get(&(y, x)).copied();
+    }
+
+    // The old boundary is no longer valid. Remove it, and let the boundary
+    // halfedges be regenerated.
+    remove_boundary_halfedges(&mut conn);
+    for b in boundary_halfedges.iter_cpy() {
+        let t = conn.at_halfedge(b).twin().try_end()?;
+        conn[t].twin = Some(b);
+    }
+    regenerate_boundary_halfedges(&mut conn);
+
+    Ok(())
+}
+
+/// Removes all the halfedges in the boundary of the mesh, leaving the
+/// halfedges on the other side of the boundary without a twin. Must be
+/// followed by a call to `regenerate_boundary_halfedges`.
+fn remove_boundary_halfedges(conn: &mut MeshConnectivity) {
+    let old_boundary = conn
+        .iter_halfedges()
+        .filter(|(_, halfedge)| halfedge.face.is_none())
+        .map(|(h, halfedge)| (h, halfedge.twin))
+        .collect_vec();
+    for (h, twin) in old_boundary {
+        if let Some(twin) = twin {
+            if conn[twin].face.is_some() {
+                conn[twin].twin = None;
+                conn.remove_halfedge(h);
+            }
+        }
+    }
+}
+
+/// Adds the boundary halfedges for every halfedge without a twin, and fixes
+/// the vertices that were pointing to a removed halfedge.
+fn regenerate_boundary_halfedges(conn: &mut MeshConnectivity) {
+    conn.add_boundary_halfedges();
+
+    let mut outgoing = HashMap::new();
+    for (h, halfedge) in conn.iter_halfedges() {
+        if let Some(v) = halfedge.vertex {
+            outgoing.insert(v, h);
+        }
+    }
+    let vertices = conn.iter_vertices().map(|(v, _)| v).collect_vec();
+    for v in vertices {
+        let is_valid = conn[v]
+            .halfedge
+            .map(|h| conn.halfedges.contains_key(h))
+            .unwrap_or(false);
+        if !is_valid {
+            conn[v].halfedge = outgoing.get(&v).copied();
+        }
+    }
+}
+
 /// Generates the flat normals channel for this mesh
 pub fn generate_flat_normals_channel(mesh: &HalfEdgeMesh) -> Result<Channel<FaceId, Vec3>> {
     let positions = mesh.read_positions();
@@ -765,38 +1167,197 @@ pub fn set_flat_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
         .replace_or_create_channel("face_normal", normals);
 
     mesh.default_channels.face_normals = Some(normals_ch_id);
+    mesh.default_channels.halfedge_normals = None;
     mesh.gen_config.smooth_normals = false;
 
     Ok(())
 }
 
+/// The ways in which the normals of the faces around a vertex can be weighted
+/// when computing its smooth normal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// All faces contribute equally.
+    Uniform,
+    /// Faces contribute proportionally to their area.
+    Area,
+    /// Faces contribute proportionally to the angle of their corner at the
+    /// vertex. This gives the best results on meshes with faces of mixed
+    /// sizes.
+    Angle,
+}
+
 /// Generates the smooth normals channel for this mesh.
 pub fn generate_smooth_normals_channel(mesh: &HalfEdgeMesh) -> Result<Channel<VertexId, Vec3>> {
+    generate_weighted_smooth_normals_channel(mesh, NormalWeighting::Uniform)
+}
+
+/// Generates the smooth normals channel for this mesh. The normal at each
+/// vertex is the sum of the normals of its adjacent faces, using the given
+/// `weighting`.
+pub fn generate_weighted_smooth_normals_channel(
+    mesh: &HalfEdgeMesh,
+    weighting: NormalWeighting,
+) -> Result<Channel<VertexId, Vec3>> {
     let positions = mesh.read_positions();
     let conn = mesh.read_connectivity();
     let mut normals = Channel::<VertexId, Vec3>::new();
 
     for (vertex, _) in conn.iter_vertices() {
-        let adjacent_faces = conn.at_vertex(vertex).adjacent_faces()?;
-        let mut normal = Vec3::ZERO;
-        for face in adjacent_faces.iter_cpy() {
-            normal += conn.face_normal(&positions, face).unwrap_or(Vec3::ZERO);
+        normals[vertex] = Vec3::ZERO;
+    }
+
+    for (face, _) in conn.iter_faces() {
+        let vertices = conn.face_vertices(face);
+        match weighting {
+            NormalWeighting::Uniform => {
+                let normal = conn.face_normal(&positions, face).unwrap_or(Vec3::ZERO);
+                for v in vertices.iter_cpy() {
+                    normals[v] += normal;
+                }
+            }
+            NormalWeighting::Area | NormalWeighting::Angle => {
+                // Newell's method: The length of this vector is twice the
+                // area of the polygon, which also works for non-planar faces.
+                let area_normal = vertices
+                    .iter()
+                    .circular_tuple_windows()
+                    .fold(Vec3::ZERO, |acc, (a, b)| {
+                        acc + positions[*a].cross(positions[*b])
+                    })
+                    * 0.5;
+                for (prev, v, next) in vertices.iter_cpy().circular_tuple_windows() {
+                    normals[v] += if weighting == NormalWeighting::Area {
+                        area_normal
+                    } else {
+                        let to_prev = positions[prev] - positions[v];
+                        let to_next = positions[next] - positions[v];
+                        if to_prev == Vec3::ZERO || to_next == Vec3::ZERO {
+                            Vec3::ZERO
+                        } else {
+                            area_normal.normalize_or_zero() * to_next.angle_between(to_prev)
+                        }
+                    };
+                }
+            }
         }
-        normals[vertex] = normal.normalize_or_zero();
+    }
+
+    for (vertex, _) in conn.iter_vertices() {
+        normals[vertex] = normals[vertex].normalize_or_zero();
     }
 
     Ok(normals)
 }
 
-/// Computes "flat" normals for this mesh. Flat normals are attached to faces.
-pub fn set_smooth_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
-    let normals = generate_smooth_normals_channel(mesh)?;
+/// Computes the smooth normals channel for this mesh, using the given
+/// `weighting`, and configures the mesh to generate smooth normals. Smooth
+/// normals are attached to vertices.
+pub fn set_smooth_normals(mesh: &mut HalfEdgeMesh, weighting: NormalWeighting) -> Result<()> {
+    let normals = generate_weighted_smooth_normals_channel(mesh, weighting)?;
     let normals_ch_id = mesh
         .channels
         .replace_or_create_channel("vertex_normal", normals);
 
     mesh.gen_config.smooth_normals = true;
     mesh.default_channels.vertex_normals = Some(normals_ch_id);
+    mesh.default_channels.halfedge_normals = None;
+
+    Ok(())
+}
+
+/// Generates a per-corner normals channel for this mesh. Normals are averaged
+/// across edges whose dihedral angle is below `angle_threshold` (in degrees),
+/// and split across sharper edges. Boundary edges and edges marked as sharp
+/// (see `mark_sharp`) always split normals.
+///
+/// Each corner is represented by the outgoing halfedge of its vertex inside the
+/// face, the same way UVs are stored.
+pub fn generate_auto_smooth_normals_channel(
+    mesh: &HalfEdgeMesh,
+    angle_threshold: f32,
+) -> Result<Channel<HalfEdgeId, Vec3>> {
+    let face_normals = generate_flat_normals_channel(mesh)?;
+    let conn = mesh.read_connectivity();
+    let threshold = angle_threshold.to_radians();
+    let sharp_ch = mesh
+        .channels
+        .read_channel_by_name::<HalfEdgeId, bool>(compact_mesh::SHARP_CHANNEL)
+        .ok();
+
+    // Returns the face at the other side of halfedge `h`, as long as the edge
+    // between them is smooth.
+    let smooth_neighbor = |h: HalfEdgeId| -> Result<Option<(HalfEdgeId, FaceId)>> {
+        let face = conn.at_halfedge(h).face().try_end()?;
+        let twin = conn.at_halfedge(h).twin().try_end()?;
+        if let Some(sharp_ch) = &sharp_ch {
+            if sharp_ch[h] || sharp_ch[twin] {
+                return Ok(None);
+            }
+        }
+        Ok(conn
+            .at_halfedge(twin)
+            .face_or_boundary()?
+            .filter(|&other| {
+                // NOTE: Degenerate faces have a zero normal. The angle is NaN
+                // in that case, so their edges are treated as sharp.
+                face_normals[face].angle_between(face_normals[other]) <= threshold
+            })
+            .map(|other| (twin, other)))
+    };
+
+    let mut normals = Channel::<HalfEdgeId, Vec3>::new();
+    for (face, _) in conn.iter_faces() {
+        for h in conn.face_edges(face).iter_cpy() {
+            let mut normal = face_normals[face];
+
+            // Rotate around the vertex crossing the outgoing edges, until a
+            // sharp edge is found or the fan is closed.
+            let mut closed = false;
+            let mut current = h;
+            while let Some((twin, other)) = smooth_neighbor(current)? {
+                current = conn.at_halfedge(twin).next().try_end()?;
+                if current == h {
+                    closed = true;
+                    break;
+                }
+                normal += face_normals[other];
+            }
+
+            // Then rotate in the opposite direction, crossing the incoming
+            // edges, to collect the rest of the smooth region.
+            if !closed {
+                let mut current = h;
+                loop {
+                    let prev = conn.at_halfedge(current).previous().try_end()?;
+                    match smooth_neighbor(prev)? {
+                        Some((twin, other)) if twin != h => {
+                            normal += face_normals[other];
+                            current = twin;
+                        }
+                        _ => break,
+                    }
+                }
+            }
+
+            normals[h] = normal.normalize_or_zero();
+        }
+    }
+
+    Ok(normals)
+}
+
+/// Computes per-corner normals for this mesh, keeping the normals smooth across
+/// edges below the `angle_threshold` (in degrees) and flat across the rest. See
+/// [`generate_auto_smooth_normals_channel`].
+pub fn auto_smooth(mesh: &mut HalfEdgeMesh, angle_threshold: f32) -> Result<()> {
+    let normals = generate_auto_smooth_normals_channel(mesh, angle_threshold)?;
+    let normals_ch_id = mesh
+        .channels
+        .replace_or_create_channel("halfedge_normal", normals);
+
+    mesh.gen_config.smooth_normals = true;
+    mesh.default_channels.halfedge_normals = Some(normals_ch_id);
 
     Ok(())
 }
@@ -843,10 +1404,129 @@ pub fn generate_full_range_uvs_channel(mesh: &HalfEdgeMesh) -> Result<Channel<Ha
     Ok(uvs)
 }
 
-pub fn set_full_range_uvs(mesh: &mut HalfEdgeMesh) -> Result<()> {
+/// Stores `uvs` in the channel for the given UV set, replacing any previous
+/// UVs in it.
+pub fn set_uvs(mesh: &mut HalfEdgeMesh, uv_ch: UvChannel, uvs: Channel<HalfEdgeId, Vec3>) {
+    let uvs_ch_id = mesh
+        .channels
+        .replace_or_create_channel(uv_ch.channel_name(), uvs);
+    mesh.default_channels.set_uv_channel(uv_ch, Some(uvs_ch_id));
+}
+
+pub fn set_full_range_uvs(mesh: &mut HalfEdgeMesh, uv_ch: UvChannel) -> Result<()> {
     let uvs = generate_full_range_uvs_channel(mesh)?;
-    let uvs_ch_id = mesh.channels.replace_or_create_channel("uv", uvs);
-    mesh.default_channels.uvs = Some(uvs_ch_id);
+    set_uvs(mesh, uv_ch, uvs);
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UvProjection {
+    /// Projects the mesh onto the plane perpendicular to the axis.
+    Planar,
+    /// Wraps a cylinder around the axis.
+    Cylindrical,
+    /// Wraps a sphere around the mesh, with its poles along the axis.
+    Spherical,
+}
+
+/// Generates the UVs for `mesh` by projecting it onto a simple shape, given
+/// by `mode`, oriented along `axis`. Cylinders and spheres are centered at
+/// the center of the mesh's bounding box. The resulting UVs are multiplied
+/// by `scale`, and stored in the channel for the `uv_ch` UV set.
+///
+/// For cylindrical and spherical projections, faces that cross the seam get
+/// the U coordinate of their vertices past the seam increased by one, so that
+/// no face stretches across the whole texture.
+pub fn project_uvs(
+    mesh: &mut HalfEdgeMesh,
+    mode: UvProjection,
+    axis: Vec3,
+    scale: Vec2,
+    uv_ch: UvChannel,
+) -> Result<()> {
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("UV projection axis cannot be zero"))?;
+    let u_dir = axis.any_orthonormal_vector();
+    let v_dir = axis.cross(u_dir);
+
+    let mut uvs = Channel::<HalfEdgeId, Vec3>::new();
+    {
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+
+        let (bb_min, bb_max) = conn.iter_vertices_with_channel(&positions).fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), (_, _, pos)| (min.min(pos), max.max(pos)),
+        );
+        let center = (bb_min + bb_max) * 0.5;
+
+        // Returns the uv for the given position, and whether its U coordinate
+        // is meaningful (it isn't for points on the axis).
+        let project = |pos: Vec3| -> (Vec2, bool) {
+            match mode {
+                UvProjection::Planar => (Vec2::new(pos.dot(u_dir), pos.dot(v_dir)), true),
+                UvProjection::Cylindrical | UvProjection::Spherical => {
+                    let local = pos - center;
+                    let (x, y, z) = (local.dot(u_dir), local.dot(v_dir), local.dot(axis));
+                    let u = y.atan2(x) / (2.0 * PI) + 0.5;
+                    let has_u = x.abs() > 1e-6 || y.abs() > 1e-6;
+                    let v = if mode == UvProjection::Cylindrical {
+                        z
+                    } else {
+                        let len = local.length();
+                        if len > 0.0 {
+                            1.0 - (z / len).clamp(-1.0, 1.0).acos() / PI
+                        } else {
+                            0.5
+                        }
+                    };
+                    (Vec2::new(u, v), has_u)
+                }
+            }
+        };
+
+        for (face, _) in conn.iter_faces() {
+            let halfedges = conn.face_edges(face);
+            let mut face_uvs = halfedges
+                .iter()
+                .map(|h| Ok(project(positions[conn.at_halfedge(*h).vertex().try_end()?])))
+                .collect::<Result<SVec<_>>>()?;
+
+            if mode != UvProjection::Planar {
+                let mut us = face_uvs
+                    .iter()
+                    .filter(|(_, has_u)| *has_u)
+                    .map(|(uv, _)| uv.x)
+                    .collect::<SVec<_>>();
+                let min_u = us.iter().copied().fold(f32::INFINITY, f32::min);
+                let max_u = us.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let crosses_seam = max_u - min_u > 0.5;
+                if crosses_seam {
+                    for u in us.iter_mut() {
+                        if *u < 0.5 {
+                            *u += 1.0;
+                        }
+                    }
+                }
+                let avg_u = us.iter().sum::<f32>() / us.len().max(1) as f32;
+                for (uv, has_u) in face_uvs.iter_mut() {
+                    if !*has_u {
+                        // Points on the axis take the U of the rest of the face.
+                        uv.x = avg_u;
+                    } else if crosses_seam && uv.x < 0.5 {
+                        uv.x += 1.0;
+                    }
+                }
+            }
+
+            for (h, (uv, _)) in halfedges.iter_cpy().zip(face_uvs) {
+                uvs[h] = (uv * scale).extend(0.0);
+            }
+        }
+    }
+
+    set_uvs(mesh, uv_ch, uvs);
     Ok(())
 }
 
@@ -854,6 +1534,17 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
     if verts.len() != 4 {
         bail!("The make_quad operation only accepts quads.")
     }
+    make_face(conn, verts)
+}
+
+/// Creates a new face between the given `verts`, which must be at least 3.
+/// Fails if any of the halfedges between consecutive vertices is already part
+/// of a face.
+pub fn make_face(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()> {
+    let n = verts.len();
+    if n < 3 {
+        bail!("A face needs at least 3 vertices.")
+    }
 
     #[derive(Clone, Copy, Debug, Default)]
     struct EdgeInfo {
@@ -863,15 +1554,15 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
         existed: bool,
     }
 
-    // The new quad face
+    // The new face
     let face = conn.alloc_face(None);
 
-    // The halfedges in the interior loop, the one that will hold the quad
+    // The halfedges in the interior loop, the one that will hold the face
     // - NOTE: Default data is replaced in the loop
-    let mut a_edges = [EdgeInfo::default(); 4];
+    let mut a_edges = vec![EdgeInfo::default(); n];
     // The halfedges in the exterior loop, the twins of interior_hs, in the same
     // order, so their next pointers are reversed to the order of the array.
-    let mut b_edges = [EdgeInfo::default(); 4];
+    let mut b_edges = vec![EdgeInfo::default(); n];
 
     // Fill the arrays
     for (i, (v1, v2)) in verts.iter_cpy().circular_tuple_windows().enumerate() {
@@ -890,11 +1581,11 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
         };
     }
 
-    // If any of the inner edges already has a face, we can't make the quad.
+    // If any of the inner edges already has a face, we can't make the face.
     for e in a_edges.iter() {
         if !conn.at_halfedge(e.id).is_boundary()? {
             bail!(
-                "All halfedges must be in boundary to make a quad but {:?} isn't",
+                "All halfedges must be in boundary to make a face but {:?} isn't",
                 e.id
             )
         }
@@ -909,7 +1600,7 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
     // Compute the predecessors of a in the original graph. We can only do this
     // as long as the mesh is well-formed because the `previous()` operator
     // traverses a full halfedge loop.
-    let mut a_prev_orig = [Default::default(); 4];
+    let mut a_prev_orig = vec![HalfEdgeId::default(); n];
     for (i, a_i) in a_edges.iter_cpy().enumerate() {
         if a_i.existed {
             a_prev_orig[i] = conn.at_halfedge(a_i.id).previous().try_end()?;
@@ -919,18 +1610,18 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
     // Fix the next pointer for 'a' predecessors (if any)
     for (i, a_i) in a_edges.iter_cpy().enumerate() {
         if a_i.existed {
-            conn[a_prev_orig[i]].next = Some(b_edges[prev_i(i, 4)].id);
+            conn[a_prev_orig[i]].next = Some(b_edges[prev_i(i, n)].id);
         }
     }
 
     // Fill data for the 'b' halfedges.
     for (i, b_i) in b_edges.iter_cpy().enumerate() {
         conn[b_i.id].twin = Some(a_edges[i].id);
-        conn[b_i.id].vertex = Some(verts[(i + 1) % 4]);
+        conn[b_i.id].vertex = Some(verts[(i + 1) % n]);
         conn[b_i.id].next = if b_i.existed {
             conn[b_i.id].next
         } else {
-            let a_prev = a_edges[prev_i(i, 4)];
+            let a_prev = a_edges[prev_i(i, n)];
             if a_prev.existed {
                 Some(
                     conn[a_prev.id]
@@ -938,7 +1629,7 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
                         .ok_or_else(|| anyhow!("Fatal: Halfedge should have next"))?,
                 )
             } else {
-                Some(b_edges[prev_i(i, 4)].id)
+                Some(b_edges[prev_i(i, n)].id)
             }
         };
         conn[b_i.id].face = if b_i.existed {
@@ -951,7 +1642,7 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
     // Fill data for the 'a' halfedges. This happens last because we need some
     // data from the original connectivity before we override it.
     for (i, a_i) in a_edges.iter_cpy().enumerate() {
-        conn[a_i.id].next = Some(a_edges[(i + 1) % 4].id);
+        conn[a_i.id].next = Some(a_edges[(i + 1) % n].id);
         conn[a_i.id].twin = Some(b_edges[i].id);
         conn[a_i.id].face = Some(face);
         conn[a_i.id].vertex = Some(verts[i]);
@@ -970,22 +1661,24 @@ pub fn make_quad(conn: &mut MeshConnectivity, verts: &[VertexId]) -> Result<()>
 
 /// Connects two (not necessarily closed) edge chains with faces. Edges are
 /// implicitly defined by the 2-size windows of vertices.
+///
+/// When both chains have the same length, they are bridged with quads.
+/// Otherwise, triangles are distributed evenly along the bridge to make up for
+/// the extra edges in the longer chain.
 pub fn bridge_chains(
     mesh: &mut HalfEdgeMesh,
     chain_1: &[VertexId],
     chain_2: &[VertexId],
     is_closed: bool,
 ) -> Result<()> {
-    if chain_1.len() != chain_2.len() {
-        bail!("Loops to bridge need to be of the same length.")
-    }
     if chain_1.is_empty() || chain_2.is_empty() {
         bail!("Loops to bridge cannot be empty.")
     }
 
     let mut conn = mesh.write_connectivity();
     let positions = mesh.read_positions();
-    let chain_len = chain_1.len(); // same length
+    let len_1 = chain_1.len();
+    let len_2 = chain_2.len();
 
     for (v, w) in chain_1
         .iter()
@@ -1003,35 +1696,39 @@ pub fn bridge_chains(
         }
     }
 
+    // NOTE: We traverse chain_2 backwards with respect to chain_1. This is
+    // because the two chains are facing in opposite directions, otherwise we
+    // wouldn't be able to bridge them
+    let chain_2_rev = chain_2.iter_cpy().rev().collect_vec();
+
+    // The vertex of chain_2 matched with the i-th vertex of chain_1, when
+    // both chains are traversed at the same relative speed.
+    let matching_vertex = |i: usize| chain_2_rev[i * len_2 / len_1];
+
     // Each vertex in the first loop needs to be mapped to a vertex in the other
     // loop. When the loops are open, there's just a single way to do it, but
-    // when the loops are closed there's `loop_len` possible combinations. We
+    // when the loops are closed there's `len_1` possible combinations. We
     // find the best possible mapping which minimizes the sum of distances
     // between vertex pairs
     let chain_1_best_shift = if is_closed {
-        // Computes the sum of distances after shifting verts_1 by i positions
+        // Computes the sum of distances after shifting chain_1 by i positions
         let sum_distances_rotated = |i: usize| {
-            let x = FloatOrd(
-                rotate_iter(chain_1.iter_cpy(), i, chain_len)
+            FloatOrd(
+                rotate_iter(chain_1.iter_cpy(), i, len_1)
                     .enumerate()
                     .map(|(j, v_sh)| {
-                        // NOTE: We index verts_2 backwards with respect to
-                        // verts_1. This is because the two chains are facing in
-                        // opposite directions, otherwise we wouldn't be able to
-                        // bridge them
-                        positions[v_sh].distance_squared(positions[chain_2[(chain_len - 1) - j]])
+                        positions[v_sh].distance_squared(positions[matching_vertex(j)])
                     })
                     .sum::<f32>(),
-            );
-            x
+            )
         };
 
         // We memoize the sum_distances in a vec because it's a relatively
         // expensive function and `position_min_by_key` will call it multiple
         // times per key.
-        let distances = (0..chain_len).map(sum_distances_rotated).collect_vec();
+        let distances = (0..len_1).map(sum_distances_rotated).collect_vec();
 
-        (0..chain_len)
+        (0..len_1)
             .position_min_by_key(|i| distances[*i])
             .expect("Loop should not be empty.")
     } else {
@@ -1039,26 +1736,38 @@ pub fn bridge_chains(
         0
     };
 
-    let chain_1_shifted =
-        rotate_iter(chain_1.iter_cpy(), chain_1_best_shift, chain_len).collect_vec();
+    let chain_1_shifted = rotate_iter(chain_1.iter_cpy(), chain_1_best_shift, len_1).collect_vec();
 
-    for (i, ((v1, v2), (v3, v4))) in chain_1_shifted
-        .iter_cpy()
-        .branch(
-            is_closed,
-            |it| it.circular_tuple_windows(),
-            |it| it.tuple_windows(),
-        )
-        .zip(chain_2.iter_cpy().rev().branch(
-            is_closed,
-            |it| it.circular_tuple_windows(),
-            |it| it.tuple_windows(),
-        ))
-        .enumerate()
-    {
-        conn.add_debug_vertex(v1, DebugMark::blue(&format!("{i}",)));
-        conn.add_debug_vertex(v3, DebugMark::blue(&format!("{i}",)));
-        make_quad(&mut conn, &[v1, v2, v4, v3])?;
+    // Walk both chains at once, one edge at a time. When the next vertex in
+    // both chains is reached at the same relative position, the edges are
+    // bridged with a quad. Otherwise, a triangle is added to advance along the
+    // chain that's falling behind.
+    let (num_edges_1, num_edges_2) = if is_closed {
+        (len_1, len_2)
+    } else {
+        (len_1 - 1, len_2 - 1)
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < num_edges_1 || j < num_edges_2 {
+        let (v1, v2) = (chain_1_shifted[i % len_1], chain_1_shifted[(i + 1) % len_1]);
+        let (v3, v4) = (chain_2_rev[j % len_2], chain_2_rev[(j + 1) % len_2]);
+        conn.add_debug_vertex(v1, DebugMark::blue(&format!("{i}")));
+        conn.add_debug_vertex(v3, DebugMark::blue(&format!("{j}")));
+
+        // Compares the relative positions (i + 1) / num_edges_1 and
+        // (j + 1) / num_edges_2, using integers to avoid rounding issues.
+        let (pos_1, pos_2) = ((i + 1) * num_edges_2, (j + 1) * num_edges_1);
+        if i < num_edges_1 && j < num_edges_2 && pos_1 == pos_2 {
+            make_quad(&mut conn, &[v1, v2, v4, v3])?;
+            i += 1;
+            j += 1;
+        } else if j == num_edges_2 || (i < num_edges_1 && pos_1 < pos_2) {
+            make_face(&mut conn, &[v1, v2, v3])?;
+            i += 1;
+        } else {
+            make_face(&mut conn, &[v1, v4, v3])?;
+            j += 1;
+        }
     }
 
     Ok(())
@@ -1342,6 +2051,44 @@ fn add_edge_chain(mesh: &HalfEdgeMesh, start: VertexId, end: Vec3) -> Result<Ver
     Ok(end_v)
 }
 
+/// Joins the two tips of a polyline, `start` and `end`, with a new edge,
+/// turning it into a closed loop.
+///
+/// This is an internal operation, used alongside `add_edge_chain` to
+/// incrementally construct closed polylines.
+fn close_edge_chain(mesh: &HalfEdgeMesh, start: VertexId, end: VertexId) -> Result<()> {
+    let mut conn = mesh.write_connectivity();
+    let tip_halfedges = |v: VertexId| -> Result<(HalfEdgeId, HalfEdgeId)> {
+        let outgoing = conn.at_vertex(v).outgoing_halfedges()?;
+        let incoming = conn.at_vertex(v).incoming_halfedges()?;
+        if incoming.len() != 1 || outgoing.len() != 1 {
+            bail!("Vertex {v:?} is not at the tip of a polyline")
+        }
+        Ok((incoming[0], outgoing[0]))
+    };
+    let (start_inc, start_out) = tip_halfedges(start)?;
+    let (end_inc, end_out) = tip_halfedges(end)?;
+
+    let h_start_end = conn.alloc_halfedge(HalfEdge {
+        vertex: Some(start),
+        ..Default::default()
+    });
+    let h_end_start = conn.alloc_halfedge(HalfEdge {
+        vertex: Some(end),
+        ..Default::default()
+    });
+
+    conn[h_start_end].twin = Some(h_end_start);
+    conn[h_start_end].next = Some(end_out);
+    conn[h_end_start].twin = Some(h_start_end);
+    conn[h_end_start].next = Some(start_out);
+
+    conn[start_inc].next = Some(h_start_end);
+    conn[end_inc].next = Some(h_end_start);
+
+    Ok(())
+}
+
 /// Adds an empty vertex to the mesh. Useful when the mesh is representing a
 /// point cloud. Otherwise it's preferrable to use higher-level operators
 pub fn add_vertex(this: &mut HalfEdgeMesh, pos: Vec3) -> Result<()> {
@@ -1371,8 +2118,6 @@ pub fn vertex_attribute_transfer<V: ChannelValue>(
     dst_mesh: &mut HalfEdgeMesh,
     channel_name: &str,
 ) -> Result<()> {
-    use rstar::{PointDistance, RTree, RTreeObject, AABB};
-
     // This is not that difficult to support, I just didn't have time to do it.
     // If done naively, this would lead to a double-borrow error on the channel.
     if channel_name == "position" {
@@ -1388,36 +2133,10 @@ pub fn vertex_attribute_transfer<V: ChannelValue>(
         .channels
         .ensure_channel::<VertexId, V>(channel_name);
 
-    // Build a spatial index for the vertices in the source mesh. This takes
-    // O(n) but in turn allows very efficient nearest-neighbor queries.
-    pub struct VertexPos {
-        vertex: VertexId,
-        pos: Vec3,
-    }
-
-    impl RTreeObject for VertexPos {
-        type Envelope = AABB<[f32; 3]>;
-        fn envelope(&self) -> Self::Envelope {
-            AABB::from_point(self.pos.to_array())
-        }
-    }
-
-    impl PointDistance for VertexPos {
-        fn distance_2(
-            &self,
-            point: &<Self::Envelope as rstar::Envelope>::Point,
-        ) -> <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar {
-            self.pos.distance_squared(Vec3::from_slice(point))
-        }
-    }
-
-    let tree_index = RTree::bulk_load(
-        src_mesh
-            .read_connectivity()
-            .iter_vertices_with_channel(&src_mesh.read_positions())
-            .map(|(v_id, _, pos)| VertexPos { vertex: v_id, pos })
-            .collect_vec(),
-    );
+    // The spatial index for the vertices in the source mesh allows very
+    // efficient nearest-neighbor queries. It is cached in the mesh, so
+    // repeated transfers from the same source don't need to rebuild it.
+    let tree_index = src_mesh.vertex_spatial_index();
 
     let src_channel = src_mesh.channels.read_channel(src_channel_id)?;
     let mut dst_channel = dst_mesh.channels.write_channel(dst_channel_id)?;
@@ -1428,20 +2147,302 @@ pub fn vertex_attribute_transfer<V: ChannelValue>(
         let nearest = tree_index
             .nearest_neighbor(&dst_pos.to_array())
             .ok_or_else(|| anyhow!("No nearest neighbor"))?;
-        let src_value = src_channel[nearest.vertex];
+        let src_value = src_channel[nearest.vertex].clone();
         dst_channel[dst_v] = src_value;
     }
 
     Ok(())
 }
 
+/// Welds together all the vertices of `mesh` that are closer than
+/// `threshold`, returning the number of vertices that were welded away.
+/// Vertices are visited in order, and each vertex absorbs all the vertices
+/// within the threshold that haven't been welded yet, keeping its position.
+///
+/// Vertices that share an edge with the vertex they are welded into are
+/// merged with `collapse_edge`. The rest, like the coincident vertices of
+/// meshes put together with `merge_with`, are welded with `weld_vertices`,
+/// which rebuilds connectivity for the affected faces. Either way, the ids of
+/// all the surviving elements (and thus their channel values) are kept.
+///
+/// Edges and faces that degenerate because of the weld are removed. This
+/// includes pairs of faces that end up on top of each other with opposite
+/// windings, which would otherwise form two-sided faces. The operation fails
+/// without modifying the mesh when welding would produce non-manifold
+/// geometry, or when the mesh contains edges that are not part of any face.
+pub fn weld_by_distance(mesh: &mut HalfEdgeMesh, threshold: f32) -> Result<usize> {
+    let tree_index = mesh.vertex_spatial_index();
+
+    // --- Cluster the vertices ---
+    let threshold2 = threshold.max(0.0).powi(2);
+    // Maps each welded vertex to the vertex it was welded into
+    let mut welded_into = HashMap::<VertexId, VertexId>::new();
+    {
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        for (v, _) in conn.iter_vertices() {
+            if welded_into.contains_key(&v) {
+                continue;
+            }
+            for other in tree_index.locate_within_distance(positions[v].to_array(), threshold2) {
+                if other.vertex != v && !welded_into.contains_key(&other.vertex) {
+                    welded_into.insert(other.vertex, v);
+                }
+            }
+        }
+    }
+
+    // Collapsing edges modifies the mesh right away, so the connectivity is
+    // restored if welding the remaining vertices fails.
+    let backup = mesh.read_connectivity().clone();
+    let result = (|| -> Result<usize> {
+        check_no_loose_edges(&mesh.read_connectivity())?;
+        let mut num_collapsed = 0;
+        let mut remaining = HashMap::new();
+        for (w, v) in welded_into.into_iter().sorted() {
+            let mut conn = mesh.write_connectivity();
+            match conn.at_vertex(v).halfedge_to(w).try_end() {
+                Ok(h) => {
+                    collapse_edge(&mut conn, h)?;
+                    num_collapsed += 1;
+                }
+                Err(_) => {
+                    remaining.insert(w, v);
+                }
+            }
+        }
+        Ok(num_collapsed + weld_vertices(mesh, &remaining)?)
+    })();
+    if result.is_err() {
+        *mesh.write_connectivity() = backup;
+    }
+    result
+}
+
+/// Returns an error if `conn` has edges that are not part of any face, which
+/// can't be welded.
+fn check_no_loose_edges(conn: &MeshConnectivity) -> Result<()> {
+    for (h, halfedge) in conn.iter_halfedges() {
+        if halfedge.face.is_none() && conn.at_halfedge(h).twin().face().try_end().is_err() {
+            bail!("Cannot weld vertices on meshes with edges that are not part of a face")
+        }
+    }
+    Ok(())
+}
+
+/// Welds each vertex in the keys of `welded_into` into the vertex it maps to,
+/// returning the number of vertices that were welded away. The target
+/// vertices must not be welded themselves. See `weld_by_distance` for the
+/// details and failure cases, which are the same.
+pub fn weld_vertices(
+    mesh: &mut HalfEdgeMesh,
+    welded_into: &HashMap<VertexId, VertexId>,
+) -> Result<usize> {
+    let mut conn = mesh.write_connectivity();
+    let num_welds = welded_into.len();
+    if num_welds == 0 {
+        return Ok(0);
+    }
+    let welded = |v: VertexId| welded_into.get(&v).copied().unwrap_or(v);
+
+    check_no_loose_edges(&conn)?;
+
+    // --- Compute the new face loops ---
+    // Nothing is modified in this step, so we can bail in case of error.
+
+    // For each face, the halfedges that survive the weld and their source vertex.
+    let mut face_loops = Vec::<(FaceId, SVec<(HalfEdgeId, VertexId)>)>::new();
+    let mut removed_faces = Vec::<FaceId>::new();
+    for (face, _) in conn.iter_faces() {
+        let halfedges = conn.face_edges(face);
+        let sources = halfedges
+            .iter()
+            .map(|h| Ok(welded(conn.at_halfedge(*h).vertex().try_end()?)))
+            .collect::<Result<SVec<_>>>()?;
+        // Edges whose endpoints got welded together are collapsed.
+        let n = halfedges.len();
+        let kept = (0..n)
+            .filter(|&i| sources[i] != sources[(i + 1) % n])
+            .map(|i| (halfedges[i], sources[i]))
+            .collect::<SVec<_>>();
+        if kept.len() < 3 {
+            removed_faces.push(face);
+        } else if kept.iter().map(|(_, v)| v).duplicates().next().is_some() {
+            bail!("Cannot weld vertices: A face would be pinched at one of its vertices")
+        } else {
+            face_loops.push((face, kept));
+        }
+    }
+
+    // Faces on top of each other with opposite winding are removed. When
+    // they have the same winding, the first one is kept.
+    fn canonical_cycle(vertices: impl Iterator<Item = VertexId>) -> SVec<VertexId> {
+        let mut cycle = vertices.collect::<SVec<_>>();
+        let min_idx = cycle.iter().position_min().unwrap_or(0);
+        cycle.rotate_left(min_idx);
+        cycle
+    }
+    let mut faces_by_cycle = HashMap::<SVec<VertexId>, SVec<usize>>::new();
+    for (i, (_, face_loop)) in face_loops.iter().enumerate() {
+        let cycle = canonical_cycle(face_loop.iter().map(|(_, v)| *v));
+        faces_by_cycle.entry(cycle).or_default().push(i);
+    }
+    let mut i = 0;
+    face_loops.retain(|(face, face_loop)| {
+        let forward = canonical_cycle(face_loop.iter().map(|(_, v)| *v));
+        let backward = canonical_cycle(face_loop.iter().rev().map(|(_, v)| *v));
+        let keep = !faces_by_cycle.contains_key(&backward) && faces_by_cycle[&forward][0] == i;
+        if !keep {
+            removed_faces.push(*face);
+        }
+        i += 1;
+        keep
+    });
+
+    let mut edge_map = HashMap::<(VertexId, VertexId), HalfEdgeId>::new();
+    for (_, face_loop) in &face_loops {
+        for ((h, v), (_, w)) in face_loop.iter().circular_tuple_windows() {
+            if edge_map.insert((*v, *w), *h).is_some() {
+                bail!("Cannot weld vertices: The result would have non-manifold edges")
+            }
+        }
+    }
+
+    // Check that all the faces around each vertex form a single fan. The
+    // faces around a vertex are linked by the edges they share, forming a
+    // chain (for boundary vertices) or a cycle. Any other structure would
+    // mean the vertex is non-manifold.
+    let mut links = HashMap::<VertexId, SVec<(VertexId, VertexId)>>::new();
+    for (_, face_loop) in &face_loops {
+        for ((_, u), (_, v), (_, w)) in face_loop.iter().circular_tuple_windows() {
+            links.entry(*v).or_default().push((*w, *u));
+        }
+    }
+    for link in links.values() {
+        let next = link.iter().copied().collect::<HashMap<_, _>>();
+        let has_incoming = link.iter().map(|(_, b)| *b).collect::<HashSet<_>>();
+        let num_nodes = link.iter().flat_map(|(a, b)| [*a, *b]).unique().count();
+        let start = link
+            .iter()
+            .map(|(a, _)| *a)
+            .find(|a| !has_incoming.contains(a))
+            .unwrap_or(link[0].0);
+        let mut count = 1;
+        let mut node = start;
+        while let Some(&n) = next.get(&node) {
+            if n == start || count > num_nodes {
+                break;
+            }
+            count += 1;
+            node = n;
+        }
+        if count != num_nodes {
+            bail!("Cannot weld vertices: The result would have non-manifold vertices")
+        }
+    }
+
+    // --- Apply the changes ---
+    let kept_halfedges = face_loops
+        .iter()
+        .flat_map(|(_, face_loop)| face_loop.iter().map(|(h, _)| *h))
+        .collect::<HashSet<_>>();
+    let removed_halfedges = conn
+        .iter_halfedges()
+        .map(|(h, _)| h)
+        .filter(|h| !kept_halfedges.contains(h))
+        .collect_vec();
+    for h in removed_halfedges {
+        conn.remove_halfedge(h);
+    }
+    for face in removed_faces {
+        conn.remove_face(face);
+    }
+    for v in welded_into.keys() {
+        conn.remove_vertex(*v);
+    }
+
+    // Vertices that were connected before the weld, but end up without any
+    // faces, are removed too.
+    let connected_vertices = conn
+        .iter_vertices()
+        .filter(|(_, vertex)| vertex.halfedge.is_some())
+        .map(|(v, _)| v)
+        .collect_vec();
+    for v in connected_vertices.iter_cpy() {
+        conn[v].halfedge = None;
+    }
+
+    for (face, face_loop) in &face_loops {
+        conn[*face].halfedge = Some(face_loop[0].0);
+        for ((h, v), (h_next, w)) in face_loop.iter().circular_tuple_windows() {
+            conn[*h].vertex = Some(*v);
+            conn[*h].next = Some(*h_next);
+            conn[*h].twin = edge_map.get(&(*w, *v)).copied();
+            conn[*v].halfedge = Some(*h);
+        }
+    }
+    conn.add_boundary_halfedges();
+
+    for v in connected_vertices {
+        if conn[v].halfedge.is_none() {
+            conn.remove_vertex(v);
+        }
+    }
+
+    Ok(num_welds)
+}
+
+/// Sets the crease weight of all the edges in `selection` to `weight`, which
+/// is clamped to the [0, 1] range. Both halfedges of each selected edge are
+/// updated. Catmull-Clark subdivision keeps edges with a weight of 1.0 sharp,
+/// and lower weights keep them sharp for fewer subdivision levels, e.g. a
+/// weight of 0.5 for one level and a weight of 0.9 for nine levels.
+pub fn set_crease(
+    mesh: &mut HalfEdgeMesh,
+    selection: &SelectionExpression,
+    weight: f32,
+) -> Result<()> {
+    let weight = weight.clamp(0.0, 1.0);
+    let ch_id = mesh
+        .channels
+        .ensure_channel::<HalfEdgeId, f32>(compact_mesh::CREASE_CHANNEL);
+    let mut crease_ch = mesh.channels.write_channel(ch_id)?;
+    let conn = mesh.read_connectivity();
+    for h in mesh.resolve_halfedge_selection_full(selection)? {
+        crease_ch[h] = weight;
+        crease_ch[conn.at_halfedge(h).twin().try_end()?] = weight;
+    }
+    Ok(())
+}
+
+/// Marks all the edges in `selection` as sharp, or clears the mark when
+/// `value` is false. Both halfedges of each selected edge are updated. Sharp
+/// edges are highlighted in the viewport, subdivided as fully sharp creases,
+/// and split the normals computed by `auto_smooth`.
+pub fn mark_sharp(
+    mesh: &mut HalfEdgeMesh,
+    selection: &SelectionExpression,
+    value: bool,
+) -> Result<()> {
+    let ch_id = mesh
+        .channels
+        .ensure_channel::<HalfEdgeId, bool>(compact_mesh::SHARP_CHANNEL);
+    let mut sharp_ch = mesh.channels.write_channel(ch_id)?;
+    let conn = mesh.read_connectivity();
+    for h in mesh.resolve_halfedge_selection_full(selection)? {
+        sharp_ch[h] = value;
+        sharp_ch[conn.at_halfedge(h).twin().try_end()?] = value;
+    }
+    Ok(())
+}
+
 pub fn set_material(
     mesh: &mut HalfEdgeMesh,
     selection: &SelectionExpression,
-    material: f32,
+    material: i32,
 ) -> Result<()> {
     // TODO: Use default channels?
-    let ch_id = mesh.channels.ensure_channel::<FaceId, f32>("material");
+    let ch_id = mesh.channels.ensure_channel::<FaceId, i32>("material");
     let mut material_ch = mesh.channels.write_channel(ch_id)?;
     let ids = mesh.resolve_face_selection_full(selection)?;
     for id in ids {
@@ -1450,6 +2451,241 @@ pub fn set_material(
     Ok(())
 }
 
+/// Sets the `color` vertex channel to `color` for all the vertices in
+/// `selection`. The channel is created if it doesn't exist, with all other
+/// vertices set to black.
+pub fn set_vertex_color(
+    mesh: &mut HalfEdgeMesh,
+    selection: &SelectionExpression,
+    color: Vec3,
+) -> Result<()> {
+    let ch_id = mesh.channels.ensure_channel::<VertexId, Vec3>("color");
+    let mut color_ch = mesh.channels.write_channel(ch_id)?;
+    for id in mesh.resolve_vertex_selection_full(selection)? {
+        color_ch[id] = color;
+    }
+    Ok(())
+}
+
+/// Sets the string channel `name` to `value` for all the elements of the given
+/// key type `kt` in `selection`. The channel is created if it doesn't exist.
+pub fn set_string_attribute(
+    mesh: &mut HalfEdgeMesh,
+    kt: ChannelKeyType,
+    selection: &SelectionExpression,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    macro_rules! impl_branch {
+        ($channel_type:ty, $resolve_fn:ident) => {{
+            let ch_id = mesh.channels.ensure_channel::<$channel_type, String>(name);
+            let mut string_ch = mesh.channels.write_channel(ch_id)?;
+            let ids = mesh.$resolve_fn(selection)?;
+            for id in ids {
+                string_ch[id] = value.to_owned();
+            }
+        }};
+    }
+
+    match kt {
+        ChannelKeyType::VertexId => {
+            impl_branch! { VertexId, resolve_vertex_selection_full }
+        }
+        ChannelKeyType::FaceId => {
+            impl_branch! { FaceId, resolve_face_selection_full }
+        }
+        ChannelKeyType::HalfEdgeId => {
+            impl_branch! { HalfEdgeId, resolve_halfedge_selection_full }
+        }
+    }
+
+    Ok(())
+}
+
+/// The element-wise operations supported by [`channel_math`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelMathOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+}
+
+/// Channel values that can be combined using a [`ChannelMathOp`].
+pub trait ChannelArith: ChannelValue + Copy {
+    fn apply(op: ChannelMathOp, a: Self, b: Self) -> Self;
+}
+
+macro_rules! impl_channel_arith {
+    ($t:ty) => {
+        impl ChannelArith for $t {
+            fn apply(op: ChannelMathOp, a: Self, b: Self) -> Self {
+                match op {
+                    ChannelMathOp::Add => a + b,
+                    ChannelMathOp::Sub => a - b,
+                    ChannelMathOp::Mul => a * b,
+                    ChannelMathOp::Div => a / b,
+                    ChannelMathOp::Min => a.min(b),
+                    ChannelMathOp::Max => a.max(b),
+                }
+            }
+        }
+    };
+}
+impl_channel_arith!(f32);
+impl_channel_arith!(Vec3);
+
+impl ChannelArith for i32 {
+    fn apply(op: ChannelMathOp, a: Self, b: Self) -> Self {
+        match op {
+            ChannelMathOp::Add => a.wrapping_add(b),
+            ChannelMathOp::Sub => a.wrapping_sub(b),
+            ChannelMathOp::Mul => a.wrapping_mul(b),
+            // NOTE: Integer division by zero would panic. Returning zero
+            // instead lets the operation complete for the other elements.
+            ChannelMathOp::Div => a.checked_div(b).unwrap_or(0),
+            ChannelMathOp::Min => a.min(b),
+            ChannelMathOp::Max => a.max(b),
+        }
+    }
+}
+
+/// Typed version of [`channel_math`].
+pub fn channel_math_typed<K: ChannelKey, V: ChannelArith>(
+    mesh: &mut HalfEdgeMesh,
+    out_name: &str,
+    a_name: &str,
+    op: ChannelMathOp,
+    b_name: &str,
+) -> Result<()> {
+    use slotmap::Key;
+    let keys: Vec<K> = {
+        let conn = mesh.read_connectivity();
+        match K::key_type() {
+            ChannelKeyType::VertexId => conn
+                .iter_vertices()
+                .map(|(v, _)| K::cast_from_ffi(v.data().as_ffi()))
+                .collect(),
+            ChannelKeyType::FaceId => conn
+                .iter_faces()
+                .map(|(f, _)| K::cast_from_ffi(f.data().as_ffi()))
+                .collect(),
+            ChannelKeyType::HalfEdgeId => conn
+                .iter_halfedges()
+                .map(|(h, _)| K::cast_from_ffi(h.data().as_ffi()))
+                .collect(),
+        }
+    };
+
+    // The values are computed before writing, because the output channel may
+    // be one of the inputs.
+    let values = {
+        let a = mesh.channels.read_channel_by_name::<K, V>(a_name)?;
+        let b = mesh.channels.read_channel_by_name::<K, V>(b_name)?;
+        keys.iter().map(|&k| V::apply(op, a[k], b[k])).collect_vec()
+    };
+
+    let out_id = mesh.channels.ensure_channel::<K, V>(out_name);
+    let mut out = mesh.channels.write_channel(out_id)?;
+    for (k, value) in keys.into_iter().zip(values) {
+        out[k] = value;
+    }
+    Ok(())
+}
+
+/// Combines the channels named `a_name` and `b_name`, with key type `kt` and
+/// value type `vt`, element by element using `op`. The result is stored in
+/// the `out_name` channel, which is created if it doesn't exist. Only
+/// numeric channels (f32, i32 and Vec3) are supported.
+pub fn channel_math(
+    mesh: &mut HalfEdgeMesh,
+    kt: ChannelKeyType,
+    vt: ChannelValueType,
+    out_name: &str,
+    a_name: &str,
+    op: ChannelMathOp,
+    b_name: &str,
+) -> Result<()> {
+    macro_rules! do_match {
+        ($($kt:ident, $vt:ident);*) => {
+            match (kt, vt) {
+                $(
+                    (ChannelKeyType::$kt, ChannelValueType::$vt) => {
+                        channel_math_typed::<$kt, $vt>(mesh, out_name, a_name, op, b_name)
+                    }
+                )*
+                _ => bail!("Channel math is not supported for {vt:?} channels"),
+            }
+        };
+    }
+
+    do_match! {
+        VertexId, f32;
+        VertexId, i32;
+        VertexId, Vec3;
+        FaceId, f32;
+        FaceId, i32;
+        FaceId, Vec3;
+        HalfEdgeId, f32;
+        HalfEdgeId, i32;
+        HalfEdgeId, Vec3
+    }
+}
+
+/// Deletes the channel called `name` with key type `kt` and value type `vt`
+/// from `mesh`. The position channel can't be deleted, but other default
+/// channels like normals or UVs can, and the mesh will behave as if they were
+/// never generated.
+pub fn delete_channel(
+    mesh: &mut HalfEdgeMesh,
+    kt: ChannelKeyType,
+    vt: ChannelValueType,
+    name: &str,
+) -> Result<()> {
+    let raw_id = mesh
+        .channels
+        .channel_id_dyn(kt, vt, name)
+        .ok_or_else(|| anyhow!("Channel named {name} does not exist"))?;
+    if (kt, vt) == (ChannelKeyType::VertexId, ChannelValueType::Vec3)
+        && ChannelId::new(raw_id) == mesh.default_channels.position
+    {
+        bail!("The position channel can't be deleted");
+    }
+
+    // The default channels are only cleared once the channel is gone, so the
+    // mesh is left untouched if the removal fails.
+    mesh.channels.remove_channel_by_name(kt, vt, name)?;
+
+    let defaults = &mut mesh.default_channels;
+    match (kt, vt) {
+        (ChannelKeyType::VertexId, ChannelValueType::Vec3) => {
+            if defaults.vertex_normals == Some(ChannelId::new(raw_id)) {
+                defaults.vertex_normals = None;
+            }
+        }
+        (ChannelKeyType::FaceId, ChannelValueType::Vec3) => {
+            if defaults.face_normals == Some(ChannelId::new(raw_id)) {
+                defaults.face_normals = None;
+            }
+        }
+        (ChannelKeyType::HalfEdgeId, ChannelValueType::Vec3) => {
+            let id = ChannelId::new(raw_id);
+            for uv_ch in UvChannel::ALL {
+                if defaults.uv_channel(uv_ch) == Some(id) {
+                    defaults.set_uv_channel(uv_ch, None);
+                }
+            }
+            if defaults.halfedge_normals == Some(id) {
+                defaults.halfedge_normals = None;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 /// TODO: Remove this once #[feature(map_first_last)] stabilizes
 pub trait MapPolyfill<T> {
     fn pop_first2(&mut self) -> Option<T>;
@@ -1475,7 +2711,11 @@ where
     }
 }
 
-pub fn copy_to_points(points: &HalfEdgeMesh, cpy_mesh: &HalfEdgeMesh) -> Result<HalfEdgeMesh> {
+pub fn copy_to_points(points: &HalfEdgeMesh, meshes: &[HalfEdgeMesh]) -> Result<HalfEdgeMesh> {
+    if meshes.is_empty() {
+        bail!("At least one mesh is needed to copy to points");
+    }
+
     let conn = points.read_connectivity();
     let position_ch = points.read_positions();
     let size_ch = points
@@ -1487,10 +2727,28 @@ pub fn copy_to_points(points: &HalfEdgeMesh, cpy_mesh: &HalfEdgeMesh) -> Result<
     let tangent_ch = points
         .channels
         .read_channel_by_name::<VertexId, Vec3>("tangent");
+    let rotation_ch = points
+        .channels
+        .read_channel_by_name::<VertexId, Vec3>("rotation");
+    let instance_index_ch = points
+        .channels
+        .read_channel_by_name::<VertexId, f32>("instance_index");
 
     let mut result = HalfEdgeMesh::new();
     for (i, (v, _)) in conn.iter_vertices().enumerate() {
-        let mut cpy_instance = cpy_mesh.clone();
+        let mesh_idx = if let Ok(ref instance_index) = instance_index_ch {
+            let idx = instance_index[v].round();
+            if idx < 0.0 || idx as usize >= meshes.len() {
+                bail!(
+                    "Invalid instance_index {idx} for point {v:?}. There are {} meshes.",
+                    meshes.len()
+                );
+            }
+            idx as usize
+        } else {
+            0
+        };
+        let mut cpy_instance = meshes[mesh_idx].clone();
         let instance_idx_ch_id = cpy_instance.channels.create_channel("instance_idx")?;
 
         // Mark all halfedges of this instance with its index
@@ -1506,22 +2764,23 @@ pub fn copy_to_points(points: &HalfEdgeMesh, cpy_mesh: &HalfEdgeMesh) -> Result<
             Vec3::ONE
         };
 
-        let rotate =
-            if let (Ok(normal_ch), Ok(tangent_ch)) = (normal_ch.as_ref(), tangent_ch.as_ref()) {
-                let normal = normal_ch[v];
-                let tangent = tangent_ch[v];
-                let cotangent = normal.cross(tangent);
-                let (_, rotate, _) = glam::Affine3A::from_cols(
-                    cotangent.into(),
-                    normal.into(),
-                    tangent.into(),
-                    glam::Vec3A::ZERO,
-                )
-                .to_scale_rotation_translation();
-                rotate.to_euler(glam::EulerRot::XYZ).into()
-            } else {
-                Vec3::ZERO
-            };
+        let rotate = if let Ok(ref rotation) = rotation_ch {
+            rotation[v]
+        } else if let (Ok(normal_ch), Ok(tangent_ch)) = (normal_ch.as_ref(), tangent_ch.as_ref()) {
+            let normal = normal_ch[v];
+            let tangent = tangent_ch[v];
+            let cotangent = normal.cross(tangent);
+            let (_, rotate, _) = glam::Affine3A::from_cols(
+                cotangent.into(),
+                normal.into(),
+                tangent.into(),
+                glam::Vec3A::ZERO,
+            )
+            .to_scale_rotation_translation();
+            rotate.to_euler(glam::EulerRot::XYZ).into()
+        } else {
+            Vec3::ZERO
+        };
 
         // Drop the channels so we can mutate the whole mesh
         drop(cpy_instance_conn);
@@ -1534,16 +2793,113 @@ pub fn copy_to_points(points: &HalfEdgeMesh, cpy_mesh: &HalfEdgeMesh) -> Result<
     Ok(result)
 }
 
+/// Returns a new mesh with `count` copies of `mesh`, each one rotated around
+/// the given `axis` going through `center`. The copies are evenly distributed
+/// along `total_angle` degrees, with the first copy left in place.
+///
+/// When `total_angle` is a full turn, the last copy is not placed on top of
+/// the first one. The copies are merged, but not welded together.
+pub fn array_radial(
+    mesh: &HalfEdgeMesh,
+    count: usize,
+    axis: Vec3,
+    center: Vec3,
+    total_angle: f32,
+) -> Result<HalfEdgeMesh> {
+    if count == 0 {
+        bail!("The number of copies must be at least one")
+    }
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("Array axis cannot be zero"))?;
+
+    let full_turn = (total_angle.abs() - 360.0).abs() < 1e-3;
+    let step = if full_turn {
+        total_angle / count as f32
+    } else {
+        total_angle / (count - 1).max(1) as f32
+    };
+
+    let make_copy = |i: usize| {
+        let copy = mesh.clone();
+        {
+            let rotation = Quat::from_axis_angle(axis, (step * i as f32).to_radians());
+            let conn = copy.read_connectivity();
+            let mut positions = copy.write_positions();
+            for (v, _) in conn.iter_vertices() {
+                positions[v] = center + rotation * (positions[v] - center);
+            }
+        }
+        copy
+    };
+
+    // Starting from a copy keeps the default channels and `gen_config` of the
+    // input mesh, which `merge_with` doesn't carry over.
+    let mut result = make_copy(0);
+    for i in 1..count {
+        result.merge_with(&make_copy(i));
+    }
+
+    Ok(result)
+}
+
+/// Returns a new mesh with `count` copies of `mesh`, each one translated by
+/// `offset` with respect to the previous one. The first copy is left in place.
+///
+/// When `relative` is true, the `offset` is multiplied by the size of the
+/// bounding box of `mesh`, so an offset of (1, 0, 0) places the copies next
+/// to each other along the X axis. The copies are merged, but not welded
+/// together.
+pub fn array_linear(
+    mesh: &HalfEdgeMesh,
+    count: usize,
+    offset: Vec3,
+    relative: bool,
+) -> Result<HalfEdgeMesh> {
+    if count == 0 {
+        bail!("The number of copies must be at least one")
+    }
+
+    let offset = if relative {
+        let (min, max) = bounding_box(mesh);
+        offset * (max - min)
+    } else {
+        offset
+    };
+
+    let make_copy = |i: usize| {
+        let copy = mesh.clone();
+        {
+            let conn = copy.read_connectivity();
+            let mut positions = copy.write_positions();
+            for (v, _) in conn.iter_vertices() {
+                positions[v] += offset * i as f32;
+            }
+        }
+        copy
+    };
+
+    // The first copy is the base of the result. See `array_radial`.
+    let mut result = make_copy(0);
+    for i in 1..count {
+        result.merge_with(&make_copy(i));
+    }
+
+    Ok(result)
+}
+
 pub fn extrude_along_curve(
     backbone: &HalfEdgeMesh,
     cross_section: &HalfEdgeMesh,
     flip: usize,
+    twist: f32,
+    scale_channel: &str,
 ) -> Result<HalfEdgeMesh> {
     let backbone_conn = backbone.read_connectivity();
     let backbone_pos = backbone.read_positions();
     let backbone_size = backbone
         .channels
-        .read_channel_by_name::<VertexId, f32>("size");
+        .read_channel_by_name::<VertexId, f32>(scale_channel);
     let backbone_nrm = backbone
         .channels
         .read_channel_by_name::<VertexId, Vec3>("normal");
@@ -1559,7 +2915,10 @@ pub fn extrude_along_curve(
 
     let mut positions = vec![];
 
-    for (v, _) in backbone_conn.iter_vertices() {
+    let num_backbone_vertices = backbone_conn.num_vertices();
+    // The tangent and normal at the previous backbone point.
+    let mut prev_frame: Option<(Vec3, Vec3)> = None;
+    for (i, (v, _)) in backbone_conn.iter_vertices().enumerate() {
         let scale = if let Ok(ref size) = backbone_size {
             Vec3::splat(size[v])
         } else {
@@ -1569,25 +2928,45 @@ pub fn extrude_along_curve(
         let rotate = if let (Ok(normal_ch), Ok(tangent_ch)) =
             (backbone_nrm.as_ref(), backbone_tgt.as_ref())
         {
-            let normal = normal_ch[v];
-            let tangent = tangent_ch[v];
+            // Rotation-minimizing frames: The normal is taken from the normal
+            // channel only at the first point. At every other point, the
+            // previous normal is transported along the change in tangent, so
+            // the cross-section doesn't flip when the normals of consecutive
+            // points are inconsistent.
+            let tangent = tangent_ch[v]
+                .try_normalize()
+                .or(prev_frame.map(|(tangent, _)| tangent))
+                .unwrap_or(Vec3::Z);
+            let normal = match prev_frame {
+                Some((prev_tangent, prev_normal)) => {
+                    Quat::from_rotation_arc(prev_tangent, tangent) * prev_normal
+                }
+                None => normal_ch[v],
+            };
+            // Re-orthogonalize, to avoid accumulating numerical errors.
+            let normal = (normal - tangent * normal.dot(tangent))
+                .try_normalize()
+                .unwrap_or_else(|| tangent.any_orthonormal_vector());
+            prev_frame = Some((tangent, normal));
+
             let cotangent = normal.cross(tangent);
-            let (_, rotate, _) = glam::Affine3A::from_cols(
-                cotangent.into(),
-                normal.into(),
-                tangent.into(),
-                glam::Vec3A::ZERO,
-            )
-            .to_scale_rotation_translation();
-            rotate.to_euler(glam::EulerRot::XYZ).into()
+            Quat::from_mat3(&glam::Mat3::from_cols(cotangent, normal, tangent))
         } else {
-            Vec3::ZERO
+            Quat::IDENTITY
         };
 
+        // The twist is applied progressively along the backbone, rotating the
+        // cross-section around the tangent.
+        let twist_angle = if num_backbone_vertices > 1 {
+            twist.to_radians() * i as f32 / (num_backbone_vertices - 1) as f32
+        } else {
+            0.0
+        };
+        let twist_rot = Quat::from_rotation_z(twist_angle);
+
         for vc in csect_chain.iter_cpy() {
             let pos = csect_pos[vc];
-            let rot = Quat::from_euler(glam::EulerRot::XYZ, rotate.x, rotate.y, rotate.z);
-            positions.push(rot * (pos * scale) + backbone_pos[v]);
+            positions.push(rotate * (twist_rot * (pos * scale)) + backbone_pos[v]);
         }
     }
 
@@ -1784,27 +3163,54 @@ pub fn resample_curve(
         bail!("A curve can only be resampled if it has 2 or more points")
     }
 
-    if is_closed {
-        bail!("TODO: Resampling closed curves is currently unimplemented.")
+    if is_closed && curve.len() < 3 {
+        bail!("A closed curve can only be resampled if it has 3 or more points")
     }
 
     let positions = mesh.write_positions();
-    let p_first = positions[curve[0]] + (positions[curve[1]] - positions[curve[0]]);
-    let p_last = positions[curve[np - 1]] + (positions[curve[np - 1]] - positions[curve[np - 2]]);
-
-    let control_points = std::iter::once(p_first)
-        .chain(curve.iter().map(|x| positions[*x]))
-        .chain(std::iter::once(p_last));
+    let control_points = if is_closed {
+        // Closed curves wrap around the control points, so the segment from
+        // the last point back to the first one is smooth too.
+        std::iter::once(positions[curve[np - 1]])
+            .chain(curve.iter().map(|x| positions[*x]))
+            .chain([positions[curve[0]], positions[curve[1]]])
+            .collect_vec()
+    } else {
+        let p_first = positions[curve[0]] + (positions[curve[1]] - positions[curve[0]]);
+        let p_last =
+            positions[curve[np - 1]] + (positions[curve[np - 1]] - positions[curve[np - 2]]);
+        std::iter::once(p_first)
+            .chain(curve.iter().map(|x| positions[*x]))
+            .chain(std::iter::once(p_last))
+            .collect_vec()
+    };
+    let segments = control_points
+        .iter_cpy()
+        .tuple_windows()
+        .map(|(p0, p1, p2, p3)| CatmullRomSegment::<8>::new(p0, p1, p2, p3, tension, alpha))
+        .collect_vec();
+
+    // On closed curves, the segment length is adjusted so the loop is divided
+    // evenly, and there's no shorter segment where the loop closes.
+    let density_mode = match density_mode {
+        ResampleCurveDensity::Uniform { segment_length } if is_closed => {
+            let total_length: f32 = segments.iter().map(|s| s.arc_length()).sum();
+            let num_segments = (total_length / segment_length).round().max(3.0);
+            ResampleCurveDensity::Uniform {
+                segment_length: total_length / num_segments,
+            }
+        }
+        density_mode => density_mode,
+    };
 
     let mut points = vec![];
     let mut tangents = vec![];
     let mut curvatures = vec![];
     let mut accelerations = vec![];
     let mut offset = 0.0;
-    for (p0, p1, p2, p3) in control_points.tuple_windows() {
-        let segment = CatmullRomSegment::<8>::new(p0, p1, p2, p3, tension, alpha);
-
-        let resolution = match density_mode {
+    let mut resolution = 0.0;
+    for segment in &segments {
+        resolution = match density_mode {
             ResampleCurveDensity::Uniform { segment_length } => segment_length,
             ResampleCurveDensity::Curvature { multiplier } => {
                 let avg_curvature = segment.average_curvature().max(1.0); // Prevent division by 0
@@ -1835,6 +3241,20 @@ pub fn resample_curve(
         offset = resolution - (total_dist - (nsegments * resolution));
     }
 
+    if is_closed {
+        // The last points may land on top of the first one, where the loop
+        // closes. Those would produce degenerate edges, so we remove them.
+        while points.len() > 1 && points[points.len() - 1].distance(points[0]) < resolution * 0.5 {
+            points.pop();
+            tangents.pop();
+            curvatures.pop();
+            accelerations.pop();
+        }
+        if points.len() < 3 {
+            bail!("Resolution is too low, closed curve has less than three points.");
+        }
+    }
+
     if points.len() < 2 {
         bail!("Resolution is too low, curve has less than two points.");
     }
@@ -1854,10 +3274,18 @@ pub fn resample_curve(
 
     // Add the first edge
     let (h_src, h_dst) = add_edge(&result_mesh, points[0], points[1])?;
+    let v0 = result_mesh
+        .read_connectivity()
+        .at_halfedge(h_src)
+        .vertex()
+        .end();
     {
         // And the tangents and normals for the first edge
-        let v0 = mesh.read_connectivity().at_halfedge(h_src).vertex().end();
-        let v1 = mesh.read_connectivity().at_halfedge(h_dst).vertex().end();
+        let v1 = result_mesh
+            .read_connectivity()
+            .at_halfedge(h_dst)
+            .vertex()
+            .end();
         tangent_ch[v0] = tangents[0];
         tangent_ch[v1] = tangents[1];
 
@@ -1872,7 +3300,11 @@ pub fn resample_curve(
     }
 
     // Add the remaining edges
-    let mut v = mesh.read_connectivity().at_halfedge(h_dst).vertex().end();
+    let mut v = result_mesh
+        .read_connectivity()
+        .at_halfedge(h_dst)
+        .vertex()
+        .end();
     for (((dst, dst_tg), dst_crv), dst_jrk) in points
         .iter_cpy()
         .zip(tangents.iter_cpy())
@@ -1887,6 +3319,10 @@ pub fn resample_curve(
         acc_ch[v] = dst_jrk;
     }
 
+    if is_closed {
+        close_edge_chain(&result_mesh, v, v0)?;
+    }
+
     drop(tangent_ch);
     drop(normal_ch);
     drop(curvature_ch);
@@ -1943,118 +3379,2062 @@ pub fn edit_geometry(
     Ok(())
 }
 
-#[blackjack_macros::blackjack_lua_module]
-pub mod lua_fns {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanMode {
+    Union,
+    Intersection,
+    Difference,
+}
 
-    use crate::lua_engine::lua_stdlib::LVec3;
-    use halfedge::compact_mesh::CompactMesh;
+/// Computes a boolean operation between the solids enclosed by meshes `a` and
+/// `b`, returning a new mesh. Both meshes should be closed (watertight) and
+/// have consistent outward-facing winding.
+///
+/// Faces of both meshes are split where they cross each other, and the parts
+/// that lie outside the result are discarded. The remaining pieces are then
+/// welded and stitched back together, splitting edges where needed, so the
+/// resulting mesh is manifold and has consistent winding.
+///
+/// Degenerate configurations are resolved deterministically:
+/// - Coplanar faces pointing in the same direction are only kept once, taking
+///   the face from `a`. For `Difference`, the overlapping region is removed.
+/// - Coplanar faces pointing in opposite directions (the solids touch) are
+///   removed for `Union` and `Intersection`, fusing the solids, and kept from
+///   `a` for `Difference`.
+/// - When the result would be non-manifold, for instance when the two solids
+///   only touch along a shared edge, an error is returned instead.
+///
+/// Only the position channel is preserved in the result.
+pub fn boolean(a: &HalfEdgeMesh, b: &HalfEdgeMesh, mode: BooleanMode) -> Result<HalfEdgeMesh> {
+    fn mesh_polygons(mesh: &HalfEdgeMesh) -> Vec<csg::CsgPolygon> {
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        conn.iter_faces()
+            .filter_map(|(face, _)| {
+                let vertices = conn
+                    .face_vertices(face)
+                    .iter()
+                    .map(|v| positions[*v])
+                    .collect_vec();
+                csg::CsgPolygon::new(vertices)
+            })
+            .collect()
+    }
 
-    use super::*;
+    // The tolerance is relative to the size of the inputs, so that results
+    // don't depend on the scale of the meshes.
+    let (min_a, max_a) = bounding_box(a);
+    let (min_b, max_b) = bounding_box(b);
+    let extent = (max_a.max(max_b) - min_a.min(min_b)).max_element();
+    let scale = extent.max(1.0);
+    let epsilon = 1e-5 * scale;
+
+    let polygons_a = mesh_polygons(a);
+    let polygons_b = mesh_polygons(b);
+    let result = match mode {
+        BooleanMode::Union => csg::union(polygons_a, polygons_b, epsilon),
+        BooleanMode::Intersection => csg::intersection(polygons_a, polygons_b, epsilon),
+        BooleanMode::Difference => csg::difference(polygons_a, polygons_b, epsilon),
+    };
 
-    /// Replaces each vertex in the `vertices` selection with a face, and moves
-    /// that face along the incident edges by a given `amount` distance.
-    #[lua(under = "Ops")]
-    pub fn chamfer(
-        vertices: SelectionExpression,
-        amount: f32,
-        mesh: &mut HalfEdgeMesh,
-    ) -> Result<()> {
-        mesh.write_connectivity().clear_debug();
-        let verts = mesh.resolve_vertex_selection_full(&vertices)?;
-        for v in verts {
-            crate::mesh::halfedge::edit_ops::chamfer_vertex(
-                &mut mesh.write_connectivity(),
-                &mut mesh.write_positions(),
-                v,
-                amount,
-            )?;
+    let (positions, polygons) = csg::stitch_polygons(&result, epsilon);
+    HalfEdgeMesh::build_from_polygons(&positions, &polygons)
+        .map_err(|err| anyhow!("The boolean operation produced a non-manifold mesh. {err}"))
+}
+
+/// Computes the convex hull of the vertices of `mesh`, returning a new closed
+/// mesh made of triangles. Only the vertex positions are considered, so this
+/// works on point clouds as well as regular meshes.
+pub fn convex_hull(mesh: &HalfEdgeMesh) -> Result<HalfEdgeMesh> {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let points = conn
+        .iter_vertices_with_channel(&positions)
+        .map(|(_, _, p)| p)
+        .collect_vec();
+    let triangles = quickhull::quickhull(&points)?;
+
+    // Only keep the points that ended up in the hull.
+    let mut positions = vec![];
+    let mut index_map = HashMap::new();
+    let polygons = triangles
+        .iter()
+        .map(|tri| {
+            tri.map(|i| {
+                *index_map.entry(i).or_insert_with(|| {
+                    positions.push(points[i]);
+                    positions.len() - 1
+                })
+            })
+        })
+        .collect_vec();
+
+    HalfEdgeMesh::build_from_polygons(&positions, &polygons)
+}
+
+/// Rebuilds the surface of `mesh` from a voxel grid with cells of size
+/// `voxel_size`, returning a new closed mesh made of quads of roughly that
+/// size. The mesh is converted to a signed distance field, and the surface is
+/// extracted from it using surface nets.
+///
+/// The result is always manifold and watertight, which makes this useful to
+/// clean up meshes with self-intersections or non-manifold parts, like the
+/// output of boolean operations. Holes smaller than a voxel are closed, and
+/// features thinner than a voxel may be lost. Parts of the mesh that overlap
+/// are merged. Only the position channel is preserved in the result.
+///
+/// Open meshes are also closed, but the result depends on which side the
+/// holes are: The inside region may extend up to one voxel beyond the bounds
+/// of the mesh, where it gets capped.
+pub fn remesh_voxel(mesh: &HalfEdgeMesh, voxel_size: f32) -> Result<HalfEdgeMesh> {
+    if voxel_size.is_nan() || voxel_size <= 0.0 {
+        bail!("Voxel size must be greater than zero");
+    }
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+
+    let mut triangles = vec![];
+    for (face, _) in conn.iter_faces() {
+        let vertices = conn.face_vertices(face);
+        for i in 1..vertices.len().saturating_sub(1) {
+            triangles.push([
+                positions[vertices[0]],
+                positions[vertices[i]],
+                positions[vertices[i + 1]],
+            ]);
         }
-        Ok(())
+    }
+    if triangles.is_empty() {
+        bail!("Cannot remesh a mesh without faces");
     }
 
-    /// Bevels the given `edges`, replacing each edge with a face and indenting
-    /// it by a given `amount` distance.
-    #[lua(under = "Ops")]
-    pub fn bevel(edges: SelectionExpression, amount: f32, mesh: &HalfEdgeMesh) -> Result<()> {
-        let edges = mesh.resolve_halfedge_selection_full(&edges)?;
-        crate::mesh::halfedge::edit_ops::bevel_edges(
-            &mut mesh.write_connectivity(),
-            &mut mesh.write_positions(),
-            &edges,
-            amount,
-        )
+    let mut field = surface_nets::SampledField::from_triangles(&triangles, voxel_size)?;
+    let (positions, quads) = field.surface_nets();
+    HalfEdgeMesh::build_from_polygons(&positions, &quads)
+}
+
+/// Builds a closed mesh for the surface where `field` is zero, inside the box
+/// between `min` and `max`. Negative values of the field are considered to be
+/// inside the surface. The field is sampled on a grid with cells of size
+/// `cell_size`, and the surface is extracted from it using surface nets.
+///
+/// The field is sampled a cell beyond the box, so that parts of the surface
+/// clipped by the box are closed.
+pub fn isosurface(
+    min: Vec3,
+    max: Vec3,
+    cell_size: f32,
+    field: impl Fn(Vec3) -> f32,
+) -> Result<HalfEdgeMesh> {
+    // Sampling outside the box with positive values closes the surface.
+    let mut sampled = surface_nets::SampledField::sample(min, max, cell_size, |p| {
+        if p.cmplt(min).any() || p.cmpgt(max).any() {
+            field(p).max(f32::MIN_POSITIVE)
+        } else {
+            field(p)
+        }
+    })?;
+    let (positions, quads) = sampled.surface_nets();
+    HalfEdgeMesh::build_from_polygons(&positions, &quads)
+}
+
+/// Replaces every edge of `mesh` with a beam of square section, with sides of
+/// length `thickness`, returning a new mesh. Each beam is oriented so that one
+/// of its sides faces along the normals of the faces around the edge.
+///
+/// Beams extend past their endpoints by half the thickness, so beams meeting
+/// at a vertex overlap instead of leaving a gap, no matter how many edges
+/// meet at that vertex. The beams are not stitched together.
+pub fn wireframe(mesh: &HalfEdgeMesh, thickness: f32) -> Result<HalfEdgeMesh> {
+    if thickness <= 0.0 {
+        bail!("Wireframe thickness must be greater than zero");
     }
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let half_thickness = thickness * 0.5;
 
-    /// Extrudes the given `faces` by a given `amount` distance.
-    #[lua(under = "Ops")]
-    pub fn extrude(faces: SelectionExpression, amount: f32, mesh: &HalfEdgeMesh) -> Result<()> {
-        let faces = mesh.resolve_face_selection_full(&faces)?;
-        crate::mesh::halfedge::edit_ops::extrude_faces(
-            &mut mesh.write_connectivity(),
-            &mut mesh.write_positions(),
-            &faces,
-            amount,
-        )?;
-        Ok(())
+    let mut beam_positions = vec![];
+    let mut polygons: Vec<[u32; 4]> = vec![];
+    let mut visited = HashSet::new();
+    for (h, _) in conn.iter_halfedges() {
+        if visited.contains(&h) {
+            continue;
+        }
+        let twin = conn.at_halfedge(h).twin().try_end()?;
+        visited.insert(h);
+        visited.insert(twin);
+
+        let (src, dst) = conn.at_halfedge(h).src_dst_pair()?;
+        let dir = match (positions[dst] - positions[src]).try_normalize() {
+            Some(dir) => dir,
+            // Zero-length edges have no beam.
+            None => continue,
+        };
+        let normal = [h, twin]
+            .iter()
+            .filter_map(|h| conn.at_halfedge(*h).face().try_end().ok())
+            .filter_map(|f| conn.face_normal(&positions, f))
+            .filter(|n| n.is_finite())
+            .fold(Vec3::ZERO, |acc, n| acc + n);
+        let up = (normal - dir * normal.dot(dir))
+            .try_normalize()
+            .unwrap_or_else(|| dir.any_orthonormal_vector());
+        let side = dir.cross(up);
+
+        // Two rings of four vertices, at the start and end of the beam.
+        let base = beam_positions.len() as u32;
+        for center in [
+            positions[src] - dir * half_thickness,
+            positions[dst] + dir * half_thickness,
+        ] {
+            for (s, u) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                beam_positions.push(center + (side * s + up * u) * half_thickness);
+            }
+        }
+        polygons.push([0, 1, 2, 3].map(|x| base + x));
+        polygons.push([4, 7, 6, 5].map(|x| base + x));
+        for i in 0..4 {
+            let j = (i + 1) % 4;
+            polygons.push([i, i + 4, j + 4, j].map(|x| base + x));
+        }
     }
 
-    /// Modifies the given mesh `a` by merging `b` into it. The `b` mesh remains
-    /// unmodified.
-    #[lua(under = "Ops")]
-    pub fn merge(a: &mut HalfEdgeMesh, b: &HalfEdgeMesh) -> Result<()> {
-        a.merge_with(b);
-        Ok(())
+    if polygons.is_empty() {
+        bail!("Cannot generate a wireframe for a mesh without edges");
     }
+    HalfEdgeMesh::build_from_polygons(&beam_positions, &polygons)
+}
 
-    /// Subdivides the given mesh, applying as many `iterations` as given. If
-    /// `catmull_clark` is true, will use catmull clark subdivision, else linear
-    /// (i.e. vertex positions remain unchanged).
-    #[lua(under = "Ops")]
-    pub fn subdivide(
-        mesh: &HalfEdgeMesh,
-        iterations: usize,
-        catmull_clark: bool,
-    ) -> Result<HalfEdgeMesh> {
-        let new_mesh = CompactMesh::<false>::from_halfedge(mesh)?;
-        Ok(new_mesh
-            .subdivide_multi(iterations, catmull_clark)
-            .to_halfedge())
+/// Returns the signed volume enclosed by `mesh`, computed as the sum of the
+/// signed volumes of the tetrahedra formed by the origin and each triangle in
+/// a fan triangulation of every face.
+///
+/// The result is only meaningful for closed meshes. It is positive when the
+/// faces point outwards, and negative when the mesh is inside-out.
+pub fn mesh_volume(mesh: &HalfEdgeMesh) -> f32 {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let volume: f32 = conn
+        .iter_faces()
+        .map(|(face, _)| {
+            let vertices = conn.face_vertices(face);
+            let v0 = positions[vertices[0]];
+            vertices[1..]
+                .iter()
+                .tuple_windows()
+                .map(|(v1, v2)| v0.dot(positions[*v1].cross(positions[*v2])))
+                .sum::<f32>()
+        })
+        .sum();
+    volume / 6.0
+}
+
+/// Returns the total area of the faces of `mesh`.
+pub fn surface_area(mesh: &HalfEdgeMesh) -> f32 {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    conn.iter_faces()
+        .map(|(face, _)| {
+            // The length of the vector area gives the exact area of planar
+            // polygons, even when they are not convex.
+            let vertices = conn.face_vertices(face);
+            let vector_area: Vec3 = vertices
+                .iter()
+                .circular_tuple_windows()
+                .map(|(v1, v2)| positions[*v1].cross(positions[*v2]))
+                .sum();
+            vector_area.length() * 0.5
+        })
+        .sum()
+}
+
+/// Returns the minimum and maximum corners of the axis-aligned bounding box
+/// of `mesh`. Returns two zero vectors when the mesh has no vertices.
+pub fn bounding_box(mesh: &HalfEdgeMesh) -> (Vec3, Vec3) {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let bounds = conn
+        .iter_vertices_with_channel(&positions)
+        .map(|(_, _, p)| (p, p))
+        .reduce(|(min, max), (p, _)| (min.min(p), max.max(p)));
+    bounds.unwrap_or((Vec3::ZERO, Vec3::ZERO))
+}
+
+/// Scales and translates the positions of `mesh` so that its bounding box
+/// fits the box between `target_min` and `target_max`.
+///
+/// When `keep_aspect` is true, the mesh is scaled uniformly so it fits the
+/// target box without distortion, and is centered inside it. Otherwise, each
+/// axis is scaled independently so the bounds match exactly. Axes where the
+/// mesh is flat are not scaled, and the mesh is centered along them.
+pub fn fit_to_box(
+    mesh: &mut HalfEdgeMesh,
+    target_min: Vec3,
+    target_max: Vec3,
+    keep_aspect: bool,
+) -> Result<()> {
+    if target_min.cmpgt(target_max).any() {
+        bail!("The target box minimum {target_min} is greater than its maximum {target_max}");
     }
+    let (min, max) = bounding_box(mesh);
+    let size = max - min;
+    let target_size = target_max - target_min;
+
+    // Axes where the mesh is flat can't be scaled to match the target.
+    let axis_scale = |i: usize| (size[i] > f32::EPSILON).then(|| target_size[i] / size[i]);
+    let scale = if keep_aspect {
+        let uniform = (0..3).filter_map(axis_scale).fold(f32::INFINITY, f32::min);
+        Vec3::splat(if uniform.is_finite() { uniform } else { 1.0 })
+    } else {
+        Vec3::from_array([0, 1, 2].map(|i| axis_scale(i).unwrap_or(1.0)))
+    };
 
-    /// Computes the smooth normals channel for the given `mesh` and sets the
-    /// mesh export settings to use smooth normals.
-    #[lua(under = "Ops")]
-    pub fn set_smooth_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
-        super::set_smooth_normals(mesh)?;
-        Ok(())
+    let center = (min + max) * 0.5;
+    let target_center = (target_min + target_max) * 0.5;
+    let conn = mesh.read_connectivity();
+    let mut positions = mesh.write_positions();
+    for (v, _) in conn.iter_vertices() {
+        positions[v] = (positions[v] - center) * scale + target_center;
     }
+    Ok(())
+}
 
-    /// Computes the flat normals channel for the given `mesh` and sets the
-    /// mesh export settings to use flat normals.
-    #[lua(under = "Ops")]
-    pub fn set_flat_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
-        super::set_flat_normals(mesh)?;
-        Ok(())
+/// A list of problems found in a mesh by `validate_mesh`.
+#[derive(Debug, Default, Clone)]
+pub struct MeshReport {
+    /// Faces whose vertices don't lie on the same plane.
+    pub non_planar_faces: Vec<FaceId>,
+    /// Edges, as pairs of vertices, shared by more than two faces.
+    pub non_manifold_edges: Vec<(VertexId, VertexId)>,
+    /// Vertices that are not connected to any edge.
+    pub isolated_vertices: Vec<VertexId>,
+    /// Faces with no area, either because they have less than three vertices
+    /// or because their vertices are collinear.
+    pub zero_area_faces: Vec<FaceId>,
+}
+
+impl MeshReport {
+    /// Returns whether no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.non_planar_faces.is_empty()
+            && self.non_manifold_edges.is_empty()
+            && self.isolated_vertices.is_empty()
+            && self.zero_area_faces.is_empty()
     }
+}
 
-    /// Given a mesh representing a polyline, resamples it using Catmull-Rom
-    /// interpolation to create a smooth path that passes through all the points
-    /// of the original curve.
-    ///
-    /// TODO: Obsolete docs!!!!!
-    /// The `resolution` will be used to determine the distance between each
-    /// pair of points in the final curve. Depending on the input curve and this
-    /// value, the actual waypoints may not be part of the final resampled
-    /// curve, but given a small enough segment length, the final curve will be
-    /// a good approximation of the input waypoints.
-    ///
-    /// The `alpha` value can be set to 0 for a uniform, 0.5 for a centripetal
-    /// and 1.0 for a chordal Catmull-Rom spline. If in doubt, pick 0.5 for good
-    /// results.
-    ///
-    /// Increasing the `tension` from 0 to 1 value will make the curves more
-    /// pronounced, as if it were increasing the tension of a rope that goes
-    /// through all the points. A good value for tension is 0.5
+/// Checks `mesh` for common problems that may cause trouble in later
+/// operations or when exporting, returning a report. Unlike the checks in
+/// `build_from_polygons`, this never fails on a malformed mesh.
+///
+/// Tolerances for planarity and area are relative to the size of each face,
+/// so the results don't depend on the scale of the mesh.
+pub fn validate_mesh(mesh: &HalfEdgeMesh) -> MeshReport {
+    const PLANARITY_TOLERANCE: f32 = 1e-3;
+    const AREA_TOLERANCE: f32 = 1e-6;
+
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let mut report = MeshReport::default();
+
+    for (face, _) in conn.iter_faces() {
+        let vertices = match conn.at_face(face).vertices() {
+            Ok(vertices) => vertices,
+            // Faces with broken connectivity can't be measured.
+            Err(_) => continue,
+        };
+        let points = vertices.iter().map(|v| positions[*v]).collect_vec();
+        // The vector area (Newell's method) gives a robust normal even for
+        // concave or slightly non-planar polygons.
+        let vector_area: Vec3 = points
+            .iter()
+            .circular_tuple_windows()
+            .map(|(p1, p2)| p1.cross(*p2))
+            .sum::<Vec3>()
+            * 0.5;
+        let (min, max) = points.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let diameter = if points.is_empty() {
+            0.0
+        } else {
+            min.distance(max)
+        };
+
+        if points.len() < 3 || vector_area.length() <= AREA_TOLERANCE * diameter * diameter {
+            report.zero_area_faces.push(face);
+            continue;
+        }
+
+        let normal = vector_area.normalize();
+        let centroid = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+        let max_distance = points
+            .iter()
+            .map(|p| (*p - centroid).dot(normal).abs())
+            .fold(0.0, f32::max);
+        if max_distance > PLANARITY_TOLERANCE * diameter {
+            report.non_planar_faces.push(face);
+        }
+    }
+
+    let mut faces_per_edge = HashMap::<(VertexId, VertexId), usize>::new();
+    for (h, halfedge) in conn.iter_halfedges() {
+        if halfedge.face.is_none() {
+            continue;
+        }
+        if let Ok((src, dst)) = conn.at_halfedge(h).src_dst_pair() {
+            *faces_per_edge
+                .entry((src.min(dst), src.max(dst)))
+                .or_default() += 1;
+        }
+    }
+    report.non_manifold_edges = faces_per_edge
+        .into_iter()
+        .filter(|(_, count)| *count > 2)
+        .map(|(edge, _)| edge)
+        .sorted()
+        .collect();
+
+    report.isolated_vertices = conn
+        .iter_vertices()
+        .filter(|(_, vertex)| vertex.halfedge.is_none())
+        .map(|(v, _)| v)
+        .collect();
+
+    report
+}
+
+/// Mirrors `mesh` across the plane that goes through the origin with normal
+/// `axis`. Returns a new mesh containing both the original faces and their
+/// reflection. The winding of the reflected faces is flipped, so that their
+/// normals keep pointing outwards.
+///
+/// Every vertex closer than `merge_threshold` to its own reflection is welded
+/// with it, and snapped to the mirror plane. Vertices lying exactly on the
+/// plane are always welded, so the seam between both halves is watertight.
+/// Faces lying entirely on the mirror plane would end up inside the result,
+/// so they are removed.
+///
+/// Only the position channel is preserved in the result.
+pub fn mirror(mesh: &HalfEdgeMesh, axis: Vec3, merge_threshold: f32) -> Result<HalfEdgeMesh> {
+    let normal = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("The mirror axis must be a non-zero vector"))?;
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let v_mapping = conn.vertex_mapping();
+    let num_vertices = conn.num_vertices();
+
+    // The first half of the positions array stores the original vertices, and
+    // the second half their reflections.
+    let mut new_positions = vec![Vec3::ZERO; 2 * num_vertices];
+    let mut welded = vec![false; num_vertices];
+    for (v, _) in conn.iter_vertices() {
+        let i = v_mapping[v] as usize;
+        let pos = positions[v];
+        let dist = pos.dot(normal);
+        if 2.0 * dist.abs() <= merge_threshold.max(0.0) {
+            welded[i] = true;
+            new_positions[i] = pos - dist * normal;
+        } else {
+            new_positions[i] = pos;
+            new_positions[i + num_vertices] = pos - 2.0 * dist * normal;
+        }
+    }
+    let reflected_index = |i: u32| {
+        if welded[i as usize] {
+            i
+        } else {
+            i + num_vertices as u32
+        }
+    };
+
+    let mut polygons = Vec::new();
+    let mut reflected_polygons = Vec::new();
+    for (face, _) in conn.iter_faces() {
+        let polygon = v_mapping.map_seq(&conn.face_vertices(face));
+        if polygon.iter().all(|i| welded[*i as usize]) {
+            continue;
+        }
+        reflected_polygons.push(
+            polygon
+                .iter()
+                .rev()
+                .map(|i| reflected_index(*i))
+                .collect_vec(),
+        );
+        polygons.push(polygon);
+    }
+    polygons.extend(reflected_polygons);
+
+    HalfEdgeMesh::build_from_polygons(&new_positions, &polygons)
+}
+
+/// Makes `mesh` exactly symmetric with respect to the plane that goes through
+/// the origin with normal `axis`. The geometry on one side of the plane is
+/// discarded, and replaced by the reflection of the other side. When
+/// `positive_to_negative` is true, the side the normal points to is kept and
+/// reflected onto the negative side, and the other way around otherwise.
+///
+/// Faces crossing the plane are cut at the plane. Vertices close to the plane,
+/// relative to the size of the mesh, are snapped exactly to it, and welded
+/// with their reflection so the seam is watertight.
+///
+/// The channels of the kept side, like UVs or materials, are copied to both
+/// sides. The vertices and corners created where faces are cut get the
+/// default values. Normals are not preserved, since they would point the
+/// wrong way on the reflected side.
+pub fn symmetrize(mesh: &mut HalfEdgeMesh, axis: Vec3, positive_to_negative: bool) -> Result<()> {
+    let normal = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("The symmetry axis must be a non-zero vector"))?;
+    let side = if positive_to_negative { 1.0 } else { -1.0 };
+
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let (min, max) = bounding_box(mesh);
+    let tolerance = (min.distance(max) * 1e-4).max(f32::EPSILON);
+    let project = |p: Vec3| p - p.dot(normal) * normal;
+    // Signed distance to the plane, positive on the side that is kept.
+    let dist = |v: VertexId| positions[v].dot(normal) * side;
+
+    // For each new vertex, whether it's on the plane and the vertex of `mesh`
+    // it comes from, if any.
+    let mut new_positions = vec![];
+    let mut on_plane = vec![];
+    let mut vertex_sources = vec![];
+    let mut kept_vertices = HashMap::<VertexId, u32>::new();
+    for (v, _) in conn.iter_vertices() {
+        let d = dist(v);
+        if d.abs() <= tolerance {
+            kept_vertices.insert(v, new_positions.len() as u32);
+            new_positions.push(project(positions[v]));
+            on_plane.push(true);
+            vertex_sources.push(Some(v));
+        } else if d > 0.0 {
+            kept_vertices.insert(v, new_positions.len() as u32);
+            new_positions.push(positions[v]);
+            on_plane.push(false);
+            vertex_sources.push(Some(v));
+        }
+    }
+
+    // Clip the faces against the plane. Intersection vertices are shared by
+    // the two faces at each side of the cut edge.
+    //
+    // Along with each polygon, the face of `mesh` it comes from, and for each
+    // of its corners, the halfedges of `mesh` with the data for the corner
+    // (e.g. UVs) and for the edge leaving it (e.g. creases).
+    let mut intersections = HashMap::<(VertexId, VertexId), u32>::new();
+    let mut polygons = vec![];
+    let mut sources = vec![];
+    for (face, _) in conn.iter_faces() {
+        let halfedges = conn.face_edges(face);
+        // The index of each corner, the halfedge of the face it was created
+        // from, and whether it is an intersection with the plane.
+        let mut corners: Vec<(u32, usize, bool)> = vec![];
+        for (w, (a, b)) in conn
+            .face_vertices(face)
+            .iter_cpy()
+            .circular_tuple_windows()
+            .enumerate()
+        {
+            if let Some(idx) = kept_vertices.get(&a) {
+                corners.push((*idx, w, false));
+            }
+            let (da, db) = (dist(a), dist(b));
+            let crosses =
+                (da > tolerance && db < -tolerance) || (da < -tolerance && db > tolerance);
+            if crosses {
+                let idx = *intersections
+                    .entry((a.min(b), a.max(b)))
+                    .or_insert_with(|| {
+                        let t = da / (da - db);
+                        new_positions.push(project(positions[a].lerp(positions[b], t)));
+                        on_plane.push(true);
+                        vertex_sources.push(None);
+                        new_positions.len() as u32 - 1
+                    });
+                corners.push((idx, w, true));
+            }
+        }
+        // Faces lying on the plane would end up inside the result.
+        if corners.len() < 3 || corners.iter().all(|(i, _, _)| on_plane[*i as usize]) {
+            continue;
+        }
+
+        let n = halfedges.len();
+        let corner_sources = corners
+            .iter()
+            .enumerate()
+            .map(|(j, &(_, w, intersection))| {
+                // The edge leaving this corner is part of an original edge
+                // when it ends at the intersection on that edge, or at the
+                // other end of the edge.
+                let (_, next_w, next_intersection) = corners[(j + 1) % corners.len()];
+                let along_edge = if next_intersection {
+                    next_w == w
+                } else {
+                    next_w == (w + 1) % n
+                };
+                (
+                    (!intersection).then(|| halfedges[w]),
+                    along_edge.then(|| halfedges[w]),
+                )
+            })
+            .collect_vec();
+        polygons.push(corners.iter().map(|(i, _, _)| *i).collect_vec());
+        sources.push((face, corner_sources));
+    }
+
+    // Reflect the kept half. Vertices on the plane are their own reflection.
+    let reflected_positions = new_positions
+        .iter()
+        .zip(on_plane.iter())
+        .filter(|(_, on_plane)| !**on_plane)
+        .map(|(p, _)| *p - 2.0 * p.dot(normal) * normal)
+        .collect_vec();
+    let mut next_index = new_positions.len() as u32;
+    let reflected_index = on_plane
+        .iter()
+        .enumerate()
+        .map(|(i, on_plane)| {
+            if *on_plane {
+                i as u32
+            } else {
+                next_index += 1;
+                next_index - 1
+            }
+        })
+        .collect_vec();
+    new_positions.extend(reflected_positions);
+    vertex_sources.extend(
+        vertex_sources
+            .iter()
+            .zip(on_plane.iter())
+            .filter(|(_, on_plane)| !**on_plane)
+            .map(|(source, _)| *source)
+            .collect_vec(),
+    );
+    let reflected_polygons = polygons
+        .iter()
+        .map(|polygon| {
+            polygon
+                .iter()
+                .rev()
+                .map(|i| reflected_index[*i as usize])
+                .collect_vec()
+        })
+        .collect_vec();
+    // Reversing a polygon also reverses its edges: The edge leaving each
+    // corner is the reflection of the edge arriving to it.
+    let reflected_sources = sources
+        .iter()
+        .map(|(face, corner_sources)| {
+            let n = corner_sources.len();
+            let corner_sources = (0..n)
+                .rev()
+                .map(|j| (corner_sources[j].0, corner_sources[(j + n - 1) % n].1))
+                .collect_vec();
+            (*face, corner_sources)
+        })
+        .collect_vec();
+    polygons.extend(reflected_polygons);
+    sources.extend(reflected_sources);
+
+    if polygons.is_empty() {
+        bail!("Symmetrize would remove all the faces in the mesh");
+    }
+    let mut result = HalfEdgeMesh::build_from_polygons(&new_positions, &polygons)?;
+
+    // `build_from_polygons` allocates the vertices in the order they first
+    // appear in the polygons, and then the faces along with their halfedges
+    // in the same order as the polygons.
+    let (vertex_pairs, face_pairs, corner_pairs, edge_pairs) = {
+        let result_conn = result.read_connectivity();
+        let vertex_pairs = polygons
+            .iter()
+            .flatten()
+            .unique()
+            .zip(result_conn.iter_vertices_ordered())
+            .filter_map(|(i, (v, _))| Some((vertex_sources[*i as usize]?, v)))
+            .collect_vec();
+        let face_pairs = sources
+            .iter()
+            .map(|(face, _)| *face)
+            .zip(result_conn.iter_faces_ordered().map(|(f, _)| f))
+            .collect_vec();
+        let corners = sources
+            .iter()
+            .flat_map(|(_, corner_sources)| corner_sources)
+            .zip(result_conn.iter_halfedges_ordered().map(|(h, _)| h))
+            .collect_vec();
+        let corner_pairs = corners
+            .iter()
+            .filter_map(|((corner, _), h)| Some(((*corner)?, *h)))
+            .collect_vec();
+        let edge_pairs = corners
+            .iter()
+            .filter_map(|((_, edge), h)| Some(((*edge)?, *h)))
+            .collect_vec();
+        (vertex_pairs, face_pairs, corner_pairs, edge_pairs)
+    };
+
+    let channels = &mesh.channels;
+    let defaults = &mesh.default_channels;
+    let position = channels.channel_name(defaults.position);
+    let vertex_normals = defaults
+        .vertex_normals
+        .and_then(|ch_id| channels.channel_name(ch_id));
+    let face_normals = defaults
+        .face_normals
+        .and_then(|ch_id| channels.channel_name(ch_id));
+    let halfedge_normals = defaults
+        .halfedge_normals
+        .and_then(|ch_id| channels.channel_name(ch_id));
+    let is_edge_channel = |vty: ChannelValueType, name: &str| {
+        (vty == ChannelValueType::f32 && name == compact_mesh::CREASE_CHANNEL)
+            || (vty == ChannelValueType::bool && name == compact_mesh::SHARP_CHANNEL)
+    };
+    result
+        .channels
+        .copy_values_from(channels, &vertex_pairs, |vty, name| {
+            vty == ChannelValueType::Vec3
+                && (Some(name) == position || Some(name) == vertex_normals)
+        });
+    result
+        .channels
+        .copy_values_from(channels, &face_pairs, |vty, name| {
+            vty == ChannelValueType::Vec3 && Some(name) == face_normals
+        });
+    result
+        .channels
+        .copy_values_from(channels, &corner_pairs, |vty, name| {
+            is_edge_channel(vty, name)
+                || (vty == ChannelValueType::Vec3 && Some(name) == halfedge_normals)
+        });
+    result
+        .channels
+        .copy_values_from(channels, &edge_pairs, |vty, name| {
+            !is_edge_channel(vty, name)
+        });
+    for uv_ch in UvChannel::ALL {
+        let ch_id = defaults
+            .uv_channel(uv_ch)
+            .and_then(|ch_id| channels.channel_name(ch_id))
+            .and_then(|name| result.channels.channel_id::<HalfEdgeId, Vec3>(name));
+        result.default_channels.set_uv_channel(uv_ch, ch_id);
+    }
+    result.gen_config = mesh.gen_config.clone();
+
+    drop(conn);
+    drop(positions);
+    *mesh = result;
+    Ok(())
+}
+
+/// Gives thickness to a surface. Returns a new mesh with an additional shell,
+/// created by offsetting each vertex of `mesh` a `thickness` distance along
+/// the opposite direction of its smooth normal. The new shell has its winding
+/// flipped, so that its normals point away from the original surface.
+///
+/// The boundary edges of both shells are bridged with quads, so that when the
+/// input is an open surface the result is closed and manifold.
+///
+/// Only the position channel is preserved in the result.
+pub fn solidify(mesh: &HalfEdgeMesh, thickness: f32) -> Result<HalfEdgeMesh> {
+    let normals = generate_smooth_normals_channel(mesh)?;
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let v_mapping = conn.vertex_mapping();
+    let num_vertices = conn.num_vertices();
+
+    // The first half of the positions array stores the original vertices, and
+    // the second half the vertices of the inner shell.
+    let mut new_positions = vec![Vec3::ZERO; 2 * num_vertices];
+    for (v, _) in conn.iter_vertices() {
+        let i = v_mapping[v] as usize;
+        new_positions[i] = positions[v];
+        new_positions[i + num_vertices] = positions[v] - normals[v] * thickness;
+    }
+    let inner = |i: u32| i + num_vertices as u32;
+
+    let mut polygons = Vec::new();
+    for (face, _) in conn.iter_faces() {
+        let polygon = v_mapping.map_seq(&conn.face_vertices(face));
+        polygons.push(polygon.iter().rev().map(|i| inner(*i)).collect_vec());
+        polygons.push(polygon);
+    }
+
+    // Each boundary halfedge `a -> b` is bridged with a quad. The winding is
+    // chosen to match the twin halfedges on the outer and inner faces.
+    for (h, halfedge) in conn.iter_halfedges() {
+        if halfedge.face.is_none() {
+            let (a, b) = conn.at_halfedge(h).src_dst_pair()?;
+            let (a, b) = (v_mapping[a], v_mapping[b]);
+            polygons.push(vec![a, b, inner(b), inner(a)]);
+        }
+    }
+
+    HalfEdgeMesh::build_from_polygons(&new_positions, &polygons)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriangulationStrategy {
+    /// Connects the first vertex of each face to every other vertex. Only
+    /// gives good results for convex faces.
+    FanFromFirst,
+    /// Repeatedly clips the "ears" of each face. Handles concave faces, as
+    /// long as they are (roughly) planar.
+    EarClipping,
+}
+
+/// Returns the triangles that result from triangulating the planar polygon
+/// with the given `points` using the ear clipping method. Triangles are given
+/// as indices into `points`, with the same winding as the polygon.
+pub fn ear_clip_polygon(points: &[Vec3]) -> Vec<[usize; 3]> {
+    // Project the polygon on its plane. The basis is chosen so that the
+    // polygon's winding is counter-clockwise in 2d.
+    let normal = points
+        .iter()
+        .circular_tuple_windows()
+        .fold(Vec3::ZERO, |acc, (a, b)| acc + a.cross(*b))
+        .normalize_or_zero();
+    if normal == Vec3::ZERO {
+        // Degenerate polygon, there's no sensible way to clip its ears.
+        return (1..points.len().saturating_sub(1))
+            .map(|i| [0, i, i + 1])
+            .collect();
+    }
+    let u = normal.any_orthonormal_vector();
+    let v = normal.cross(u);
+    let points = points
+        .iter()
+        .map(|p| Vec2::new(p.dot(u), p.dot(v)))
+        .collect_vec();
+
+    let cross = |a: Vec2, b: Vec2, c: Vec2| (b - a).perp_dot(c - b);
+    let in_triangle = |p: Vec2, a: Vec2, b: Vec2, c: Vec2| {
+        cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+    };
+
+    let mut remaining = (0..points.len()).collect_vec();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            )
+        };
+        let is_ear = |i: usize| {
+            let (a, b, c) = corner(i);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            cross(pa, pb, pc) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|&&j| j != a && j != b && j != c)
+                    .all(|&j| !in_triangle(points[j], pa, pb, pc))
+        };
+        // When no ear can be found, the polygon is degenerate (e.g. it
+        // self-intersects or is not planar). Clip the first corner anyway so
+        // that we always produce a result.
+        let i = (0..n).find(|i| is_ear(*i)).unwrap_or(0);
+        let (a, b, c) = corner(i);
+        triangles.push([a, b, c]);
+        remaining.remove(i);
+    }
+    if let [a, b, c] = remaining[..] {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
+
+/// Splits every face of the mesh with more than three vertices into
+/// triangles, using the given `strategy`. Returns a new mesh.
+///
+/// The vertices, as well as the original faces and halfedges keep their ids,
+/// so their channels are preserved. The new faces created by the
+/// triangulation copy the channel values of the face they come from, and each
+/// new diagonal halfedge copies the values of the face's halfedge leaving the
+/// same vertex, so per-corner data like UVs is kept.
+pub fn triangulate(mesh: &HalfEdgeMesh, strategy: TriangulationStrategy) -> Result<HalfEdgeMesh> {
+    let result = mesh.clone();
+    let mut new_faces = vec![];
+    let mut new_halfedges = vec![];
+    {
+        let mut conn = result.write_connectivity();
+        let positions = result.read_positions();
+
+        let faces = conn.iter_faces().map(|(f, _)| f).collect_vec();
+        for face in faces {
+            let halfedges = conn.face_edges(face);
+            let vertices = conn.face_vertices(face);
+            let n = halfedges.len();
+            if n <= 3 {
+                continue;
+            }
+
+            let triangles = match strategy {
+                TriangulationStrategy::FanFromFirst => (1..n - 1).map(|i| [0, i, i + 1]).collect(),
+                TriangulationStrategy::EarClipping => {
+                    ear_clip_polygon(&vertices.iter().map(|v| positions[*v]).collect_vec())
+                }
+            };
+
+            // The halfedge going from the i-th to the j-th vertex of the face.
+            // Edges on the face's boundary are reused, and new halfedges are
+            // allocated for the diagonals.
+            let mut diagonals = HashMap::<(usize, usize), HalfEdgeId>::new();
+            let mut halfedge_between = |conn: &mut MeshConnectivity, i: usize, j: usize| {
+                if j == (i + 1) % n {
+                    halfedges[i]
+                } else {
+                    *diagonals
+                        .entry((i, j))
+                        .or_insert_with(|| conn.alloc_halfedge(HalfEdge::default()))
+                }
+            };
+
+            for (k, [a, b, c]) in triangles.iter_cpy().enumerate() {
+                let tri_face = if k == 0 {
+                    face
+                } else {
+                    let tri_face = conn.alloc_face(None);
+                    new_faces.push((face, tri_face));
+                    tri_face
+                };
+                let tri_halfedges = [
+                    halfedge_between(&mut conn, a, b),
+                    halfedge_between(&mut conn, b, c),
+                    halfedge_between(&mut conn, c, a),
+                ];
+                for (idx, h) in tri_halfedges.iter_cpy().enumerate() {
+                    conn[h].vertex = Some(vertices[[a, b, c][idx]]);
+                    conn[h].next = Some(tri_halfedges[(idx + 1) % 3]);
+                    conn[h].face = Some(tri_face);
+                }
+                conn[tri_face].halfedge = Some(tri_halfedges[0]);
+            }
+
+            for (&(i, j), &h) in &diagonals {
+                conn[h].twin = Some(
+                    *diagonals
+                        .get(&(j, i))
+                        .ok_or_else(|| anyhow!("Invalid triangulation for face {face:?}"))?,
+                );
+                new_halfedges.push((halfedges[i], h));
+            }
+        }
+    }
+    result.channels.copy_values(&new_faces);
+    result.channels.copy_values(&new_halfedges);
+    Ok(result)
+}
+
+/// Returns the error quadric for the plane with the given `normal` that goes
+/// through `point`, as described in "Surface Simplification Using Quadric
+/// Error Metrics" (Garland & Heckbert, 1997).
+fn plane_quadric(normal: Vec3, point: Vec3) -> Mat4 {
+    let p = normal.extend(-normal.dot(point));
+    Mat4::from_cols(p * p.x, p * p.y, p * p.z, p * p.w)
+}
+
+/// Returns the error of placing a vertex at `pos` for the given `quadric`.
+fn quadric_error(quadric: &Mat4, pos: Vec3) -> f32 {
+    let p = pos.extend(1.0);
+    p.dot(*quadric * p)
+}
+
+/// Returns the position that minimizes the error for the given `quadric`
+/// when collapsing the edge between `a` and `b`. When the optimal position
+/// can't be found, or is too far away from the edge, the best position among
+/// the endpoints and the midpoint is used instead.
+fn optimal_collapse_position(quadric: &Mat4, a: Vec3, b: Vec3) -> Vec3 {
+    let m = glam::Mat3::from_cols(
+        quadric.x_axis.truncate(),
+        quadric.y_axis.truncate(),
+        quadric.z_axis.truncate(),
+    );
+    let midpoint = (a + b) * 0.5;
+    if m.determinant().abs() > 1e-6 {
+        let optimal = m.inverse() * -quadric.w_axis.truncate();
+        if optimal.is_finite() && optimal.distance(midpoint) <= a.distance(b) {
+            return optimal;
+        }
+    }
+    [a, b, midpoint]
+        .into_iter()
+        .min_by_key(|p| FloatOrd(quadric_error(quadric, *p)))
+        .unwrap_or(midpoint)
+}
+
+/// Returns whether the edge of `h` can be collapsed without producing
+/// non-manifold geometry (i.e. the "link condition" holds).
+fn can_collapse_edge(conn: &MeshConnectivity, h: HalfEdgeId) -> Result<bool> {
+    let (v, w) = conn.at_halfedge(h).src_dst_pair()?;
+    let t = conn.at_halfedge(h).twin().try_end()?;
+    let f_h = conn.at_halfedge(h).face_or_boundary()?;
+    let f_t = conn.at_halfedge(t).face_or_boundary()?;
+    if f_h.is_none() && f_t.is_none() {
+        return Ok(false);
+    }
+
+    // The only vertices shared by the neighborhoods of `v` and `w` should be
+    // the opposite vertices of triangles adjacent to the edge. Otherwise,
+    // collapsing would produce duplicate edges.
+    let mut allowed_common = SVec::new();
+    for (h, face) in [(h, f_h), (t, f_t)] {
+        if let Some(face) = face {
+            let face_edges = conn.face_edges(face);
+            if face_edges.len() == 3 {
+                let h_prev = conn.at_halfedge(h).previous().try_end()?;
+                let h_next = conn.at_halfedge(h).next().try_end()?;
+                // Collapsing an isolated triangle would leave a dangling edge
+                if conn.at_halfedge(h_prev).twin().is_boundary()?
+                    && conn.at_halfedge(h_next).twin().is_boundary()?
+                {
+                    return Ok(false);
+                }
+                allowed_common.push(conn.at_halfedge(h_prev).vertex().try_end()?);
+            }
+        }
+    }
+    let neighbors = |x: VertexId| -> Result<HashSet<VertexId>> {
+        conn.at_vertex(x)
+            .outgoing_halfedges()?
+            .iter()
+            .map(|h| Ok(conn.at_halfedge(*h).dst_vertex().try_end()?))
+            .collect()
+    };
+    let v_neighbors = neighbors(v)?;
+    let num_common = neighbors(w)?
+        .iter()
+        .filter(|x| v_neighbors.contains(x))
+        .count();
+    if num_common != allowed_common.len() {
+        return Ok(false);
+    }
+
+    // Faces shared by `v` and `w` other than the ones adjacent to the edge
+    // would get pinched.
+    let v_faces = conn.at_vertex(v).adjacent_faces()?;
+    for face in conn.at_vertex(w).adjacent_faces()? {
+        if v_faces.contains(&face) && Some(face) != f_h && Some(face) != f_t {
+            return Ok(false);
+        }
+    }
+
+    // Joining two boundaries through an interior edge would pinch the mesh.
+    let is_boundary_vertex = |x: VertexId| -> Result<bool> {
+        for h in conn.at_vertex(x).outgoing_halfedges()? {
+            if conn.at_halfedge(h).is_boundary()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+    if f_h.is_some() && f_t.is_some() && is_boundary_vertex(v)? && is_boundary_vertex(w)? {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Reduces the number of faces of the mesh to (roughly) `target_ratio` times
+/// the original face count by repeatedly collapsing the edge that introduces
+/// the least error, measured using quadric error metrics. Returns a new mesh.
+///
+/// Collapses that would produce non-manifold geometry or flip the orientation
+/// of any face are skipped, so the target may not be reached for some meshes.
+/// Boundary edges are penalized to preserve the mesh silhouette. Faces with
+/// more than three vertices are supported, but they will only disappear once
+/// they have been reduced to triangles.
+///
+/// The vertex normals and UVs of the mesh are interpolated at each collapse
+/// point.
+pub fn decimate(mesh: &HalfEdgeMesh, target_ratio: f32) -> Result<HalfEdgeMesh> {
+    /// The weight of the planes that preserve boundary edges
+    const BOUNDARY_WEIGHT: f32 = 100.0;
+
+    let result = mesh.clone();
+    {
+        let mut conn = result.write_connectivity();
+        let mut positions = result.write_positions();
+        let mut vertex_normals = result
+            .default_channels
+            .vertex_normals
+            .map(|ch_id| result.channels.write_channel(ch_id))
+            .transpose()?;
+        let mut uv_channels = UvChannel::ALL
+            .iter()
+            .filter_map(|uv_ch| result.default_channels.uv_channel(*uv_ch))
+            .map(|ch_id| result.channels.write_channel(ch_id))
+            .collect::<Result<Vec<_>>>()?;
+
+        let target_faces =
+            (conn.num_faces() as f32 * target_ratio.clamp(0.0, 1.0)).round() as usize;
+
+        // Newell's method, which is robust for non-convex polygons.
+        let polygon_normal = |points: &[Vec3]| {
+            points
+                .iter()
+                .circular_tuple_windows()
+                .fold(Vec3::ZERO, |acc, (a, b)| acc + a.cross(*b))
+        };
+
+        // --- Compute the initial quadrics ---
+        let mut quadrics = conn
+            .iter_vertices()
+            .map(|(v, _)| (v, Mat4::ZERO))
+            .collect::<HashMap<_, _>>();
+        for (face, _) in conn.iter_faces() {
+            let vertices = conn.face_vertices(face);
+            let normal = polygon_normal(&vertices.iter().map(|v| positions[*v]).collect_vec())
+                .normalize_or_zero();
+            for v in vertices.iter_cpy() {
+                *quadrics.entry(v).or_insert(Mat4::ZERO) += plane_quadric(normal, positions[v]);
+            }
+            // Boundary edges get an additional plane, perpendicular to the
+            // face, that penalizes moving their vertices away from the edge.
+            for h in conn.face_edges(face) {
+                if conn.at_halfedge(h).twin().is_boundary()? {
+                    let (a, b) = conn.at_halfedge(h).src_dst_pair()?;
+                    let edge_normal = (positions[b] - positions[a])
+                        .cross(normal)
+                        .normalize_or_zero();
+                    let quadric = plane_quadric(edge_normal, positions[a]) * BOUNDARY_WEIGHT;
+                    *quadrics.entry(a).or_insert(Mat4::ZERO) += quadric;
+                    *quadrics.entry(b).or_insert(Mat4::ZERO) += quadric;
+                }
+            }
+        }
+
+        // --- Collapse edges in order of increasing error ---
+
+        // Each vertex has a version number, which is increased every time the
+        // vertex is modified. This is used to discard outdated entries in the
+        // queue, since binary heaps don't support updating priorities.
+        let mut versions = conn
+            .iter_vertices()
+            .map(|(v, _)| (v, 0u32))
+            .collect::<HashMap<_, _>>();
+        let mut queue = BinaryHeap::new();
+        let push_edges = |queue: &mut BinaryHeap<_>,
+                          conn: &MeshConnectivity,
+                          positions: &Positions,
+                          quadrics: &HashMap<VertexId, Mat4>,
+                          versions: &HashMap<VertexId, u32>,
+                          v: VertexId|
+         -> Result<()> {
+            for h in conn.at_vertex(v).outgoing_halfedges()? {
+                let w = conn.at_halfedge(h).dst_vertex().try_end()?;
+                let quadric = quadrics[&v] + quadrics[&w];
+                let pos = optimal_collapse_position(&quadric, positions[v], positions[w]);
+                let error = quadric_error(&quadric, pos);
+                queue.push(Reverse((FloatOrd(error), v, w, versions[&v], versions[&w])));
+            }
+            Ok(())
+        };
+        for (v, _) in conn.iter_vertices() {
+            push_edges(&mut queue, &conn, &positions, &quadrics, &versions, v)?;
+        }
+
+        while conn.num_faces() > target_faces {
+            let (v, w) = match queue.pop() {
+                Some(Reverse((_, v, w, version_v, version_w))) => {
+                    if versions.get(&v) != Some(&version_v) || versions.get(&w) != Some(&version_w)
+                    {
+                        continue;
+                    }
+                    (v, w)
+                }
+                None => break,
+            };
+            let h = match conn.at_vertex(v).halfedge_to(w).try_end() {
+                Ok(h) => h,
+                Err(_) => continue,
+            };
+            if !can_collapse_edge(&conn, h)? {
+                continue;
+            }
+
+            let quadric = quadrics[&v] + quadrics[&w];
+            let (pos_v, pos_w) = (positions[v], positions[w]);
+            let pos = optimal_collapse_position(&quadric, pos_v, pos_w);
+
+            // Reject the collapse if any of the remaining faces would flip
+            let t = conn.at_halfedge(h).twin().try_end()?;
+            let f_h = conn.at_halfedge(h).face_or_boundary()?;
+            let f_t = conn.at_halfedge(t).face_or_boundary()?;
+            let mut flips = false;
+            for face in conn
+                .at_vertex(v)
+                .adjacent_faces()?
+                .into_iter()
+                .chain(conn.at_vertex(w).adjacent_faces()?)
+            {
+                if Some(face) == f_h || Some(face) == f_t {
+                    continue;
+                }
+                let vertices = conn.face_vertices(face);
+                let before = polygon_normal(&vertices.iter().map(|x| positions[*x]).collect_vec());
+                let after = polygon_normal(
+                    &vertices
+                        .iter()
+                        .map(|x| {
+                            if *x == v || *x == w {
+                                pos
+                            } else {
+                                positions[*x]
+                            }
+                        })
+                        .collect_vec(),
+                );
+                if before.dot(after) <= 0.0 {
+                    flips = true;
+                    break;
+                }
+            }
+            if flips {
+                continue;
+            }
+
+            // Interpolate the channels at the collapse point
+            let edge = pos_w - pos_v;
+            let s = if edge.length_squared() > 0.0 {
+                ((pos - pos_v).dot(edge) / edge.length_squared()).clamp(0.0, 1.0)
+            } else {
+                0.5
+            };
+            if let Some(vertex_normals) = vertex_normals.as_mut() {
+                vertex_normals[v] = vertex_normals[v]
+                    .lerp(vertex_normals[w], s)
+                    .normalize_or_zero();
+            }
+            for uvs in uv_channels.iter_mut() {
+                // The uvs of the corners at both ends of the edge, taken from
+                // one of the faces adjacent to it.
+                let (uv_v, uv_w) = if f_h.is_some() {
+                    (uvs[h], uvs[conn.at_halfedge(h).next().try_end()?])
+                } else {
+                    (uvs[conn.at_halfedge(t).next().try_end()?], uvs[t])
+                };
+                for h_v in conn.at_vertex(v).outgoing_halfedges()? {
+                    uvs[h_v] += (uv_w - uv_v) * s;
+                }
+                for h_w in conn.at_vertex(w).outgoing_halfedges()? {
+                    uvs[h_w] += (uv_v - uv_w) * (1.0 - s);
+                }
+            }
+
+            collapse_edge(&mut conn, h)?;
+            positions[v] = pos;
+            quadrics.insert(v, quadric);
+            quadrics.remove(&w);
+            versions.remove(&w);
+            *versions.entry(v).or_default() += 1;
+            push_edges(&mut queue, &conn, &positions, &quadrics, &versions, v)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Adds a new polyline to the mesh, disconnected from everything else, going
+/// through the given `points`. When `is_closed` is true, the last point is
+/// connected back to the first. Returns the vertices of the polyline, in order.
+fn add_polyline(mesh: &HalfEdgeMesh, points: &[Vec3], is_closed: bool) -> Result<SVec<VertexId>> {
+    if points.len() < 2 {
+        bail!("A polyline needs at least two points")
+    }
+    let mut conn = mesh.write_connectivity();
+    let mut positions = mesh.write_positions();
+
+    let vertices = points
+        .iter()
+        .map(|p| conn.alloc_vertex(&mut positions, *p, None))
+        .collect::<SVec<_>>();
+    let num_edges = if is_closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+
+    // For each edge, the halfedges going forward and backward along the line.
+    let mut forward = SVec::new();
+    let mut backward = SVec::new();
+    for i in 0..num_edges {
+        let (v, w) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+        let h_fwd = conn.alloc_halfedge(HalfEdge {
+            vertex: Some(v),
+            ..Default::default()
+        });
+        let h_bwd = conn.alloc_halfedge(HalfEdge {
+            vertex: Some(w),
+            ..Default::default()
+        });
+        conn[h_fwd].twin = Some(h_bwd);
+        conn[h_bwd].twin = Some(h_fwd);
+        conn[v].halfedge = Some(h_fwd);
+        if !is_closed && i == num_edges - 1 {
+            conn[w].halfedge = Some(h_bwd);
+        }
+        forward.push(h_fwd);
+        backward.push(h_bwd);
+    }
+
+    for i in 0..num_edges {
+        if is_closed {
+            conn[forward[i]].next = Some(forward[(i + 1) % num_edges]);
+            conn[backward[i]].next = Some(backward[(i + num_edges - 1) % num_edges]);
+        } else {
+            // At the tips, the line turns around to continue the other way.
+            conn[forward[i]].next = Some(if i == num_edges - 1 {
+                backward[i]
+            } else {
+                forward[i + 1]
+            });
+            conn[backward[i]].next = Some(if i == 0 { forward[0] } else { backward[i - 1] });
+        }
+    }
+
+    Ok(vertices)
+}
+
+/// Creates a surface of revolution by rotating the `profile` polyline around
+/// an axis, given by `axis` and a point `center` on it. The profile is copied
+/// `steps` times, each one rotated a fraction of the total `angle` (in
+/// degrees), and consecutive copies are bridged with quads.
+///
+/// When `angle` is a full turn, the last copy is bridged back to the first one
+/// instead of leaving a seam of duplicated vertices. This needs at least three
+/// steps, since fewer copies can't enclose the axis. Vertices of the profile
+/// that lie on the axis are not merged, `weld_by_distance` can be used for that.
+pub fn spin(
+    profile: &HalfEdgeMesh,
+    axis: Vec3,
+    center: Vec3,
+    angle: f32,
+    steps: usize,
+) -> Result<HalfEdgeMesh> {
+    if steps == 0 {
+        bail!("The number of steps must be at least one")
+    }
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("Spin axis cannot be zero"))?;
+
+    let (chain, is_closed) = {
+        let conn = profile.read_connectivity();
+        let bag = conn.iter_halfedges().map(|(h, _)| h).collect_vec();
+        sort_bag_of_edges(&conn, &bag)?
+    };
+    let points = {
+        let positions = profile.read_positions();
+        chain.iter().map(|v| positions[*v]).collect_vec()
+    };
+
+    let full_turn = (angle.abs() - 360.0).abs() < 1e-3;
+    if full_turn && steps < 3 {
+        bail!("A full turn spin needs at least three steps")
+    }
+    let num_copies = if full_turn { steps } else { steps + 1 };
+
+    let mut result = HalfEdgeMesh::new();
+    let mut copies = Vec::with_capacity(num_copies);
+    for i in 0..num_copies {
+        let rotation = Quat::from_axis_angle(axis, (angle * i as f32 / steps as f32).to_radians());
+        let rotated = points
+            .iter()
+            .map(|p| center + rotation * (*p - center))
+            .collect_vec();
+        copies.push(add_polyline(&result, &rotated, is_closed)?);
+    }
+
+    let mut bridge = |a: &[VertexId], b: &[VertexId]| {
+        // Chains are bridged facing in opposite directions.
+        let b_reversed = b.iter_cpy().rev().collect_vec();
+        bridge_chains(&mut result, a, &b_reversed, is_closed)
+    };
+    for (a, b) in copies.iter().tuple_windows() {
+        bridge(a, b)?;
+    }
+    if full_turn {
+        bridge(&copies[num_copies - 1], &copies[0])?;
+    }
+
+    Ok(result)
+}
+
+/// Helper for the deformers along an axis. Calls `deform` for every vertex of
+/// the mesh, and replaces its position with the result.
+///
+/// Vertex positions are given to `deform` in local coordinates, relative to
+/// the center of the mesh's bounding box, and split as `(along, across)`:
+/// `along` is the signed distance along the axis, and `across` is the vector
+/// perpendicular to it. The function also gets the distance along the axis
+/// from the bounding box min, and the total length of the bounding box along
+/// the axis. It should return the new position in local coordinates.
+fn deform_along_axis(
+    mesh: &HalfEdgeMesh,
+    axis: Vec3,
+    deform: impl Fn(f32, Vec3, f32, f32) -> Vec3,
+) -> Result<()> {
+    let conn = mesh.read_connectivity();
+    let mut positions = mesh.write_positions();
+    if conn.num_vertices() == 0 {
+        return Ok(());
+    }
+
+    let (bb_min, bb_max) = conn.iter_vertices_with_channel(&positions).fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), (_, _, pos)| (min.min(pos), max.max(pos)),
+    );
+    let center = (bb_min + bb_max) * 0.5;
+    let (min, max) = conn.iter_vertices_with_channel(&positions).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min, max), (_, _, pos)| {
+            let along = (pos - center).dot(axis);
+            (min.min(along), max.max(along))
+        },
+    );
+
+    let vertices = conn.iter_vertices().map(|(v, _)| v).collect_vec();
+    for v in vertices {
+        let local = positions[v] - center;
+        let along = local.dot(axis);
+        let across = local - axis * along;
+        positions[v] = center + deform(along, across, along - min, max - min);
+    }
+    Ok(())
+}
+
+/// Twists the mesh around `axis`, which goes through the center of the
+/// mesh's bounding box. The rotation angle grows linearly with the distance
+/// along the axis, starting at zero at the bounding box min and increasing by
+/// `amount` degrees per unit of distance.
+pub fn twist(mesh: &HalfEdgeMesh, axis: Vec3, amount: f32) -> Result<()> {
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("Twist axis cannot be zero"))?;
+    deform_along_axis(mesh, axis, |along, across, distance, _| {
+        let rotation = Quat::from_axis_angle(axis, (amount * distance).to_radians());
+        axis * along + rotation * across
+    })
+}
+
+/// Tapers the mesh along `axis`, which goes through the center of the mesh's
+/// bounding box. Vertices are scaled away from the axis by a factor that goes
+/// linearly from 1 at the bounding box min to `factor` at the max.
+pub fn taper(mesh: &HalfEdgeMesh, axis: Vec3, factor: f32) -> Result<()> {
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("Taper axis cannot be zero"))?;
+    deform_along_axis(mesh, axis, |along, across, distance, length| {
+        let t = if length > 0.0 { distance / length } else { 0.0 };
+        axis * along + across * (1.0 + (factor - 1.0) * t)
+    })
+}
+
+/// Bends `axis`, which goes through the center of the mesh's bounding box,
+/// into a circular arc turning a total of `angle` degrees between the
+/// bounding box min and max. Distances along the axis are preserved. The mesh
+/// bends towards the direction given by `Vec3::any_orthonormal_vector` for
+/// the axis.
+pub fn bend(mesh: &HalfEdgeMesh, axis: Vec3, angle: f32) -> Result<()> {
+    let axis = axis
+        .try_normalize()
+        .ok_or_else(|| anyhow!("Bend axis cannot be zero"))?;
+    let angle = angle.to_radians();
+    if angle.abs() < 1e-6 {
+        return Ok(());
+    }
+    let dir = axis.any_orthonormal_vector();
+    deform_along_axis(mesh, axis, |along, across, distance, length| {
+        let radius = length / angle;
+        if !radius.is_finite() {
+            return axis * along + across;
+        }
+        // The arc starts at the bounding box min, and curves towards `dir`.
+        let x = across.dot(dir);
+        let phi = distance / radius;
+        axis * (along - distance + (radius - x) * phi.sin())
+            + dir * (radius - (radius - x) * phi.cos())
+            + (across - dir * x)
+    })
+}
+
+/// Displaces each vertex of the mesh along its smooth normal, by an amount
+/// given by 3d perlin noise sampled at the vertex position multiplied by
+/// `frequency`. The noise, which goes roughly from -1 to 1, is scaled by
+/// `amount`. The same `seed` always produces the same result.
+pub fn displace(mesh: &mut HalfEdgeMesh, amount: f32, frequency: f32, seed: u32) -> Result<()> {
+    use noise::{NoiseFn, Seedable};
+
+    let normals = generate_smooth_normals_channel(mesh)?;
+    let perlin = noise::Perlin::new().set_seed(seed);
+
+    let conn = mesh.read_connectivity();
+    let mut positions = mesh.write_positions();
+    for (v, _) in conn.iter_vertices() {
+        let p = positions[v] * frequency;
+        // NOTE: The noise crate may crash with non-finite inputs
+        if !p.is_finite() {
+            bail!("Cannot displace vertex with position {}", positions[v]);
+        }
+        let value = perlin.get([p.x as f64, p.y as f64, p.z as f64]) as f32;
+        positions[v] += normals[v] * value * amount;
+    }
+    Ok(())
+}
+
+/// Replaces each of the given `faces` by a fan of triangles around a new
+/// vertex at the face's centroid, displaced `offset` along the face normal.
+///
+/// The new center vertices are added to the `poked` vertex group, so they can
+/// be selected by subsequent operations.
+pub fn poke_faces(mesh: &mut HalfEdgeMesh, faces: &[FaceId], offset: f32) -> Result<()> {
+    let group_id = mesh.channels.ensure_channel::<VertexId, bool>("poked");
+    let mut group = mesh.channels.write_channel(group_id)?;
+    let mut conn = mesh.write_connectivity();
+    let mut positions = mesh.write_positions();
+
+    for face in faces.iter_cpy() {
+        let halfedges = conn.face_edges(face);
+        let vertices = conn.face_vertices(face);
+        let n = halfedges.len();
+        let normal = conn
+            .face_normal(&positions, face)
+            .ok_or_else(|| anyhow!("Attempted to poke a face with only two vertices."))?;
+        let center = conn.face_vertex_average(&positions, face) + normal * offset;
+        let x = conn.alloc_vertex(&mut positions, center, None);
+        group[x] = true;
+
+        // For the i-th triangle, the halfedges going from the face's boundary
+        // to the center vertex, and from the center vertex back to it.
+        let to_center = (0..n)
+            .map(|_| conn.alloc_halfedge(HalfEdge::default()))
+            .collect::<SVec<_>>();
+        let from_center = (0..n)
+            .map(|_| conn.alloc_halfedge(HalfEdge::default()))
+            .collect::<SVec<_>>();
+
+        for i in 0..n {
+            let tri_face = if i == 0 { face } else { conn.alloc_face(None) };
+            let (h, h_to, h_from) = (halfedges[i], to_center[i], from_center[i]);
+
+            conn[h_to].vertex = Some(vertices[(i + 1) % n]);
+            conn[h_to].twin = Some(from_center[(i + 1) % n]);
+            conn[h_from].vertex = Some(x);
+            conn[h_from].twin = Some(to_center[(i + n - 1) % n]);
+
+            conn[h].next = Some(h_to);
+            conn[h_to].next = Some(h_from);
+            conn[h_from].next = Some(h);
+            for h in [h, h_to, h_from] {
+                conn[h].face = Some(tri_face);
+            }
+            conn[tri_face].halfedge = Some(h);
+        }
+        conn[x].halfedge = Some(from_center[0]);
+    }
+
+    Ok(())
+}
+
+/// Moves every vertex of the mesh along its smooth normal by `amount`.
+/// Positive amounts make the mesh fatter, negative amounts shrink it. Unlike
+/// `solidify`, no new geometry is created.
+///
+/// When the mesh has a vertex normals channel, its normals are used.
+/// Otherwise, smooth normals are computed.
+pub fn shrink_fatten(mesh: &mut HalfEdgeMesh, amount: f32) -> Result<()> {
+    let normals = match mesh.default_channels.vertex_normals {
+        Some(ch_id) => mesh.channels.read_channel(ch_id)?.clone(),
+        None => generate_smooth_normals_channel(mesh)?,
+    };
+    let conn = mesh.read_connectivity();
+    let mut positions = mesh.write_positions();
+    for (v, _) in conn.iter_vertices() {
+        positions[v] += normals[v] * amount;
+    }
+    Ok(())
+}
+
+/// Reverses the winding of all the loops containing the given `halfedges`.
+/// The set of halfedges must be closed under the `next` and `twin`
+/// relations, e.g. all the halfedges of a connected shell, including its
+/// boundary. Halfedges without a twin are allowed, and will still have no
+/// twin after the flip.
+///
+/// Every halfedge keeps its source vertex, so vertices, faces and any
+/// channels keyed by them, including uvs, remain valid after the flip. Use
+/// `flip_halfedge_channels` to fix the edge and normal channels afterwards.
+fn flip_halfedges(conn: &mut MeshConnectivity, halfedges: &[HalfEdgeId]) -> Result<()> {
+    let mut new_pointers = Vec::with_capacity(halfedges.len());
+    for h in halfedges.iter_cpy() {
+        // After the flip, `h` goes towards the source of its previous
+        // halfedge. Its twin is the halfedge in the opposite loop leaving
+        // from that vertex.
+        let prev = conn.at_halfedge(h).previous().try_end()?;
+        let twin = conn[prev]
+            .twin
+            .map(|t| conn.at_halfedge(t).next().try_end())
+            .transpose()?;
+        new_pointers.push((h, prev, twin));
+    }
+    for (h, next, twin) in new_pointers {
+        conn[h].next = Some(next);
+        conn[h].twin = twin;
+    }
+    Ok(())
+}
+
+/// Returns the `(h, next(h))` pairs for the given `halfedges`. After
+/// `flip_halfedges`, the old `next` of `h` is the halfedge spanning the same
+/// edge in the same loop, so these are the pairs `flip_halfedge_channels`
+/// needs. Must be called before the flip.
+fn flipped_edge_pairs(
+    conn: &MeshConnectivity,
+    halfedges: &[HalfEdgeId],
+) -> Result<Vec<(HalfEdgeId, HalfEdgeId)>> {
+    halfedges
+        .iter_cpy()
+        .map(|h| Ok((h, conn.at_halfedge(h).next().try_end()?)))
+        .collect()
+}
+
+/// Updates the channels of `mesh` after flipping the halfedges in
+/// `edge_pairs`, as returned by `flipped_edge_pairs`. Corner values, like
+/// uvs, stay where they are since the halfedges keep their source vertex.
+/// Edge values, like creases, move to the halfedge now spanning their edge.
+/// The default normal channels are negated on the flipped elements.
+fn flip_halfedge_channels(
+    mesh: &HalfEdgeMesh,
+    edge_pairs: &[(HalfEdgeId, HalfEdgeId)],
+) -> Result<()> {
+    fn move_values<V: ChannelValue>(
+        mesh: &HalfEdgeMesh,
+        name: &str,
+        pairs: &[(HalfEdgeId, HalfEdgeId)],
+    ) {
+        if let Ok(mut ch) = mesh.channels.write_channel_by_name::<HalfEdgeId, V>(name) {
+            let values = pairs
+                .iter()
+                .map(|(from, _)| ch[*from].clone())
+                .collect_vec();
+            for ((_, to), value) in pairs.iter().zip(values) {
+                ch[*to] = value;
+            }
+        }
+    }
+    move_values::<f32>(mesh, compact_mesh::CREASE_CHANNEL, edge_pairs);
+    move_values::<bool>(mesh, compact_mesh::SHARP_CHANNEL, edge_pairs);
+
+    let conn = mesh.read_connectivity();
+    let halfedges = edge_pairs.iter().map(|(h, _)| *h).collect_vec();
+    let mut vertices = HashSet::new();
+    let mut faces = HashSet::new();
+    for h in halfedges.iter_cpy() {
+        vertices.insert(conn.at_halfedge(h).vertex().try_end()?);
+        if let Some(f) = conn[h].face {
+            faces.insert(f);
+        }
+    }
+
+    let default_ch = &mesh.default_channels;
+    if let Some(ch_id) = default_ch.vertex_normals {
+        let mut normals = mesh.channels.write_channel(ch_id)?;
+        for v in vertices {
+            normals[v] = -normals[v];
+        }
+    }
+    if let Some(ch_id) = default_ch.face_normals {
+        let mut normals = mesh.channels.write_channel(ch_id)?;
+        for f in faces {
+            normals[f] = -normals[f];
+        }
+    }
+    if let Some(ch_id) = default_ch.halfedge_normals {
+        let mut normals = mesh.channels.write_channel(ch_id)?;
+        for h in halfedges {
+            normals[h] = -normals[h];
+        }
+    }
+    Ok(())
+}
+
+/// Flips the normals of the given `faces` by reversing their winding order.
+///
+/// Faces adjacent to the selection can't be connected to the flipped faces
+/// anymore, since their shared edges would go in the same direction. The
+/// mesh is split along the border of the selection, duplicating the vertices
+/// there, so flipping part of a surface opens a seam around it. The
+/// duplicated vertices get the channel values of the original ones.
+pub fn flip_faces(mesh: &mut HalfEdgeMesh, faces: &[FaceId]) -> Result<()> {
+    let (split_vertices, edge_pairs) = flip_faces_connectivity(mesh, faces)?;
+    mesh.channels
+        .copy_values(&split_vertices.into_iter().collect_vec());
+    flip_halfedge_channels(mesh, &edge_pairs)
+}
+
+/// The connectivity part of `flip_faces`. Returns the pairs of split
+/// vertices and the pairs from `flipped_edge_pairs`.
+fn flip_faces_connectivity(
+    mesh: &HalfEdgeMesh,
+    faces: &[FaceId],
+) -> Result<(HashMap<VertexId, VertexId>, Vec<(HalfEdgeId, HalfEdgeId)>)> {
+    let mut conn = mesh.write_connectivity();
+    let mut positions = mesh.write_positions();
+
+    let selected = faces.iter_cpy().collect::<HashSet<_>>();
+    let mut halfedges = vec![];
+    for face in selected.iter_cpy() {
+        halfedges.extend(conn.at_face(face).halfedges()?);
+    }
+    let selected_halfedges = halfedges.iter_cpy().collect::<HashSet<_>>();
+
+    // --- Find the vertices in the border of the selection ---
+    let is_unselected_face = |conn: &MeshConnectivity, h: HalfEdgeId| {
+        conn[h]
+            .face
+            .map(|f| !selected.contains(&f))
+            .unwrap_or(false)
+    };
+    let mut border_vertices = vec![];
+    let mut seen = HashSet::new();
+    for h in halfedges.iter_cpy() {
+        let v = conn.at_halfedge(h).vertex().try_end()?;
+        if !seen.insert(v) {
+            continue;
+        }
+        let outgoing = conn.at_vertex(v).outgoing_halfedges()?;
+        if let Some(h_out) = outgoing
+            .iter_cpy()
+            .find(|h_out| is_unselected_face(&conn, *h_out))
+        {
+            border_vertices.push((v, h_out));
+        }
+    }
+
+    remove_boundary_halfedges(&mut conn);
+
+    // --- Split the mesh along the border ---
+    let mut split_vertices = HashMap::new();
+    for (v, h_out) in border_vertices {
+        let pos = positions[v];
+        let v2 = conn.alloc_vertex(&mut positions, pos, None);
+        conn[v].halfedge = Some(h_out);
+        split_vertices.insert(v, v2);
+    }
+    for h in halfedges.iter_cpy() {
+        let v = conn.at_halfedge(h).vertex().try_end()?;
+        if let Some(&v2) = split_vertices.get(&v) {
+            conn[h].vertex = Some(v2);
+            conn[v2].halfedge = Some(h);
+        }
+        if let Some(t) = conn[h].twin {
+            if !selected_halfedges.contains(&t) {
+                conn[h].twin = None;
+                conn[t].twin = None;
+            }
+        }
+    }
+
+    let edge_pairs = flipped_edge_pairs(&conn, &halfedges)?;
+    flip_halfedges(&mut conn, &halfedges)?;
+    regenerate_boundary_halfedges(&mut conn);
+
+    Ok((split_vertices, edge_pairs))
+}
+
+/// Makes the normals of the mesh point outwards. The mesh is split into
+/// shells, by flood-filling across shared edges, and each shell with a
+/// negative signed volume gets its winding flipped. Each shell is processed
+/// independently.
+///
+/// Note that the halfedge connectivity already forces adjacent faces to have
+/// consistent winding, so inconsistencies can only exist between separate
+/// shells.
+pub fn recalculate_normals_outside(mesh: &mut HalfEdgeMesh) -> Result<()> {
+    let mut conn = mesh.write_connectivity();
+    let positions = mesh.read_positions();
+
+    let mut edge_pairs = vec![];
+    let mut visited = HashSet::new();
+    let all_halfedges = conn.iter_halfedges().map(|(h, _)| h).collect_vec();
+    for h0 in all_halfedges {
+        if visited.contains(&h0) {
+            continue;
+        }
+
+        // --- Flood fill the shell ---
+        let mut shell = vec![];
+        let mut stack = vec![h0];
+        visited.insert(h0);
+        while let Some(h) = stack.pop() {
+            shell.push(h);
+            let next = conn.at_halfedge(h).next().try_end()?;
+            let twin = conn.at_halfedge(h).twin().try_end()?;
+            if conn.at_halfedge(twin).dst_vertex().try_end()?
+                != conn.at_halfedge(h).vertex().try_end()?
+            {
+                bail!("Found an edge with inconsistent winding. The mesh is malformed.")
+            }
+            for other in [next, twin] {
+                if visited.insert(other) {
+                    stack.push(other);
+                }
+            }
+        }
+
+        // --- Compute the signed volume ---
+        let faces = shell
+            .iter()
+            .filter_map(|h| conn[*h].face)
+            .collect::<HashSet<_>>();
+        let mut volume = 0.0;
+        for face in faces {
+            let vertices = conn.face_vertices(face);
+            let p0 = positions[vertices[0]];
+            for (v1, v2) in vertices[1..].iter().tuple_windows() {
+                volume += p0.dot(positions[*v1].cross(positions[*v2])) / 6.0;
+            }
+        }
+
+        if volume < 0.0 {
+            edge_pairs.extend(flipped_edge_pairs(&conn, &shell)?);
+            flip_halfedges(&mut conn, &shell)?;
+        }
+    }
+
+    drop(conn);
+    drop(positions);
+    flip_halfedge_channels(mesh, &edge_pairs)
+}
+
+#[blackjack_macros::blackjack_lua_module]
+pub mod lua_fns {
+
+    use crate::lua_engine::lua_stdlib::{LMeshList, LVec3};
+    use halfedge::compact_mesh::CompactMesh;
+
+    use super::*;
+
+    /// Replaces each vertex in the `vertices` selection with a face, and moves
+    /// that face along the incident edges by a given `amount` distance.
+    #[lua(under = "Ops")]
+    pub fn chamfer(
+        vertices: SelectionExpression,
+        amount: f32,
+        mesh: &mut HalfEdgeMesh,
+    ) -> Result<()> {
+        mesh.write_connectivity().clear_debug();
+        let verts = mesh.resolve_vertex_selection_full(&vertices)?;
+        for v in verts {
+            crate::mesh::halfedge::edit_ops::chamfer_vertex(
+                &mut mesh.write_connectivity(),
+                &mut mesh.write_positions(),
+                v,
+                amount,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Bevels the given `edges`, replacing each edge with a strip of
+    /// `segments` faces and indenting it by a given `amount` distance. The
+    /// `profile`, from 0 to 1, controls the roundness of the bevel.
+    #[lua(under = "Ops")]
+    pub fn bevel(
+        edges: SelectionExpression,
+        amount: f32,
+        segments: usize,
+        profile: f32,
+        mesh: &HalfEdgeMesh,
+    ) -> Result<()> {
+        let edges = mesh.resolve_halfedge_selection_full(&edges)?;
+        crate::mesh::halfedge::edit_ops::bevel_edges_segments(
+            &mut mesh.write_connectivity(),
+            &mut mesh.write_positions(),
+            &edges,
+            amount,
+            segments,
+            profile,
+        )
+    }
+
+    /// Extrudes the given `faces` by a given `amount` distance.
+    #[lua(under = "Ops")]
+    pub fn extrude(faces: SelectionExpression, amount: f32, mesh: &HalfEdgeMesh) -> Result<()> {
+        let faces = mesh.resolve_face_selection_full(&faces)?;
+        crate::mesh::halfedge::edit_ops::extrude_faces(
+            &mut mesh.write_connectivity(),
+            &mut mesh.write_positions(),
+            &faces,
+            amount,
+        )?;
+        Ok(())
+    }
+
+    /// Generates UVs for `mesh` by projecting it onto a plane, a cylinder or
+    /// a sphere, depending on `mode`, oriented along `axis`. The UVs are
+    /// multiplied by `scale` and stored in the `uv_channel`, which can be
+    /// either "uv" or "uv_lightmap", and defaults to "uv" when omitted.
+    #[lua(under = "Ops")]
+    pub fn project_uvs(
+        mesh: &mut HalfEdgeMesh,
+        mode: String,
+        axis: LVec3,
+        scale: LVec3,
+        uv_channel: Option<String>,
+    ) -> Result<()> {
+        let mode = match mode.as_str() {
+            "Planar" => UvProjection::Planar,
+            "Cylindrical" => UvProjection::Cylindrical,
+            "Spherical" => UvProjection::Spherical,
+            _ => bail!("Invalid UV projection mode: {mode}"),
+        };
+        let uv_ch = UvChannel::from_channel_name(uv_channel.as_deref().unwrap_or("uv"))?;
+        super::project_uvs(mesh, mode, axis.0, scale.0.truncate(), uv_ch)
+    }
+
+    /// Flips the normals of the `faces` in `mesh`.
+    #[lua(under = "Ops")]
+    pub fn flip_normals(faces: SelectionExpression, mesh: &mut HalfEdgeMesh) -> Result<()> {
+        let faces = mesh.resolve_face_selection_full(&faces)?;
+        super::flip_faces(mesh, &faces)
+    }
+
+    /// Makes the normals of every shell in `mesh` point outwards.
+    #[lua(under = "Ops")]
+    pub fn recalculate_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
+        super::recalculate_normals_outside(mesh)
+    }
+
+    /// Extrudes the boundary edges in the `edges` selection, creating a new
+    /// quad for each of them that goes from the edge to a copy of it moved
+    /// by `offset`.
+    #[lua(under = "Ops")]
+    pub fn extrude_edges(
+        edges: SelectionExpression,
+        offset: LVec3,
+        mesh: &mut HalfEdgeMesh,
+    ) -> Result<()> {
+        let edges = mesh.resolve_halfedge_selection_full(&edges)?;
+        super::extrude_edges(mesh, &edges, offset.0)
+    }
+
+    /// Extrudes each face in `faces` separately along its own normal, by a
+    /// given `amount` distance.
+    #[lua(under = "Ops")]
+    pub fn extrude_individual(
+        faces: SelectionExpression,
+        amount: f32,
+        mesh: &HalfEdgeMesh,
+    ) -> Result<()> {
+        let faces = mesh.resolve_face_selection_full(&faces)?;
+        crate::mesh::halfedge::edit_ops::extrude_faces_individual(
+            &mut mesh.write_connectivity(),
+            &mut mesh.write_positions(),
+            &faces,
+            amount,
+        )?;
+        Ok(())
+    }
+
+    /// Modifies the given mesh `a` by merging `b` into it. The `b` mesh remains
+    /// unmodified.
+    #[lua(under = "Ops")]
+    pub fn merge(a: &mut HalfEdgeMesh, b: &HalfEdgeMesh) -> Result<()> {
+        a.merge_with(b);
+        Ok(())
+    }
+
+    /// Modifies the given mesh `a` by merging `b` into it, and welds the
+    /// boundary vertices of `b` to the ones in `a` closer than `threshold`.
+    /// Returns the number of welded vertices. The `b` mesh remains
+    /// unmodified.
+    #[lua(under = "Ops")]
+    pub fn merge_welded(a: &mut HalfEdgeMesh, b: &HalfEdgeMesh, threshold: f32) -> Result<usize> {
+        a.merge_welded(b, threshold)
+    }
+
+    /// Subdivides the given mesh, applying as many `iterations` as given. If
+    /// `catmull_clark` is true, will use catmull clark subdivision, else linear
+    /// (i.e. vertex positions remain unchanged, and each n-gon is split into
+    /// n quads). Catmull clark subdivision
+    /// respects the edge creases set with `Ops.set_crease`. UVs and other
+    /// channels are interpolated for the new elements, except for normals.
+    #[lua(under = "Ops")]
+    pub fn subdivide(
+        mesh: &HalfEdgeMesh,
+        iterations: usize,
+        catmull_clark: bool,
+    ) -> Result<HalfEdgeMesh> {
+        let new_mesh = CompactMesh::<false>::from_halfedge(mesh)?;
+        Ok(new_mesh
+            .subdivide_multi(iterations, catmull_clark)
+            .to_halfedge())
+    }
+
+    /// Computes the smooth normals channel for the given `mesh` and sets the
+    /// mesh export settings to use smooth normals. The `weighting` of the
+    /// adjacent face normals can be "Uniform", "Area" or "Angle", and defaults
+    /// to "Uniform" when omitted.
+    #[lua(under = "Ops")]
+    pub fn set_smooth_normals(mesh: &mut HalfEdgeMesh, weighting: Option<String>) -> Result<()> {
+        let weighting = match weighting.as_deref().unwrap_or("Uniform") {
+            "Uniform" => NormalWeighting::Uniform,
+            "Area" => NormalWeighting::Area,
+            "Angle" => NormalWeighting::Angle,
+            other => bail!("Invalid normal weighting: {other}"),
+        };
+        super::set_smooth_normals(mesh, weighting)?;
+        Ok(())
+    }
+
+    /// Computes the flat normals channel for the given `mesh` and sets the
+    /// mesh export settings to use flat normals.
+    #[lua(under = "Ops")]
+    pub fn set_flat_normals(mesh: &mut HalfEdgeMesh) -> Result<()> {
+        super::set_flat_normals(mesh)?;
+        Ok(())
+    }
+
+    /// Computes per-corner normals for the given `mesh`, which are smooth
+    /// across edges with a dihedral angle below `angle` degrees and flat across
+    /// the sharper ones.
+    #[lua(under = "Ops")]
+    pub fn auto_smooth(mesh: &mut HalfEdgeMesh, angle: f32) -> Result<()> {
+        super::auto_smooth(mesh, angle)
+    }
+
+    /// Given a mesh representing a polyline, resamples it using Catmull-Rom
+    /// interpolation to create a smooth path that passes through all the points
+    /// of the original curve.
+    ///
+    /// TODO: Obsolete docs!!!!!
+    /// The `resolution` will be used to determine the distance between each
+    /// pair of points in the final curve. Depending on the input curve and this
+    /// value, the actual waypoints may not be part of the final resampled
+    /// curve, but given a small enough segment length, the final curve will be
+    /// a good approximation of the input waypoints.
+    ///
+    /// The `alpha` value can be set to 0 for a uniform, 0.5 for a centripetal
+    /// and 1.0 for a chordal Catmull-Rom spline. If in doubt, pick 0.5 for good
+    /// results.
+    ///
+    /// Increasing the `tension` from 0 to 1 value will make the curves more
+    /// pronounced, as if it were increasing the tension of a rope that goes
+    /// through all the points. A good value for tension is 0.5
+    ///
+    /// Closed curves are resampled as a closed loop. When using the `Uniform`
+    /// density mode, the segment length is slightly adjusted so the loop is
+    /// divided evenly.
     #[lua(under = "Ops")]
     pub fn resample_curve(
         mesh: &HalfEdgeMesh,
@@ -2079,7 +5459,8 @@ pub mod lua_fns {
     }
 
     /// Given two edge selections, bridges the two edge selections with quads
-    /// spanning every pair of consecutive edges.
+    /// spanning every pair of consecutive edges. When the selections have a
+    /// different number of edges, triangles are added where needed.
     ///
     /// The `flip` parameter can be used to select a permutation for the winding
     /// order of each of the input loops.
@@ -2162,11 +5543,130 @@ pub mod lua_fns {
     pub fn set_material(
         mesh: &mut HalfEdgeMesh,
         selection: SelectionExpression,
-        material_index: f32,
+        material_index: i32,
     ) -> Result<()> {
         super::set_material(mesh, &selection, material_index)
     }
 
+    /// Sets the `color` channel for all vertices in `selection` to the given
+    /// RGB `color`, with components in the [0, 1] range. Vertex colors are
+    /// shown in the viewport and exported by game engine integrations.
+    #[lua(under = "Ops")]
+    pub fn set_vertex_color(
+        mesh: &mut HalfEdgeMesh,
+        selection: SelectionExpression,
+        color: LVec3,
+    ) -> Result<()> {
+        super::set_vertex_color(mesh, &selection, color.0)
+    }
+
+    /// Sets the crease weight of the `edges` in `selection` to `weight`. When
+    /// using catmull clark subdivision, edges with a weight of 1.0 stay
+    /// sharp, and lower weights keep them sharp for fewer levels.
+    #[lua(under = "Ops")]
+    pub fn set_crease(
+        mesh: &mut HalfEdgeMesh,
+        edges: SelectionExpression,
+        weight: f32,
+    ) -> Result<()> {
+        super::set_crease(mesh, &edges, weight)
+    }
+
+    /// Marks the `edges` in `selection` as sharp, or clears the mark when
+    /// `value` is false. Sharp edges are highlighted in the viewport, and
+    /// stay sharp when using catmull clark subdivision.
+    #[lua(under = "Ops")]
+    pub fn mark_sharp(
+        mesh: &mut HalfEdgeMesh,
+        edges: SelectionExpression,
+        value: bool,
+    ) -> Result<()> {
+        super::mark_sharp(mesh, &edges, value)
+    }
+
+    /// Sets the string channel called `name` to `value` for all the elements
+    /// of the given `key_type` in `selection`. String channels can be used to
+    /// attach human-readable labels or tags to mesh elements.
+    #[lua(under = "Ops")]
+    pub fn set_string_attribute(
+        mesh: &mut HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        selection: SelectionExpression,
+        name: String,
+        value: String,
+    ) -> Result<()> {
+        super::set_string_attribute(mesh, key_type, &selection, &name, &value)
+    }
+
+    /// Combines the channels named `a_name` and `b_name` element by element,
+    /// storing the result in the `out_name` channel. Both channels must have
+    /// the given `key_type` and `value_type`, which should be numeric (F32,
+    /// I32 or VEC3). The `op` can be one of "add", "sub", "mul", "div", "min"
+    /// or "max".
+    #[lua(under = "Ops")]
+    pub fn channel_math(
+        mesh: &mut HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        value_type: ChannelValueType,
+        out_name: String,
+        a_name: String,
+        op: String,
+        b_name: String,
+    ) -> Result<()> {
+        let op = match op.as_str() {
+            "add" => ChannelMathOp::Add,
+            "sub" => ChannelMathOp::Sub,
+            "mul" => ChannelMathOp::Mul,
+            "div" => ChannelMathOp::Div,
+            "min" => ChannelMathOp::Min,
+            "max" => ChannelMathOp::Max,
+            _ => bail!("Invalid channel math operation: {op}"),
+        };
+        super::channel_math(mesh, key_type, value_type, &out_name, &a_name, op, &b_name)
+    }
+
+    /// Copies the channel named `from` with given `key_type` and `value_type`
+    /// into a channel named `to`. The `to` channel is created if it doesn't
+    /// exist, or overwritten otherwise.
+    #[lua(under = "Ops")]
+    pub fn copy_channel(
+        mesh: &mut HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        value_type: ChannelValueType,
+        from: String,
+        to: String,
+    ) -> Result<()> {
+        mesh.channels
+            .copy_channel_dyn(key_type, value_type, &from, &to)?;
+        Ok(())
+    }
+
+    /// Renames the channel named `old` with given `key_type` and `value_type`
+    /// to `new`. Fails if there's already a channel called `new`.
+    #[lua(under = "Ops")]
+    pub fn rename_channel(
+        mesh: &mut HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        value_type: ChannelValueType,
+        old: String,
+        new: String,
+    ) -> Result<()> {
+        mesh.channels
+            .rename_channel_dyn(key_type, value_type, &old, &new)
+    }
+
+    /// Deletes the channel called `name` with given `key_type` and
+    /// `value_type`. Useful to clean up temporary channels before exporting.
+    #[lua(under = "Ops")]
+    pub fn delete_channel(
+        mesh: &mut HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        value_type: ChannelValueType,
+        name: String,
+    ) -> Result<()> {
+        super::delete_channel(mesh, key_type, value_type, &name)
+    }
+
     /// Given a source mesh (`src_mesh`) and a destination mesh (`dst_mesh`),
     /// transfers the vertex channel with given `value_type` and `channel_name`
     /// from source to mesh.
@@ -2187,34 +5687,74 @@ pub mod lua_fns {
             ChannelValueType::f32 => {
                 super::vertex_attribute_transfer::<f32>(src_mesh, dst_mesh, &channel_name)
             }
+            ChannelValueType::i32 => {
+                super::vertex_attribute_transfer::<i32>(src_mesh, dst_mesh, &channel_name)
+            }
             ChannelValueType::bool => {
                 super::vertex_attribute_transfer::<bool>(src_mesh, dst_mesh, &channel_name)
             }
+            ChannelValueType::String => {
+                super::vertex_attribute_transfer::<String>(src_mesh, dst_mesh, &channel_name)
+            }
         }
     }
 
     /// Generates an UV channel (HalfEdgeId -> Vec3) for the mesh where ever
     /// polygon is mapped to the full UV range. Triangles will take half the UV
     /// space, quads will take the full space, and n-gons will take as much
-    /// space as possible, being centered in the middle.
+    /// space as possible, being centered in the middle. The `uv_channel` can
+    /// be either "uv" or "uv_lightmap", and defaults to "uv" when omitted.
     #[lua(under = "Ops")]
-    pub fn set_full_range_uvs(mesh: &mut HalfEdgeMesh) -> Result<()> {
-        super::set_full_range_uvs(mesh)
+    pub fn set_full_range_uvs(mesh: &mut HalfEdgeMesh, uv_channel: Option<String>) -> Result<()> {
+        let uv_ch = UvChannel::from_channel_name(uv_channel.as_deref().unwrap_or("uv"))?;
+        super::set_full_range_uvs(mesh, uv_ch)
     }
 
     /// Given a `points` mesh, taken as a point cloud and another `mesh`, returs
     /// a new mesh where `mesh` is instanced at every point of the point cloud.
+    /// Instead of a single mesh, `mesh` can also be a table of meshes.
     ///
     /// The following additional channels influence the behavior of this
     /// operation:
     ///
-    /// - The `normal` and `tangent` vertex channels, if present, will be used
-    /// to set the orientation of the cross-section at each point.
-    /// - The `size` vertex channel will be used to scale the cross section at
-    /// each point.
+    /// - The `rotation` vertex channel, if present, sets the orientation of
+    /// the instance at each point, as euler angles in radians.
+    /// - Otherwise, the `normal` and `tangent` vertex channels, if present,
+    /// will be used to set the orientation of the instance at each point.
+    /// - The `size` vertex channel will be used to scale the instance at each
+    /// point.
+    /// - The `instance_index` vertex channel selects which mesh in the table
+    /// is placed at each point. When missing, the first mesh is used.
+    #[lua(under = "Ops")]
+    pub fn copy_to_points(points: &HalfEdgeMesh, mesh: LMeshList) -> Result<HalfEdgeMesh> {
+        super::copy_to_points(points, &mesh.0)
+    }
+
+    /// Returns a new mesh with `count` copies of `mesh` rotated around `axis`
+    /// going through `center`, evenly distributed along `angle` degrees. Use
+    /// an angle of 360 for a full circle of copies.
+    #[lua(under = "Ops")]
+    pub fn radial_array(
+        mesh: &HalfEdgeMesh,
+        count: usize,
+        axis: LVec3,
+        center: LVec3,
+        angle: f32,
+    ) -> Result<HalfEdgeMesh> {
+        super::array_radial(mesh, count, axis.0, center.0, angle)
+    }
+
+    /// Returns a new mesh with `count` copies of `mesh`, each one translated
+    /// by `offset` from the previous one. When `relative` is true, the offset
+    /// is measured in multiples of the mesh's bounding box size.
     #[lua(under = "Ops")]
-    pub fn copy_to_points(points: &HalfEdgeMesh, mesh: &HalfEdgeMesh) -> Result<HalfEdgeMesh> {
-        super::copy_to_points(points, mesh)
+    pub fn linear_array(
+        mesh: &HalfEdgeMesh,
+        count: usize,
+        offset: LVec3,
+        relative: bool,
+    ) -> Result<HalfEdgeMesh> {
+        super::array_linear(mesh, count, offset.0, relative)
     }
 
     /// Given a `backbone` mesh and a cross-section mesh, both polylines,
@@ -2224,16 +5764,31 @@ pub mod lua_fns {
     /// operation:
     ///
     /// - The `normal` and `tangent` vertex channels, if present, will be used
-    /// to set the orientation of the cross-section at each point.
-    /// - The `size` vertex channel will be used to scale the cross section at
-    /// each point.
+    /// to set the orientation of the cross-section at each point. Only the
+    /// first normal is used: The rest are computed by transporting it along
+    /// the backbone's tangents, which avoids sudden flips.
+    /// - The `f32` vertex channel named `scale_channel` (e.g. `size`), if
+    /// present, will be used to scale the cross section at each point.
+    ///
+    /// The `twist` is the total rotation angle, in degrees, applied to the
+    /// cross-section from the start to the end of the backbone. Intermediate
+    /// points get a proportional amount of rotation around the tangent. It
+    /// defaults to 0 when omitted, and `scale_channel` defaults to `size`.
     #[lua(under = "Ops")]
     pub fn extrude_along_curve(
         backbone: &HalfEdgeMesh,
         cross_section: &HalfEdgeMesh,
         flip: usize,
+        twist: Option<f32>,
+        scale_channel: Option<String>,
     ) -> Result<HalfEdgeMesh> {
-        super::extrude_along_curve(backbone, cross_section, flip)
+        super::extrude_along_curve(
+            backbone,
+            cross_section,
+            flip,
+            twist.unwrap_or(0.0),
+            scale_channel.as_deref().unwrap_or("size"),
+        )
     }
 
     /// Applies a transformation to the given selection of mesh elements
@@ -2350,4 +5905,898 @@ pub mod lua_fns {
 
         Ok(h)
     }
+
+    /// Mirrors the `mesh` across the plane through the origin with normal
+    /// `axis`, returning a new mesh. Vertices closer than `threshold` to their
+    /// reflection are welded together to keep the seam watertight.
+    #[lua(under = "Ops")]
+    pub fn mirror(mesh: &HalfEdgeMesh, axis: LVec3, threshold: f32) -> Result<HalfEdgeMesh> {
+        super::mirror(mesh, axis.0, threshold)
+    }
+
+    /// Makes `mesh` symmetric with respect to the plane through the origin with
+    /// normal `axis`, by replacing one of its halves with the reflection of
+    /// the other. The `direction` can be "PositiveToNegative", to keep the
+    /// half the normal points to, or "NegativeToPositive".
+    #[lua(under = "Ops")]
+    pub fn symmetrize(mesh: &mut HalfEdgeMesh, axis: LVec3, direction: String) -> Result<()> {
+        let positive_to_negative = match direction.as_str() {
+            "PositiveToNegative" => true,
+            "NegativeToPositive" => false,
+            _ => bail!("Invalid symmetrize direction: {direction}"),
+        };
+        super::symmetrize(mesh, axis.0, positive_to_negative)
+    }
+
+    /// Gives thickness to the `mesh`, by adding an inner shell offset along
+    /// the vertex normals by a given `thickness` distance. Open boundaries are
+    /// closed with walls connecting both shells.
+    #[lua(under = "Ops")]
+    pub fn solidify(mesh: &HalfEdgeMesh, thickness: f32) -> Result<HalfEdgeMesh> {
+        super::solidify(mesh, thickness)
+    }
+
+    /// Welds together all vertices in `mesh` closer than `threshold` to each
+    /// other. Returns the number of vertices that were welded away.
+    #[lua(under = "Ops")]
+    pub fn merge_by_distance(mesh: &mut HalfEdgeMesh, threshold: f32) -> Result<usize> {
+        super::weld_by_distance(mesh, threshold)
+    }
+
+    /// Splits all faces in `mesh` into triangles. The `strategy` can be either
+    /// "FanFromFirst" or "EarClipping". Returns a new mesh.
+    #[lua(under = "Ops")]
+    pub fn triangulate(mesh: &HalfEdgeMesh, strategy: String) -> Result<HalfEdgeMesh> {
+        let strategy = match strategy.as_str() {
+            "FanFromFirst" => TriangulationStrategy::FanFromFirst,
+            "EarClipping" => TriangulationStrategy::EarClipping,
+            _ => bail!("Invalid triangulation strategy: {strategy}"),
+        };
+        super::triangulate(mesh, strategy)
+    }
+
+    /// Twists `mesh` around `axis`, by `amount` degrees per unit of distance
+    /// along the axis.
+    #[lua(under = "Ops")]
+    pub fn twist(mesh: &mut HalfEdgeMesh, axis: LVec3, amount: f32) -> Result<()> {
+        super::twist(mesh, axis.0, amount)
+    }
+
+    /// Scales `mesh` away from `axis`, going from no scaling at one end to
+    /// `factor` at the other.
+    #[lua(under = "Ops")]
+    pub fn taper(mesh: &mut HalfEdgeMesh, axis: LVec3, factor: f32) -> Result<()> {
+        super::taper(mesh, axis.0, factor)
+    }
+
+    /// Bends `mesh` so that `axis` turns `angle` degrees from one end to the
+    /// other.
+    #[lua(under = "Ops")]
+    pub fn bend(mesh: &mut HalfEdgeMesh, axis: LVec3, angle: f32) -> Result<()> {
+        super::bend(mesh, axis.0, angle)
+    }
+
+    /// Displaces the vertices of `mesh` along their normals using perlin
+    /// noise sampled at the vertex positions scaled by `frequency`. The noise
+    /// is scaled by `amount`, and changing the `seed` gives a different result.
+    #[lua(under = "Ops")]
+    pub fn displace(mesh: &mut HalfEdgeMesh, amount: f32, frequency: f32, seed: u32) -> Result<()> {
+        super::displace(mesh, amount, frequency, seed)
+    }
+
+    /// Replaces each face in the `faces` selection with a fan of triangles
+    /// around its center, moved `offset` along the face normal. The new
+    /// vertices are added to the `poked` vertex group.
+    #[lua(under = "Ops")]
+    pub fn poke(faces: SelectionExpression, offset: f32, mesh: &mut HalfEdgeMesh) -> Result<()> {
+        let faces = mesh.resolve_face_selection_full(&faces)?;
+        super::poke_faces(mesh, &faces, offset)
+    }
+
+    /// Moves the vertices of `mesh` along their normals by `amount`. Use
+    /// negative values to shrink the mesh.
+    #[lua(under = "Ops")]
+    pub fn shrink_fatten(mesh: &mut HalfEdgeMesh, amount: f32) -> Result<()> {
+        super::shrink_fatten(mesh, amount)
+    }
+
+    /// Reduces the face count of `mesh` to a `ratio` of the original, using
+    /// quadric error metrics. Returns a new mesh.
+    #[lua(under = "Ops")]
+    pub fn decimate(mesh: &HalfEdgeMesh, ratio: f32) -> Result<HalfEdgeMesh> {
+        super::decimate(mesh, ratio)
+    }
+
+    /// Rotates the `profile` polyline around the given `axis` going through
+    /// `center`, in `steps` increments covering `angle` degrees. Returns a new
+    /// mesh with the surface of revolution.
+    #[lua(under = "Ops")]
+    pub fn spin(
+        profile: &HalfEdgeMesh,
+        axis: LVec3,
+        center: LVec3,
+        angle: f32,
+        steps: usize,
+    ) -> Result<HalfEdgeMesh> {
+        super::spin(profile, axis.0, center.0, angle, steps)
+    }
+
+    /// Performs a loop cut on the edge ring of each edge in `edges`, inserting
+    /// `num_cuts` new edge loops on each ring. Rings with several selected
+    /// edges are only cut once.
+    #[lua(under = "Ops")]
+    pub fn loop_cut(
+        mesh: &mut HalfEdgeMesh,
+        edges: SelectionExpression,
+        num_cuts: usize,
+    ) -> Result<()> {
+        let edges = mesh.resolve_halfedge_selection_full(&edges)?;
+        super::loop_cut_edges(mesh, &edges, num_cuts)
+    }
+
+    /// Computes a boolean operation between the solids enclosed by meshes `a`
+    /// and `b`, returning a new mesh. The `mode` can be one of "Union",
+    /// "Intersection" or "Difference". Both meshes should be closed.
+    #[lua(under = "Ops")]
+    pub fn mesh_boolean(a: &HalfEdgeMesh, b: &HalfEdgeMesh, mode: String) -> Result<HalfEdgeMesh> {
+        let mode = match mode.as_str() {
+            "Union" => BooleanMode::Union,
+            "Intersection" => BooleanMode::Intersection,
+            "Difference" => BooleanMode::Difference,
+            _ => bail!("Invalid boolean mode: {mode}"),
+        };
+        super::boolean(a, b, mode)
+    }
+
+    /// Returns the convex hull of the vertices in `mesh`.
+    #[lua(under = "Ops")]
+    pub fn convex_hull(mesh: &HalfEdgeMesh) -> Result<HalfEdgeMesh> {
+        super::convex_hull(mesh)
+    }
+
+    /// Rebuilds the surface of `mesh` as a watertight mesh made of quads of
+    /// roughly `voxel_size`. Useful to clean up the result of booleans.
+    #[lua(under = "Ops")]
+    pub fn voxel_remesh(mesh: &HalfEdgeMesh, voxel_size: f32) -> Result<HalfEdgeMesh> {
+        super::remesh_voxel(mesh, voxel_size)
+    }
+
+    /// Returns a new mesh where every edge of `mesh` is replaced by a beam
+    /// with a square section of the given `thickness`.
+    #[lua(under = "Ops")]
+    pub fn wireframe(mesh: &HalfEdgeMesh, thickness: f32) -> Result<HalfEdgeMesh> {
+        super::wireframe(mesh, thickness)
+    }
+
+    /// Returns the signed volume enclosed by `mesh`. The volume is negative
+    /// when the mesh is inside-out. Only meaningful for closed meshes.
+    #[lua(under = "Blackjack")]
+    pub fn mesh_volume(mesh: &HalfEdgeMesh) -> f32 {
+        super::mesh_volume(mesh)
+    }
+
+    /// Returns the total area of the faces of `mesh`.
+    #[lua(under = "Blackjack")]
+    pub fn surface_area(mesh: &HalfEdgeMesh) -> f32 {
+        super::surface_area(mesh)
+    }
+
+    /// Returns the minimum and maximum corners of the bounding box of `mesh`,
+    /// as two separate values.
+    #[lua(under = "Blackjack")]
+    pub fn bounding_box(mesh: &HalfEdgeMesh) -> (LVec3, LVec3) {
+        let (min, max) = super::bounding_box(mesh);
+        (LVec3(min), LVec3(max))
+    }
+
+    impl<'lua> mlua::ToLua<'lua> for MeshReport {
+        fn to_lua(self, lua: &'lua mlua::Lua) -> mlua::Result<mlua::Value<'lua>> {
+            let table = lua.create_table()?;
+            table.set("is_valid", self.is_valid())?;
+            table.set("non_planar_faces", self.non_planar_faces)?;
+            table.set(
+                "non_manifold_edges",
+                self.non_manifold_edges
+                    .into_iter()
+                    .map(|(a, b)| vec![a, b])
+                    .collect_vec(),
+            )?;
+            table.set("isolated_vertices", self.isolated_vertices)?;
+            table.set("zero_area_faces", self.zero_area_faces)?;
+            Ok(mlua::Value::Table(table))
+        }
+    }
+
+    /// Scales and translates `mesh` so its bounding box fits between the `min`
+    /// and `max` corners. When `keep_aspect` is true, the mesh is scaled
+    /// uniformly and centered in the box, instead of stretched to fill it.
+    #[lua(under = "Ops")]
+    pub fn fit_to_box(
+        mesh: &mut HalfEdgeMesh,
+        min: LVec3,
+        max: LVec3,
+        keep_aspect: bool,
+    ) -> Result<()> {
+        super::fit_to_box(mesh, min.0, max.0, keep_aspect)
+    }
+
+    /// Checks `mesh` for problems, without failing. Returns a table with the
+    /// `non_planar_faces`, `non_manifold_edges` (as pairs of vertices),
+    /// `isolated_vertices` and `zero_area_faces` that were found, and an
+    /// `is_valid` flag that is true when the mesh has no problems.
+    #[lua(under = "Blackjack")]
+    pub fn validate(mesh: &HalfEdgeMesh) -> MeshReport {
+        super::validate_mesh(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounding_box() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0)).unwrap();
+        let half = Vec3::new(0.5, 1.0, 1.5);
+        assert_eq!(bounding_box(&mesh), (-half, half));
+
+        // Removed vertices keep their entry in the position channel, but
+        // should not count towards the bounds.
+        add_vertex(&mut mesh, Vec3::splat(10.0)).unwrap();
+        assert_eq!(bounding_box(&mesh), (-half, Vec3::splat(10.0)));
+        {
+            let mut conn = mesh.write_connectivity();
+            let (v, _) = conn.iter_vertices_ordered().last().unwrap();
+            conn.remove_vertex(v);
+        }
+        assert_eq!(bounding_box(&mesh), (-half, half));
+    }
+
+    #[test]
+    fn test_fit_to_box() {
+        let build = || {
+            let mut mesh = primitives::Box::build(Vec3::ONE, Vec3::new(1.0, 2.0, 4.0)).unwrap();
+            // A removed vertex, which should be ignored.
+            add_vertex(&mut mesh, Vec3::splat(10.0)).unwrap();
+            {
+                let mut conn = mesh.write_connectivity();
+                let (v, _) = conn.iter_vertices_ordered().last().unwrap();
+                conn.remove_vertex(v);
+            }
+            mesh
+        };
+        let (target_min, target_max) = (Vec3::ZERO, Vec3::splat(2.0));
+
+        let mut stretched = build();
+        fit_to_box(&mut stretched, target_min, target_max, false).unwrap();
+        assert_eq!(bounding_box(&stretched), (target_min, target_max));
+
+        // The longest side fits the box, and the mesh is centered in it.
+        let mut uniform = build();
+        fit_to_box(&mut uniform, target_min, target_max, true).unwrap();
+        assert_eq!(
+            bounding_box(&uniform),
+            (Vec3::new(0.75, 0.5, 0.0), Vec3::new(1.25, 1.5, 2.0))
+        );
+    }
+
+    #[test]
+    fn test_merge_welded() {
+        let grid = |center: Vec3| {
+            primitives::Grid::build(center, Vec3::Y, Vec2::splat(2.0), 3, 3).unwrap()
+        };
+
+        // Two grids sharing the side at x = 1 are welded along it.
+        let mut mesh = grid(Vec3::ZERO);
+        let num_welds = mesh
+            .merge_welded(&grid(Vec3::new(2.0, 0.0, 0.0)), 1e-4)
+            .unwrap();
+        assert_eq!(num_welds, 4);
+        let conn = mesh.read_connectivity();
+        assert_eq!(conn.num_vertices(), 2 * 16 - 4);
+        assert_eq!(conn.num_faces(), 2 * 9);
+
+        // The seam is manifold: Its edges are shared by two faces, so the
+        // only boundary left is the outline of the combined 6x3 grid.
+        assert_eq!(conn.num_halfedges(), 2 * (6 * 4 + 7 * 3));
+        let boundary = conn
+            .iter_halfedges()
+            .filter(|(_, h)| h.face.is_none())
+            .count();
+        assert_eq!(boundary, 2 * (6 + 3));
+        let pairs = conn
+            .iter_halfedges()
+            .map(|(h, _)| conn.at_halfedge(h).src_dst_pair().unwrap())
+            .collect_vec();
+        assert_eq!(pairs.iter().unique().count(), pairs.len());
+        drop(conn);
+
+        // Grids touching at a single corner would form a non-manifold
+        // vertex, so nothing changes.
+        let mut mesh = grid(Vec3::ZERO);
+        assert!(mesh
+            .merge_welded(&grid(Vec3::new(2.0, 0.0, 2.0)), 1e-4)
+            .is_err());
+        let conn = mesh.read_connectivity();
+        assert_eq!(conn.num_vertices(), 16);
+        assert_eq!(conn.num_faces(), 9);
+        assert_eq!(
+            bounding_box(&mesh),
+            (Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn test_auto_smooth_normals() {
+        let mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let face_normals = generate_flat_normals_channel(&mesh).unwrap();
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        let corners = conn
+            .iter_faces()
+            .flat_map(|(f, _)| conn.face_edges(f).into_iter().map(move |h| (f, h)))
+            .collect_vec();
+        assert_eq!(corners.len(), 24);
+
+        // The box edges are at 90 degrees, so they are all sharp: Every corner
+        // gets the normal of its face.
+        let sharp = generate_auto_smooth_normals_channel(&mesh, 30.0).unwrap();
+        for &(f, h) in &corners {
+            assert!(sharp[h].abs_diff_eq(face_normals[f], 1e-5));
+        }
+
+        // All edges are smooth: The three faces around each vertex are
+        // averaged, pointing away from the center of the box.
+        let smooth = generate_auto_smooth_normals_channel(&mesh, 100.0).unwrap();
+        for &(_, h) in &corners {
+            let v = conn.at_halfedge(h).vertex().end();
+            let expected = positions[v].normalize();
+            assert!(smooth[h].abs_diff_eq(expected, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_weld_by_distance_collapses_edges() {
+        // A quad with a very short side, and a triangle next to it whose
+        // vertices almost touch the quad, without sharing any edge.
+        let positions = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 1e-5),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1e-5, 0.0, 0.0),
+            Vec3::new(1.0 + 1e-5, 0.0, 0.0),
+            Vec3::new(0.5, 0.0, -1.0),
+        ];
+        let polygons = vec![vec![0, 3, 2, 1], vec![4, 5, 6]];
+        let (mut mesh, ids) = build_with_ids(&positions, &polygons);
+
+        // The short side is collapsed, turning the quad into a triangle. The
+        // other triangle is welded to it, and they end up sharing an edge.
+        assert_eq!(weld_by_distance(&mut mesh, 1e-3).unwrap(), 3);
+        let conn = mesh.read_connectivity();
+        let mesh_positions = mesh.read_positions();
+        assert_eq!(conn.num_vertices(), 4);
+        assert_eq!(conn.num_faces(), 2);
+        assert!(conn
+            .iter_faces()
+            .all(|(f, _)| conn.face_vertices(f).len() == 3));
+        assert_eq!(conn.num_halfedges(), 2 * 5);
+        for (v, _) in conn.iter_vertices() {
+            assert!([0, 2, 3, 6].iter().any(|i| ids[*i] == v));
+            assert_eq!(
+                mesh_positions[v],
+                positions[ids.iter().position(|id| *id == v).unwrap()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_remesh_voxel_open_mesh() {
+        // A unit cube missing its +X face.
+        let corner =
+            |i: usize| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32) - 0.5;
+        let positions = (0..8).map(corner).collect_vec();
+        let polygons = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+        ];
+        let open = HalfEdgeMesh::build_from_polygons(&positions, &polygons).unwrap();
+
+        let remeshed = remesh_voxel(&open, 0.1).unwrap();
+        let conn = remeshed.read_connectivity();
+        assert!(conn.num_faces() > 0);
+        assert_eq!(
+            conn.iter_halfedges()
+                .filter(|(_, h)| h.face.is_none())
+                .count(),
+            0
+        );
+        drop(conn);
+
+        // The hole is capped by the border of the voxel grid.
+        let (min, max) = bounding_box(&remeshed);
+        assert!((min.x + 0.5).abs() < 0.1);
+        assert!(max.x > 0.5 && max.x < 0.7);
+    }
+
+    #[test]
+    fn test_triangulate_keeps_channels() {
+        let positions = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        let mut quad = HalfEdgeMesh::build_from_polygons(&positions, &[[0, 1, 2, 3]]).unwrap();
+        // Each corner's UV is its position on the XZ plane.
+        let corner_uv = |p: Vec3| Vec3::new(p.x, p.z, 0.0);
+        let mut uvs = Channel::<HalfEdgeId, Vec3>::new();
+        let material = quad.channels.ensure_channel::<FaceId, f32>("material");
+        {
+            let conn = quad.read_connectivity();
+            let positions = quad.read_positions();
+            let (face, _) = conn.iter_faces().next().unwrap();
+            for h in conn.face_edges(face) {
+                let v = conn.at_halfedge(h).vertex().end();
+                uvs[h] = corner_uv(positions[v]);
+            }
+            quad.channels.write_channel(material).unwrap()[face] = 3.0;
+        }
+        set_uvs(&mut quad, UvChannel::Main, uvs);
+
+        for strategy in [
+            TriangulationStrategy::FanFromFirst,
+            TriangulationStrategy::EarClipping,
+        ] {
+            let tris = triangulate(&quad, strategy).unwrap();
+            let conn = tris.read_connectivity();
+            let positions = tris.read_positions();
+            let uvs = tris.read_uvs().unwrap();
+            let material = tris.channels.read_channel(material).unwrap();
+            assert_eq!(conn.num_faces(), 2);
+            for (face, _) in conn.iter_faces() {
+                assert_eq!(material[face], 3.0);
+                for h in conn.face_edges(face) {
+                    let v = conn.at_halfedge(h).vertex().end();
+                    assert_eq!(uvs[h], corner_uv(positions[v]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_extrude_along_curve() {
+        let backbone =
+            primitives::Line::build_straight_line(Vec3::ZERO, Vec3::new(0.0, 0.0, 2.0), 2).unwrap();
+        let cross_section = primitives::Line::build_from_points(vec![Vec3::X, Vec3::Y]).unwrap();
+
+        // The points of the cross-section at the given height along the
+        // backbone, which goes along +Z.
+        let ring = |mesh: &HalfEdgeMesh, z: f32| {
+            let conn = mesh.read_connectivity();
+            let positions = mesh.read_positions();
+            conn.iter_vertices()
+                .map(|(v, _)| positions[v])
+                .filter(|p| (p.z - z).abs() < 1e-4)
+                .collect_vec()
+        };
+        let contains = |points: &[Vec3], p: Vec3| points.iter().any(|q| q.abs_diff_eq(p, 1e-4));
+
+        // The twist rotates the last cross-section around the backbone.
+        let twisted = extrude_along_curve(&backbone, &cross_section, 0, 90.0, "size").unwrap();
+        let (first, last) = (ring(&twisted, 0.0), ring(&twisted, 2.0));
+        assert_eq!((first.len(), last.len()), (2, 2));
+        let rotation = Quat::from_rotation_z(90f32.to_radians());
+        for p in first {
+            assert!(contains(&last, rotation * p + Vec3::new(0.0, 0.0, 2.0)));
+        }
+
+        // The scale channel scales each cross-section.
+        let mut scaled_backbone = backbone.clone();
+        let radius = scaled_backbone
+            .channels
+            .ensure_channel::<VertexId, f32>("radius");
+        {
+            let conn = scaled_backbone.read_connectivity();
+            let mut radius = scaled_backbone.channels.write_channel(radius).unwrap();
+            for (i, (v, _)) in conn.iter_vertices().enumerate() {
+                radius[v] = if i == 2 { 2.0 } else { 1.0 };
+            }
+        }
+        let scaled =
+            extrude_along_curve(&scaled_backbone, &cross_section, 0, 0.0, "radius").unwrap();
+        let (first, last) = (ring(&scaled, 0.0), ring(&scaled, 2.0));
+        for p in first {
+            assert!((p.length() - 1.0).abs() < 1e-4);
+            assert!(contains(&last, p * 2.0 + Vec3::new(0.0, 0.0, 2.0)));
+        }
+    }
+
+    #[test]
+    fn test_symmetrize() {
+        // A box crossing the YZ plane, with more of it on the positive side.
+        let mut mesh = primitives::Box::build(Vec3::new(0.25, 0.0, 0.0), Vec3::ONE).unwrap();
+        // Each face is tagged with its normal's X, and each corner gets the
+        // position of its vertex as UV.
+        let face_normals = generate_flat_normals_channel(&mesh).unwrap();
+        let tag = mesh.channels.ensure_channel::<FaceId, f32>("tag");
+        let mut uvs = Channel::<HalfEdgeId, Vec3>::new();
+        {
+            let conn = mesh.read_connectivity();
+            let positions = mesh.read_positions();
+            let mut tag = mesh.channels.write_channel(tag).unwrap();
+            for (f, _) in conn.iter_faces() {
+                tag[f] = face_normals[f].x;
+                for h in conn.face_edges(f) {
+                    uvs[h] = positions[conn.at_halfedge(h).vertex().end()];
+                }
+            }
+        }
+        set_uvs(&mut mesh, UvChannel::Main, uvs);
+
+        symmetrize(&mut mesh, Vec3::X, true).unwrap();
+
+        // The +X half is kept, and its side faces are cut at the plane. The
+        // seam is welded, so the result is closed.
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        assert_eq!(conn.num_vertices(), 4 + 4 + 4);
+        assert_eq!(conn.num_faces(), 2 * 5);
+        assert!(conn.iter_halfedges().all(|(_, h)| h.face.is_some()));
+        assert_eq!(
+            bounding_box(&mesh),
+            (Vec3::new(-0.75, -0.5, -0.5), Vec3::new(0.75, 0.5, 0.5))
+        );
+
+        // Channels are copied to both sides. New corners on the plane get the
+        // default value.
+        let face_normals = generate_flat_normals_channel(&mesh).unwrap();
+        let tag = mesh
+            .channels
+            .read_channel_by_name::<FaceId, f32>("tag")
+            .unwrap();
+        let uvs = mesh.read_uvs().unwrap();
+        for (f, _) in conn.iter_faces() {
+            assert!((tag[f] - face_normals[f].x.abs()).abs() < 1e-5);
+            for h in conn.face_edges(f) {
+                let p = positions[conn.at_halfedge(h).vertex().end()];
+                if p.x.abs() < 1e-5 {
+                    assert_eq!(uvs[h], Vec3::ZERO);
+                } else {
+                    assert_eq!(uvs[h], Vec3::new(p.x.abs(), p.y, p.z));
+                }
+            }
+        }
+    }
+
+    /// Builds a mesh from `polygons`, and returns it along with the ids of the
+    /// vertices at each of the `positions`.
+    fn build_with_ids(
+        positions: &[Vec3],
+        polygons: &[Vec<usize>],
+    ) -> (HalfEdgeMesh, Vec<VertexId>) {
+        let mesh = HalfEdgeMesh::build_from_polygons(positions, polygons).unwrap();
+        let ids = {
+            let conn = mesh.read_connectivity();
+            let mesh_positions = mesh.read_positions();
+            positions
+                .iter()
+                .map(|p| {
+                    conn.iter_vertices()
+                        .find(|(v, _)| mesh_positions[*v] == *p)
+                        .unwrap()
+                        .0
+                })
+                .collect_vec()
+        };
+        (mesh, ids)
+    }
+
+    /// Counts the faces of `mesh` with 3 and 4 sides, and its boundary
+    /// halfedges.
+    fn count_tris_quads_boundary(mesh: &HalfEdgeMesh) -> (usize, usize, usize) {
+        let conn = mesh.read_connectivity();
+        let sides = conn.iter_faces().map(|(f, _)| conn.face_edges(f).len());
+        let (tris, quads) = sides.fold((0, 0), |(t, q), n| match n {
+            3 => (t + 1, q),
+            4 => (t, q + 1),
+            _ => (t, q),
+        });
+        let boundary = conn
+            .iter_halfedges()
+            .filter(|(_, h)| h.face.is_none())
+            .count();
+        (tris, quads, boundary)
+    }
+
+    #[test]
+    fn test_bridge_closed_chains() {
+        // A square at z = 0 facing down, and an octagon at z = 1 facing up.
+        // Bridging them closes the tube.
+        let square =
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| Vec3::new(x, y, 0.0));
+        let octagon = (0..8).map(|i| {
+            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+            Vec3::new(1.5 * angle.cos(), 1.5 * angle.sin(), 1.0)
+        });
+        let positions = square.into_iter().chain(octagon).collect_vec();
+        let polygons = vec![vec![3, 2, 1, 0], (4..12).collect_vec()];
+
+        for swap in [false, true] {
+            let (mut mesh, ids) = build_with_ids(&positions, &polygons);
+            // Both chains follow their boundary halfedges.
+            let chain_square = ids[0..4].to_vec();
+            let chain_octagon = ids[4..12].iter_cpy().rev().collect_vec();
+            if swap {
+                bridge_chains(&mut mesh, &chain_octagon, &chain_square, true).unwrap();
+            } else {
+                bridge_chains(&mut mesh, &chain_square, &chain_octagon, true).unwrap();
+            }
+
+            // The two caps, one of them a quad, plus 4 quads and 4 triangles.
+            // The result is a closed manifold: V - E + F = 12 - 20 + 10 = 2.
+            let (tris, quads, boundary) = count_tris_quads_boundary(&mesh);
+            assert_eq!((tris, quads, boundary), (4, 4 + 1, 0));
+            let conn = mesh.read_connectivity();
+            assert_eq!(conn.num_faces(), 10);
+            assert_eq!(conn.iter_halfedges().count(), 2 * 20);
+        }
+    }
+
+    #[test]
+    fn test_bridge_open_chains() {
+        // A pentagon whose top side has 2 edges, and a heptagon above it whose
+        // bottom side has 4 edges.
+        let positions = [
+            (0.0, -1.0),
+            (2.0, -1.0),
+            (2.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 1.0),
+            (0.5, 1.0),
+            (1.0, 1.0),
+            (1.5, 1.0),
+            (2.0, 1.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+        ]
+        .map(|(x, y)| Vec3::new(x, y, 0.0));
+        let polygons = vec![(0..5).collect_vec(), (5..12).collect_vec()];
+
+        for swap in [false, true] {
+            let (mut mesh, ids) = build_with_ids(&positions, &polygons);
+            let chain_short = [ids[4], ids[3], ids[2]];
+            let chain_long = [ids[9], ids[8], ids[7], ids[6], ids[5]];
+            if swap {
+                bridge_chains(&mut mesh, &chain_long, &chain_short, false).unwrap();
+            } else {
+                bridge_chains(&mut mesh, &chain_short, &chain_long, false).unwrap();
+            }
+
+            // The two polygons, plus 2 quads and 2 triangles. The result is a
+            // disk, whose boundary is the rest of both polygons plus the two
+            // sides of the bridge.
+            let (tris, quads, boundary) = count_tris_quads_boundary(&mesh);
+            assert_eq!((tris, quads, boundary), (2, 2, 3 + 3 + 2));
+            assert_eq!(mesh.read_connectivity().num_faces(), 6);
+        }
+    }
+
+    /// A unit box centered at `center`, with flat normals and each corner's
+    /// UV set to the position of its vertex.
+    fn box_with_uvs(center: Vec3) -> HalfEdgeMesh {
+        let mut mesh = primitives::Box::build(center, Vec3::ONE).unwrap();
+        let mut uvs = Channel::<HalfEdgeId, Vec3>::new();
+        {
+            let conn = mesh.read_connectivity();
+            let positions = mesh.read_positions();
+            for (h, _) in conn.iter_halfedges() {
+                uvs[h] = positions[conn.at_halfedge(h).vertex().end()];
+            }
+        }
+        set_uvs(&mut mesh, UvChannel::Main, uvs);
+        mesh.gen_config.smooth_normals = true;
+        mesh
+    }
+
+    /// Checks that each corner of `mesh` still has the UV given by
+    /// `box_with_uvs`, once mapped back to the original copy by `untransform`.
+    fn assert_uvs_follow(mesh: &HalfEdgeMesh, untransform: impl Fn(Vec3) -> Vec3) {
+        assert!(mesh.gen_config.smooth_normals);
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        let uvs = mesh.read_uvs().unwrap();
+        for (h, _) in conn.iter_halfedges() {
+            let p = untransform(positions[conn.at_halfedge(h).vertex().end()]);
+            assert!(uvs[h].abs_diff_eq(p, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_array_radial() {
+        let mesh = box_with_uvs(Vec3::new(2.0, 0.0, 0.0));
+
+        // A full turn places the copies every 90 degrees around the Y axis.
+        let result = array_radial(&mesh, 4, Vec3::Y, Vec3::ZERO, 360.0).unwrap();
+        assert_eq!(result.read_connectivity().num_vertices(), 4 * 8);
+        assert_eq!(result.read_connectivity().num_faces(), 4 * 6);
+        let (min, max) = bounding_box(&result);
+        assert!(min.abs_diff_eq(Vec3::new(-2.5, -0.5, -2.5), 1e-5));
+        assert!(max.abs_diff_eq(Vec3::new(2.5, 0.5, 2.5), 1e-5));
+        // Rotates each vertex back to the quadrant of the original box.
+        assert_uvs_follow(&result, |p| {
+            let quarter_turns = (p.z.atan2(p.x) / std::f32::consts::FRAC_PI_2).round();
+            Quat::from_rotation_y(quarter_turns * std::f32::consts::FRAC_PI_2) * p
+        });
+
+        // Otherwise, the last copy is placed at `total_angle`.
+        let result = array_radial(&mesh, 3, Vec3::Y, Vec3::ZERO, 180.0).unwrap();
+        assert_eq!(result.read_connectivity().num_vertices(), 3 * 8);
+        let (min, max) = bounding_box(&result);
+        assert!(min.abs_diff_eq(Vec3::new(-2.5, -0.5, -2.5), 1e-5));
+        assert!(max.abs_diff_eq(Vec3::new(2.5, 0.5, 0.5), 1e-5));
+    }
+
+    #[test]
+    fn test_array_linear() {
+        let mesh = box_with_uvs(Vec3::ZERO);
+
+        let result = array_linear(&mesh, 3, Vec3::new(2.0, 0.0, 0.0), false).unwrap();
+        assert_eq!(result.read_connectivity().num_vertices(), 3 * 8);
+        assert_eq!(result.read_connectivity().num_faces(), 3 * 6);
+        let (min, max) = bounding_box(&result);
+        assert_eq!(min, Vec3::splat(-0.5));
+        assert_eq!(max, Vec3::new(4.5, 0.5, 0.5));
+        // Moves each vertex back to the box it was copied from.
+        assert_uvs_follow(&result, |p| {
+            p - Vec3::new(2.0 * (p.x / 2.0).round(), 0.0, 0.0)
+        });
+
+        // Relative offsets are multiplied by the size of the mesh, so the
+        // copies are placed next to each other.
+        let result = array_linear(&mesh, 4, Vec3::new(0.0, 1.0, 0.0), true).unwrap();
+        let (min, max) = bounding_box(&result);
+        assert_eq!(min, Vec3::splat(-0.5));
+        assert_eq!(max, Vec3::new(0.5, 3.5, 0.5));
+    }
+
+    #[test]
+    fn test_smooth_normals_weighting() {
+        // The corner of a cube at the origin, with its faces pointing away
+        // from the cube. The -X side is split in two faces of different sizes
+        // and corner angles, and is larger than the other two sides.
+        let positions = [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.5, 1.0),
+            Vec3::new(0.0, 2.0, 2.0),
+        ];
+        let polygons = vec![
+            vec![0, 3, 2, 1],
+            vec![0, 1, 4, 5],
+            vec![0, 5, 6],
+            vec![0, 6, 7, 3],
+        ];
+
+        let normal_at_corner = |weighting: NormalWeighting| {
+            let (mut mesh, ids) = build_with_ids(&positions, &polygons);
+            set_smooth_normals(&mut mesh, weighting).unwrap();
+            let normals = mesh.read_vertex_normals().unwrap();
+            normals[ids[0]]
+        };
+
+        // Each face counts once, so the split side counts twice.
+        let uniform = normal_at_corner(NormalWeighting::Uniform);
+        assert!(uniform.abs_diff_eq(Vec3::new(-2.0, -1.0, -1.0).normalize(), 1e-5));
+        // The -X side has an area of 0.25 + 1.5, the other two sides of 1.
+        let area = normal_at_corner(NormalWeighting::Area);
+        assert!(area.abs_diff_eq(Vec3::new(-1.75, -1.0, -1.0).normalize(), 1e-5));
+        // The corner angles of each side add up to 90 degrees, so the result
+        // doesn't depend on how the sides are split.
+        let angle = normal_at_corner(NormalWeighting::Angle);
+        assert!(angle.abs_diff_eq(Vec3::splat(-1.0).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_loop_cut_edges() {
+        // Both halfedges of an edge. Its ring goes around 4 faces of the box,
+        // and each one is split in two.
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let edge = {
+            let conn = mesh.read_connectivity();
+            let (h, _) = conn.iter_halfedges().next().unwrap();
+            [h, conn.at_halfedge(h).twin().end()]
+        };
+        loop_cut_edges(&mut mesh, &edge, 1).unwrap();
+        assert_eq!(mesh.read_connectivity().num_faces(), 6 + 4);
+
+        // Every edge. Each of the 3 rings of the box is cut once, which splits
+        // every face in four.
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let edges = mesh
+            .resolve_halfedge_selection_full(&SelectionExpression::All)
+            .unwrap();
+        loop_cut_edges(&mut mesh, &edges, 1).unwrap();
+        assert_eq!(mesh.read_connectivity().num_faces(), 6 * 4);
+    }
+
+    #[test]
+    fn test_flip_faces_channels() {
+        // Flipping the whole box doesn't split anything
+        let mut mesh = box_with_uvs(Vec3::ZERO);
+        auto_smooth(&mut mesh, 30.0).unwrap();
+        let crease_id = mesh
+            .channels
+            .ensure_channel::<HalfEdgeId, f32>(compact_mesh::CREASE_CHANNEL);
+        let (a, b) = {
+            let conn = mesh.read_connectivity();
+            let h = conn.iter_halfedges().next().unwrap().0;
+            let mut crease = mesh.channels.write_channel(crease_id).unwrap();
+            crease[h] = 1.0;
+            crease[conn.at_halfedge(h).twin().end()] = 1.0;
+            conn.at_halfedge(h).src_dst_pair().unwrap()
+        };
+        let faces = mesh
+            .read_connectivity()
+            .iter_faces()
+            .map(|(f, _)| f)
+            .collect_vec();
+        flip_faces(&mut mesh, &faces).unwrap();
+
+        assert_uvs_follow(&mesh, |p| p);
+        let conn = mesh.read_connectivity();
+        let positions = mesh.read_positions();
+        let normals = mesh
+            .channels
+            .read_channel(mesh.default_channels.halfedge_normals.unwrap())
+            .unwrap();
+        let crease = mesh
+            .channels
+            .read_channel_by_name::<HalfEdgeId, f32>(compact_mesh::CREASE_CHANNEL)
+            .unwrap();
+        for (h, _) in conn.iter_halfedges() {
+            let face = conn.at_halfedge(h).face().end();
+            let normal = conn.face_normal(&positions, face).unwrap();
+            // The box is centered at the origin, so normals now point inwards
+            assert!(normal.dot(positions[conn.at_halfedge(h).vertex().end()]) < 0.0);
+            assert!(normals[h].abs_diff_eq(normal, 1e-5));
+
+            let (src, dst) = conn.at_halfedge(h).src_dst_pair().unwrap();
+            let is_creased = (src, dst) == (a, b) || (src, dst) == (b, a);
+            assert_eq!(crease[h], if is_creased { 1.0 } else { 0.0 });
+        }
+
+        // Flipping a single face splits its vertices, which keep their uvs
+        let mut mesh = box_with_uvs(Vec3::ZERO);
+        let face = mesh.read_connectivity().iter_faces().next().unwrap().0;
+        flip_faces(&mut mesh, &[face]).unwrap();
+        assert_eq!(mesh.read_connectivity().num_vertices(), 12);
+        assert_uvs_follow(&mesh, |p| p);
+    }
+
+    #[test]
+    fn test_spin_steps() {
+        let profile = HalfEdgeMesh::new();
+        add_polyline(&profile, &[Vec3::X, Vec3::new(1.0, 1.0, 0.0)], false).unwrap();
+
+        for steps in [1, 2] {
+            assert!(spin(&profile, Vec3::Y, Vec3::ZERO, 360.0, steps).is_err());
+        }
+        let mesh = spin(&profile, Vec3::Y, Vec3::ZERO, 360.0, 3).unwrap();
+        assert_eq!(mesh.read_connectivity().num_vertices(), 6);
+        assert_eq!(mesh.read_connectivity().num_faces(), 3);
+
+        let mesh = spin(&profile, Vec3::Y, Vec3::ZERO, 90.0, 1).unwrap();
+        assert_eq!(mesh.read_connectivity().num_vertices(), 4);
+        assert_eq!(mesh.read_connectivity().num_faces(), 1);
+    }
 }
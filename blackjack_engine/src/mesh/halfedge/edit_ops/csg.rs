@@ -0,0 +1,561 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Constructive solid geometry on polygon soups, using BSP trees. This is the
+//! backend for the mesh boolean operations in `edit_ops`.
+//!
+//! The algorithm is the classic one popularized by csg.js: Each operand is
+//! turned into a BSP tree, and the trees are used to clip each other's
+//! polygons. Clipping leaves T-junctions behind, so the output of the
+//! boolean is re-stitched by [`stitch_polygons`] before a halfedge mesh is
+//! built from it.
+//!
+//! All the tree operations are iterative, because BSP trees built from
+//! convex meshes degenerate into lists and a recursive implementation would
+//! overflow the stack for moderately large meshes.
+
+use crate::prelude::*;
+
+/// Classification of a point or polygon with respect to a plane. Stored as
+/// bitflags so that a polygon's classification is the bitwise or of its
+/// vertices' classifications.
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub w: f32,
+}
+
+impl Plane {
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.w = -self.w;
+    }
+
+    fn classify(&self, point: Vec3, epsilon: f32) -> u8 {
+        let t = self.normal.dot(point) - self.w;
+        if t < -epsilon {
+            BACK
+        } else if t > epsilon {
+            FRONT
+        } else {
+            COPLANAR
+        }
+    }
+
+    /// Returns the point where the segment `a`-`b` crosses this plane. The
+    /// computation is done in a canonical order of the endpoints so that two
+    /// polygons sharing an edge get a bitwise identical split point.
+    fn intersect_segment(&self, a: Vec3, b: Vec3) -> Vec3 {
+        let (a, b) = if a.to_array() <= b.to_array() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let t = (self.w - self.normal.dot(a)) / self.normal.dot(b - a);
+        a.lerp(b, t.clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CsgPolygon {
+    pub vertices: Vec<Vec3>,
+    pub plane: Plane,
+}
+
+impl CsgPolygon {
+    /// Creates a new polygon. The plane is computed using Newell's method, so
+    /// that non-planar polygons get a reasonable best-fit plane. Returns None
+    /// for degenerate polygons.
+    pub fn new(vertices: Vec<Vec3>) -> Option<Self> {
+        if vertices.len() < 3 {
+            return None;
+        }
+        let mut normal = Vec3::ZERO;
+        let mut centroid = Vec3::ZERO;
+        for (a, b) in vertices.iter().circular_tuple_windows() {
+            normal.x += (a.y - b.y) * (a.z + b.z);
+            normal.y += (a.z - b.z) * (a.x + b.x);
+            normal.z += (a.x - b.x) * (a.y + b.y);
+            centroid += *a;
+        }
+        centroid /= vertices.len() as f32;
+        let normal = normal.try_normalize()?;
+        Some(Self {
+            plane: Plane {
+                normal,
+                w: normal.dot(centroid),
+            },
+            vertices,
+        })
+    }
+
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        self.plane.flip();
+    }
+}
+
+/// The lists where a polygon can end up after being split by a plane.
+#[derive(Default)]
+struct SplitResult {
+    coplanar_front: Vec<CsgPolygon>,
+    coplanar_back: Vec<CsgPolygon>,
+    front: Vec<CsgPolygon>,
+    back: Vec<CsgPolygon>,
+}
+
+fn split_polygon(plane: &Plane, polygon: CsgPolygon, epsilon: f32, out: &mut SplitResult) {
+    let types = polygon
+        .vertices
+        .iter()
+        .map(|v| plane.classify(*v, epsilon))
+        .collect::<SVec<_>>();
+    let polygon_type = types.iter().fold(COPLANAR, |acc, t| acc | t);
+
+    match polygon_type {
+        COPLANAR => {
+            if plane.normal.dot(polygon.plane.normal) > 0.0 {
+                out.coplanar_front.push(polygon)
+            } else {
+                out.coplanar_back.push(polygon)
+            }
+        }
+        FRONT => out.front.push(polygon),
+        BACK => out.back.push(polygon),
+        _ => {
+            let n = polygon.vertices.len();
+            let mut f = Vec::with_capacity(n + 1);
+            let mut b = Vec::with_capacity(n + 1);
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let (ti, tj) = (types[i], types[j]);
+                let (vi, vj) = (polygon.vertices[i], polygon.vertices[j]);
+                if ti != BACK {
+                    f.push(vi);
+                }
+                if ti != FRONT {
+                    b.push(vi);
+                }
+                if ti | tj == SPANNING {
+                    let v = plane.intersect_segment(vi, vj);
+                    f.push(v);
+                    b.push(v);
+                }
+            }
+            // NOTE: Fragments keep the plane of the polygon they were split
+            // from. Recomputing it would introduce numerical drift.
+            if f.len() >= 3 {
+                out.front.push(CsgPolygon {
+                    vertices: f,
+                    plane: polygon.plane,
+                });
+            }
+            if b.len() >= 3 {
+                out.back.push(CsgPolygon {
+                    vertices: b,
+                    plane: polygon.plane,
+                });
+            }
+        }
+    }
+}
+
+struct BspNode {
+    plane: Plane,
+    polygons: Vec<CsgPolygon>,
+    front: Option<usize>,
+    back: Option<usize>,
+}
+
+/// A BSP tree, stored as an arena of nodes. The root, if any, is the node at
+/// index 0.
+pub struct BspTree {
+    nodes: Vec<BspNode>,
+    epsilon: f32,
+}
+
+impl BspTree {
+    pub fn new(polygons: Vec<CsgPolygon>, epsilon: f32) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            epsilon,
+        };
+        tree.build(polygons);
+        tree
+    }
+
+    fn alloc_node(&mut self, plane: Plane) -> usize {
+        self.nodes.push(BspNode {
+            plane,
+            polygons: Vec::new(),
+            front: None,
+            back: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Inserts the given polygons in the tree, creating new nodes as needed.
+    /// Each new node uses the plane of the first polygon that reaches it.
+    pub fn build(&mut self, polygons: Vec<CsgPolygon>) {
+        if polygons.is_empty() {
+            return;
+        }
+        if self.nodes.is_empty() {
+            self.alloc_node(polygons[0].plane);
+        }
+
+        let mut stack = vec![(0, polygons)];
+        while let Some((node, polygons)) = stack.pop() {
+            let plane = self.nodes[node].plane;
+            let mut split = SplitResult::default();
+            for polygon in polygons {
+                split_polygon(&plane, polygon, self.epsilon, &mut split);
+            }
+            let node_polygons = &mut self.nodes[node].polygons;
+            node_polygons.extend(split.coplanar_front);
+            node_polygons.extend(split.coplanar_back);
+
+            if !split.front.is_empty() {
+                let front = match self.nodes[node].front {
+                    Some(front) => front,
+                    None => {
+                        let front = self.alloc_node(split.front[0].plane);
+                        self.nodes[node].front = Some(front);
+                        front
+                    }
+                };
+                stack.push((front, split.front));
+            }
+            if !split.back.is_empty() {
+                let back = match self.nodes[node].back {
+                    Some(back) => back,
+                    None => {
+                        let back = self.alloc_node(split.back[0].plane);
+                        self.nodes[node].back = Some(back);
+                        back
+                    }
+                };
+                stack.push((back, split.back));
+            }
+        }
+    }
+
+    /// Converts solid space into empty space and vice versa.
+    pub fn invert(&mut self) {
+        for node in &mut self.nodes {
+            node.polygons.iter_mut().for_each(|p| p.flip());
+            node.plane.flip();
+            std::mem::swap(&mut node.front, &mut node.back);
+        }
+    }
+
+    /// Removes all the parts of `polygons` that are inside this tree's solid.
+    pub fn clip_polygons(&self, polygons: Vec<CsgPolygon>) -> Vec<CsgPolygon> {
+        if self.nodes.is_empty() {
+            return polygons;
+        }
+
+        let mut result = Vec::new();
+        let mut stack = vec![(0, polygons)];
+        while let Some((node, polygons)) = stack.pop() {
+            let node = &self.nodes[node];
+            let mut split = SplitResult::default();
+            for polygon in polygons {
+                split_polygon(&node.plane, polygon, self.epsilon, &mut split);
+            }
+            let mut front = split.front;
+            front.extend(split.coplanar_front);
+            let mut back = split.back;
+            back.extend(split.coplanar_back);
+
+            match node.front {
+                Some(f) => stack.push((f, front)),
+                None => result.extend(front),
+            }
+            // Polygons falling behind a leaf are inside the solid: Drop them.
+            if let Some(b) = node.back {
+                stack.push((b, back));
+            }
+        }
+        result
+    }
+
+    /// Removes all the polygons of this tree that are inside `other`.
+    pub fn clip_to(&mut self, other: &BspTree) {
+        for node in &mut self.nodes {
+            let polygons = std::mem::take(&mut node.polygons);
+            node.polygons = other.clip_polygons(polygons);
+        }
+    }
+
+    fn into_polygons(self) -> Vec<CsgPolygon> {
+        self.nodes
+            .into_iter()
+            .flat_map(|node| node.polygons.into_iter())
+            .collect()
+    }
+}
+
+/// Returns the polygons of the solid `a ∪ b`.
+///
+/// Coplanar faces pointing in the same direction are only kept from `a`.
+/// Coplanar faces pointing in opposite directions (i.e. the two solids are
+/// touching) are removed from both operands, fusing the solids together.
+pub fn union(a: Vec<CsgPolygon>, b: Vec<CsgPolygon>, epsilon: f32) -> Vec<CsgPolygon> {
+    let mut a = BspTree::new(a, epsilon);
+    let mut b = BspTree::new(b, epsilon);
+    a.clip_to(&b);
+    b.clip_to(&a);
+    b.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.build(b.into_polygons());
+    a.into_polygons()
+}
+
+/// Returns the polygons of the solid `a ∩ b`.
+///
+/// Coplanar faces pointing in the same direction are only kept from `a`.
+/// Coplanar faces pointing in opposite directions enclose no volume, and
+/// are removed.
+pub fn intersection(a: Vec<CsgPolygon>, b: Vec<CsgPolygon>, epsilon: f32) -> Vec<CsgPolygon> {
+    let mut a = BspTree::new(a, epsilon);
+    let mut b = BspTree::new(b, epsilon);
+    a.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.clip_to(&b);
+    b.clip_to(&a);
+    a.build(b.into_polygons());
+    a.invert();
+    a.into_polygons()
+}
+
+/// Returns the polygons of the solid `a - b`.
+///
+/// Where the two solids have coplanar faces pointing in the same direction,
+/// the face is removed from the result, carving the volume of `b` out of
+/// `a`. Coplanar faces pointing in opposite directions are kept from `a`.
+pub fn difference(a: Vec<CsgPolygon>, b: Vec<CsgPolygon>, epsilon: f32) -> Vec<CsgPolygon> {
+    let mut a = BspTree::new(a, epsilon);
+    let mut b = BspTree::new(b, epsilon);
+    a.invert();
+    a.clip_to(&b);
+    b.clip_to(&a);
+    b.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.build(b.into_polygons());
+    a.invert();
+    a.into_polygons()
+}
+
+/// Converts the output of a CSG operation into an indexed polygon list,
+/// suitable for [`HalfEdgeMesh::build_from_polygons`].
+///
+/// The BSP clipping splits polygons independently of their neighbours, so its
+/// output is a soup with duplicated vertices and T-junctions. This function:
+/// - Welds together all the vertices closer than `epsilon`.
+/// - Splits every edge that has a welded vertex lying on it, so that both
+///   sides of an edge reference the exact same vertex sequence.
+/// - Removes the degenerate geometry left behind by the previous steps.
+pub fn stitch_polygons(polygons: &[CsgPolygon], epsilon: f32) -> (Vec<Vec3>, Vec<Vec<u32>>) {
+    // --- Weld vertices ---
+    // Vertices are hashed in a grid with cells of size epsilon, and compared
+    // against the existing vertices of the neighbouring cells.
+    let cell = |p: Vec3| (p / epsilon).floor().as_ivec3();
+    let mut grid = HashMap::<glam::IVec3, SVec<u32>>::new();
+    let mut positions = Vec::<Vec3>::new();
+    let mut indexed = Vec::<Vec<u32>>::with_capacity(polygons.len());
+    for polygon in polygons {
+        let mut indices = Vec::with_capacity(polygon.vertices.len());
+        for &v in &polygon.vertices {
+            let c = cell(v);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbour = c + glam::IVec3::new(dx, dy, dz);
+                        for &idx in grid.get(&neighbour).into_iter().flatten() {
+                            if positions[idx as usize].distance_squared(v) <= epsilon * epsilon {
+                                found = Some(idx);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            let idx = found.unwrap_or_else(|| {
+                let idx = positions.len() as u32;
+                positions.push(v);
+                grid.entry(c).or_default().push(idx);
+                idx
+            });
+            indices.push(idx);
+        }
+        indexed.push(indices);
+    }
+
+    // --- Fix T-junctions ---
+    // Vertices are sorted along the X axis to quickly find the candidates
+    // that may lie on a given edge.
+    let mut sorted = (0..positions.len() as u32).collect_vec();
+    sorted.sort_by(|a, b| {
+        positions[*a as usize]
+            .x
+            .total_cmp(&positions[*b as usize].x)
+    });
+    let mut split_polygons = Vec::with_capacity(indexed.len());
+    for indices in indexed {
+        let mut new_indices = Vec::with_capacity(indices.len());
+        for (&a, &b) in indices.iter().circular_tuple_windows() {
+            new_indices.push(a);
+            if a == b {
+                continue;
+            }
+            let (pa, pb) = (positions[a as usize], positions[b as usize]);
+            let (min_x, max_x) = (pa.x.min(pb.x) - epsilon, pa.x.max(pb.x) + epsilon);
+            let start = sorted.partition_point(|i| positions[*i as usize].x < min_x);
+
+            let ab = pb - pa;
+            let len2 = ab.length_squared();
+            let mut on_edge = SVec::<(f32, u32)>::new();
+            for &c in sorted[start..]
+                .iter()
+                .take_while(|i| positions[**i as usize].x <= max_x)
+            {
+                if c == a || c == b {
+                    continue;
+                }
+                let pc = positions[c as usize];
+                let t = (pc - pa).dot(ab) / len2;
+                if t <= 0.0 || t >= 1.0 {
+                    continue;
+                }
+                if (pa + ab * t).distance_squared(pc) <= epsilon * epsilon {
+                    on_edge.push((t, c));
+                }
+            }
+            on_edge.sort_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+            new_indices.extend(on_edge.iter().map(|(_, c)| *c));
+        }
+        split_polygons.push(new_indices);
+    }
+
+    // --- Cleanup degenerate geometry ---
+    let mut result = Vec::with_capacity(split_polygons.len());
+    for mut indices in split_polygons {
+        indices.dedup();
+        while indices.len() > 1 && indices.first() == indices.last() {
+            indices.pop();
+        }
+        // When welding makes a polygon touch itself, it is split into simple
+        // loops at the repeated vertex.
+        let mut current = Vec::<u32>::with_capacity(indices.len());
+        for idx in indices {
+            if let Some(pos) = current.iter().position(|i| *i == idx) {
+                let subloop = current.split_off(pos + 1);
+                if subloop.len() >= 2 {
+                    let mut subloop = subloop;
+                    subloop.insert(0, idx);
+                    result.push(subloop);
+                }
+            } else {
+                current.push(idx);
+            }
+        }
+        if current.len() >= 3 {
+            result.push(current);
+        }
+    }
+
+    (positions, result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cube(center: Vec3, size: f32) -> Vec<CsgPolygon> {
+        #[rustfmt::skip]
+        let corners = [
+            (-1, -1, -1), (1, -1, -1), (1, 1, -1), (-1, 1, -1),
+            (-1, -1, 1), (1, -1, 1), (1, 1, 1), (-1, 1, 1),
+        ];
+        #[rustfmt::skip]
+        let faces = [
+            [0, 3, 2, 1], [4, 5, 6, 7], [0, 1, 5, 4],
+            [2, 3, 7, 6], [1, 2, 6, 5], [0, 4, 7, 3],
+        ];
+        faces
+            .iter()
+            .map(|face| {
+                let vertices = face
+                    .iter()
+                    .map(|&i| {
+                        let (x, y, z) = corners[i];
+                        center + Vec3::new(x as f32, y as f32, z as f32) * size * 0.5
+                    })
+                    .collect();
+                CsgPolygon::new(vertices).unwrap()
+            })
+            .collect()
+    }
+
+    /// Checks that every oriented edge appears exactly once and has a twin.
+    /// Returns the number of (vertices, faces) of the result.
+    fn assert_closed_manifold(polygons: &[CsgPolygon]) -> (usize, usize) {
+        let (_, polygons) = stitch_polygons(polygons, 1e-5);
+        let mut edges = HashSet::new();
+        for polygon in &polygons {
+            for (a, b) in polygon.iter().circular_tuple_windows() {
+                assert!(edges.insert((*a, *b)), "Duplicate edge");
+            }
+        }
+        for (a, b) in &edges {
+            assert!(edges.contains(&(*b, *a)), "Boundary edge");
+        }
+        let vertices = polygons.iter().flatten().unique().count();
+        // Euler characteristic of a sphere
+        assert_eq!(vertices + polygons.len() - edges.len() / 2, 2);
+        (vertices, polygons.len())
+    }
+
+    #[test]
+    fn test_overlapping_cubes() {
+        let a = cube(Vec3::ZERO, 1.0);
+        let b = cube(Vec3::splat(0.5), 1.0);
+        assert_closed_manifold(&union(a.clone(), b.clone(), 1e-5));
+        assert_closed_manifold(&difference(a.clone(), b.clone(), 1e-5));
+        // The intersection is a smaller cube
+        assert_eq!(assert_closed_manifold(&intersection(a, b, 1e-5)), (8, 6));
+    }
+
+    #[test]
+    fn test_coplanar_cubes() {
+        let a = cube(Vec3::ZERO, 1.0);
+        // Touching cubes get fused together
+        let touching = cube(Vec3::X, 1.0);
+        assert_closed_manifold(&union(a.clone(), touching.clone(), 1e-5));
+        assert!(intersection(a.clone(), touching, 1e-5).is_empty());
+
+        // Identical cubes
+        assert_eq!(
+            assert_closed_manifold(&union(a.clone(), a.clone(), 1e-5)),
+            (8, 6)
+        );
+        assert!(difference(a.clone(), a.clone(), 1e-5).is_empty());
+
+        // A notch carved at the bottom face of the cube
+        let inner = cube(Vec3::new(0.0, -0.25, 0.0), 0.5);
+        assert_closed_manifold(&difference(a, inner, 1e-5));
+    }
+}
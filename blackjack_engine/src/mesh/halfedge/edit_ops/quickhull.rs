@@ -0,0 +1,273 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Three-dimensional convex hulls using the quickhull algorithm. This is the
+//! backend for the `convex_hull` operation in `edit_ops`.
+//!
+//! The hull starts as a tetrahedron spanned by four extreme points. Every
+//! remaining point is assigned to a face it lies in front of. Then, for each
+//! face with points in front of it, the farthest point is added to the hull:
+//! The faces visible from it are removed, and the hole they leave is closed
+//! by connecting its border, the horizon, to the new point.
+
+use crate::prelude::*;
+
+/// A face of the convex hull under construction.
+struct HullFace {
+    vertices: [usize; 3],
+    normal: Vec3,
+    offset: f32,
+    /// Points that lie in front of this face, and are still not part of the
+    /// hull.
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl HullFace {
+    fn new(points: &[Vec3], vertices: [usize; 3]) -> Self {
+        let [a, b, c] = vertices.map(|i| points[i]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(a),
+            outside: vec![],
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) - self.offset
+    }
+}
+
+/// Computes the convex hull of the given `points` using the quickhull
+/// algorithm. Returns the triangles of the hull, as indices into `points`,
+/// with their normals pointing outwards.
+pub fn quickhull(points: &[Vec3]) -> Result<Vec<[usize; 3]>> {
+    if points.len() < 4 {
+        bail!("The convex hull needs at least four points.")
+    }
+
+    let (min, max) = points.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    );
+    let eps = (max - min).length() * 1e-5;
+
+    // --- Build the initial tetrahedron ---
+    let farthest = |dist: &dyn Fn(Vec3) -> f32| {
+        (0..points.len())
+            .map(|i| (i, dist(points[i])))
+            .max_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+            .unwrap()
+    };
+    let degenerate_err = || anyhow!("The convex hull can't be computed for points on a plane.");
+    let i0 = (0..points.len())
+        .min_by(|&a, &b| points[a].x.total_cmp(&points[b].x))
+        .unwrap();
+    let (i1, d1) = farthest(&|p| p.distance(points[i0]));
+    if d1 <= eps {
+        return Err(degenerate_err());
+    }
+    let dir = (points[i1] - points[i0]) / d1;
+    let (i2, d2) = farthest(&|p| {
+        let v = p - points[i0];
+        (v - dir * v.dot(dir)).length()
+    });
+    if d2 <= eps {
+        return Err(degenerate_err());
+    }
+    let normal = (points[i1] - points[i0])
+        .cross(points[i2] - points[i0])
+        .normalize();
+    let (i3, d3) = farthest(&|p| normal.dot(p - points[i0]).abs());
+    if d3 <= eps {
+        return Err(degenerate_err());
+    }
+
+    let mut faces = vec![];
+    let centroid = (points[i0] + points[i1] + points[i2] + points[i3]) / 4.0;
+    for tri in [[i0, i1, i2], [i0, i1, i3], [i0, i2, i3], [i1, i2, i3]] {
+        let mut face = HullFace::new(points, tri);
+        if face.distance(centroid) > 0.0 {
+            face = HullFace::new(points, [tri[0], tri[2], tri[1]]);
+        }
+        faces.push(face);
+    }
+
+    let assign =
+        |faces: &mut Vec<HullFace>, candidates: &[usize], new_faces: std::ops::Range<usize>| {
+            for i in candidates.iter().copied() {
+                if let Some(face) = faces[new_faces.clone()]
+                    .iter_mut()
+                    .find(|f| f.distance(points[i]) > eps)
+                {
+                    face.outside.push(i);
+                }
+            }
+        };
+    let all_points = (0..points.len())
+        .filter(|i| ![i0, i1, i2, i3].contains(i))
+        .collect_vec();
+    assign(&mut faces, &all_points, 0..4);
+
+    // --- Expand the hull ---
+    while let Some(f) = faces.iter().position(|f| f.alive && !f.outside.is_empty()) {
+        let eye = *faces[f]
+            .outside
+            .iter()
+            .max_by(|&&a, &&b| {
+                faces[f]
+                    .distance(points[a])
+                    .total_cmp(&faces[f].distance(points[b]))
+            })
+            .unwrap();
+
+        // Flood fill the faces visible from the eye point.
+        let mut edge_to_face = HashMap::new();
+        for (i, face) in faces.iter().enumerate().filter(|(_, f)| f.alive) {
+            for (a, b) in face.vertices.iter().copied().circular_tuple_windows() {
+                edge_to_face.insert((a, b), i);
+            }
+        }
+        // Numerical issues, e.g. with nearly coplanar points, may leave the
+        // hull with edges that have no face on the other side.
+        let twin_face = |a: usize, b: usize| {
+            edge_to_face.get(&(b, a)).copied().ok_or_else(|| {
+                anyhow!("The convex hull can't be computed: The points are too close to a plane.")
+            })
+        };
+        let mut visible = HashSet::new();
+        let mut stack = vec![f];
+        visible.insert(f);
+        while let Some(f) = stack.pop() {
+            for (a, b) in faces[f].vertices.iter().copied().circular_tuple_windows() {
+                let neighbor = twin_face(a, b)?;
+                if faces[neighbor].distance(points[eye]) > eps && visible.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        // The horizon is formed by the edges between visible and non-visible
+        // faces. Each one is connected to the eye point with a new face.
+        let mut orphans = vec![];
+        let first_new = faces.len();
+        for f in visible.iter().copied().sorted() {
+            let vertices = faces[f].vertices;
+            for (a, b) in vertices.iter().copied().circular_tuple_windows() {
+                if !visible.contains(&twin_face(a, b)?) {
+                    faces.push(HullFace::new(points, [a, b, eye]));
+                }
+            }
+            faces[f].alive = false;
+            orphans.extend(std::mem::take(&mut faces[f].outside));
+        }
+        orphans.retain(|&i| i != eye);
+        let last_new = faces.len();
+        assign(&mut faces, &orphans, first_new..last_new);
+    }
+
+    Ok(faces
+        .iter()
+        .filter(|f| f.alive)
+        .map(|f| f.vertices)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks that the hull is a closed manifold with outwards-facing
+    /// triangles and that no point lies outside of it. Returns the number of
+    /// triangles.
+    fn assert_valid_hull(points: &[Vec3]) -> usize {
+        let triangles = quickhull(points).unwrap();
+        let mut edges = HashSet::new();
+        for tri in &triangles {
+            for (a, b) in tri.iter().circular_tuple_windows() {
+                assert!(edges.insert((*a, *b)), "Duplicate edge");
+            }
+        }
+        for (a, b) in &edges {
+            assert!(edges.contains(&(*b, *a)), "Boundary edge");
+        }
+        let vertices = triangles.iter().flatten().unique().count();
+        // Euler characteristic of a sphere
+        assert_eq!(vertices + triangles.len() - edges.len() / 2, 2);
+
+        for tri in &triangles {
+            let [a, b, c] = tri.map(|i| points[i]);
+            let normal = (b - a).cross(c - a).normalize();
+            for p in points {
+                assert!(normal.dot(*p - a) < 1e-4, "Point outside of the hull");
+            }
+        }
+        triangles.len()
+    }
+
+    #[test]
+    fn test_cube_hull() {
+        let mut points = vec![Vec3::splat(0.5)];
+        for (x, y, z) in (0..2)
+            .cartesian_product(0..2)
+            .cartesian_product(0..2)
+            .map(|((x, y), z)| (x, y, z))
+        {
+            points.push(Vec3::new(x as f32, y as f32, z as f32));
+        }
+        assert_eq!(assert_valid_hull(&points), 12);
+    }
+
+    #[test]
+    fn test_random_points_hull() {
+        // Simple LCG, to get a deterministic point cloud.
+        let mut state = 12345u32;
+        let mut random = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0
+        };
+        let points = (0..1000)
+            .map(|_| Vec3::new(random(), random(), random()))
+            .collect_vec();
+        assert_valid_hull(&points);
+    }
+
+    #[test]
+    fn test_coplanar_points() {
+        let points = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::X + Vec3::Y];
+        assert!(quickhull(&points).is_err());
+
+        // A grid on a tilted plane, with some noise way below the tolerance.
+        let normal = Vec3::new(1.0, 2.0, 3.0).normalize();
+        let (u, v) = normal.any_orthonormal_pair();
+        let points = (0..10)
+            .cartesian_product(0..10)
+            .map(|(i, j)| {
+                let noise = ((i * 7 + j * 13) % 5) as f32 * 1e-9;
+                u * i as f32 + v * j as f32 + normal * noise
+            })
+            .collect_vec();
+        assert!(quickhull(&points).is_err());
+    }
+
+    #[test]
+    fn test_duplicated_points() {
+        // Every corner of the cube appears three times.
+        let corners =
+            (0..8).map(|i| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32));
+        let points = corners.flat_map(|p| [p, p, p]).collect_vec();
+        assert_eq!(assert_valid_hull(&points), 12);
+
+        // Points duplicated inside the hull, and on its faces.
+        let mut points = points;
+        points.extend([Vec3::splat(0.5); 4]);
+        points.extend([Vec3::new(0.5, 0.5, 1.0); 4]);
+        assert_eq!(assert_valid_hull(&points), 12);
+    }
+}
@@ -0,0 +1,510 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Scalar fields sampled on a regular grid, and their conversion to quad
+//! meshes using surface nets. This is the backend for the `remesh_voxel` and
+//! `isosurface` operations in `edit_ops`.
+//!
+//! Surface nets places one vertex inside each grid cell crossed by the
+//! surface, at the average of the points where the surface crosses the cell
+//! edges. Then, every grid edge crossed by the surface generates a quad
+//! connecting the vertices of the four cells around it.
+//!
+//! The plain algorithm produces non-manifold geometry when the surface has
+//! features thinner than a cell, like two sheets touching at a cell corner.
+//! To avoid this, the sign of the field is first fixed so that the inside
+//! region is "well-composed", meaning no cell has inside corners touching
+//! only diagonally. With that guarantee, the resulting mesh is always a
+//! closed manifold.
+
+use crate::prelude::*;
+
+/// The maximum number of points in a grid. This prevents running out of
+/// memory when the grid spacing is too small.
+pub const MAX_GRID_POINTS: usize = 256 * 256 * 256;
+
+/// A scalar field sampled on the points of a regular grid. Negative values
+/// are inside the surface, and the rest are outside.
+pub struct SampledField {
+    pub origin: Vec3,
+    pub spacing: f32,
+    pub dims: [usize; 3],
+    pub values: Vec<f32>,
+}
+
+impl SampledField {
+    /// Creates a new grid with the given number of points along each axis,
+    /// with all the values set to `fill`. Returns an error if the grid would
+    /// exceed [`MAX_GRID_POINTS`].
+    pub fn new(origin: Vec3, spacing: f32, dims: [usize; 3], fill: f32) -> Result<Self> {
+        if dims.iter().any(|d| *d < 2) {
+            bail!("The grid needs at least two points along each axis.")
+        }
+        let num_points = dims
+            .iter()
+            .try_fold(1usize, |acc, d| acc.checked_mul(*d))
+            .filter(|n| *n <= MAX_GRID_POINTS)
+            .ok_or_else(|| {
+                anyhow!(
+                    "The grid resolution is too high. At most {MAX_GRID_POINTS} \
+                     grid points are supported, try with a bigger cell size."
+                )
+            })?;
+        Ok(Self {
+            origin,
+            spacing,
+            dims,
+            values: vec![fill; num_points],
+        })
+    }
+
+    /// Creates a grid covering the box between `min` and `max`, with an
+    /// additional margin of one cell around it.
+    pub fn covering(min: Vec3, max: Vec3, spacing: f32, fill: f32) -> Result<Self> {
+        if spacing.is_nan() || spacing <= 0.0 {
+            bail!("The grid cell size must be positive.")
+        }
+        let origin = min - Vec3::splat(spacing);
+        let cells = ((max - origin) / spacing).ceil() + Vec3::ONE;
+        Self::new(
+            origin,
+            spacing,
+            [
+                cells.x as usize + 1,
+                cells.y as usize + 1,
+                cells.z as usize + 1,
+            ],
+            fill,
+        )
+    }
+
+    /// Samples the field given by `f` on a grid covering the box between
+    /// `min` and `max`, with a margin of one cell around it.
+    pub fn sample(min: Vec3, max: Vec3, spacing: f32, f: impl Fn(Vec3) -> f32) -> Result<Self> {
+        let mut field = Self::covering(min, max, spacing, 0.0)?;
+        let [nx, ny, nz] = field.dims;
+        for (k, j, i) in itertools::iproduct!(0..nz, 0..ny, 0..nx) {
+            let idx = field.index(i, j, k);
+            field.values[idx] = f(field.point(i, j, k));
+        }
+        Ok(field)
+    }
+
+    /// Computes the signed distance field of a closed triangle mesh, sampled
+    /// on a grid with the given `spacing` covering the mesh. Triangles should
+    /// have their normals pointing outwards.
+    ///
+    /// Only distances up to one grid cell are computed, farther points are
+    /// clamped to that value. The sign is computed by casting a ray along the
+    /// X axis for each row of the grid and counting the triangles it crosses
+    /// on each direction, so overlapping or inverted parts of the mesh are
+    /// also considered inside.
+    ///
+    /// Points on the border of the grid are always outside. When the mesh is
+    /// not closed, some rays never leave the mesh, and the inside region is
+    /// closed by the border instead of running into it.
+    pub fn from_triangles(triangles: &[[Vec3; 3]], spacing: f32) -> Result<Self> {
+        let (min, max) = triangles.iter().flatten().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        if triangles.is_empty() || !min.is_finite() || !max.is_finite() {
+            bail!("Cannot compute the distance field of an empty mesh.")
+        }
+        // NOTE: The grid is shifted by a fraction of a cell, so that rays
+        // don't go exactly through the edges of axis-aligned meshes, which
+        // would count some crossings twice.
+        let shift = Vec3::new(0.0173, 0.0291, 0.0347) * spacing;
+        let mut field = Self::covering(min - shift, max, spacing, spacing)?;
+        let [nx, ny, nz] = field.dims;
+
+        let mut winding = vec![0i32; field.values.len()];
+        for tri in triangles {
+            let [a, b, c] = *tri;
+            let (t_min, t_max) = (a.min(b).min(c), a.max(b).max(c));
+            let lo = field.cell_of(t_min);
+            let hi = field.cell_of(t_max);
+
+            // --- Distances ---
+            // Points farther than one cell away are already at the clamped
+            // distance, so only the triangle's bounding box plus a margin of
+            // one cell needs to be visited.
+            for (k, j, i) in itertools::iproduct!(
+                lo[2].saturating_sub(1)..(hi[2] + 3).min(nz),
+                lo[1].saturating_sub(1)..(hi[1] + 3).min(ny),
+                lo[0].saturating_sub(1)..(hi[0] + 3).min(nx)
+            ) {
+                let p = field.point(i, j, k);
+                let dist = closest_point_on_triangle(p, a, b, c).distance(p);
+                let idx = field.index(i, j, k);
+                field.values[idx] = field.values[idx].min(dist);
+            }
+
+            // --- Ray crossings ---
+            // The triangle is projected on the YZ plane. Its signed area is
+            // the X component of its normal.
+            let area = (b.y - a.y) * (c.z - a.z) - (b.z - a.z) * (c.y - a.y);
+            if area == 0.0 {
+                continue;
+            }
+            let edge = |p: Vec3, q: Vec3, y: f32, z: f32| {
+                ((q.y - p.y) * (z - p.z) - (q.z - p.z) * (y - p.y)) / area
+            };
+            for (k, j) in
+                itertools::iproduct!(lo[2]..(hi[2] + 2).min(nz), lo[1]..(hi[1] + 2).min(ny))
+            {
+                let row = field.point(0, j, k);
+                let (wa, wb, wc) = (
+                    edge(b, c, row.y, row.z),
+                    edge(c, a, row.y, row.z),
+                    edge(a, b, row.y, row.z),
+                );
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let x = wa * a.x + wb * b.x + wc * c.x;
+                let i = ((x - field.origin.x) / field.spacing).ceil().max(0.0) as usize;
+                if i < nx {
+                    // Entering the mesh through a triangle facing towards -X
+                    // increases the winding number.
+                    winding[field.index(i, j, k)] += if area < 0.0 { 1 } else { -1 };
+                }
+            }
+        }
+
+        for (k, j) in itertools::iproduct!(0..nz, 0..ny) {
+            let border_row = k == 0 || k == nz - 1 || j == 0 || j == ny - 1;
+            let mut w = 0;
+            for i in 0..nx {
+                let idx = field.index(i, j, k);
+                w += winding[idx];
+                let border = border_row || i == 0 || i == nx - 1;
+                if w != 0 && !border {
+                    field.values[idx] = -field.values[idx];
+                }
+            }
+        }
+
+        Ok(field)
+    }
+
+    pub fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        i + self.dims[0] * (j + self.dims[1] * k)
+    }
+
+    pub fn point(&self, i: usize, j: usize, k: usize) -> Vec3 {
+        self.origin + Vec3::new(i as f32, j as f32, k as f32) * self.spacing
+    }
+
+    /// Returns the grid coordinates of the cell containing `p`, clamped to
+    /// the grid.
+    fn cell_of(&self, p: Vec3) -> [usize; 3] {
+        let c = ((p - self.origin) / self.spacing).floor().max(Vec3::ZERO);
+        [
+            (c.x as usize).min(self.dims[0] - 1),
+            (c.y as usize).min(self.dims[1] - 1),
+            (c.z as usize).min(self.dims[2] - 1),
+        ]
+    }
+
+    fn is_inside(&self, idx: usize) -> bool {
+        self.values[idx] < 0.0
+    }
+
+    /// Returns the grid indices of the eight corners of the cell whose
+    /// minimum corner is at `(i, j, k)`. Bit 0 of the corner number is the
+    /// offset along X, bit 1 along Y and bit 2 along Z.
+    fn cell_corners(&self, i: usize, j: usize, k: usize) -> [usize; 8] {
+        let base = self.index(i, j, k);
+        let (dy, dz) = (self.dims[0], self.dims[0] * self.dims[1]);
+        [
+            base,
+            base + 1,
+            base + dy,
+            base + dy + 1,
+            base + dz,
+            base + dz + 1,
+            base + dz + dy,
+            base + dz + dy + 1,
+        ]
+    }
+
+    /// Moves grid points to the inside region until no cell has inside
+    /// corners touching only diagonally, either across a face or across the
+    /// cell, and the same for outside corners. Returns the number of points
+    /// that were moved.
+    ///
+    /// Points are only ever moved inside, so this terminates. In practice,
+    /// only a few points around thin features need to be fixed.
+    pub fn make_well_composed(&mut self) -> usize {
+        // Cell faces, as corner numbers in cyclic order.
+        const FACES: [[usize; 4]; 6] = [
+            [0, 2, 6, 4],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [2, 3, 7, 6],
+            [0, 1, 3, 2],
+            [4, 5, 7, 6],
+        ];
+        // Moved points get a tiny negative value, so the surface is placed
+        // right at them.
+        let inside_value = -self.spacing * 1e-3;
+
+        let [nx, ny, nz] = self.dims;
+        let mut num_fixed = 0;
+        loop {
+            let mut changed = false;
+            for (k, j, i) in itertools::iproduct!(0..nz - 1, 0..ny - 1, 0..nx - 1) {
+                let corners = self.cell_corners(i, j, k);
+                let inside = corners.map(|c| self.is_inside(c));
+                let num_inside = inside.iter().filter(|x| **x).count();
+
+                let mut fix = None;
+                for [a, b, c, d] in FACES {
+                    if inside[a] == inside[c] && inside[b] == inside[d] && inside[a] != inside[b] {
+                        fix = Some(if inside[a] { b } else { a });
+                        break;
+                    }
+                }
+                if fix.is_none() && (num_inside == 2 || num_inside == 6) {
+                    // Two antipodal corners, different from the rest.
+                    let odd = (0..8).find(|c| inside[*c] == (num_inside == 2)).unwrap();
+                    if inside[odd ^ 7] == inside[odd] {
+                        fix = Some(if num_inside == 2 { odd ^ 1 } else { odd });
+                    }
+                }
+
+                if let Some(corner) = fix {
+                    self.values[corners[corner]] = inside_value;
+                    num_fixed += 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return num_fixed;
+            }
+        }
+    }
+
+    /// Extracts the surface at the zero level of this field using surface
+    /// nets. Returns the vertex positions and the quads of the mesh, with
+    /// their normals pointing towards the positive side of the field.
+    ///
+    /// Only surfaces that don't touch the borders of the grid are closed.
+    pub fn surface_nets(&mut self) -> (Vec<Vec3>, Vec<[u32; 4]>) {
+        // Cell edges, as pairs of corner numbers.
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ];
+
+        self.make_well_composed();
+
+        let [nx, ny, nz] = self.dims;
+        let mut positions = vec![];
+        let mut cell_vertex = vec![u32::MAX; self.values.len()];
+        for (k, j, i) in itertools::iproduct!(0..nz - 1, 0..ny - 1, 0..nx - 1) {
+            let corners = self.cell_corners(i, j, k);
+            let mut sum = Vec3::ZERO;
+            let mut count = 0;
+            for (a, b) in EDGES {
+                let (va, vb) = (self.values[corners[a]], self.values[corners[b]]);
+                if (va < 0.0) != (vb < 0.0) {
+                    let offset = |c: usize| {
+                        Vec3::new((c & 1) as f32, ((c >> 1) & 1) as f32, (c >> 2) as f32)
+                    };
+                    let t = va / (va - vb);
+                    sum += offset(a).lerp(offset(b), t);
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                cell_vertex[corners[0]] = positions.len() as u32;
+                positions.push(self.point(i, j, k) + sum / count as f32 * self.spacing);
+            }
+        }
+
+        let mut quads = vec![];
+        let strides = [1, nx, nx * ny];
+        for (k, j, i) in itertools::iproduct!(1..nz - 1, 1..ny - 1, 1..nx - 1) {
+            let idx = self.index(i, j, k);
+            for axis in 0..3 {
+                if self.is_inside(idx) == self.is_inside(idx + strides[axis]) {
+                    continue;
+                }
+                // The other two axes, in cyclic order so the quad below is
+                // counter-clockwise when seen from the positive `axis`.
+                let (db, dc) = (strides[(axis + 1) % 3], strides[(axis + 2) % 3]);
+                let mut quad = [
+                    cell_vertex[idx - db - dc],
+                    cell_vertex[idx - dc],
+                    cell_vertex[idx],
+                    cell_vertex[idx - db],
+                ];
+                if !self.is_inside(idx) {
+                    quad.reverse();
+                }
+                quads.push(quad);
+            }
+        }
+
+        (positions, quads)
+    }
+}
+
+/// Returns the point of the triangle `abc` closest to `p`. See "Real-Time
+/// Collision Detection", by Christer Ericson, section 5.1.5.
+fn closest_point_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks that the quads form a closed manifold, and returns its Euler
+    /// characteristic.
+    fn euler_characteristic(positions: &[Vec3], quads: &[[u32; 4]]) -> i64 {
+        let mut edges = HashSet::new();
+        for quad in quads {
+            assert!(quad.iter().all(|v| (*v as usize) < positions.len()));
+            for (a, b) in quad.iter().circular_tuple_windows() {
+                assert!(edges.insert((*a, *b)), "Non-manifold edge");
+            }
+        }
+        for (a, b) in &edges {
+            assert!(edges.contains(&(*b, *a)), "Boundary edge");
+        }
+        positions.len() as i64 - (edges.len() / 2) as i64 + quads.len() as i64
+    }
+
+    #[test]
+    fn test_sphere() {
+        let mut field =
+            SampledField::sample(Vec3::splat(-1.0), Vec3::ONE, 0.1, |p| p.length() - 1.0).unwrap();
+        let (positions, quads) = field.surface_nets();
+        assert_eq!(euler_characteristic(&positions, &quads), 2);
+        for p in &positions {
+            assert!((p.length() - 1.0).abs() < 0.05);
+        }
+        // Normals point outwards
+        for quad in &quads {
+            let [a, b, c, _] = quad.map(|v| positions[v as usize]);
+            assert!((b - a).cross(c - a).dot(a) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_thin_features() {
+        // Two spheres touching at a point, and a sheet thinner than a cell.
+        // Both produce non-manifold geometry unless the field is fixed.
+        let mut field = SampledField::sample(
+            Vec3::new(-2.0, -1.0, -1.0),
+            Vec3::new(2.0, 1.0, 1.0),
+            0.13,
+            |p| {
+                let spheres = (p - Vec3::X).length().min((p + Vec3::X).length()) - 1.0;
+                let sheet = p.y.abs().max(p.x.abs() - 1.5).max(p.z.abs() - 0.8) - 0.02;
+                spheres.min(sheet)
+            },
+        )
+        .unwrap();
+        let (positions, quads) = field.surface_nets();
+        euler_characteristic(&positions, &quads);
+    }
+
+    #[test]
+    fn test_triangle_distance_field() {
+        // A unit cube centered at the origin, with its faces split into
+        // triangles facing outwards.
+        let corner =
+            |i: usize| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32) - 0.5;
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let triangles = quads
+            .iter()
+            .flat_map(|[a, b, c, d]| [[*a, *b, *c], [*a, *c, *d]])
+            .map(|tri| tri.map(corner))
+            .collect_vec();
+
+        let field = SampledField::from_triangles(&triangles, 0.1).unwrap();
+        let [nx, ny, nz] = field.dims;
+        for (k, j, i) in itertools::iproduct!(0..nz, 0..ny, 0..nx) {
+            let p = field.point(i, j, k);
+            let q = p.abs() - Vec3::splat(0.5);
+            let sdf = q.max(Vec3::ZERO).length() + q.max_element().min(0.0);
+            let value = field.values[field.index(i, j, k)];
+            assert!(
+                (value - sdf.clamp(-0.1, 0.1)).abs() < 1e-4,
+                "Wrong value at {p}"
+            );
+        }
+
+        // The same cube, with its faces pointing inwards, is still inside.
+        let flipped = triangles.iter().map(|[a, b, c]| [*a, *c, *b]).collect_vec();
+        let flipped_field = SampledField::from_triangles(&flipped, 0.1).unwrap();
+        assert!(field
+            .values
+            .iter()
+            .zip(flipped_field.values.iter())
+            .all(|(a, b)| (*a < 0.0) == (*b < 0.0)));
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::json;
+
+use crate::prelude::*;
+
+/// glTF constants, as defined in the specification.
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Vertex data ready to be written as glTF. Vertices are not shared between
+/// faces, because normals and UVs may be different for each face corner.
+struct GltfBuffers {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    /// The texture coordinates for each UV set in the mesh, along with the
+    /// index of their `TEXCOORD_n` attribute. Indices are dense, so the first
+    /// UV set present in the mesh is always `TEXCOORD_0`.
+    uvs: Vec<(usize, Vec<Vec2>)>,
+    /// Triangle indices, grouped by material index.
+    indices: BTreeMap<u32, Vec<u32>>,
+}
+
+fn generate_buffers(mesh: &HalfEdgeMesh) -> Result<GltfBuffers> {
+    let conn = mesh.read_connectivity();
+    // glTF doesn't allow empty accessors, and the bounds of the positions
+    // would be infinite.
+    if conn.num_faces() == 0 {
+        bail!("Cannot export a mesh without faces to glTF");
+    }
+    let positions_ch = mesh.read_positions();
+    let uv_chs = UvChannel::ALL
+        .iter()
+        .filter_map(|uv_ch| mesh.read_uv_channel(*uv_ch))
+        .enumerate()
+        .collect_vec();
+    let materials_ch = mesh
+        .channels
+        .read_channel_by_name::<FaceId, i32>("material")
+        .ok();
+
+    // Per-corner normals are attached to halfedges, smooth normals to
+    // vertices, and flat normals to faces.
+    let halfedge_normals = mesh.read_halfedge_normals();
+    let (vertex_normals, face_normals) = if halfedge_normals.is_some() {
+        (None, None)
+    } else if mesh.gen_config.smooth_normals {
+        let normals = match mesh.read_vertex_normals() {
+            Some(ch) => Channel::clone(&ch),
+            None => edit_ops::generate_smooth_normals_channel(mesh)?,
+        };
+        (Some(normals), None)
+    } else {
+        let normals = match mesh.read_face_normals() {
+            Some(ch) => Channel::clone(&ch),
+            None => edit_ops::generate_flat_normals_channel(mesh)?,
+        };
+        (None, Some(normals))
+    };
+
+    let mut buffers = GltfBuffers {
+        positions: vec![],
+        normals: vec![],
+        uvs: uv_chs.iter().map(|(idx, _)| (*idx, vec![])).collect(),
+        indices: BTreeMap::new(),
+    };
+
+    for (face, _) in conn.iter_faces_ordered() {
+        let material = materials_ch.as_ref().map(|ch| ch[face]).unwrap_or(0);
+        if material < 0 {
+            bail!("Invalid material index {material} for face {face:?}");
+        }
+
+        let vertices = conn.face_vertices(face);
+        let halfedges = conn.face_edges(face);
+        let first = buffers.positions.len() as u32;
+        for (&v, &h) in vertices.iter().zip(halfedges.iter()) {
+            buffers.positions.push(positions_ch[v]);
+            buffers
+                .normals
+                .push(match (&halfedge_normals, &vertex_normals, &face_normals) {
+                    (Some(normals), _, _) => normals[h],
+                    (_, Some(normals), _) => normals[v],
+                    (_, _, Some(normals)) => normals[face],
+                    _ => unreachable!(),
+                });
+            for ((_, uvs), (_, uvs_ch)) in buffers.uvs.iter_mut().zip(&uv_chs) {
+                // NOTE: glTF puts the UV origin at the top-left corner.
+                let uv = uvs_ch[h];
+                uvs.push(Vec2::new(uv.x, 1.0 - uv.y));
+            }
+        }
+
+        let points = vertices.iter().map(|v| positions_ch[*v]).collect_vec();
+        let triangles = edit_ops::ear_clip_polygon(&points);
+        buffers
+            .indices
+            .entry(material as u32)
+            .or_default()
+            .extend(triangles.iter().flatten().map(|i| first + *i as u32));
+    }
+
+    Ok(buffers)
+}
+
+/// Builds the glTF JSON document and its binary buffer. The `buffer_uri` is
+/// the uri of the buffer for the text format, and `None` for binary files.
+fn build_document(buffers: &GltfBuffers, buffer_uri: Option<&str>) -> (serde_json::Value, Vec<u8>) {
+    let mut bin = Vec::<u8>::new();
+    let mut buffer_views = vec![];
+    let mut accessors = vec![];
+
+    let mut push_view = |bin: &mut Vec<u8>, data: Vec<u8>, target: u32| {
+        let offset = bin.len();
+        bin.extend(&data);
+        // Every view is aligned to 4 bytes, as required by the accessors.
+        bin.resize((bin.len() + 3) / 4 * 4, 0);
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        buffer_views.len() - 1
+    };
+
+    fn floats(values: impl Iterator<Item = f32>) -> Vec<u8> {
+        values.flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    let (min, max) = buffers.positions.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    );
+    let view = push_view(
+        &mut bin,
+        floats(buffers.positions.iter().flat_map(|p| p.to_array())),
+        TARGET_ARRAY_BUFFER,
+    );
+    accessors.push(json!({
+        "bufferView": view,
+        "componentType": COMPONENT_FLOAT,
+        "count": buffers.positions.len(),
+        "type": "VEC3",
+        "min": min.to_array(),
+        "max": max.to_array(),
+    }));
+    let mut attributes = json!({ "POSITION": accessors.len() - 1 });
+
+    let view = push_view(
+        &mut bin,
+        floats(buffers.normals.iter().flat_map(|n| n.to_array())),
+        TARGET_ARRAY_BUFFER,
+    );
+    accessors.push(json!({
+        "bufferView": view,
+        "componentType": COMPONENT_FLOAT,
+        "count": buffers.normals.len(),
+        "type": "VEC3",
+    }));
+    attributes["NORMAL"] = json!(accessors.len() - 1);
+
+    for (idx, uvs) in &buffers.uvs {
+        let view = push_view(
+            &mut bin,
+            floats(uvs.iter().flat_map(|uv| uv.to_array())),
+            TARGET_ARRAY_BUFFER,
+        );
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_FLOAT,
+            "count": uvs.len(),
+            "type": "VEC2",
+        }));
+        attributes[format!("TEXCOORD_{idx}")] = json!(accessors.len() - 1);
+    }
+
+    // One primitive per material, all of them sharing the same attributes.
+    let mut primitives = vec![];
+    for (material, indices) in &buffers.indices {
+        let view = push_view(
+            &mut bin,
+            indices.iter().flat_map(|i| i.to_le_bytes()).collect_vec(),
+            TARGET_ELEMENT_ARRAY_BUFFER,
+        );
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        primitives.push(json!({
+            "attributes": attributes.clone(),
+            "indices": accessors.len() - 1,
+            "material": material,
+        }));
+    }
+
+    let num_materials = buffers.indices.keys().max().map(|m| m + 1).unwrap_or(0);
+    let materials = (0..num_materials)
+        .map(|i| json!({ "name": format!("material_{i}") }))
+        .collect_vec();
+
+    let mut buffer = json!({ "byteLength": bin.len() });
+    if let Some(uri) = buffer_uri {
+        buffer["uri"] = json!(uri);
+    }
+
+    let document = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "Blackjack: https://github.com/setzer22/blackjack",
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": primitives }],
+        "materials": materials,
+        "buffers": [buffer],
+        "bufferViews": buffer_views,
+        "accessors": accessors,
+    });
+
+    (document, bin)
+}
+
+/// Appends a GLB chunk with the given `chunk_type` and `data`, padding it to
+/// a multiple of 4 bytes with `padding`.
+fn write_glb_chunk(out: &mut Vec<u8>, chunk_type: u32, mut data: Vec<u8>, padding: u8) {
+    data.resize((data.len() + 3) / 4 * 4, padding);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(chunk_type.to_le_bytes());
+    out.extend(data);
+}
+
+/// Exports `mesh` as a glTF 2.0 file at `path`. When `binary` is true, a
+/// single `.glb` file is written. Otherwise, `path` contains the JSON document
+/// and the binary buffer is written next to it, with a `.bin` extension.
+///
+/// Faces are triangulated, and faces with different values in the `material`
+/// channel are exported as separate primitives. Normals are exported flat or
+/// smooth, depending on the mesh generation config. The main UVs are exported
+/// as `TEXCOORD_0`, and the lightmap UVs as `TEXCOORD_1`. When the mesh only
+/// has lightmap UVs, these are exported as `TEXCOORD_0` instead.
+///
+/// Returns an error when the mesh has no faces, since there would be nothing
+/// to write.
+pub fn to_gltf(mesh: &HalfEdgeMesh, path: &Path, binary: bool) -> Result<()> {
+    let buffers = generate_buffers(mesh)?;
+
+    if binary {
+        let (document, bin) = build_document(&buffers, None);
+        let mut chunks = vec![];
+        write_glb_chunk(
+            &mut chunks,
+            GLB_CHUNK_JSON,
+            serde_json::to_vec(&document)?,
+            b' ',
+        );
+        write_glb_chunk(&mut chunks, GLB_CHUNK_BIN, bin, 0);
+
+        let mut glb = vec![];
+        glb.extend(GLB_MAGIC.to_le_bytes());
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((12 + chunks.len() as u32).to_le_bytes());
+        glb.extend(chunks);
+        std::fs::write(path, glb)?;
+    } else {
+        let bin_path = path.with_extension("bin");
+        let bin_uri = bin_path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| anyhow!("Invalid glTF path: {}", path.display()))?;
+        let (document, bin) = build_document(&buffers, Some(bin_uri));
+        std::fs::write(path, serde_json::to_string_pretty(&document)?)?;
+        std::fs::write(&bin_path, bin)?;
+    }
+
+    Ok(())
+}
+
+#[blackjack_macros::blackjack_lua_module]
+mod lua_api {
+    use super::*;
+
+    /// Saves this mesh as a glTF 2.0 file at a given `path`. When `binary` is
+    /// true, a single `.glb` file is written. Otherwise, a `.gltf` file is
+    /// written along with a `.bin` file for the vertex data. The path's parent
+    /// folder must exist. Existing files will be overwritten.
+    #[lua(under = "Export")]
+    pub fn gltf(mesh: &HalfEdgeMesh, path: String, binary: bool) -> Result<()> {
+        to_gltf(mesh, Path::new(&path), binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_export_glb() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let selection = selection::SelectionExpression::parse("0").unwrap();
+        edit_ops::set_material(&mut mesh, &selection, 1).unwrap();
+
+        let buffers = generate_buffers(&mesh).unwrap();
+        assert_eq!(buffers.positions.len(), 24);
+        assert_eq!(buffers.indices[&0].len(), 5 * 6);
+        assert_eq!(buffers.indices[&1].len(), 6);
+
+        let path = std::env::temp_dir().join("blackjack_test_export.glb");
+        to_gltf(&mesh, &path, true).unwrap();
+        let glb = std::fs::read(&path).unwrap();
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+    }
+
+    #[test]
+    pub fn test_export_lightmap_uvs() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        edit_ops::set_full_range_uvs(&mut mesh, UvChannel::Lightmap).unwrap();
+
+        let buffers = generate_buffers(&mesh).unwrap();
+        assert_eq!(buffers.uvs.len(), 1);
+        assert_eq!(buffers.uvs[0].1.len(), 24);
+
+        let (document, _) = build_document(&buffers, None);
+        let attributes = &document["meshes"][0]["primitives"][0]["attributes"];
+        assert!(attributes.get("TEXCOORD_0").is_some());
+        assert!(attributes.get("TEXCOORD_1").is_none());
+
+        // With both UV sets, the lightmap UVs go after the main UVs.
+        edit_ops::set_full_range_uvs(&mut mesh, UvChannel::Main).unwrap();
+        let buffers = generate_buffers(&mesh).unwrap();
+        let (document, _) = build_document(&buffers, None);
+        let attributes = &document["meshes"][0]["primitives"][0]["attributes"];
+        assert!(attributes.get("TEXCOORD_0").is_some());
+        assert!(attributes.get("TEXCOORD_1").is_some());
+    }
+
+    #[test]
+    pub fn test_export_empty_mesh() {
+        let path = std::env::temp_dir().join("blackjack_test_export_empty.glb");
+        let mut mesh = HalfEdgeMesh::new();
+        assert!(to_gltf(&mesh, &path, true).is_err());
+
+        // Loose vertices are not enough either.
+        edit_ops::add_vertex(&mut mesh, Vec3::ONE).unwrap();
+        assert!(to_gltf(&mesh, &path, true).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -14,8 +14,12 @@ pub struct VertexIndexBuffers {
     pub positions: Vec<Vec3>,
     /// Vertex normals, one per vertex.
     pub normals: Vec<Vec3>,
+    /// Vertex colors, one per vertex. White when the mesh has no `color`
+    /// channel. When material colors are enabled, tinted with the color of
+    /// the face's material, see [`material_color`].
+    pub colors: Vec<Vec3>,
     /// Indices: 3*N where N is the number of triangles. Indices point to
-    /// elements of `positions` and `normals`.
+    /// elements of `positions`, `normals` and `colors`.
     pub indices: Vec<u32>,
 }
 
@@ -28,6 +32,9 @@ pub struct VertexIndexBuffers {
 pub struct PointBuffers {
     /// Vertex positions
     pub positions: Vec<Vec3>,
+    /// Point sizes, one per vertex. Read from the `size` vertex channel, like
+    /// `copy_to_points` does, or 1.0 when the mesh has no such channel.
+    pub sizes: Vec<f32>,
 }
 
 /// This representation is suitable to draw the halfedge's vertices using
@@ -50,11 +57,37 @@ pub struct FaceOverlayBuffers {
     pub max_id: u32,
 }
 
+/// Returns the color used to tint the faces with the given material index in
+/// the viewport. Faces with the default material, 0, are not tinted.
+pub fn material_color(material: i32) -> Vec3 {
+    const PALETTE: [[f32; 3]; 7] = [
+        [0.95, 0.45, 0.40],
+        [0.45, 0.75, 0.95],
+        [0.55, 0.90, 0.45],
+        [0.95, 0.80, 0.35],
+        [0.75, 0.50, 0.95],
+        [0.40, 0.90, 0.85],
+        [0.95, 0.55, 0.80],
+    ];
+    if material == 0 {
+        Vec3::ONE
+    } else {
+        Vec3::from(PALETTE[(material - 1).rem_euclid(PALETTE.len() as i32) as usize])
+    }
+}
+
 impl HalfEdgeMesh {
     /// Generates the [`TriangleBuffers`] for this mesh. Suitable to be uploaded
     /// to the GPU.
+    ///
+    /// When `material_colors` is set, faces are tinted according to their
+    /// material index.
     #[profiling::function]
-    pub fn generate_triangle_buffers_flat(&self, force_gen: bool) -> Result<VertexIndexBuffers> {
+    pub fn generate_triangle_buffers_flat(
+        &self,
+        force_gen: bool,
+        material_colors: bool,
+    ) -> Result<VertexIndexBuffers> {
         let positions_ch = self.read_positions();
         let conn = self.read_connectivity();
 
@@ -72,13 +105,22 @@ impl HalfEdgeMesh {
             normal_ch = extend_lifetime.as_ref().unwrap();
         }
 
+        let color_ch = self.read_vertex_colors();
+        let color = |v: VertexId| color_ch.as_ref().map(|ch| ch[v]).unwrap_or(Vec3::ONE);
+        let material_ch = self.read_face_materials().filter(|_| material_colors);
+
         let mut positions = vec![];
         let mut normals = vec![];
+        let mut colors = vec![];
 
         for (face_id, _face) in conn.faces.iter() {
             // We try to be a bit forgiving here. We don't want to stop
             // rendering even if we have slightly malformed meshes.
             let normal = normal_ch[face_id];
+            let tint = material_ch
+                .as_ref()
+                .map(|ch| material_color(ch[face_id]))
+                .unwrap_or(Vec3::ONE);
 
             let vertices = conn.face_vertices(face_id);
 
@@ -91,6 +133,9 @@ impl HalfEdgeMesh {
                 normals.push(normal);
                 normals.push(normal);
                 normals.push(normal);
+                colors.push(color(v1) * tint);
+                colors.push(color(v2) * tint);
+                colors.push(color(v3) * tint);
             }
         }
 
@@ -98,13 +143,32 @@ impl HalfEdgeMesh {
             indices: (0u32..positions.len() as u32).collect(),
             positions,
             normals,
+            colors,
         })
     }
 
     /// If `force_gen` is true, ignores any existing vertex normals channel in
     /// the mesh and generates one from scratch instead. This is used in some
     /// viewport modes.
-    pub fn generate_triangle_buffers_smooth(&self, force_gen: bool) -> Result<VertexIndexBuffers> {
+    ///
+    /// When the mesh has per-corner normals, those are used instead, unless
+    /// `force_gen` is set.
+    ///
+    /// When `material_colors` is set, faces are tinted according to their
+    /// material index.
+    pub fn generate_triangle_buffers_smooth(
+        &self,
+        force_gen: bool,
+        material_colors: bool,
+    ) -> Result<VertexIndexBuffers> {
+        if !force_gen {
+            if let Some(normal_ch) = self.read_halfedge_normals() {
+                return Ok(
+                    self.generate_triangle_buffers_per_corner(|_, h| normal_ch[h], material_colors)
+                );
+            }
+        }
+
         let positions_ch = self.read_positions();
         let conn = self.read_connectivity();
 
@@ -123,10 +187,18 @@ impl HalfEdgeMesh {
             normal_ch = extend_lifetime.as_ref().unwrap();
         }
 
+        // Faces with different materials can't share vertices, because each
+        // one has a different tint.
+        if material_colors && self.read_face_materials().is_some() {
+            return Ok(self.generate_triangle_buffers_per_corner(|v, _| normal_ch[v], true));
+        }
+
         let mut v_id_to_idx =
             slotmap::SecondaryMap::<VertexId, u32>::with_capacity(conn.vertices.capacity());
+        let color_ch = self.read_vertex_colors();
         let mut positions = vec![];
         let mut normals = vec![];
+        let mut colors = vec![];
 
         conn.iter_vertices_with_channel(&positions_ch)
             .enumerate()
@@ -134,6 +206,7 @@ impl HalfEdgeMesh {
                 v_id_to_idx.insert(v_id, idx as u32);
                 positions.push(pos);
                 normals.push(normal_ch[v_id]);
+                colors.push(color_ch.as_ref().map(|ch| ch[v_id]).unwrap_or(Vec3::ONE));
                 Ok(())
             })?;
 
@@ -151,10 +224,59 @@ impl HalfEdgeMesh {
         Ok(VertexIndexBuffers {
             positions,
             normals,
+            colors,
             indices,
         })
     }
 
+    /// Generates the triangle buffers using the per-corner normals returned
+    /// by `normal`, given the vertex of each corner and its outgoing halfedge
+    /// inside the face. Vertices are not shared between faces, since each
+    /// face corner may have a different normal or color.
+    fn generate_triangle_buffers_per_corner(
+        &self,
+        normal: impl Fn(VertexId, HalfEdgeId) -> Vec3,
+        material_colors: bool,
+    ) -> VertexIndexBuffers {
+        let positions_ch = self.read_positions();
+        let conn = self.read_connectivity();
+        let color_ch = self.read_vertex_colors();
+        let color = |v: VertexId| color_ch.as_ref().map(|ch| ch[v]).unwrap_or(Vec3::ONE);
+        let material_ch = self.read_face_materials().filter(|_| material_colors);
+
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut colors = vec![];
+        let mut indices = vec![];
+
+        for (face_id, _face) in conn.faces.iter() {
+            let first = positions.len() as u32;
+            let vertices = conn.face_vertices(face_id);
+            let halfedges = conn.face_edges(face_id);
+            let tint = material_ch
+                .as_ref()
+                .map(|ch| material_color(ch[face_id]))
+                .unwrap_or(Vec3::ONE);
+            for (&v, &h) in vertices.iter().zip(halfedges.iter()) {
+                positions.push(positions_ch[v]);
+                normals.push(normal(v, h));
+                colors.push(color(v) * tint);
+            }
+            for (i2, i3) in (first + 1..first + vertices.len() as u32).tuple_windows() {
+                indices.push(first);
+                indices.push(i2);
+                indices.push(i3);
+            }
+        }
+
+        VertexIndexBuffers {
+            positions,
+            normals,
+            colors,
+            indices,
+        }
+    }
+
     pub fn generate_face_overlay_buffers(&self, hover: Option<u32>) -> FaceOverlayBuffers {
         let positions_ch = self.read_positions();
         let conn = self.read_connectivity();
@@ -209,14 +331,17 @@ impl HalfEdgeMesh {
     /// Generates the [`PointBuffers`] for this mesh. Suitable to be uploaded to
     /// the GPU.
     pub fn generate_point_buffers(&self) -> PointBuffers {
+        let size_ch = self.channels.read_channel_by_name::<VertexId, f32>("size");
         let mut positions = Vec::new();
-        for (_, _, pos) in self
+        let mut sizes = Vec::new();
+        for (v, _, pos) in self
             .read_connectivity()
             .iter_vertices_with_channel(&self.read_positions())
         {
-            positions.push(pos)
+            positions.push(pos);
+            sizes.push(size_ch.as_ref().map(|ch| ch[v]).unwrap_or(1.0));
         }
-        PointBuffers { positions }
+        PointBuffers { positions, sizes }
     }
 
     /// Generates the [`LineBuffers`] for this mesh. Suitable to be uploaded to
@@ -230,6 +355,11 @@ impl HalfEdgeMesh {
         let positions_ch = self.read_positions();
         let conn = self.read_connectivity();
 
+        let sharp_ch = self
+            .channels
+            .read_channel_by_name::<HalfEdgeId, bool>(compact_mesh::SHARP_CHANNEL)
+            .ok();
+
         let mut visited = HashSet::new();
         let mut positions = Vec::new();
         let mut colors = Vec::new();
@@ -258,6 +388,9 @@ impl HalfEdgeMesh {
                     dbg_edge.color.b() as f32 / 255.0,
                 );
                 colors.push(color)
+            } else if sharp_ch.as_ref().map_or(false, |ch| ch[h] || ch[tw]) {
+                // Sharp edges are highlighted in cyan
+                colors.push(Vec3::new(0.0, 0.8, 1.0))
             } else {
                 colors.push(Vec3::splat(1.0))
             }
@@ -336,4 +469,31 @@ impl HalfEdgeMesh {
 
         Ok(LineBuffers { colors, positions })
     }
+
+    /// Generates a line for each vertex, starting at the vertex and going
+    /// `length` units along its normal. Normals are read from the vertex
+    /// normals channel, or generated as smooth normals when the mesh has none.
+    pub fn generate_vertex_normal_buffers(&self, length: f32) -> Result<LineBuffers> {
+        let positions_ch = self.read_positions();
+        let conn = self.read_connectivity();
+
+        let generated_normals;
+        let existing_normals_ch = self.read_vertex_normals();
+        let normal_ch = match existing_normals_ch.as_deref() {
+            Some(normal_ch) => normal_ch,
+            None => {
+                generated_normals = edit_ops::generate_smooth_normals_channel(self)?;
+                &generated_normals
+            }
+        };
+
+        let mut positions = vec![];
+        let mut colors = vec![];
+        for (v_id, _v, pos) in conn.iter_vertices_with_channel(&positions_ch) {
+            positions.extend([pos, pos + normal_ch[v_id] * length]);
+            colors.push(Vec3::new(0.3, 0.6, 1.0));
+        }
+
+        Ok(LineBuffers { colors, positions })
+    }
 }
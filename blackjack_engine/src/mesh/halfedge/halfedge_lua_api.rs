@@ -23,6 +23,42 @@ mod lua_api {
         HalfEdgeMesh::default()
     }
 
+    /// The number of each kind of element in a mesh.
+    pub struct MeshElementCounts {
+        vertices: usize,
+        faces: usize,
+        halfedges: usize,
+    }
+
+    impl<'lua> mlua::ToLua<'lua> for MeshElementCounts {
+        fn to_lua(self, lua: &'lua mlua::Lua) -> mlua::Result<mlua::Value<'lua>> {
+            let table = lua.create_table()?;
+            table.set("vertices", self.vertices)?;
+            table.set("faces", self.faces)?;
+            table.set("halfedges", self.halfedges)?;
+            Ok(mlua::Value::Table(table))
+        }
+    }
+
+    /// Returns a table with the number of `vertices`, `faces` and `halfedges`
+    /// in `mesh`. Boundary halfedges are included in the count.
+    #[lua(under = "Blackjack")]
+    fn mesh_counts(mesh: &HalfEdgeMesh) -> MeshElementCounts {
+        let conn = mesh.read_connectivity();
+        MeshElementCounts {
+            vertices: conn.num_vertices(),
+            faces: conn.num_faces(),
+            halfedges: conn.num_halfedges(),
+        }
+    }
+
+    /// Returns the ids of the vertices of the face with `face_id` in `mesh`,
+    /// in order. The length of the list is the number of sides of the face.
+    #[lua(under = "Blackjack")]
+    fn face_vertices(mesh: &HalfEdgeMesh, face_id: FaceId) -> Result<Vec<VertexId>> {
+        mesh.face_vertices(face_id)
+    }
+
     /// The 'vertex' is one of the three mesh elements. Channels attached to
     /// vertices have this key type.
     #[lua(under = "Types")]
@@ -46,10 +82,18 @@ mod lua_api {
     #[lua(under = "Types")]
     const F32: ChannelValueType = ChannelValueType::f32;
 
+    /// The type of integer channels associated to a mesh element.
+    #[lua(under = "Types")]
+    const I32: ChannelValueType = ChannelValueType::i32;
+
     /// The type of boolean channels (groups) associated to a mesh element.
     #[lua(under = "Types")]
     const BOOL: ChannelValueType = ChannelValueType::bool;
 
+    /// The type of string channels associated to a mesh element.
+    #[lua(under = "Types")]
+    const STRING: ChannelValueType = ChannelValueType::String;
+
     #[lua_impl]
     impl HalfEdgeMesh {
         // ==== CORE ====
@@ -271,6 +315,62 @@ mod lua_api {
             })
         }
 
+        /// Returns an iterator over the (id, value) pairs of the mesh channel
+        /// with key type `kty`, value type `vty` and `name`. Unlike
+        /// `HalfEdgeMesh::get_assoc_channel`, values are converted to Lua one
+        /// at a time, so this is a good fit for large channels, e.g.:
+        ///
+        /// ```lua
+        /// for v, pos in mesh:iter_channel(Types.VERTEX_ID, Types.VEC3, "position") do
+        ///     ...
+        /// end
+        /// ```
+        ///
+        /// The channel is read every time the iterator is advanced, so it is
+        /// fine to write to it through a shared channel while iterating.
+        #[lua(hidden)]
+        fn iter_channel<'lua>(
+            &self,
+            lua: &'lua Lua,
+            kty: ChannelKeyType,
+            vty: ChannelValueType,
+            name: String,
+        ) -> Result<Function<'lua>> {
+            use slotmap::Key;
+            let channel = self.channels.channel_rc_dyn(kty, vty, &name)?;
+            let conn = self.read_connectivity();
+            let keys: Vec<u64> = match kty {
+                ChannelKeyType::VertexId => conn
+                    .iter_vertices()
+                    .map(|(v_id, _)| v_id.data().as_ffi())
+                    .collect(),
+                ChannelKeyType::FaceId => conn
+                    .iter_faces()
+                    .map(|(f_id, _)| f_id.data().as_ffi())
+                    .collect(),
+                ChannelKeyType::HalfEdgeId => conn
+                    .iter_halfedges()
+                    .map(|(h_id, _)| h_id.data().as_ffi())
+                    .collect(),
+            };
+            let mut i = 0;
+            Ok(lua.create_function_mut(move |lua, ()| {
+                let pair = if i < keys.len() {
+                    channel
+                        .try_borrow()
+                        .map_err(|err| {
+                            anyhow::anyhow!("Channel '{name}' could not be borrowed: {err}")
+                        })
+                        .map_lua_err()?
+                        .get_pair_lua(lua, keys[i])
+                } else {
+                    (mlua::Value::Nil, mlua::Value::Nil)
+                };
+                i += 1;
+                Ok(pair)
+            })?)
+        }
+
         // ==== REDUCTIONS ====
 
         /// A reduction over an element type of this mesh (vertex, face,
@@ -334,6 +434,7 @@ mod lua_api {
         #[lua(this = "read_connectivity()", map = "x.to_vec()")]
         pub fn face_edges(&self, face_id: FaceId) -> Vec<HalfEdgeId>;
 
+        /// Returns a list of vertices for the given `face_id`.
         #[lua]
         pub fn face_vertices(&self, face_id: FaceId) -> Result<Vec<VertexId>> {
             Ok(self
@@ -7,6 +7,7 @@
 use std::f32::consts::PI;
 
 use super::*;
+use crate::mesh::heightmap::HeightMap;
 
 pub struct Box;
 
@@ -82,16 +83,31 @@ impl Circle {
             .collect_vec()
     }
 
-    pub fn build(center: Vec3, radius: f32, num_vertices: usize) -> Result<HalfEdgeMesh> {
-        let verts = Self::make_verts(center, radius, num_vertices);
+    /// Builds a regular polygon with `num_vertices` sides, lying on the plane
+    /// with the given `normal`. When `filled` is true, the result is a single
+    /// n-gon face. Otherwise, it is a closed polyline, with no faces.
+    pub fn build(
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        num_vertices: usize,
+        filled: bool,
+    ) -> Result<HalfEdgeMesh> {
+        if num_vertices < 3 {
+            bail!("A circle needs at least three vertices.")
+        }
+        let normal = normal
+            .try_normalize()
+            .ok_or_else(|| anyhow!("The circle normal can't be zero."))?;
+        let rotation = Quat::from_rotation_arc(Vec3::Y, normal);
+        let verts = Self::make_verts(Vec3::ZERO, radius, num_vertices)
+            .into_iter()
+            .map(|v| center + rotation * v)
+            .collect_vec();
         let polygon = (0..num_vertices).collect_vec();
 
-        HalfEdgeMesh::build_from_polygons(&verts, &[&polygon])
-    }
-
-    pub fn build_open(center: Vec3, radius: f32, num_vertices: usize) -> Result<HalfEdgeMesh> {
-        let circle = Self::build(center, radius, num_vertices)?;
-        {
+        let circle = HalfEdgeMesh::build_from_polygons(&verts, &[&polygon])?;
+        if !filled {
             let mut conn = circle.write_connectivity();
             let (v, _) = conn.iter_vertices().next().unwrap();
             let halfedge = conn.at_vertex(v).halfedge().end();
@@ -292,6 +308,86 @@ impl Polygon {
     }
 }
 
+/// A chain of edges going through a list of points, with no faces. Like
+/// [`Line`], the result has `normal` and `tangent` channels, so it can be
+/// used as a backbone for curve operations.
+pub struct Polyline;
+impl Polyline {
+    /// Builds a polyline through `points`. When `closed` is true, the last
+    /// point is connected back to the first one, forming a loop.
+    pub fn build(points: &[Vec3], closed: bool) -> Result<HalfEdgeMesh> {
+        if !closed {
+            return Line::build_from_points(points.to_vec());
+        }
+        if points.len() < 3 {
+            bail!("A closed polyline needs at least three points.")
+        }
+
+        let n = points.len();
+        let tangent = |i: usize| {
+            let n1 = (points[i] - points[(i + n - 1) % n]).normalize_or_zero();
+            let n2 = (points[(i + 1) % n] - points[i]).normalize_or_zero();
+            (n1 + n2).normalize_or_zero()
+        };
+
+        let mut mesh = HalfEdgeMesh::new();
+        let tangent_channel_id = mesh.channels.ensure_channel::<VertexId, Vec3>("tangent");
+        let normal_channel_id = mesh.channels.ensure_channel::<VertexId, Vec3>("normal");
+        let mut conn = mesh.write_connectivity();
+        let mut pos = mesh.write_positions();
+        let mut norm = mesh.channels.write_channel(normal_channel_id)?;
+        let mut tang = mesh.channels.write_channel(tangent_channel_id)?;
+
+        let vertices = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let v = conn.alloc_vertex(&mut pos, *p, None);
+                tang[v] = tangent(i);
+                norm[v] = tang[v].any_orthonormal_vector();
+                v
+            })
+            .collect_vec();
+
+        let mut forward_halfedges = vec![];
+        let mut backward_halfedges = vec![];
+        for (v, w) in vertices.iter_cpy().circular_tuple_windows() {
+            let h_v_w = conn.alloc_halfedge(HalfEdge {
+                vertex: Some(v),
+                ..Default::default()
+            });
+            let h_w_v = conn.alloc_halfedge(HalfEdge {
+                vertex: Some(w),
+                ..Default::default()
+            });
+            conn[h_v_w].twin = Some(h_w_v);
+            conn[h_w_v].twin = Some(h_v_w);
+            conn[v].halfedge = Some(h_v_w);
+
+            forward_halfedges.push(h_v_w);
+            backward_halfedges.push(h_w_v);
+        }
+
+        // Two loops, going around the polyline in opposite directions
+        for (h, h2) in forward_halfedges.iter_cpy().circular_tuple_windows() {
+            conn[h].next = Some(h2);
+        }
+        for (h, h2) in backward_halfedges.iter_cpy().rev().circular_tuple_windows() {
+            conn[h].next = Some(h2);
+        }
+
+        drop(conn);
+        drop(pos);
+        drop(norm);
+        drop(tang);
+
+        Ok(mesh)
+    }
+}
+
+/// Cones and truncated cones. When `capped` is true, the top and bottom are
+/// closed with a single n-gon sharing the vertices of the side faces.
+/// Otherwise, they are left as open boundaries.
 pub struct Cone;
 impl Cone {
     pub fn build(
@@ -300,11 +396,19 @@ impl Cone {
         bottom_radius: f32,
         height: f32,
         num_vertices: usize,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
         if top_radius.abs() <= 1e-5 {
-            Self::build_cone(center, bottom_radius, height, num_vertices)
+            Self::build_cone(center, bottom_radius, height, num_vertices, capped)
         } else {
-            Self::build_truncated_cone(center, top_radius, bottom_radius, height, num_vertices)
+            Self::build_truncated_cone(
+                center,
+                top_radius,
+                bottom_radius,
+                height,
+                num_vertices,
+                capped,
+            )
         }
     }
     pub fn build_cone(
@@ -312,6 +416,7 @@ impl Cone {
         bottom_radius: f32,
         height: f32,
         num_vertices: usize,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
         let v_offset = Vec3::new(0.0, height / 2.0, 0.0);
         let mut verts = Circle::make_verts(center - v_offset, bottom_radius, num_vertices);
@@ -321,7 +426,10 @@ impl Cone {
             .map(|v| [v, (v + 1) % num_vertices, num_vertices])
             .collect_vec();
         let bottom_face = (0..num_vertices).rev().collect_vec();
-        let mut faces = vec![bottom_face.as_slice()];
+        let mut faces = vec![];
+        if capped {
+            faces.push(bottom_face.as_slice());
+        }
         faces.extend(side_faces.iter().map(|x| x.as_slice()));
 
         HalfEdgeMesh::build_from_polygons(&verts, &faces)
@@ -332,6 +440,7 @@ impl Cone {
         bottom_radius: f32,
         height: f32,
         num_vertices: usize,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
         let v_offset = Vec3::new(0.0, height / 2.0, 0.0);
         let mut verts = Circle::make_verts(center - v_offset, bottom_radius, num_vertices);
@@ -349,27 +458,107 @@ impl Cone {
             .collect_vec();
         let bottom_face = (0..num_vertices).rev().collect_vec();
         let top_face = (num_vertices..(2 * num_vertices)).collect_vec();
-        let mut faces = vec![bottom_face.as_slice(), top_face.as_slice()];
+        let mut faces = vec![];
+        if capped {
+            faces.push(bottom_face.as_slice());
+            faces.push(top_face.as_slice());
+        }
         faces.extend(side_faces.iter().map(|x| x.as_slice()));
 
         HalfEdgeMesh::build_from_polygons(&verts, &faces)
     }
 }
 
-struct Cylinder;
+/// A cylinder. See [`Cone`] for the meaning of `capped`.
+pub struct Cylinder;
 impl Cylinder {
     pub fn build(
         center: Vec3,
         radius: f32,
         height: f32,
         num_vertices: usize,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
-        Cone::build_truncated_cone(center, radius, radius, height, num_vertices)
+        Cone::build_truncated_cone(center, radius, radius, height, num_vertices, capped)
     }
 }
 
+/// A subdivided plane, made of `rows` by `cols` quads.
 pub struct Grid;
 impl Grid {
+    /// Builds a grid of the given `size`, centered at `center` and facing
+    /// towards `normal`. With a single row and column, the result is the same
+    /// as a [`Quad`] whose right vector is the X axis projected on the plane.
+    pub fn build(
+        center: Vec3,
+        normal: Vec3,
+        size: Vec2,
+        rows: u32,
+        cols: u32,
+    ) -> Result<HalfEdgeMesh> {
+        if rows == 0 || cols == 0 {
+            bail!("A grid needs at least one row and one column.")
+        }
+        let normal = normal
+            .try_normalize()
+            .ok_or_else(|| anyhow!("The grid normal can't be zero."))?;
+        let right = (Vec3::X - normal * normal.dot(Vec3::X))
+            .try_normalize()
+            .unwrap_or(Vec3::Z);
+        let forward = normal.cross(right);
+
+        let hsize = size * 0.5;
+        let mut vertices = vec![];
+        for j in 0..=rows {
+            for i in 0..=cols {
+                let x = hsize.x - size.x * i as f32 / cols as f32;
+                let y = hsize.y - size.y * j as f32 / rows as f32;
+                vertices.push(center + x * right + y * forward);
+            }
+        }
+
+        let idx = |i: u32, j: u32| j * (cols + 1) + i;
+        let mut polygons = vec![];
+        for j in 0..rows {
+            for i in 0..cols {
+                polygons.push([idx(i, j), idx(i + 1, j), idx(i + 1, j + 1), idx(i, j + 1)]);
+            }
+        }
+
+        HalfEdgeMesh::build_from_polygons(&vertices, &polygons)
+    }
+}
+
+/// A grid on the XZ plane, displaced vertically by a heightmap.
+pub struct Terrain;
+impl Terrain {
+    /// Builds a terrain of the given `size`, centered at the origin and made
+    /// of `rows` by `cols` quads. The height of each vertex is the bilinearly
+    /// interpolated value of the `heightmap` at that point, scaled by
+    /// `max_height`, so the grid can be denser than the heightmap.
+    pub fn build(
+        heightmap: &HeightMap,
+        size: Vec2,
+        max_height: f32,
+        rows: u32,
+        cols: u32,
+    ) -> Result<HalfEdgeMesh> {
+        if size.x <= 0.0 || size.y <= 0.0 {
+            bail!("The terrain size must be positive.")
+        }
+        let mesh = Grid::build(Vec3::ZERO, Vec3::Y, size, rows, cols)?;
+        for (_, pos) in mesh.write_positions().iter_mut() {
+            let u = pos.x / size.x + 0.5;
+            let v = pos.z / size.y + 0.5;
+            pos.y = heightmap.sample_bilinear(u, v) * max_height;
+        }
+        Ok(mesh)
+    }
+}
+
+/// A point cloud, with points arranged in a grid.
+pub struct PointGrid;
+impl PointGrid {
     pub fn build(x: u32, y: u32, spacing_x: f32, spacing_y: f32) -> Result<HalfEdgeMesh> {
         let mesh = HalfEdgeMesh::new();
         let mut conn = mesh.write_connectivity();
@@ -451,6 +640,55 @@ impl Catenary {
     }
 }
 
+/// A helix (or screw) curve, rising along the Y axis from the origin. The
+/// result has `normal` and `tangent` channels, with normals pointing towards
+/// the helix axis, so it can be used as a backbone for curve extrusion.
+pub struct Helix;
+impl Helix {
+    /// Builds a helix with `segments` edges, making `turns` full turns of the
+    /// given `radius` while rising to `height`. The first point is at
+    /// `start_angle` degrees around the Y axis. Looking from above, the helix
+    /// turns counter-clockwise, unless `clockwise` is true.
+    pub fn build(
+        turns: f32,
+        segments: u32,
+        height: f32,
+        radius: f32,
+        start_angle: f32,
+        clockwise: bool,
+    ) -> Result<HalfEdgeMesh> {
+        if segments == 0 {
+            bail!("A helix needs at least one segment.")
+        }
+        let direction = if clockwise { -1.0 } else { 1.0 };
+        let start_angle = start_angle.to_radians();
+        // Derivative of the angle with respect to the curve parameter t.
+        let angle_speed = direction * 2.0 * PI * turns;
+        let angle = |i: u32| start_angle + angle_speed * (i as f32 / segments as f32);
+
+        let position = |i: u32| {
+            let theta = angle(i);
+            let t = i as f32 / segments as f32;
+            Vec3::new(radius * theta.cos(), height * t, -radius * theta.sin())
+        };
+        let tangent = |i: u32| {
+            let theta = angle(i);
+            Vec3::new(
+                -radius * theta.sin() * angle_speed,
+                height,
+                -radius * theta.cos() * angle_speed,
+            )
+            .try_normalize()
+            .unwrap_or(Vec3::Y)
+        };
+        let normal = |i: u32| {
+            let theta = angle(i);
+            Vec3::new(-theta.cos(), 0.0, theta.sin())
+        };
+        Line::build_with_normals(&position, &normal, &tangent, segments)
+    }
+}
+
 /// Golden ratio, Phi, `(1 + 5.sqrt())/2`
 const PHI: f32 = 1.618_034;
 /// An Icosahedron, a regular 20-sided convex polyhedra. Useful for approximating spheres.
@@ -521,19 +759,24 @@ mod lua_api {
         Quad::build(center.0, normal.0, right.0, size.0.truncate())
     }
 
-    /// Creates an open circle (polyline) with given `center`, `radius` and
-    /// `num_vertices`.
+    /// Creates a circle with given `center`, `normal`, `radius` and
+    /// `num_vertices`. When `filled` is false, the circle is a closed polyline
+    /// that can be used as a cross-section for curve extrusion. Otherwise,
+    /// it's a single n-gon face.
     #[lua(under = "Primitives")]
-    fn circle(center: LVec3, radius: f32, num_vertices: f32, filled: bool) -> Result<HalfEdgeMesh> {
-        if filled {
-            Circle::build(center.0, radius, num_vertices as usize)
-        } else {
-            Circle::build_open(center.0, radius, num_vertices as usize)
-        }
+    fn circle(
+        center: LVec3,
+        normal: LVec3,
+        radius: f32,
+        num_vertices: f32,
+        filled: bool,
+    ) -> Result<HalfEdgeMesh> {
+        Circle::build(center.0, normal.0, radius, num_vertices as usize, filled)
     }
 
     /// Creates a truncated cone with the given `center`, `bottom_radius`, `top_radius`,
     /// `height`, and `num_vertices` around its radius. A `top_radius` of 0 will make a standard cone.
+    /// When `capped` is false, the top and bottom are left open.
     #[lua(under = "Primitives")]
     fn cone(
         center: LVec3,
@@ -541,6 +784,7 @@ mod lua_api {
         top_radius: f32,
         height: f32,
         num_vertices: f32,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
         Cone::build(
             center.0,
@@ -548,18 +792,21 @@ mod lua_api {
             bottom_radius,
             height,
             num_vertices as usize,
+            capped,
         )
     }
 
     /// Creates a cylinder with the given `center`, `radius`, `height`, and `num_vertices around its radius`.
+    /// When `capped` is false, the top and bottom are left open.
     #[lua(under = "Primitives")]
     fn cylinder(
         center: LVec3,
         radius: f32,
         height: f32,
         num_vertices: f32,
+        capped: bool,
     ) -> Result<HalfEdgeMesh> {
-        Cylinder::build(center.0, radius, height, num_vertices as usize)
+        Cylinder::build(center.0, radius, height, num_vertices as usize, capped)
     }
 
     /// Creates a UV-sphere with given `center` and `radius`. The `rings` and
@@ -584,10 +831,11 @@ mod lua_api {
         Line::build_straight_line(start.0, end.0, segments)
     }
 
-    /// Creates a polyline from a given sequence of `points`.
+    /// Creates a polyline from a given sequence of `points`. When `closed` is
+    /// true, the last point is connected back to the first one.
     #[lua(under = "Primitives")]
-    fn line_from_points(points: Vec<LVec3>) -> Result<HalfEdgeMesh> {
-        Line::build_from_points(LVec3::cast_vector(points))
+    fn line_from_points(points: Vec<LVec3>, closed: bool) -> Result<HalfEdgeMesh> {
+        Polyline::build(&LVec3::cast_vector(points), closed)
     }
 
     /// Creates a catenary curve, the curve followed by a chain or rope hanging between two points,
@@ -598,6 +846,24 @@ mod lua_api {
         Catenary::build(start.0, end.0, sag, segments)
     }
 
+    /// Creates a helix rising along the Y axis, with a number of `segments`,
+    /// `turns`, total `height` and `radius`. The first point is placed at
+    /// `start_angle` degrees around the axis, and the helix turns
+    /// counter-clockwise when looking from above, unless `clockwise` is set.
+    /// The result has `normal` and `tangent` channels, so it can be used
+    /// directly as a backbone to extrude along.
+    #[lua(under = "Primitives")]
+    fn helix(
+        turns: f32,
+        segments: u32,
+        height: f32,
+        radius: f32,
+        start_angle: f32,
+        clockwise: bool,
+    ) -> Result<HalfEdgeMesh> {
+        Helix::build(turns, segments, height, radius, start_angle, clockwise)
+    }
+
     /// Creates a single polygon from a given set of points.
     #[lua(under = "Primitives")]
     fn polygon(points: Vec<LVec3>) -> Result<HalfEdgeMesh> {
@@ -606,8 +872,37 @@ mod lua_api {
 
     ///Creates a point cloud arranged in a grid
     #[lua(under = "Primitives")]
-    fn grid(x: u32, y: u32, spacing_x: f32, spacing_y: f32) -> Result<HalfEdgeMesh> {
-        Grid::build(x, y, spacing_x, spacing_y)
+    fn point_grid(x: u32, y: u32, spacing_x: f32, spacing_y: f32) -> Result<HalfEdgeMesh> {
+        PointGrid::build(x, y, spacing_x, spacing_y)
+    }
+
+    /// Creates a plane with the given `center`, `normal` and `size`,
+    /// subdivided into `rows` by `cols` quads. Calling this with the arguments
+    /// of `point_grid` still works, but is deprecated.
+    #[lua(under = "Primitives")]
+    fn grid(
+        center: LVec3,
+        normal: LVec3,
+        size: LVec3,
+        rows: u32,
+        cols: u32,
+    ) -> Result<HalfEdgeMesh> {
+        Grid::build(center.0, normal.0, size.0.truncate(), rows, cols)
+    }
+
+    /// Creates a terrain of the given `size` from the grayscale image at
+    /// `path`, subdivided into `rows` by `cols` quads. The brightest pixels
+    /// are displaced up to `max_height`.
+    #[lua(under = "Primitives")]
+    fn terrain_from_heightmap(
+        path: String,
+        size: LVec3,
+        max_height: f32,
+        rows: u32,
+        cols: u32,
+    ) -> Result<HalfEdgeMesh> {
+        let heightmap = crate::lua_engine::lua_stdlib::load_heightmap_image(&path)?;
+        Terrain::build(&heightmap, size.0.truncate(), max_height, rows, cols)
     }
 }
 
@@ -616,27 +911,75 @@ mod test {
     use super::*;
     #[test]
     fn test_cone() {
-        let cone = Cone::build(Vec3::ZERO, 0.0, 1.0, 1.0, 8).unwrap();
+        let cone = Cone::build(Vec3::ZERO, 0.0, 1.0, 1.0, 8, true).unwrap();
         assert_eq!(cone.read_connectivity().num_vertices(), 9);
 
-        Cone::build(Vec3::ZERO, 1.0, 2.0, 1.0, 8).unwrap();
-        Cone::build_cone(Vec3::ZERO, 1.0, 1.0, 8).unwrap();
-        Cone::build_truncated_cone(Vec3::ZERO, 1.0, 2.0, 1.0, 8).unwrap();
+        Cone::build(Vec3::ZERO, 1.0, 2.0, 1.0, 8, true).unwrap();
+        Cone::build_cone(Vec3::ZERO, 1.0, 1.0, 8, true).unwrap();
+        Cone::build_truncated_cone(Vec3::ZERO, 1.0, 2.0, 1.0, 8, true).unwrap();
+
+        let open_cone = Cone::build(Vec3::ZERO, 0.0, 1.0, 1.0, 8, false).unwrap();
+        assert_eq!(open_cone.read_connectivity().num_vertices(), 9);
+        assert_eq!(open_cone.read_connectivity().num_faces(), 8);
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = Grid::build(Vec3::ZERO, Vec3::Y, Vec2::new(2.0, 3.0), 3, 4).unwrap();
+        assert_eq!(grid.read_connectivity().num_vertices(), 20);
+        assert_eq!(grid.read_connectivity().num_faces(), 12);
+
+        // A single cell grid matches the quad primitive
+        let grid = Grid::build(Vec3::ONE, Vec3::Y, Vec2::ONE, 1, 1).unwrap();
+        let quad = Quad::build(Vec3::ONE, Vec3::Y, Vec3::X, Vec2::ONE).unwrap();
+        let grid_positions = grid.read_positions().iter().map(|(_, p)| *p).collect_vec();
+        let quad_positions = quad.read_positions().iter().map(|(_, p)| *p).collect_vec();
+        assert_eq!(grid_positions, quad_positions);
+
+        assert!(Grid::build(Vec3::ZERO, Vec3::Y, Vec2::ONE, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_terrain() {
+        // A ramp going from 0 to 1 along the X axis, sampled with a grid that
+        // is denser than the heightmap.
+        let heightmap = HeightMap::from_shape_fn(2, 2, |(x, _)| x as f32);
+        let terrain = Terrain::build(&heightmap, Vec2::new(2.0, 2.0), 3.0, 4, 4).unwrap();
+        assert_eq!(terrain.read_connectivity().num_faces(), 16);
+        for (_, pos) in terrain.read_positions().iter() {
+            assert!((pos.y - (pos.x + 1.0) * 1.5).abs() < 1e-5);
+        }
+
+        assert!(Terrain::build(&heightmap, Vec2::ZERO, 1.0, 1, 1).is_err());
     }
 
     #[test]
     fn test_cylinder() {
-        Cylinder::build(Vec3::ZERO, 1.0, 1.0, 8).unwrap();
+        let cylinder = Cylinder::build(Vec3::ZERO, 1.0, 1.0, 8, true).unwrap();
+        assert_eq!(cylinder.read_connectivity().num_faces(), 10);
+
+        let tube = Cylinder::build(Vec3::ZERO, 1.0, 1.0, 8, false).unwrap();
+        assert_eq!(tube.read_connectivity().num_vertices(), 16);
+        assert_eq!(tube.read_connectivity().num_faces(), 8);
     }
 
     #[test]
     fn test_circle() {
-        Circle::build(Vec3::ZERO, 1.0, 24).unwrap();
-        Circle::build(Vec3::ZERO, 1.0, 3).unwrap();
+        Circle::build(Vec3::ZERO, Vec3::Y, 1.0, 24, true).unwrap();
+        Circle::build(Vec3::ZERO, Vec3::Y, 1.0, 3, true).unwrap();
 
         // Not enough vertices to make a circle
-        assert!(Circle::build(Vec3::ZERO, 1.0, 2).is_err());
-        assert!(Circle::build(Vec3::ZERO, 1.0, 0).is_err());
+        assert!(Circle::build(Vec3::ZERO, Vec3::Y, 1.0, 2, true).is_err());
+        assert!(Circle::build(Vec3::ZERO, Vec3::Y, 1.0, 0, true).is_err());
+
+        // Open circles are closed polylines, with no faces
+        let polyline = Circle::build(Vec3::ZERO, Vec3::X, 1.0, 8, false).unwrap();
+        let conn = polyline.read_connectivity();
+        assert_eq!(conn.num_faces(), 0);
+        assert_eq!(conn.num_halfedges(), 16);
+        for (_, pos) in polyline.read_positions().iter() {
+            assert!(pos.x.abs() < 1e-5);
+        }
     }
 
     #[test]
@@ -651,6 +994,28 @@ mod test {
         assert!(pos.iter().map(|x| x.1).contains(&end));
     }
 
+    #[test]
+    fn test_helix() {
+        let helix = Helix::build(2.0, 16, 1.0, 0.5, 0.0, false).unwrap();
+        assert_eq!(helix.read_connectivity().num_vertices(), 17);
+        let normals = helix
+            .channels
+            .read_channel_by_name::<VertexId, Vec3>("normal")
+            .unwrap();
+        let tangents = helix
+            .channels
+            .read_channel_by_name::<VertexId, Vec3>("tangent")
+            .unwrap();
+        for (v, pos) in helix.read_positions().iter() {
+            assert!((pos.xz().length() - 0.5).abs() < 1e-5);
+            assert!(normals[v].dot(tangents[v]).abs() < 1e-5);
+            // Normals point towards the axis
+            assert!(normals[v].dot(*pos) < 0.0);
+        }
+
+        assert!(Helix::build(1.0, 0, 1.0, 1.0, 0.0, false).is_err());
+    }
+
     #[test]
     fn test_line_from_points() {
         // Too few points can cause problems with normal/tangent calculations
@@ -659,6 +1024,25 @@ mod test {
         Line::build_from_points(vec![Vec3::ZERO, Vec3::Y]).unwrap();
     }
 
+    #[test]
+    fn test_closed_polyline() {
+        let points = [Vec3::ZERO, Vec3::X, Vec3::X + Vec3::Z, Vec3::Z];
+        let polyline = Polyline::build(&points, true).unwrap();
+        let conn = polyline.read_connectivity();
+        assert_eq!(conn.num_vertices(), 4);
+        assert_eq!(conn.num_halfedges(), 8);
+        assert_eq!(conn.num_faces(), 0);
+
+        let bag = conn.iter_halfedges().map(|(h, _)| h).collect_vec();
+        let (chain, is_closed) = super::super::edit_ops::sort_bag_of_edges(&conn, &bag).unwrap();
+        assert_eq!(chain.len(), 4);
+        assert!(is_closed);
+
+        let open = Polyline::build(&points, false).unwrap();
+        assert_eq!(open.read_connectivity().num_halfedges(), 6);
+        assert!(Polyline::build(&points[0..2], true).is_err());
+    }
+
     #[test]
     fn test_icosahedron() {
         Icosahedron::build(Vec3::ZERO, 1.).unwrap();
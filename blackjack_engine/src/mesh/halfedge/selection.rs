@@ -5,24 +5,39 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::prelude::*;
-use std::ops::Range;
+use std::{collections::BTreeMap, ops::Range};
 
 use slotmap::SlotMap;
 
 use std::fmt::Write;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SelectionFragment {
     Group(String),
     Range(Range<u32>),
     Single(u32),
+    /// Faces whose normal is at most `max_angle` degrees away from `normal`.
+    Normal {
+        normal: Vec3,
+        max_angle: f32,
+    },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SelectionExpression {
     All,
     None,
     Explicit(Vec<SelectionFragment>),
+    /// Elements selected by both sub-expressions.
+    And(Box<SelectionExpression>, Box<SelectionExpression>),
+    /// Elements selected by any of the sub-expressions.
+    Or(Box<SelectionExpression>, Box<SelectionExpression>),
+    /// Elements not selected by the sub-expression.
+    Not(Box<SelectionExpression>),
+    /// A reference to a named selection set stored in the graph. Sets must be
+    /// replaced by their contents with [`SelectionExpression::expand_sets`]
+    /// before the selection can be resolved.
+    Set(String),
 }
 
 pub enum SelectionKind {
@@ -41,20 +56,29 @@ impl SelectionExpression {
     /// * // Select all elements
     /// 0..1 // Select a range of elements
     /// 0..5, 7..10, 13, 17, 22 // Select multiple ranges, and some single faces
+    /// @name, group("other name") // Select the elements in a group
+    /// normal(0, 1, 0, 30) // Select faces facing up, with a 30 degree tolerance
+    /// (0..10) and not 5 // Combine selections with `and`, `or` and `not`
+    /// set("my_loop") // Select the elements in a named selection set
     ///  // (empty string), selects nothing
     /// ```
+    ///
+    /// The `not` operator has the highest precedence, followed by `and` and
+    /// then `or`. Parentheses can be used to group sub-expressions, and an
+    /// empty pair of parentheses selects nothing.
     pub fn parse(input: &str) -> Result<SelectionExpression> {
         use nom::character::complete::{alphanumeric1, anychar};
-        use nom::combinator::verify;
+        use nom::combinator::{not, peek, verify};
         use nom::multi::many0_count;
+        use nom::number::complete::float;
         use nom::sequence::pair;
         use nom::{
             branch::alt,
-            bytes::complete::tag,
+            bytes::complete::{tag, take_till},
             character::complete::{char, digit1},
             combinator::{map, opt, recognize},
             multi::{many0, separated_list1},
-            sequence::{preceded, tuple},
+            sequence::{delimited, preceded, tuple},
             IResult, Parser,
         };
 
@@ -94,8 +118,53 @@ impl SelectionExpression {
             .parse(input)
         }
 
+        /// Same as `group_fragment`, but the group name is quoted, so it can
+        /// contain any character other than a double quote.
+        fn group_call_fragment(input: &str) -> IResult<&str, SelectionFragment> {
+            map(
+                tuple((
+                    tag("group("),
+                    whitespace,
+                    delimited(char('"'), take_till(|c| c == '"'), char('"')),
+                    whitespace,
+                    tag(")"),
+                )),
+                |(_, _, name, _, _): (_, _, &str, _, _)| SelectionFragment::Group(name.into()),
+            )
+            .parse(input)
+        }
+
+        fn normal_fragment(input: &str) -> IResult<&str, SelectionFragment> {
+            fn arg(input: &str) -> IResult<&str, f32> {
+                preceded(whitespace, float).parse(input)
+            }
+            fn comma(input: &str) -> IResult<&str, &str> {
+                preceded(whitespace, tag(",")).parse(input)
+            }
+            map(
+                tuple((
+                    tag("normal("),
+                    tuple((arg, comma, arg, comma, arg, comma, arg)),
+                    whitespace,
+                    tag(")"),
+                )),
+                |(_, (x, _, y, _, z, _, max_angle), _, _)| SelectionFragment::Normal {
+                    normal: Vec3::new(x, y, z),
+                    max_angle,
+                },
+            )
+            .parse(input)
+        }
+
         fn selection_fragment(input: &str) -> IResult<&str, SelectionFragment> {
-            alt((group_fragment, range, single)).parse(input)
+            alt((
+                normal_fragment,
+                group_call_fragment,
+                group_fragment,
+                range,
+                single,
+            ))
+            .parse(input)
         }
 
         fn fragments_all(input: &str) -> IResult<&str, SelectionExpression> {
@@ -126,10 +195,74 @@ impl SelectionExpression {
             .parse(input)
         }
 
+        /// Matches the keyword `k`, which can't be directly followed by other
+        /// alphanumeric characters.
+        fn keyword<'a>(k: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, ()> {
+            map(
+                tuple((whitespace, tag(k), not(peek(alphanumeric1)))),
+                |_| (),
+            )
+        }
+
+        fn set_expression(input: &str) -> IResult<&str, SelectionExpression> {
+            map(
+                tuple((
+                    whitespace,
+                    tag("set("),
+                    whitespace,
+                    delimited(char('"'), take_till(|c| c == '"'), char('"')),
+                    whitespace,
+                    tag(")"),
+                )),
+                |(_, _, _, name, _, _): (_, _, _, &str, _, _)| {
+                    SelectionExpression::Set(name.into())
+                },
+            )
+            .parse(input)
+        }
+
+        fn parenthesized(input: &str) -> IResult<&str, SelectionExpression> {
+            map(
+                tuple((whitespace, tag("("), opt(expression), whitespace, tag(")"))),
+                |(_, _, expr, _, _)| expr.unwrap_or(SelectionExpression::None),
+            )
+            .parse(input)
+        }
+
+        fn not_expression(input: &str) -> IResult<&str, SelectionExpression> {
+            alt((
+                map(preceded(keyword("not"), not_expression), |expr| {
+                    SelectionExpression::Not(Box::new(expr))
+                }),
+                parenthesized,
+                set_expression,
+                fragments,
+            ))
+            .parse(input)
+        }
+
+        fn and_expression(input: &str) -> IResult<&str, SelectionExpression> {
+            let (input, first) = not_expression(input)?;
+            let (input, rest) = many0(preceded(keyword("and"), not_expression)).parse(input)?;
+            let expr = rest.into_iter().fold(first, |a, b| {
+                SelectionExpression::And(Box::new(a), Box::new(b))
+            });
+            Ok((input, expr))
+        }
+
+        fn expression(input: &str) -> IResult<&str, SelectionExpression> {
+            let (input, first) = and_expression(input)?;
+            let (input, rest) = many0(preceded(keyword("or"), and_expression)).parse(input)?;
+            let expr = rest.into_iter().fold(first, |a, b| {
+                SelectionExpression::Or(Box::new(a), Box::new(b))
+            });
+            Ok((input, expr))
+        }
+
         if input.trim().is_empty() {
             Ok(SelectionExpression::None)
         } else {
-            fragments(input)
+            expression(input)
                 .map_err(|err| anyhow::anyhow!("Error parsing selection: {}", err))
                 .and_then(|(extra_input, parsed)| {
                     if !extra_input.trim().is_empty() {
@@ -142,7 +275,27 @@ impl SelectionExpression {
     }
 
     pub fn unparse(&self) -> String {
+        // Sub-expressions are wrapped in parentheses, unless they're simple
+        // enough that they don't need them.
+        fn unparse_operand(expr: &SelectionExpression) -> String {
+            match expr {
+                SelectionExpression::All
+                | SelectionExpression::Explicit(_)
+                | SelectionExpression::Not(_)
+                | SelectionExpression::Set(_) => expr.unparse(),
+                _ => format!("({})", expr.unparse()),
+            }
+        }
+
         match self {
+            SelectionExpression::And(a, b) => {
+                format!("{} and {}", unparse_operand(a), unparse_operand(b))
+            }
+            SelectionExpression::Or(a, b) => {
+                format!("{} or {}", unparse_operand(a), unparse_operand(b))
+            }
+            SelectionExpression::Not(a) => format!("not {}", unparse_operand(a)),
+            SelectionExpression::Set(name) => format!("set(\"{name}\")"),
             SelectionExpression::All => "*".into(),
             SelectionExpression::None => "".into(),
             SelectionExpression::Explicit(segments) => {
@@ -155,11 +308,26 @@ impl SelectionExpression {
                         write!(out, ", ").unwrap();
                     }
                     match segment {
-                        SelectionFragment::Group(name) => write!(out, "@{name}").unwrap(),
+                        SelectionFragment::Group(name) => {
+                            // Use the short syntax, unless the name needs quoting
+                            let short = format!("@{name}");
+                            let parsed = SelectionExpression::parse(&short).ok();
+                            if parsed == Some(Self::Explicit(vec![segment.clone()])) {
+                                write!(out, "{short}").unwrap()
+                            } else {
+                                write!(out, "group(\"{name}\")").unwrap()
+                            }
+                        }
                         SelectionFragment::Range(r) => {
                             write!(out, "{}..{}", r.start, r.end).unwrap()
                         }
                         SelectionFragment::Single(i) => write!(out, "{i}").unwrap(),
+                        SelectionFragment::Normal { normal, max_angle } => write!(
+                            out,
+                            "normal({}, {}, {}, {max_angle})",
+                            normal.x, normal.y, normal.z
+                        )
+                        .unwrap(),
                     }
                 }
                 out
@@ -168,6 +336,65 @@ impl SelectionExpression {
     }
 }
 
+impl SelectionExpression {
+    /// Returns a copy of this expression where every reference to a selection
+    /// set is replaced by the parsed contents of that set. The contents of a
+    /// set may reference other sets, but not themselves.
+    pub fn expand_sets(&self, sets: &BTreeMap<String, String>) -> Result<SelectionExpression> {
+        fn expand(
+            expr: &SelectionExpression,
+            sets: &BTreeMap<String, String>,
+            visiting: &mut Vec<String>,
+        ) -> Result<SelectionExpression> {
+            Ok(match expr {
+                SelectionExpression::Set(name) => {
+                    if visiting.contains(name) {
+                        bail!("The selection set '{name}' references itself.")
+                    }
+                    let contents = sets
+                        .get(name)
+                        .ok_or_else(|| anyhow!("No selection set named '{name}'."))?;
+                    let parsed = SelectionExpression::parse(contents)
+                        .map_err(|err| anyhow!("In selection set '{name}': {err}"))?;
+                    visiting.push(name.clone());
+                    let expanded = expand(&parsed, sets, visiting)?;
+                    visiting.pop();
+                    expanded
+                }
+                SelectionExpression::And(a, b) => SelectionExpression::And(
+                    Box::new(expand(a, sets, visiting)?),
+                    Box::new(expand(b, sets, visiting)?),
+                ),
+                SelectionExpression::Or(a, b) => SelectionExpression::Or(
+                    Box::new(expand(a, sets, visiting)?),
+                    Box::new(expand(b, sets, visiting)?),
+                ),
+                SelectionExpression::Not(a) => {
+                    SelectionExpression::Not(Box::new(expand(a, sets, visiting)?))
+                }
+                SelectionExpression::All
+                | SelectionExpression::None
+                | SelectionExpression::Explicit(_) => expr.clone(),
+            })
+        }
+        expand(self, sets, &mut vec![])
+    }
+
+    /// Returns whether this expression references any selection set.
+    pub fn has_sets(&self) -> bool {
+        match self {
+            SelectionExpression::Set(_) => true,
+            SelectionExpression::And(a, b) | SelectionExpression::Or(a, b) => {
+                a.has_sets() || b.has_sets()
+            }
+            SelectionExpression::Not(a) => a.has_sets(),
+            SelectionExpression::All
+            | SelectionExpression::None
+            | SelectionExpression::Explicit(_) => false,
+        }
+    }
+}
+
 pub enum ResolvedSelection<Id: slotmap::Key> {
     All,
     None,
@@ -205,13 +432,54 @@ impl HalfEdgeMesh {
                                     ids.push(id);
                                 }
                             }
+                            SelectionFragment::Normal { normal, max_angle } => {
+                                if K::key_type() != ChannelKeyType::FaceId {
+                                    bail!("Normal selections can only be used on faces.")
+                                }
+                                if normal.length_squared() == 0.0 {
+                                    bail!("The reference direction can't be zero.")
+                                }
+                                let face = FaceId::from(id.data());
+                                let conn = self.read_connectivity();
+                                let positions = self.read_positions();
+                                if face_within_angle(&conn, &positions, face, *normal, *max_angle) {
+                                    ids.push(id);
+                                }
+                            }
                         }
                     }
                 }
                 Ok(ResolvedSelection::Explicit(ids))
             }
+            SelectionExpression::And(_, _)
+            | SelectionExpression::Or(_, _)
+            | SelectionExpression::Not(_) => {
+                let resolve_set = |expr: &SelectionExpression| -> Result<HashSet<K>> {
+                    Ok(match self.resolve_explicit_selection(data, expr)? {
+                        ResolvedSelection::All => data.keys().collect(),
+                        ResolvedSelection::None => HashSet::new(),
+                        ResolvedSelection::Explicit(ids) => ids.into_iter().collect(),
+                    })
+                };
+                let selected = match fragments {
+                    SelectionExpression::And(a, b) => &resolve_set(a)? & &resolve_set(b)?,
+                    SelectionExpression::Or(a, b) => &resolve_set(a)? | &resolve_set(b)?,
+                    SelectionExpression::Not(a) => {
+                        let excluded = resolve_set(a)?;
+                        data.keys().filter(|id| !excluded.contains(id)).collect()
+                    }
+                    _ => unreachable!(),
+                };
+                // Keep the ids in a consistent order
+                Ok(ResolvedSelection::Explicit(
+                    data.keys().filter(|id| selected.contains(id)).collect(),
+                ))
+            }
             SelectionExpression::All => Ok(ResolvedSelection::All),
             SelectionExpression::None => Ok(ResolvedSelection::None),
+            SelectionExpression::Set(name) => {
+                bail!("The selection set '{name}' can only be used in node parameters.")
+            }
         }
     }
 
@@ -288,6 +556,192 @@ impl HalfEdgeMesh {
     }
 }
 
+/// Returns whether the normal of `face` is at most `max_angle` degrees away
+/// from `reference`. Degenerate faces, which have no normal, never match.
+fn face_within_angle(
+    conn: &MeshConnectivity,
+    positions: &Positions,
+    face: FaceId,
+    reference: Vec3,
+    max_angle: f32,
+) -> bool {
+    match conn.face_normal(positions, face) {
+        Some(normal) if normal.is_finite() => {
+            normal.angle_between(reference) <= max_angle.to_radians()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the faces of `mesh` whose normal is at most `max_angle` degrees
+/// away from the `reference` direction. Degenerate faces are skipped.
+pub fn select_by_normal(
+    mesh: &HalfEdgeMesh,
+    reference: Vec3,
+    max_angle: f32,
+) -> Result<Vec<FaceId>> {
+    if reference.length_squared() == 0.0 {
+        bail!("The reference direction can't be zero.")
+    }
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    Ok(conn
+        .iter_faces()
+        .map(|(f, _)| f)
+        .filter(|f| face_within_angle(&conn, &positions, *f, reference, max_angle))
+        .collect())
+}
+
+/// Returns a selection expression that matches exactly the elements of
+/// `data` in `ids`. Consecutive elements are merged into ranges.
+fn selection_from_ids<K: ChannelKey, V>(data: &SlotMap<K, V>, ids: &[K]) -> SelectionExpression {
+    let ids = ids.iter().copied().collect::<HashSet<_>>();
+    let mut fragments = vec![];
+    let mut indices = data
+        .iter()
+        .enumerate()
+        .filter(|(_, (id, _))| ids.contains(id))
+        .map(|(i, _)| i as u32)
+        .peekable();
+    while let Some(start) = indices.next() {
+        let mut end = start + 1;
+        while indices.peek() == Some(&end) {
+            indices.next();
+            end += 1;
+        }
+        if end == start + 1 {
+            fragments.push(SelectionFragment::Single(start));
+        } else {
+            fragments.push(SelectionFragment::Range(start..end));
+        }
+    }
+    if fragments.is_empty() {
+        SelectionExpression::None
+    } else {
+        SelectionExpression::Explicit(fragments)
+    }
+}
+
+/// Grows `selected` by `steps`, where each step adds all the elements in the
+/// `neighbors` of the current selection. Negative steps shrink the selection
+/// instead, removing the elements that have any neighbor outside of it.
+/// Returns the elements in the same order as they are stored in `data`.
+fn grow_selection<K: ChannelKey, V>(
+    data: &SlotMap<K, V>,
+    selected: Vec<K>,
+    steps: i32,
+    neighbors: impl Fn(K) -> Result<SVec<K>>,
+) -> Result<Vec<K>> {
+    let mut selected = selected.into_iter().collect::<HashSet<_>>();
+    for _ in 0..steps.abs() {
+        let mut next = HashSet::new();
+        if steps > 0 {
+            next.extend(selected.iter().copied());
+            for id in selected.iter().copied() {
+                next.extend(neighbors(id)?);
+            }
+        } else {
+            for id in selected.iter().copied() {
+                if neighbors(id)?.iter().all(|n| selected.contains(n)) {
+                    next.insert(id);
+                }
+            }
+        }
+        selected = next;
+    }
+    Ok(data
+        .iter()
+        .map(|(id, _)| id)
+        .filter(|id| selected.contains(id))
+        .collect())
+}
+
+/// Grows the vertex selection `sel` by `steps`. Each step adds the vertices
+/// connected by an edge to the selection. Negative steps shrink it instead.
+pub fn grow_vertex_selection(
+    mesh: &HalfEdgeMesh,
+    sel: &SelectionExpression,
+    steps: i32,
+) -> Result<Vec<VertexId>> {
+    let selected = mesh.resolve_vertex_selection_full(sel)?;
+    let conn = mesh.read_connectivity();
+    grow_selection(&conn.vertices, selected, steps, |v| {
+        let mut vertices = SVec::new();
+        for h in conn.at_vertex(v).outgoing_halfedges()? {
+            vertices.push(conn.at_halfedge(h).dst_vertex().try_end()?);
+        }
+        Ok(vertices)
+    })
+}
+
+/// Grows the face selection `sel` by `steps`. Each step adds the faces that
+/// share at least one vertex with the selection. Negative steps shrink it
+/// instead.
+pub fn grow_face_selection(
+    mesh: &HalfEdgeMesh,
+    sel: &SelectionExpression,
+    steps: i32,
+) -> Result<Vec<FaceId>> {
+    let selected = mesh.resolve_face_selection_full(sel)?;
+    let conn = mesh.read_connectivity();
+    grow_selection(&conn.faces, selected, steps, |f| {
+        let mut faces = SVec::new();
+        for v in conn.at_face(f).vertices()? {
+            faces.extend(conn.at_vertex(v).adjacent_faces()?);
+        }
+        Ok(faces)
+    })
+}
+
+/// Grows the edge selection `sel` by `steps`. Each step adds the edges that
+/// share at least one vertex with the selection. Both halfedges of every
+/// added edge are selected. Negative steps shrink the selection instead.
+pub fn grow_halfedge_selection(
+    mesh: &HalfEdgeMesh,
+    sel: &SelectionExpression,
+    steps: i32,
+) -> Result<Vec<HalfEdgeId>> {
+    let selected = mesh.resolve_halfedge_selection_full(sel)?;
+    let conn = mesh.read_connectivity();
+    grow_selection(&conn.halfedges, selected, steps, |h| {
+        let (src, dst) = conn.at_halfedge(h).src_dst_pair()?;
+        let mut halfedges = SVec::new();
+        for v in [src, dst] {
+            halfedges.extend(conn.at_vertex(v).outgoing_halfedges()?);
+            halfedges.extend(conn.at_vertex(v).incoming_halfedges()?);
+        }
+        Ok(halfedges)
+    })
+}
+
+/// Returns all the vertices connected to any of the `seeds` through a path
+/// of edges, in the same order they are stored in the mesh.
+fn linked_vertices(mesh: &HalfEdgeMesh, seeds: &[VertexId]) -> Result<Vec<VertexId>> {
+    let conn = mesh.read_connectivity();
+    let mut visited = seeds.iter().copied().collect::<HashSet<_>>();
+    let mut stack = seeds.to_vec();
+    while let Some(v) = stack.pop() {
+        for h in conn.at_vertex(v).outgoing_halfedges()? {
+            let w = conn.at_halfedge(h).dst_vertex().try_end()?;
+            if visited.insert(w) {
+                stack.push(w);
+            }
+        }
+    }
+    Ok(conn
+        .iter_vertices()
+        .map(|(v, _)| v)
+        .filter(|v| visited.contains(v))
+        .collect())
+}
+
+/// Returns the vertices in the connected shell that contains `seed`. The
+/// shell is found by flood-filling across the edges of the mesh, so separate
+/// shells are never crossed, even when they touch.
+pub fn select_linked(mesh: &HalfEdgeMesh, seed: VertexId) -> Result<Vec<VertexId>> {
+    linked_vertices(mesh, &[seed])
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -326,6 +780,99 @@ mod test {
             expl(&[Range(1..5), Range(7..10), Range(15..16), Single(18), Single(22), Single(27)]));
         assert_eq!(SelectionExpression::parse("@test, 4, 3..5, @another").unwrap(), 
             expl(&[Group("test".into()), Single(4), Range(3..5), Group("another".into())]));
+        assert_eq!(SelectionExpression::parse("normal(0, 1, 0, 30)").unwrap(), 
+            expl(&[Normal { normal: Vec3::Y, max_angle: 30.0 }]));
+        assert_eq!(SelectionExpression::parse("normal(-1,0.5,0,45.5), 3").unwrap(), 
+            expl(&[Normal { normal: Vec3::new(-1.0, 0.5, 0.0), max_angle: 45.5 }, Single(3)]));
+        assert_eq!(SelectionExpression::parse("group(\"test\"), group( \"Big Group\" )").unwrap(), 
+            expl(&[Group("test".into()), Group("Big Group".into())]));
+        assert_eq!(expl(&[Group("test".into()), Group("Big Group".into())]).unparse(),
+            "@test, group(\"Big Group\")");
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_operators() {
+        use super::SelectionExpression::*;
+        use super::SelectionFragment::*;
+        fn expl(v: &[SelectionFragment]) -> Box<SelectionExpression> {
+            Box::new(Explicit(v.to_vec()))
+        }
+
+        assert_eq!(SelectionExpression::parse("(1..10) and not 5").unwrap(),
+            And(expl(&[Range(1..10)]), Box::new(Not(expl(&[Single(5)])))));
+        assert_eq!(SelectionExpression::parse("1, 2 or 3 and 4").unwrap(),
+            Or(expl(&[Single(1), Single(2)]), Box::new(And(expl(&[Single(3)]), expl(&[Single(4)])))));
+        assert_eq!(SelectionExpression::parse("()").unwrap(), SelectionExpression::None);
+
+        for input in ["(1 or 2) and (3 or @test)", "* and not normal(0, 1, 0, 30)", "not ()"] {
+            let parsed = SelectionExpression::parse(input).unwrap();
+            assert_eq!(SelectionExpression::parse(&parsed.unparse()).unwrap(), parsed);
+        }
+
+        assert!(SelectionExpression::parse("1 and").is_err());
+        assert!(SelectionExpression::parse("(1").is_err());
+        assert!(SelectionExpression::parse("1 andnot 2").is_err());
+    }
+
+    #[test]
+    fn test_resolve_operators() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let resolve = |s: &str| {
+            mesh.resolve_face_selection_full(&SelectionExpression::parse(s).unwrap())
+                .unwrap()
+                .len()
+        };
+        assert_eq!(resolve("0..4 and not 1"), 3);
+        assert_eq!(resolve("0 or 5 or 0"), 2);
+        assert_eq!(resolve("not 0..2"), 4);
+        assert_eq!(resolve("* and ()"), 0);
+    }
+
+    #[test]
+    fn test_selection_sets() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let sel = |s: &str| SelectionExpression::parse(s).unwrap();
+        let sets: BTreeMap<String, String> = [
+            ("top", "0, 1"),
+            ("my loop", "set(\"top\") or 4"),
+            ("cycle", "set(\"cycle\")"),
+            ("broken", "1 and"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(
+            sel("set( \"my loop\" ) and not 1"),
+            SelectionExpression::And(
+                std::boxed::Box::new(SelectionExpression::Set("my loop".into())),
+                std::boxed::Box::new(SelectionExpression::Not(std::boxed::Box::new(sel("1")))),
+            )
+        );
+        let parsed = sel("set(\"top\") or 3");
+        assert_eq!(sel(&parsed.unparse()), parsed);
+
+        let expanded = sel("set(\"my loop\") and not 1")
+            .expand_sets(&sets)
+            .unwrap();
+        assert_eq!(expanded, sel("((0, 1) or 4) and not 1"));
+        assert_eq!(
+            mesh.resolve_face_selection_full(&expanded).unwrap().len(),
+            2
+        );
+
+        assert!(sel("1 or not set(\"top\")").has_sets());
+        assert!(!expanded.has_sets());
+        assert!(sel("set(\"missing\")").expand_sets(&sets).is_err());
+        assert!(sel("set(\"cycle\")").expand_sets(&sets).is_err());
+        assert!(sel("set(\"broken\")").expand_sets(&sets).is_err());
+        // Sets can't be resolved before they're expanded
+        assert!(mesh
+            .resolve_face_selection_full(&sel("set(\"top\")"))
+            .is_err());
     }
 
     #[test]
@@ -337,6 +884,79 @@ mod test {
         assert!(SelectionExpression::parse("1,2,3,a").is_err());
         assert!(SelectionExpression::parse("potato").is_err());
         assert!(SelectionExpression::parse("@1").is_err());
+        assert!(SelectionExpression::parse("normal(0, 1, 0)").is_err());
+        assert!(SelectionExpression::parse("group(test)").is_err());
+    }
+
+    #[test]
+    fn test_grow_selection() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let sel = |s: &str| SelectionExpression::parse(s).unwrap();
+
+        // Every vertex in a cube has three neighbors
+        assert_eq!(grow_vertex_selection(&mesh, &sel("0"), 1).unwrap().len(), 4);
+        assert_eq!(grow_vertex_selection(&mesh, &sel("0"), 2).unwrap().len(), 7);
+        assert_eq!(grow_vertex_selection(&mesh, &sel("0"), 3).unwrap().len(), 8);
+        assert_eq!(grow_vertex_selection(&mesh, &sel("0"), 0).unwrap().len(), 1);
+
+        // All faces share a vertex, except the opposite one
+        let grown = grow_face_selection(&mesh, &sel("0"), 1).unwrap();
+        assert_eq!(grown.len(), 5);
+        let conn = mesh.read_connectivity();
+        let grown_sel = selection_from_ids(&conn.faces, &grown);
+        assert_eq!(grow_face_selection(&mesh, &grown_sel, -1).unwrap().len(), 1);
+        assert_eq!(grow_face_selection(&mesh, &sel("*"), -1).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_resolve_group() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mut mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let faces = mesh
+            .resolve_face_selection_full(&SelectionExpression::parse("1, 3").unwrap())
+            .unwrap();
+        let group_id = mesh.channels.ensure_channel::<FaceId, bool>("my group");
+        let mut group = mesh.channels.write_channel(group_id).unwrap();
+        for face in faces.iter_cpy() {
+            group[face] = true;
+        }
+        drop(group);
+
+        let sel = SelectionExpression::parse("group(\"my group\")").unwrap();
+        assert_eq!(mesh.resolve_face_selection_full(&sel).unwrap(), faces);
+        let sel = SelectionExpression::parse("group(\"missing\")").unwrap();
+        assert!(mesh.resolve_face_selection_full(&sel).is_err());
+    }
+
+    #[test]
+    fn test_select_by_normal() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        assert_eq!(select_by_normal(&mesh, Vec3::Y, 30.0).unwrap().len(), 1);
+        assert_eq!(select_by_normal(&mesh, Vec3::Y, 91.0).unwrap().len(), 5);
+
+        let sel = SelectionExpression::parse("normal(0, -1, 0, 10)").unwrap();
+        assert_eq!(mesh.resolve_face_selection_full(&sel).unwrap().len(), 1);
+        assert!(mesh.resolve_vertex_selection_full(&sel).is_err());
+
+        assert!(select_by_normal(&mesh, Vec3::ZERO, 30.0).is_err());
+        let sel = SelectionExpression::parse("normal(0, 0, 0, 30)").unwrap();
+        assert!(mesh.resolve_face_selection_full(&sel).is_err());
+    }
+
+    #[test]
+    fn test_select_linked() {
+        use crate::mesh::halfedge::primitives::Box;
+        let mut mesh = Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        mesh.merge_with(&Box::build(Vec3::X * 3.0, Vec3::ONE).unwrap());
+
+        let seeds = mesh
+            .resolve_vertex_selection_full(&SelectionExpression::parse("9").unwrap())
+            .unwrap();
+        let linked = select_linked(&mesh, seeds[0]).unwrap();
+        assert_eq!(linked.len(), 8);
+        assert!(linked.contains(&seeds[0]));
     }
 }
 
@@ -352,6 +972,46 @@ mod lua_api {
         SelectionExpression::parse(&expr)
     }
 
+    /// Grows the selection `sel` of elements of the given `key_type` by
+    /// `steps`. Each step adds all the elements adjacent to the current
+    /// selection. Use negative steps to shrink the selection.
+    #[lua(under = "Blackjack")]
+    fn grow_selection(
+        mesh: &HalfEdgeMesh,
+        key_type: ChannelKeyType,
+        sel: SelectionExpression,
+        steps: i32,
+    ) -> Result<SelectionExpression> {
+        let conn = mesh.read_connectivity();
+        Ok(match key_type {
+            ChannelKeyType::VertexId => {
+                selection_from_ids(&conn.vertices, &grow_vertex_selection(mesh, &sel, steps)?)
+            }
+            ChannelKeyType::FaceId => {
+                selection_from_ids(&conn.faces, &grow_face_selection(mesh, &sel, steps)?)
+            }
+            ChannelKeyType::HalfEdgeId => selection_from_ids(
+                &conn.halfedges,
+                &grow_halfedge_selection(mesh, &sel, steps)?,
+            ),
+        })
+    }
+
+    /// Returns a selection with all the vertices in the shells of `mesh` that
+    /// contain a vertex from `seed_selection`.
+    #[lua(under = "Blackjack")]
+    fn select_linked(
+        mesh: &HalfEdgeMesh,
+        seed_selection: SelectionExpression,
+    ) -> Result<SelectionExpression> {
+        let seeds = mesh.resolve_vertex_selection_full(&seed_selection)?;
+        let linked = linked_vertices(mesh, &seeds)?;
+        Ok(selection_from_ids(
+            &mesh.read_connectivity().vertices,
+            &linked,
+        ))
+    }
+
     #[lua_impl]
     impl SelectionExpression {
         /// Returns a canonical string representation for this selection
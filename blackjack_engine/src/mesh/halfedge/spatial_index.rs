@@ -0,0 +1,124 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use rstar::RTree;
+use slotmap::Key;
+
+use crate::{prelude::*, sync::RefCounted};
+
+/// A vertex position, which can be stored in an rstar spatial index.
+#[derive(Debug, Clone, Copy)]
+pub struct VertexPos {
+    pub vertex: VertexId,
+    pub pos: Vec3,
+}
+
+impl rstar::RTreeObject for VertexPos {
+    type Envelope = rstar::AABB<[f32; 3]>;
+    fn envelope(&self) -> Self::Envelope {
+        rstar::AABB::from_point(self.pos.to_array())
+    }
+}
+
+impl rstar::PointDistance for VertexPos {
+    fn distance_2(
+        &self,
+        point: &<Self::Envelope as rstar::Envelope>::Point,
+    ) -> <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar {
+        self.pos.distance_squared(Vec3::from_slice(point))
+    }
+}
+
+/// A spatial index over the vertex positions of a mesh, cached inside the
+/// mesh. The fingerprint identifies the vertex positions the index was built
+/// from, so it can be discarded when they change.
+#[derive(Clone)]
+pub(crate) struct CachedSpatialIndex {
+    fingerprint: u64,
+    tree: RefCounted<RTree<VertexPos>>,
+}
+
+impl std::fmt::Debug for CachedSpatialIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedSpatialIndex")
+            .field("fingerprint", &self.fingerprint)
+            .field("size", &self.tree.size())
+            .finish()
+    }
+}
+
+/// Computes a hash of the vertices of a mesh and their positions. Hashing is
+/// linear on the number of vertices, which is much cheaper than rebuilding the
+/// spatial index.
+fn positions_fingerprint(mesh: &HalfEdgeMesh) -> u64 {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let mut hasher = DefaultHasher::new();
+    for (v, _, pos) in conn.iter_vertices_with_channel(&positions) {
+        v.data().as_ffi().hash(&mut hasher);
+        pos.to_array().map(f32::to_bits).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl HalfEdgeMesh {
+    /// Returns a spatial index over the vertex positions of this mesh, which
+    /// allows efficient nearest-neighbor queries.
+    ///
+    /// The index is built the first time it's requested, and reused by later
+    /// calls as long as the vertices and their positions stay the same. When
+    /// they change, the index is rebuilt on the next call.
+    pub fn vertex_spatial_index(&self) -> RefCounted<RTree<VertexPos>> {
+        let fingerprint = positions_fingerprint(self);
+        let mut cached = self.spatial_index.borrow_mut();
+        match &*cached {
+            Some(index) if index.fingerprint == fingerprint => index.tree.clone(),
+            _ => {
+                // Building the index takes O(n log n)
+                let tree = RefCounted::new(RTree::bulk_load(
+                    self.read_connectivity()
+                        .iter_vertices_with_channel(&self.read_positions())
+                        .map(|(vertex, _, pos)| VertexPos { vertex, pos })
+                        .collect_vec(),
+                ));
+                *cached = Some(CachedSpatialIndex {
+                    fingerprint,
+                    tree: tree.clone(),
+                });
+                tree
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_spatial_index_cache() {
+        let mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let index = mesh.vertex_spatial_index();
+        assert_eq!(index.size(), 8);
+        assert!(RefCounted::ptr_eq(&index, &mesh.vertex_spatial_index()));
+
+        // Clones share the index, since their positions are the same.
+        let cloned = mesh.clone();
+        assert!(RefCounted::ptr_eq(&index, &cloned.vertex_spatial_index()));
+
+        let (v, _) = mesh.read_connectivity().iter_vertices().next().unwrap();
+        mesh.write_positions()[v] = Vec3::splat(10.0);
+        let new_index = mesh.vertex_spatial_index();
+        assert!(!RefCounted::ptr_eq(&index, &new_index));
+        let nearest = new_index.nearest_neighbor(&[9.0, 9.0, 9.0]).unwrap();
+        assert_eq!(nearest.vertex, v);
+    }
+}
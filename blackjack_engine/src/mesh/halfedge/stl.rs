@@ -0,0 +1,106 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::prelude::*;
+
+/// Returns the triangles of `mesh` as a list of vertex positions, after
+/// triangulating all its faces.
+fn triangle_soup(mesh: &HalfEdgeMesh) -> Result<Vec<[Vec3; 3]>> {
+    let triangulated = edit_ops::triangulate(mesh, edit_ops::TriangulationStrategy::EarClipping)?;
+    let conn = triangulated.read_connectivity();
+    let positions = triangulated.read_positions();
+    Ok(conn
+        .iter_faces_ordered()
+        .filter_map(|(face, _)| match conn.face_vertices(face).as_slice() {
+            &[a, b, c] => Some([positions[a], positions[b], positions[c]]),
+            // Degenerate faces with less than three vertices are skipped.
+            _ => None,
+        })
+        .collect())
+}
+
+/// Exports `mesh` as an STL file at `path`, using the binary format when
+/// `binary` is true, and the ASCII format otherwise.
+///
+/// Faces are triangulated and each triangle is written with its facet normal,
+/// following the winding of the face. The mesh should be closed and have its
+/// normals facing outwards, which can be ensured with `recalculate_normals`.
+pub fn to_stl(mesh: &HalfEdgeMesh, path: &Path, binary: bool) -> Result<()> {
+    let triangles = triangle_soup(mesh)?;
+    let facet_normal = |[a, b, c]: [Vec3; 3]| (b - a).cross(c - a).normalize_or_zero();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    if binary {
+        let mut header = [0u8; 80];
+        let title = b"Generated by Blackjack: https://github.com/setzer22/blackjack";
+        header[..title.len()].copy_from_slice(title);
+        writer.write_all(&header)?;
+        writer.write_all(&(triangles.len() as u32).to_le_bytes())?;
+        for triangle in triangles {
+            for v in std::iter::once(facet_normal(triangle)).chain(triangle) {
+                for x in v.to_array() {
+                    writer.write_all(&x.to_le_bytes())?;
+                }
+            }
+            // Attribute byte count, unused.
+            writer.write_all(&0u16.to_le_bytes())?;
+        }
+    } else {
+        writeln!(writer, "solid blackjack")?;
+        for triangle in triangles {
+            let n = facet_normal(triangle);
+            writeln!(writer, "  facet normal {} {} {}", n.x, n.y, n.z)?;
+            writeln!(writer, "    outer loop")?;
+            for v in triangle {
+                writeln!(writer, "      vertex {} {} {}", v.x, v.y, v.z)?;
+            }
+            writeln!(writer, "    endloop")?;
+            writeln!(writer, "  endfacet")?;
+        }
+        writeln!(writer, "endsolid blackjack")?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[blackjack_macros::blackjack_lua_module]
+mod lua_api {
+    use super::*;
+
+    /// Saves this mesh as an STL file at a given `path`, using the binary
+    /// format when `binary` is true or the ASCII one otherwise. Faces are
+    /// triangulated. The path's parent folder must exist. If there was a file
+    /// at that path, it will be overwritten.
+    #[lua(under = "Export")]
+    pub fn stl(mesh: &HalfEdgeMesh, path: String, binary: bool) -> Result<()> {
+        to_stl(mesh, Path::new(&path), binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_export_stl() {
+        let mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        let triangles = triangle_soup(&mesh).unwrap();
+        assert_eq!(triangles.len(), 12);
+
+        let path = std::env::temp_dir().join("blackjack_test_export.stl");
+        to_stl(&mesh, &path, true).unwrap();
+        let stl = std::fs::read(&path).unwrap();
+        assert_eq!(u32::from_le_bytes(stl[80..84].try_into().unwrap()), 12);
+        assert_eq!(stl.len(), 84 + 50 * 12);
+    }
+}
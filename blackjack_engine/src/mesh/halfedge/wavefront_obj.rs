@@ -8,7 +8,7 @@ use slotmap::SecondaryMap;
 use std::{
     fs::File,
     io::{BufReader, BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use wavefront_rs::obj::{
     self,
@@ -35,12 +35,13 @@ impl HalfEdgeMesh {
         writeln!(writer)?;
 
         let conn = self.read_connectivity();
+        let positions = self.read_positions();
 
-        for (idx, (v_id, _, pos)) in conn
-            .iter_vertices_with_channel(&self.read_positions())
-            .enumerate()
-        {
+        // NOTE: Elements are written in the order they were added to the mesh,
+        // so that exporting the same mesh always produces the same file.
+        for (idx, (v_id, _)) in conn.iter_vertices_ordered().enumerate() {
             imap.insert(v_id, (idx + 1) as i32);
+            let pos = positions[v_id];
             obj::format_writer::FormatWriter::write(
                 &mut writer,
                 &Entity::Vertex {
@@ -53,11 +54,35 @@ impl HalfEdgeMesh {
             writeln!(writer)?;
         }
 
+        // Since UVs and per-corner normals are stored in halfedges, we need the
+        // same mapping as `imap` above, but for halfedges instead.
+        let h_imap: SecondaryMap<HalfEdgeId, i32> = conn
+            .iter_halfedges_ordered()
+            .enumerate()
+            .map(|(idx, (h, _))| (h, (idx + 1) as i32))
+            .collect();
+
         let mut has_normals = false;
-        if self.gen_config.smooth_normals {
+        let mut halfedge_normals = false;
+        if let Some(h_normals_ch) = self.read_halfedge_normals() {
+            has_normals = true;
+            halfedge_normals = true;
+            for (h, _) in conn.iter_halfedges_ordered() {
+                let normal = h_normals_ch[h];
+                obj::format_writer::FormatWriter::write(
+                    &mut writer,
+                    &Entity::VertexNormal {
+                        x: normal.x as f64,
+                        y: normal.y as f64,
+                        z: normal.z as f64,
+                    },
+                );
+                writeln!(writer)?;
+            }
+        } else if self.gen_config.smooth_normals {
             if let Some(v_normals_ch) = self.read_vertex_normals() {
                 has_normals = true;
-                for (v, _) in conn.iter_vertices() {
+                for (v, _) in conn.iter_vertices_ordered() {
                     let normal = v_normals_ch[v];
                     obj::format_writer::FormatWriter::write(
                         &mut writer,
@@ -75,14 +100,12 @@ impl HalfEdgeMesh {
             println!("TODO: Exporting per-face normals is not yet implemented.")
         }
 
-        // Since UVs are stored in halfedges, we need the same mapping as `imap`
-        // above, but for halfedges instead.
-        let mut h_imap = SecondaryMap::<HalfEdgeId, i32>::new();
+        // NOTE: OBJ files only support a single UV set, so the lightmap UVs are
+        // not exported.
         let mut has_uvs = false;
         if let Some(uvs_ch) = self.read_uvs() {
             has_uvs = true;
-            for (idx, (h, _)) in conn.iter_halfedges().enumerate() {
-                h_imap.insert(h, (idx + 1) as i32);
+            for (h, _) in conn.iter_halfedges_ordered() {
                 let uv = uvs_ch[h];
                 obj::format_writer::FormatWriter::write(
                     &mut writer,
@@ -96,7 +119,7 @@ impl HalfEdgeMesh {
             }
         }
 
-        for (face_id, _) in conn.iter_faces() {
+        for (face_id, _) in conn.iter_faces_ordered() {
             let vertices = conn
                 .face_vertices(face_id)
                 .iter()
@@ -106,7 +129,9 @@ impl HalfEdgeMesh {
                     // TODO: For now we rely on emitting one normal per vertex.
                     // Sometimes there might be less, when we implement flat
                     // shaded normals.
-                    normal: if has_normals {
+                    normal: if halfedge_normals {
+                        Some(h_imap[*h_id] as i64)
+                    } else if has_normals {
                         Some(imap[*v_id] as i64)
                     } else {
                         None
@@ -125,23 +150,115 @@ impl HalfEdgeMesh {
         Ok(())
     }
 
-    pub fn from_wavefront_obj(path: PathBuf) -> Result<HalfEdgeMesh> {
+    /// Loads the Wavefront OBJ file at `path`. Polygons are kept as they are,
+    /// so n-gons are not triangulated. When the file has texture coordinates
+    /// or normals, they are stored in the `uv` and `vertex_normal` channels.
+    pub fn from_wavefront_obj(path: &Path) -> Result<HalfEdgeMesh> {
         let mut reader = BufReader::new(File::open(path)?);
         let mut positions = vec![];
+        let mut uvs = vec![];
+        let mut normals = vec![];
         let mut polygons = vec![];
         obj::read_lexer::ReadLexer::read_to_end(&mut reader, |entity| match entity {
             Entity::Vertex { x, y, z, w: _w } => {
                 positions.push(Vec3::new(x as f32, y as f32, z as f32));
             }
+            Entity::VertexTexture { u, v, w: _w } => {
+                uvs.push(Vec3::new(u as f32, v.unwrap_or(0.0) as f32, 0.0));
+            }
+            Entity::VertexNormal { x, y, z } => {
+                normals.push(Vec3::new(x as f32, y as f32, z as f32));
+            }
             Entity::Face { vertices } => {
-                // NOTE: OBJ Wavefront indices start at 1
-                let polygon: SVec<usize> =
-                    vertices.iter().map(|v| (v.vertex - 1) as usize).collect();
-                polygons.push(polygon);
+                polygons.push(vertices);
             }
             _ => {}
         })?;
-        halfedge::HalfEdgeMesh::build_from_polygons(&positions, &polygons)
+
+        // NOTE: OBJ Wavefront indices start at 1. Negative indices are
+        // relative to the end of the list, with -1 being the last element.
+        let resolve = |idx: i64, len: usize, kind: &str| -> Result<usize> {
+            let resolved = if idx < 0 { len as i64 + idx } else { idx - 1 };
+            if resolved < 0 || resolved >= len as i64 {
+                bail!("Invalid {kind} index in OBJ file: {idx}");
+            }
+            Ok(resolved as usize)
+        };
+
+        let position_indices = polygons
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .map(|fv| resolve(fv.vertex, positions.len(), "vertex"))
+                    .collect::<Result<SVec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut mesh = halfedge::HalfEdgeMesh::build_from_polygons(&positions, &position_indices)?;
+
+        let has_uvs = polygons.iter().flatten().all(|fv| fv.texture.is_some());
+        let has_normals = polygons.iter().flatten().all(|fv| fv.normal.is_some());
+        if polygons.is_empty() || !(has_uvs || has_normals) {
+            return Ok(mesh);
+        }
+
+        let mut uvs_ch = Channel::<HalfEdgeId, Vec3>::new();
+        let mut normals_ch = Channel::<VertexId, Vec3>::new();
+        {
+            let conn = mesh.read_connectivity();
+
+            // NOTE: `build_from_polygons` allocates vertices in the order they
+            // first appear in the polygon list, and faces in the same order as
+            // the polygons. This lets us find the vertex for each OBJ index.
+            let mut index_to_vertex = HashMap::<usize, VertexId>::new();
+            let mut vertices = conn.iter_vertices().map(|(v, _)| v);
+            for idx in position_indices.iter().flatten() {
+                if !index_to_vertex.contains_key(idx) {
+                    let v = vertices
+                        .next()
+                        .ok_or_else(|| anyhow!("Mismatched vertex count in OBJ import"))?;
+                    index_to_vertex.insert(*idx, v);
+                }
+            }
+
+            for ((face, _), (polygon, indices)) in conn
+                .iter_faces()
+                .zip(polygons.iter().zip(position_indices.iter()))
+            {
+                for h in conn.face_edges(face) {
+                    let v = conn.at_halfedge(h).vertex().try_end()?;
+                    let corner = indices
+                        .iter()
+                        .position(|idx| index_to_vertex[idx] == v)
+                        .ok_or_else(|| anyhow!("Mismatched face in OBJ import"))?;
+                    let fv = &polygon[corner];
+                    if let (true, Some(t)) = (has_uvs, fv.texture) {
+                        uvs_ch[h] = uvs[resolve(t, uvs.len(), "texture")?];
+                    }
+                    if let (true, Some(n)) = (has_normals, fv.normal) {
+                        // Vertices shared by several faces may reference
+                        // different normals. We average them all.
+                        normals_ch[v] += normals[resolve(n, normals.len(), "normal")?];
+                    }
+                }
+            }
+        }
+
+        if has_uvs {
+            let uvs_ch_id = mesh.channels.replace_or_create_channel("uv", uvs_ch);
+            mesh.default_channels.uvs = Some(uvs_ch_id);
+        }
+        if has_normals {
+            for (_, normal) in normals_ch.iter_mut() {
+                *normal = normal.normalize_or_zero();
+            }
+            let normals_ch_id = mesh
+                .channels
+                .replace_or_create_channel("vertex_normal", normals_ch);
+            mesh.default_channels.vertex_normals = Some(normals_ch_id);
+            mesh.gen_config.smooth_normals = true;
+        }
+
+        Ok(mesh)
     }
 }
 
@@ -159,13 +276,18 @@ mod lua_api {
     }
 
     /// Loads a wavefront OBJ file from disk at the given `path` and returns a
-    /// `HalfEdgeMesh`.
-    ///
-    /// NOTE: This currently only loads vertex positions, no normals or texture
-    /// coordinates.
+    /// `HalfEdgeMesh`. Same as `Primitives.load_obj`.
     #[lua(under = "HalfEdgeMesh")]
     pub fn from_wavefront_obj(path: String) -> Result<HalfEdgeMesh> {
-        HalfEdgeMesh::from_wavefront_obj(path.into())
+        HalfEdgeMesh::from_wavefront_obj(Path::new(&path))
+    }
+
+    /// Loads a wavefront OBJ file from disk at the given `path`. Polygons are
+    /// preserved as-is, and texture coordinates and normals are loaded when
+    /// present in the file.
+    #[lua(under = "Primitives")]
+    pub fn load_obj(path: String) -> Result<HalfEdgeMesh> {
+        HalfEdgeMesh::from_wavefront_obj(Path::new(&path))
     }
 }
 
@@ -175,9 +297,89 @@ mod tests {
 
     #[test]
     pub fn test_load_obj() {
-        HalfEdgeMesh::from_wavefront_obj("../test/test_mesh.obj".into())
+        HalfEdgeMesh::from_wavefront_obj(Path::new("../test/test_mesh.obj"))
             .unwrap()
             .to_wavefront_obj("/tmp/output.obj")
             .unwrap();
     }
+
+    #[test]
+    pub fn test_obj_roundtrip() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        edit_ops::set_smooth_normals(&mut mesh, edit_ops::NormalWeighting::Uniform).unwrap();
+        // Each face gets different UVs, so the test can tell corners apart.
+        let mut uvs = Channel::<HalfEdgeId, Vec3>::new();
+        {
+            let conn = mesh.read_connectivity();
+            let positions = mesh.read_positions();
+            for (i, (face, _)) in conn.iter_faces().enumerate() {
+                for h in conn.face_edges(face) {
+                    let p = positions[conn.at_halfedge(h).vertex().end()];
+                    uvs[h] = Vec3::new(p.x + p.z + i as f32, p.y, 0.0);
+                }
+            }
+        }
+        edit_ops::set_uvs(&mut mesh, UvChannel::Main, uvs);
+
+        let path = std::env::temp_dir().join("blackjack_test_roundtrip.obj");
+        mesh.to_wavefront_obj(&path).unwrap();
+        let loaded = HalfEdgeMesh::from_wavefront_obj(&path).unwrap();
+
+        // Returns the position, UV and normal at each corner of the mesh.
+        let corners = |mesh: &HalfEdgeMesh| {
+            let conn = mesh.read_connectivity();
+            let positions = mesh.read_positions();
+            let uvs = mesh.read_uvs().unwrap();
+            let normals = mesh.read_vertex_normals().unwrap();
+            let mut corners = conn
+                .iter_halfedges()
+                .filter(|(h, _)| conn.at_halfedge(*h).face().try_end().is_ok())
+                .map(|(h, _)| {
+                    let v = conn.at_halfedge(h).vertex().end();
+                    (positions[v], uvs[h], normals[v])
+                })
+                .collect_vec();
+            corners.sort_by(|a, b| {
+                let key = |c: &(Vec3, Vec3, Vec3)| [c.0.to_array(), c.1.to_array()];
+                key(a).partial_cmp(&key(b)).unwrap()
+            });
+            corners
+        };
+        let (original, loaded) = (corners(&mesh), corners(&loaded));
+        assert_eq!(original.len(), 24);
+        assert_eq!(original.len(), loaded.len());
+        for (a, b) in original.iter().zip(&loaded) {
+            assert!((a.0 - b.0).length() < 1e-5);
+            assert!((a.1 - b.1).length() < 1e-5);
+            assert!((a.2 - b.2).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    pub fn test_export_order_is_stable() {
+        let mut mesh = primitives::Box::build(Vec3::ZERO, Vec3::ONE).unwrap();
+        {
+            let mut conn = mesh.write_connectivity();
+            let (h, _) = conn.iter_halfedges().next().unwrap();
+            edit_ops::collapse_edge(&mut conn, h).unwrap();
+        }
+        // The new vertex reuses the slot of the one removed by the collapse,
+        // but it should still be exported last.
+        let new_pos = Vec3::new(5.0, 6.0, 7.0);
+        edit_ops::add_vertex(&mut mesh, new_pos).unwrap();
+
+        let path = std::env::temp_dir().join("blackjack_test_export_order.obj");
+        mesh.to_wavefront_obj(&path).unwrap();
+        let obj = std::fs::read_to_string(&path).unwrap();
+        let last_vertex = obj
+            .lines()
+            .filter(|l| l.starts_with("v "))
+            .last()
+            .unwrap()
+            .split_whitespace()
+            .skip(1)
+            .map(|x| x.parse::<f32>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(last_vertex, new_pos.to_array());
+    }
 }
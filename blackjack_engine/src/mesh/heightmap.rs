@@ -55,12 +55,46 @@ impl HeightMap {
         }
     }
 
+    /// Builds a heightmap with a grid of `width` times `height`, where each
+    /// cell is filled with the result of calling `f` with its coordinates.
+    pub fn from_shape_fn(
+        width: usize,
+        height: usize,
+        f: impl FnMut((usize, usize)) -> f32,
+    ) -> HeightMap {
+        Self {
+            inner: ndarray::Array2::from_shape_fn((width, height), f),
+        }
+    }
+
+    /// Samples the heightmap at normalized coordinates `u` and `v`, in the
+    /// [0, 1] range, using bilinear interpolation between the four nearest
+    /// cells. Coordinates outside the range are clamped to the border.
+    pub fn sample_bilinear(&self, u: f32, v: f32) -> f32 {
+        let (width, height) = self.inner.dim();
+        if width == 0 || height == 0 {
+            return 0.0;
+        }
+
+        let x = u.clamp(0.0, 1.0) * (width - 1) as f32;
+        let y = v.clamp(0.0, 1.0) * (height - 1) as f32;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let h = &self.inner;
+        let top = h[(x0, y0)] * (1.0 - tx) + h[(x1, y0)] * tx;
+        let bottom = h[(x0, y1)] * (1.0 - tx) + h[(x1, y1)] * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
     pub fn generate_triangle_buffers(&self) -> VertexIndexBuffers {
         // If the terrain is too small to compute normals, return an empty buffer
         if self.inner.ncols() < 4 || self.inner.nrows() < 4 {
             return VertexIndexBuffers {
                 positions: vec![],
                 normals: vec![],
+                colors: vec![],
                 indices: vec![],
             };
         }
@@ -118,6 +152,7 @@ impl HeightMap {
         }
 
         VertexIndexBuffers {
+            colors: vec![Vec3::ONE; positions.len()],
             positions,
             normals,
             indices,
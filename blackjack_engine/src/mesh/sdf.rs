@@ -0,0 +1,235 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use anyhow::{bail, Result};
+use glam::{Vec2, Vec3};
+
+use crate::mesh::halfedge::{edit_ops, HalfEdgeMesh};
+
+/// A signed distance field, built as a tree of primitive shapes combined
+/// with boolean operations. Distances are negative inside the shape.
+///
+/// Unlike mesh booleans, combining distance fields never fails, and allows
+/// smooth blends between shapes. The field is converted to a mesh with
+/// `to_mesh`.
+#[derive(Clone, Debug)]
+pub enum Sdf {
+    /// A sphere centered at the origin.
+    Sphere {
+        radius: f32,
+    },
+    /// An axis-aligned box centered at the origin, with the given size.
+    Cuboid {
+        size: Vec3,
+    },
+    /// A cylinder centered at the origin, along the Y axis.
+    Cylinder {
+        radius: f32,
+        height: f32,
+    },
+    /// A torus centered at the origin, lying on the XZ plane.
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    /// The first shape, minus the second one.
+    Difference(Box<Sdf>, Box<Sdf>),
+    /// A union where the shapes blend together in the regions closer than
+    /// the given distance to both of them.
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f32),
+    Translate(Box<Sdf>, Vec3),
+}
+
+impl Sdf {
+    /// Evaluates the field at `p`, returning the signed distance to the
+    /// surface of this shape. For smooth unions, the result is only an
+    /// approximation.
+    pub fn eval(&self, p: Vec3) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => p.length() - radius,
+            Sdf::Cuboid { size } => {
+                let q = p.abs() - *size / 2.0;
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+            }
+            Sdf::Cylinder { radius, height } => {
+                let d = Vec2::new(
+                    Vec2::new(p.x, p.z).length() - radius,
+                    p.y.abs() - height / 2.0,
+                );
+                d.max(Vec2::ZERO).length() + d.max_element().min(0.0)
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let q = Vec2::new(Vec2::new(p.x, p.z).length() - major_radius, p.y);
+                q.length() - minor_radius
+            }
+            Sdf::Union(a, b) => a.eval(p).min(b.eval(p)),
+            Sdf::Intersection(a, b) => a.eval(p).max(b.eval(p)),
+            Sdf::Difference(a, b) => a.eval(p).max(-b.eval(p)),
+            Sdf::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.eval(p), b.eval(p));
+                if *k <= 0.0 {
+                    return da.min(db);
+                }
+                // Polynomial smooth minimum, by Inigo Quilez.
+                let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            Sdf::Translate(sdf, offset) => sdf.eval(p - *offset),
+        }
+    }
+
+    /// Converts this distance field to a closed mesh of quads. Only the part
+    /// of the shape inside a box of size `bounds`, centered at the origin, is
+    /// meshed. The `resolution` is the number of grid cells along the longest
+    /// side of the box.
+    pub fn to_mesh(&self, bounds: Vec3, resolution: usize) -> Result<HalfEdgeMesh> {
+        if bounds.cmple(Vec3::ZERO).any() {
+            bail!("The bounds must be positive along all axes")
+        }
+        if resolution == 0 {
+            bail!("The resolution must be at least one")
+        }
+        let cell_size = bounds.max_element() / resolution as f32;
+        edit_ops::isosurface(-bounds / 2.0, bounds / 2.0, cell_size, |p| self.eval(p))
+    }
+}
+
+#[blackjack_macros::blackjack_lua_module]
+mod lua_api {
+    use super::*;
+    use crate::lua_engine::lua_stdlib::LVec3;
+
+    /// Returns the distance field of a sphere of the given `radius`, centered
+    /// at the origin.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_sphere(radius: f32) -> Sdf {
+        Sdf::Sphere { radius }
+    }
+
+    /// Returns the distance field of a box with the given `size`, centered at
+    /// the origin.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_box(size: LVec3) -> Sdf {
+        Sdf::Cuboid { size: size.0 }
+    }
+
+    /// Returns the distance field of a cylinder with the given `radius` and
+    /// `height`, centered at the origin and aligned with the Y axis.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_cylinder(radius: f32, height: f32) -> Sdf {
+        Sdf::Cylinder { radius, height }
+    }
+
+    /// Returns the distance field of a torus lying on the XZ plane, centered
+    /// at the origin.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_torus(major_radius: f32, minor_radius: f32) -> Sdf {
+        Sdf::Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    /// Returns the union of the distance fields `a` and `b`.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_union(a: &Sdf, b: &Sdf) -> Sdf {
+        Sdf::Union(Box::new(a.clone()), Box::new(b.clone()))
+    }
+
+    /// Returns the intersection of the distance fields `a` and `b`.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_intersection(a: &Sdf, b: &Sdf) -> Sdf {
+        Sdf::Intersection(Box::new(a.clone()), Box::new(b.clone()))
+    }
+
+    /// Returns the distance field `a` with the shape of `b` removed from it.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_difference(a: &Sdf, b: &Sdf) -> Sdf {
+        Sdf::Difference(Box::new(a.clone()), Box::new(b.clone()))
+    }
+
+    /// Returns the union of the distance fields `a` and `b`, blending them
+    /// smoothly where they are closer than `k` units.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_smooth_union(a: &Sdf, b: &Sdf, k: f32) -> Sdf {
+        Sdf::SmoothUnion(Box::new(a.clone()), Box::new(b.clone()), k)
+    }
+
+    /// Returns the distance field `sdf`, moved by `offset`.
+    #[lua(under = "Blackjack")]
+    pub fn sdf_translate(sdf: &Sdf, offset: LVec3) -> Sdf {
+        Sdf::Translate(Box::new(sdf.clone()), offset.0)
+    }
+
+    /// Builds a mesh for the surface of the distance field `sdf`, inside a
+    /// box of size `bounds` centered at the origin. The `resolution` is the
+    /// number of grid cells along the longest side of the box.
+    #[lua(under = "Ops")]
+    pub fn sdf_to_mesh(sdf: &Sdf, bounds: LVec3, resolution: usize) -> Result<HalfEdgeMesh> {
+        sdf.to_mesh(bounds.0, resolution)
+    }
+
+    #[lua_impl]
+    impl Sdf {
+        /// Returns the signed distance from `point` to the surface of this
+        /// shape. The distance is negative inside the shape.
+        #[lua]
+        fn distance(&self, point: LVec3) -> f32 {
+            self.eval(point.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_distances() {
+        let sphere = Sdf::Sphere { radius: 1.0 };
+        let cube = Sdf::Translate(
+            Box::new(Sdf::Cuboid {
+                size: Vec3::splat(2.0),
+            }),
+            Vec3::X,
+        );
+        assert_eq!(sphere.eval(Vec3::new(0.0, 3.0, 0.0)), 2.0);
+        assert_eq!(cube.eval(Vec3::new(1.0, 0.5, 0.0)), -0.5);
+
+        let union = Sdf::Union(Box::new(sphere.clone()), Box::new(cube.clone()));
+        let difference = Sdf::Difference(Box::new(sphere.clone()), Box::new(cube.clone()));
+        let smooth = Sdf::SmoothUnion(Box::new(sphere), Box::new(cube), 0.5);
+        let p = Vec3::new(-0.5, 0.0, 0.0);
+        assert_eq!(union.eval(p), -0.5);
+        assert_eq!(difference.eval(p), -0.5);
+        assert!(difference.eval(Vec3::new(0.5, 0.0, 0.0)) > 0.0);
+        // Smooth unions grow the shape where both are close.
+        let p = Vec3::new(0.0, 1.2, 0.0);
+        assert!(smooth.eval(p) < union.eval(p) - 0.1);
+    }
+
+    #[test]
+    fn test_sdf_to_mesh() {
+        let sphere = Sdf::Sphere { radius: 1.0 };
+        let mesh = sphere.to_mesh(Vec3::splat(2.5), 40).unwrap();
+        assert!(mesh
+            .read_positions()
+            .iter()
+            .all(|(_, p)| (p.length() - 1.0).abs() < 0.05));
+        let volume = edit_ops::mesh_volume(&mesh);
+        assert!((volume - 4.0 / 3.0 * std::f32::consts::PI).abs() < 0.1);
+
+        // Shapes clipped by the bounds are closed.
+        let clipped = sphere.to_mesh(Vec3::new(1.0, 2.5, 2.5), 40).unwrap();
+        let volume = edit_ops::mesh_volume(&clipped);
+        assert!(volume > 0.0 && volume < 4.0 / 3.0 * std::f32::consts::PI);
+    }
+}
@@ -15,8 +15,8 @@ pub struct GodotLuaIo {
 }
 
 impl LuaFileIo for GodotLuaIo {
-    fn base_folder(&self) -> &str {
-        &self.base_folder
+    fn search_folders(&self) -> &[String] {
+        std::slice::from_ref(&self.base_folder)
     }
 
     fn find_run_files(&self) -> Box<dyn Iterator<Item = String>> {
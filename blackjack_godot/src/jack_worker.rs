@@ -0,0 +1,119 @@
+// Copyright (C) 2023 setzer22 and contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+};
+
+use blackjack_engine::{
+    graph::BjkGraph,
+    graph_interpreter::ExternalParameterValues,
+    lua_engine::{LuaRuntime, DEFAULT_OP_TIMEOUT},
+    mesh::halfedge::HalfEdgeMesh,
+};
+
+use crate::{godot_lua_io::GodotLuaIo, run_jack_graph, JackId};
+
+/// A request to run the graph of a jack in the worker thread.
+pub struct JackRequest {
+    pub jack_id: JackId,
+    pub graph: Arc<BjkGraph>,
+    pub params: ExternalParameterValues,
+}
+
+/// The outcome of running a `JackRequest`.
+pub struct JackResponse {
+    pub jack_id: JackId,
+    pub result: Result<HalfEdgeMesh, String>,
+    /// The lines logged by the Lua code while running the graph. When running
+    /// the graph fails, they are part of the error instead.
+    pub log_lines: Vec<String>,
+}
+
+/// Runs jack graphs on a background thread, so heavy graphs don't block the
+/// Godot main thread. The worker owns a separate Lua runtime, since Lua states
+/// can't be shared between threads. Meshes are sent back to the main thread,
+/// which is possible because the engine is compiled with the `sync` feature.
+pub struct JackWorker {
+    requests: Sender<JackRequest>,
+    responses: Receiver<JackResponse>,
+}
+
+impl JackWorker {
+    /// Spawns the worker thread. Lua files are loaded from `library_path`.
+    pub fn spawn(library_path: String) -> Self {
+        let (req_tx, req_rx) = mpsc::channel::<JackRequest>();
+        let (res_tx, res_rx) = mpsc::channel::<JackResponse>();
+
+        std::thread::spawn(move || {
+            let lua_runtime = match LuaRuntime::initialize_custom(
+                GodotLuaIo {
+                    base_folder: library_path,
+                },
+                Some(DEFAULT_OP_TIMEOUT),
+            ) {
+                Ok(lua_runtime) => lua_runtime,
+                Err(err) => {
+                    // Report the error on every request, so the jacks waiting
+                    // for it don't wait forever.
+                    for request in req_rx.iter() {
+                        let _ = res_tx.send(JackResponse {
+                            jack_id: request.jack_id,
+                            result: Err(format!("Error while loading Blackjack runtime: {err}")),
+                            log_lines: vec![],
+                        });
+                    }
+                    return;
+                }
+            };
+
+            // The thread stops when the sender is dropped.
+            while let Ok(first) = req_rx.recv() {
+                // When several requests for the same jack are queued, only the
+                // last one needs to run. This avoids falling behind when a
+                // parameter is changed every frame.
+                let mut pending = vec![first];
+                for request in req_rx.try_iter() {
+                    pending.retain(|r| r.jack_id != request.jack_id);
+                    pending.push(request);
+                }
+
+                for request in pending {
+                    let (result, log_lines) =
+                        run_jack_graph(&lua_runtime, &request.graph, request.params);
+                    let response = JackResponse {
+                        jack_id: request.jack_id,
+                        result,
+                        log_lines,
+                    };
+                    if res_tx.send(response).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            requests: req_tx,
+            responses: res_rx,
+        }
+    }
+
+    /// Queues a request. The response can be obtained later with
+    /// `try_responses`.
+    pub fn send(&self, request: JackRequest) -> anyhow::Result<()> {
+        self.requests
+            .send(request)
+            .map_err(|_| anyhow::anyhow!("The Blackjack worker thread has stopped."))
+    }
+
+    /// Returns the responses for all the requests that finished since the
+    /// last call, without blocking.
+    pub fn try_responses(&self) -> impl Iterator<Item = JackResponse> + '_ {
+        self.responses.try_iter()
+    }
+}
@@ -10,17 +10,18 @@ use blackjack_engine::graph::BjkNodeId;
 use blackjack_engine::graph::DependencyKind;
 use blackjack_engine::graph_interpreter::ExternalParameter;
 use blackjack_engine::graph_interpreter::ExternalParameterValues;
-use blackjack_engine::lua_engine::ProgramResult;
-use blackjack_engine::lua_engine::RenderableThing;
 use gdnative::api::Material;
 use slotmap::KeyData;
+use slotmap::SecondaryMap;
 use slotmap::SlotMap;
 use std::collections::BTreeMap;
 use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 use blackjack_engine::graph::BlackjackValue;
 use blackjack_engine::graph::InputValueConfig;
 use blackjack_engine::lua_engine::LuaRuntime;
+use blackjack_engine::lua_engine::DEFAULT_OP_TIMEOUT;
 use blackjack_engine::mesh::halfedge::HalfEdgeMesh;
 use blackjack_engine::prelude::selection::SelectionExpression;
 use blackjack_engine::prelude::*;
@@ -30,8 +31,10 @@ use gdnative::prelude::*;
 use anyhow::Result;
 
 use crate::godot_lua_io::GodotLuaIo;
+use crate::jack_worker::{JackRequest, JackWorker};
 
 mod godot_lua_io;
+mod jack_worker;
 
 slotmap::new_key_type! { pub struct JackId; }
 
@@ -50,8 +53,13 @@ impl ToVariant for JackId {
 }
 
 pub struct BlackjackJackAsset {
-    graph: BjkGraph,
+    graph: Arc<BjkGraph>,
     params: ExternalParameterValues,
+    /// The mesh generated by the last successful call to `update_jack`.
+    last_mesh: Option<HalfEdgeMesh>,
+    /// The result of the last `update_jack_async` request, once the worker
+    /// thread finishes running it. Taken by `poll_jack`.
+    async_result: Option<Result<HalfEdgeMesh, String>>,
 }
 
 /// A singleton node that manages the lifetime for all the loaded jacks. This
@@ -66,6 +74,9 @@ pub struct BlackjackJackAsset {
 pub struct BlackjackGodotRuntime {
     lua_runtime: LuaRuntime,
     jacks: SlotMap<JackId, Option<BlackjackJackAsset>>,
+    library_path: String,
+    /// Runs graphs for `update_jack_async`. Spawned on first use.
+    worker: Option<JackWorker>,
 }
 
 static LUA_NEEDS_INIT: AtomicBool = AtomicBool::new(true);
@@ -82,13 +93,18 @@ impl BlackjackGodotRuntime {
                 godot_error!("Invalid path in project settings {e}");
                 "".into()
             });
-        let lua_runtime = LuaRuntime::initialize_custom(GodotLuaIo {
-            base_folder: library_path,
-        })?;
+        let lua_runtime = LuaRuntime::initialize_custom(
+            GodotLuaIo {
+                base_folder: library_path.clone(),
+            },
+            Some(DEFAULT_OP_TIMEOUT),
+        )?;
 
         Ok(Self {
             lua_runtime,
             jacks: SlotMap::with_key(),
+            library_path,
+            worker: None,
         })
     }
 
@@ -134,6 +150,41 @@ impl BlackjackGodotRuntime {
     }
 }
 
+/// Runs the graph of a jack, and returns the generated mesh along with the
+/// lines logged by its Lua code. Errors are converted to strings to be passed
+/// to GDScript, and already contain the logged lines.
+fn run_jack_graph(
+    lua_runtime: &LuaRuntime,
+    graph: &BjkGraph,
+    params: ExternalParameterValues,
+) -> (Result<HalfEdgeMesh, String>, Vec<String>) {
+    match blackjack_engine::run_jack_graph(lua_runtime, graph, params) {
+        Ok((mesh, log_lines)) => (Ok(mesh), log_lines),
+        Err(err) => (Err(format!("{err:?}")), vec![]),
+    }
+}
+
+impl BlackjackGodotRuntime {
+    /// Converts the result of running a jack's graph into the value returned
+    /// to GDScript. Generated meshes are also kept, so they can be used to
+    /// generate collision shapes.
+    fn finish_update(
+        &mut self,
+        jack_id: JackId,
+        result: Result<HalfEdgeMesh, String>,
+        materials: Vec<Ref<Material>>,
+    ) -> Option<UpdateJackResult> {
+        match result {
+            Ok(mesh) => {
+                let godot_mesh = halfedge_to_godot_mesh(&mesh, materials).unwrap();
+                self.jacks.get_mut(jack_id)?.as_mut()?.last_mesh = Some(mesh);
+                Some(UpdateJackResult::Ok(godot_mesh))
+            }
+            Err(err) => Some(UpdateJackResult::Err(err)),
+        }
+    }
+}
+
 #[derive(ToVariant)]
 pub enum UpdateJackResult {
     Ok(Ref<gd::ArrayMesh>),
@@ -207,11 +258,14 @@ impl BlackjackApi {
             let loaded = SerializedBjkGraph::load_from_string(&contents.to_string())
                 .and_then(|x| x.into_runtime());
             match loaded {
-                Ok((rt_data, _, _)) => {
+                Ok((mut rt_data, _, _)) => {
+                    rt_data.fill_missing_inputs(&runtime.lua_runtime.node_definitions);
                     if let Some(params) = rt_data.external_parameters {
                         *runtime.jacks.get_mut(jack_id)? = Some(BlackjackJackAsset {
-                            graph: rt_data.graph,
+                            graph: Arc::new(rt_data.graph),
                             params,
+                            last_mesh: None,
+                            async_result: None,
                         });
                         Some(true)
                     } else {
@@ -260,6 +314,14 @@ impl BlackjackApi {
                         *sel = None;
                     }
                 }
+                blackjack_engine::graph::BlackjackValue::Bool(b) => {
+                    let new_b = new_value.try_to::<bool>().ok()?;
+                    *b = new_b;
+                }
+                blackjack_engine::graph::BlackjackValue::Expression(e) => {
+                    let new_e = new_value.try_to::<String>().ok()?;
+                    *e = new_e;
+                }
                 blackjack_engine::graph::BlackjackValue::None => {}
             }
             Some(true)
@@ -278,6 +340,15 @@ impl BlackjackApi {
             max: Option<f32>,
         }
 
+        #[derive(FromVariant, ToVariant)]
+        struct EnumDef {
+            label: String,
+            addr: GdExternalParameter,
+            typ: String,
+            val: String,
+            values: Vec<String>,
+        }
+
         #[derive(FromVariant, ToVariant)]
         struct GenericDef {
             label: String,
@@ -293,21 +364,9 @@ impl BlackjackApi {
             let mut params = VariantArray::new();
 
             let node_definitions = &runtime.lua_runtime.node_definitions;
-            for (param_addr, value) in jack.params.0.iter() {
+            for (param_addr, value) in jack.params.iter_in_declaration_order(&jack.graph) {
                 let node = &jack.graph.nodes[param_addr.node_id];
-                let node_def = node_definitions.node_def(&node.op_name);
-                if node_def.is_none() {
-                    godot_error!(
-                        "Could not get parameters for Jack. No node definition found for {}",
-                        node.op_name
-                    );
-                    return None;
-                }
-                let node_def = node_def.unwrap();
-                let param_def = node_def
-                    .inputs
-                    .iter()
-                    .find(|i| i.name == param_addr.param_name);
+                let param_def = node.input_def(&param_addr.param_name, node_definitions);
                 if param_def.is_none() {
                     godot_error!(
                         "Could not get parameters for Jack. No parameter {} found for node {:?}",
@@ -347,6 +406,15 @@ impl BlackjackApi {
                                 max: *max,
                             })
                         }
+                        (InputValueConfig::Enum { values, .. }, BlackjackValue::String(s)) => {
+                            params.push(EnumDef {
+                                label,
+                                addr,
+                                typ: "Enum".into(),
+                                val: s.clone(),
+                                values: values.clone(),
+                            })
+                        }
                         (_, BlackjackValue::String(s)) => params.push(GenericDef {
                             label,
                             addr,
@@ -364,6 +432,19 @@ impl BlackjackApi {
                                 .unparse()
                                 .to_variant(),
                         }),
+                        (_, BlackjackValue::Bool(b)) => params.push(GenericDef {
+                            label,
+                            addr,
+                            typ: "Bool".into(),
+                            val: b.to_variant(),
+                        }),
+                        // Expressions are edited as text.
+                        (_, BlackjackValue::Expression(e)) => params.push(GenericDef {
+                            label,
+                            addr,
+                            typ: "String".into(),
+                            val: e.clone().to_variant(),
+                        }),
                         // TODO: For now this ignore any malformed parameters.
                         _ => continue,
                     }
@@ -383,29 +464,90 @@ impl BlackjackApi {
         Self::with_runtime(|runtime| {
             let jack = runtime.jacks.get(jack_id)?.as_ref()?;
 
-            match blackjack_engine::graph_interpreter::run_graph(
-                &runtime.lua_runtime.lua,
-                &jack.graph,
-                jack.graph
-                    .default_node
-                    .ok_or_else(|| godot_error!("Default node not set for this jack file."))
-                    .ok()?,
-                jack.params.clone(),
-                &runtime.lua_runtime.node_definitions,
-                None,
-            ) {
-                Ok(ProgramResult {
-                    renderable: Some(RenderableThing::HalfEdgeMesh(mesh)),
-                    ..
-                }) => {
-                    let godot_mesh = halfedge_to_godot_mesh(&mesh, materials).unwrap();
-                    Some(UpdateJackResult::Ok(godot_mesh))
+            let (result, log_lines) =
+                run_jack_graph(&runtime.lua_runtime, &jack.graph, jack.params.clone());
+
+            // Forward anything logged by the Lua code to the Godot console.
+            for line in log_lines {
+                godot_print!("{line}");
+            }
+
+            runtime.finish_update(jack_id, result, materials)
+        })
+    }
+
+    /// Same as `update_jack`, but the graph runs on a worker thread so the
+    /// main thread is not blocked. Returns immediately, and the result can be
+    /// retrieved later with `poll_jack`. If the jack is updated again before
+    /// the worker gets to it, only the most recent request runs.
+    #[method]
+    fn update_jack_async(&mut self, jack_id: JackId) -> Option<bool> {
+        Self::with_runtime(|runtime| {
+            let jack = runtime.jacks.get(jack_id)?.as_ref()?;
+            let request = JackRequest {
+                jack_id,
+                graph: jack.graph.clone(),
+                params: jack.params.clone(),
+            };
+            let library_path = runtime.library_path.clone();
+            runtime
+                .worker
+                .get_or_insert_with(|| JackWorker::spawn(library_path))
+                .send(request)
+                .map_err(|err| godot_error!("{err}"))
+                .ok()?;
+            Some(true)
+        })
+    }
+
+    /// Returns the result of the last `update_jack_async` call for this jack,
+    /// in the same format as `update_jack`. Returns null while the worker is
+    /// still running it, or when there was no pending request.
+    #[method]
+    fn poll_jack(
+        &mut self,
+        jack_id: JackId,
+        materials: Vec<Ref<Material>>,
+    ) -> Option<UpdateJackResult> {
+        Self::with_runtime(|runtime| {
+            if let Some(worker) = &runtime.worker {
+                for response in worker.try_responses() {
+                    for line in response.log_lines {
+                        godot_print!("{line}");
+                    }
+                    // The jack may have been removed meanwhile.
+                    if let Some(Some(jack)) = runtime.jacks.get_mut(response.jack_id) {
+                        jack.async_result = Some(response.result);
+                    }
                 }
-                Ok(_) => Some(UpdateJackResult::Err(
-                    "This renderable type is not supported. @Heightmap".into(),
-                )),
-                Err(err) => Some(UpdateJackResult::Err(err.to_string())),
             }
+            let result = runtime
+                .jacks
+                .get_mut(jack_id)?
+                .as_mut()?
+                .async_result
+                .take()?;
+            runtime.finish_update(jack_id, result, materials)
+        })
+    }
+
+    /// Builds a collision shape for the mesh generated by the last call to
+    /// `update_jack`. When `convex` is true, returns a `ConvexPolygonShape`
+    /// wrapping the convex hull of the mesh, which is suitable for dynamic
+    /// bodies. Otherwise, returns a `ConcavePolygonShape` with all the
+    /// triangles of the mesh.
+    #[method]
+    fn make_collision(&self, jack_id: JackId, convex: bool) -> Option<Ref<gd::Shape>> {
+        Self::with_runtime(|runtime| {
+            let jack = runtime.jacks.get(jack_id)?.as_ref()?;
+            let mesh = jack
+                .last_mesh
+                .as_ref()
+                .ok_or_else(|| godot_error!("The jack must be updated before making collisions."))
+                .ok()?;
+            halfedge_to_godot_shape(mesh, convex)
+                .map_err(|err| godot_error!("Could not generate collision shape: {err}"))
+                .ok()
         })
     }
 }
@@ -414,12 +556,19 @@ impl BlackjackApi {
 pub struct GdMeshBuffers {
     gd_verts: PoolArray<Vector3>,
     gd_uvs: PoolArray<Vector2>,
+    gd_uvs2: PoolArray<Vector2>,
     gd_normals: PoolArray<Vector3>,
+    gd_colors: PoolArray<Color>,
+    gd_tangents: PoolArray<f32>,
     gd_indices: PoolArray<i32>,
     counter: i32,
 }
 
-/// Converts a Blackjack HalfEdgeMesh into a Godot ArrayMesh
+/// Converts a Blackjack HalfEdgeMesh into a Godot ArrayMesh. Faces are split
+/// into one surface per index in the `material` face channel. Besides
+/// positions, the UVs (both the main and lightmap sets), vertex or per-corner
+/// normals and the `color` vertex channel are exported when the mesh has them,
+/// as well as tangents (see `compute_godot_tangents`).
 fn halfedge_to_godot_mesh(
     mesh: &HalfEdgeMesh,
     materials_vec: Vec<Ref<Material>>,
@@ -429,21 +578,28 @@ fn halfedge_to_godot_mesh(
     let conn = mesh.read_connectivity();
     let positions = mesh.read_positions();
     let normals = mesh.read_vertex_normals(); // TODO: No face normal support for now
+    let halfedge_normals = mesh.read_halfedge_normals();
     let uvs = mesh.read_uvs();
+    let uvs2 = mesh.read_uv_channel(UvChannel::Lightmap);
+    let colors = mesh.read_vertex_colors();
+    let tangents = compute_godot_tangents(mesh)?;
     let materials = mesh
         .channels
-        .read_channel_by_name::<FaceId, f32>("material");
+        .read_channel_by_name::<FaceId, i32>("material");
 
     for (f_id, _) in conn.iter_faces() {
         let material_idx = if let Ok(materials) = &materials {
-            materials[f_id] as i32
+            materials[f_id]
         } else {
             0
         };
         let GdMeshBuffers {
             ref mut gd_verts,
             ref mut gd_uvs,
+            ref mut gd_uvs2,
             ref mut gd_normals,
+            ref mut gd_colors,
+            ref mut gd_tangents,
             ref mut gd_indices,
             ref mut counter,
         } = surfaces.entry(material_idx).or_default();
@@ -464,12 +620,32 @@ fn halfedge_to_godot_mesh(
                 // UV y coordinate needs to be flipped in Godot meshes.
                 gd_uvs.push(Vector2::new(uv.x, -uv.y));
             }
+            if let Some(uvs2) = uvs2.as_ref() {
+                let uv = uvs2[h_id];
+                gd_uvs2.push(Vector2::new(uv.x, -uv.y));
+            }
 
-            // Normal
-            if let Some(normals) = normals.as_ref() {
+            // Normal. Per-corner normals take precedence over vertex normals.
+            if let Some(normals) = halfedge_normals.as_ref() {
+                let normal = normals[h_id];
+                gd_normals.push(Vector3::new(normal.x, normal.y, normal.z));
+            } else if let Some(normals) = normals.as_ref() {
                 let normal = normals[v_id];
                 gd_normals.push(Vector3::new(normal.x, normal.y, normal.z));
             }
+
+            // Color
+            if let Some(colors) = colors.as_ref() {
+                let color = colors[v_id];
+                gd_colors.push(Color::from_rgb(color.x, color.y, color.z));
+            }
+
+            // Tangent
+            if let Some(tangents) = tangents.as_ref() {
+                for x in tangents[h_id].to_array() {
+                    gd_tangents.push(x);
+                }
+            }
         }
 
         // Indices. Simple fan triangulation using the face vertices.
@@ -488,7 +664,10 @@ fn halfedge_to_godot_mesh(
         GdMeshBuffers {
             gd_verts,
             gd_uvs,
+            gd_uvs2,
             gd_normals,
+            gd_colors,
+            gd_tangents,
             gd_indices,
             counter: _,
         },
@@ -500,9 +679,18 @@ fn halfedge_to_godot_mesh(
         if uvs.is_some() {
             arr.set(gd::Mesh::ARRAY_TEX_UV as i32, gd_uvs);
         }
-        if normals.is_some() {
+        if uvs2.is_some() {
+            arr.set(gd::Mesh::ARRAY_TEX_UV2 as i32, gd_uvs2);
+        }
+        if normals.is_some() || halfedge_normals.is_some() {
             arr.set(gd::Mesh::ARRAY_NORMAL as i32, gd_normals);
         }
+        if colors.is_some() {
+            arr.set(gd::Mesh::ARRAY_COLOR as i32, gd_colors);
+        }
+        if tangents.is_some() {
+            arr.set(gd::Mesh::ARRAY_TANGENT as i32, gd_tangents);
+        }
         arr.set(gd::Mesh::ARRAY_INDEX as i32, gd_indices);
 
         mesh.add_surface_from_arrays(
@@ -520,6 +708,151 @@ fn halfedge_to_godot_mesh(
     Ok(mesh.into_shared())
 }
 
+/// Converts a Blackjack HalfEdgeMesh into a Godot collision shape. See
+/// `BlackjackApi::make_collision`.
+fn halfedge_to_godot_shape(mesh: &HalfEdgeMesh, convex: bool) -> Result<Ref<gd::Shape>> {
+    let to_gd = |v: Vec3| Vector3::new(v.x, v.y, v.z);
+
+    if convex {
+        let hull = edit_ops::convex_hull(mesh)?;
+        let mut points = PoolArray::new();
+        for (_, pos) in hull.read_positions().iter() {
+            points.push(to_gd(*pos));
+        }
+        let shape = gd::ConvexPolygonShape::new();
+        shape.set_points(points);
+        Ok(shape.upcast::<gd::Shape>().into_shared())
+    } else {
+        let triangulated =
+            edit_ops::triangulate(mesh, edit_ops::TriangulationStrategy::EarClipping)?;
+        let conn = triangulated.read_connectivity();
+        let positions = triangulated.read_positions();
+        let mut faces = PoolArray::new();
+        for (f_id, _) in conn.iter_faces() {
+            // NOTE: Reverse order because godot uses the other winding
+            // direction. Degenerate faces are skipped.
+            if let &[a, b, c] = conn.face_vertices(f_id).as_slice() {
+                faces.push(to_gd(positions[c]));
+                faces.push(to_gd(positions[b]));
+                faces.push(to_gd(positions[a]));
+            }
+        }
+        let shape = gd::ConcavePolygonShape::new();
+        shape.set_faces(faces);
+        Ok(shape.upcast::<gd::Shape>().into_shared())
+    }
+}
+
+/// Computes a tangent for every face corner of `mesh`, in the format Godot
+/// expects: A unit vector pointing towards increasing U, orthogonal to the
+/// corner's normal, with the handedness of the tangent frame stored in the W
+/// component.
+///
+/// The normal of each corner comes from the per-corner normals when the mesh
+/// has them (see `read_halfedge_normals`), or from the vertex normals
+/// otherwise. When the mesh has a `tangent` vertex channel, it is used as is.
+/// Otherwise, tangents are computed from the UVs, accumulating the
+/// contribution of every face around each vertex that shares the corner's
+/// normal, so tangents are split along the same edges as the normals. Returns
+/// `None` when the mesh has no normals, or has neither tangents nor UVs.
+fn compute_godot_tangents(mesh: &HalfEdgeMesh) -> Result<Option<SecondaryMap<HalfEdgeId, Vec4>>> {
+    let conn = mesh.read_connectivity();
+    let positions = mesh.read_positions();
+    let halfedge_normals = mesh.read_halfedge_normals();
+    let vertex_normals = mesh.read_vertex_normals();
+
+    // The vertex and normal of each face corner.
+    let mut corners = SecondaryMap::<HalfEdgeId, (VertexId, Vec3)>::new();
+    for (f_id, _) in conn.iter_faces() {
+        for h_id in conn.face_edges(f_id).iter_cpy() {
+            let v_id = conn.at_halfedge(h_id).vertex().try_end()?;
+            let normal = if let Some(normals) = halfedge_normals.as_ref() {
+                normals[h_id]
+            } else if let Some(normals) = vertex_normals.as_ref() {
+                normals[v_id]
+            } else {
+                return Ok(None);
+            };
+            corners.insert(h_id, (v_id, normal));
+        }
+    }
+
+    let mut tangents = SecondaryMap::new();
+    if let Ok(tangent_ch) = mesh
+        .channels
+        .read_channel_by_name::<VertexId, Vec3>("tangent")
+    {
+        for (h_id, &(v_id, n)) in &corners {
+            let t = tangent_ch[v_id];
+            tangents.insert(h_id, (t - n * n.dot(t)).normalize_or_zero().extend(1.0));
+        }
+        return Ok(Some(tangents));
+    }
+
+    let uvs = if let Some(uvs) = mesh.read_uvs() {
+        uvs
+    } else {
+        return Ok(None);
+    };
+
+    // Accumulated (tangent, bitangent) directions for each vertex. Corners of
+    // the same vertex with different normals are accumulated separately.
+    let smooth_group = |h_id: HalfEdgeId| {
+        let (v_id, normal) = corners[h_id];
+        (v_id, normal.to_array().map(f32::to_bits))
+    };
+    let mut frames = HashMap::<(VertexId, [u32; 3]), (Vec3, Vec3)>::new();
+    for (f_id, _) in conn.iter_faces() {
+        let face_corners = conn
+            .face_edges(f_id)
+            .iter_cpy()
+            .map(|h_id| {
+                // UV y coordinate is flipped in Godot meshes, so the
+                // bitangent must follow the flipped direction too.
+                let uv = uvs[h_id];
+                (h_id, positions[corners[h_id].0], Vec2::new(uv.x, -uv.y))
+            })
+            .collect_vec();
+        if face_corners.len() < 3 {
+            continue;
+        }
+
+        // Same fan triangulation used to generate the indices.
+        let (h0, p0, uv0) = face_corners[0];
+        for (&(h1, p1, uv1), &(h2, p2, uv2)) in face_corners[1..].iter().tuple_windows() {
+            let (e1, e2) = (p1 - p0, p2 - p0);
+            let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                // Degenerate UVs, the triangle doesn't define a tangent frame.
+                continue;
+            }
+            let t = (e1 * d2.y - e2 * d1.y) / det;
+            let b = (e2 * d1.x - e1 * d2.x) / det;
+            for h_id in [h0, h1, h2] {
+                let frame = frames
+                    .entry(smooth_group(h_id))
+                    .or_insert((Vec3::ZERO, Vec3::ZERO));
+                frame.0 += t;
+                frame.1 += b;
+            }
+        }
+    }
+
+    for (h_id, &(_, n)) in &corners {
+        let (t, b) = frames
+            .get(&smooth_group(h_id))
+            .copied()
+            .unwrap_or((Vec3::ZERO, Vec3::ZERO));
+        // Gram-Schmidt orthogonalization against the normal
+        let t = (t - n * n.dot(t)).normalize_or_zero();
+        let handedness = if n.cross(t).dot(b) < 0.0 { -1.0 } else { 1.0 };
+        tangents.insert(h_id, t.extend(handedness));
+    }
+
+    Ok(Some(tangents))
+}
+
 #[cfg(not(feature = "library"))]
 fn init(handle: InitHandle) {
     handle.add_tool_class::<BlackjackApi>();
@@ -69,7 +69,7 @@ GizmoHelpers.tweak_transform = function(translation_param, rotation_param, scale
                 gizmo:set_pre_rotation(inputs[opts.pre_rotation_param])
             end
             if opts.pre_scale_param ~= nil then
-                gizmo:set_pre_rotation(inputs[opts.pre_scale_param])
+                gizmo:set_pre_scale(inputs[opts.pre_scale_param])
             end
 
             return gizmo
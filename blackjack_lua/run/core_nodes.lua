@@ -49,12 +49,39 @@ local primitives = {
         gizmos = { Gz.tweak_point("center") },
         returns = "out_mesh",
     },
+    MakePlaneGrid = {
+        label = "Grid",
+        op = function(inputs)
+            return {
+                out_mesh = Primitives.grid(
+                    inputs.center,
+                    inputs.normal,
+                    inputs.size,
+                    inputs.rows,
+                    inputs.cols
+                ),
+            }
+        end,
+        inputs = {
+            P.v3("center", vector(0, 0, 0)),
+            P.v3("normal", vector(0, 1, 0)),
+            P.v3("size", vector(1, 1, 1)),
+            P.scalar_int("rows", { default = 4, min = 1, soft_max = 64 }),
+            P.scalar_int("cols", { default = 4, min = 1, soft_max = 64 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        gizmos = { Gz.tweak_point("center") },
+        returns = "out_mesh",
+    },
     MakeCircle = {
         label = "Circle",
         op = function(inputs)
             return {
                 out_mesh = Primitives.circle(
                     inputs.center,
+                    inputs.normal,
                     inputs.radius,
                     inputs.num_vertices,
                     inputs.fill == "N-Gon"
@@ -63,6 +90,7 @@ local primitives = {
         end,
         inputs = {
             P.v3("center", vector(0, 0, 0)),
+            P.v3("normal", vector(0, 1, 0)),
             P.scalar("radius", { default = 1.0, min = 0.0 }),
             P.scalar_int("num_vertices", { default = 8, min = 3, soft_max = 32 }),
             P.enum("fill", { "None", "N-Gon" }, 0),
@@ -133,6 +161,31 @@ local primitives = {
         },
         returns = "out_heightmap",
     },
+    MakeTerrainFromImage = {
+        label = "Terrain (Image)",
+        op = function(inputs)
+            return {
+                out_mesh = Primitives.terrain_from_heightmap(
+                    inputs.path,
+                    inputs.size,
+                    inputs.max_height,
+                    inputs.rows,
+                    inputs.cols
+                ),
+            }
+        end,
+        inputs = {
+            P.file("path", "open"),
+            P.v3("size", vector(10, 10, 0)),
+            P.scalar("max_height", { default = 1.0, soft_min = 0.0, soft_max = 10.0 }),
+            P.scalar_int("rows", { default = 64, min = 1, soft_max = 512 }),
+            P.scalar_int("cols", { default = 64, min = 1, soft_max = 512 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+    },
     MakeCode = {
         label = "Lua String",
         op = function(inputs)
@@ -183,10 +236,11 @@ local primitives = {
             for point in inputs.points:gmatch("([^ \n]+)") do
                 table.insert(points, V.from_string(point))
             end
-            return { out_mesh = Primitives.line_from_points(points) }
+            return { out_mesh = Primitives.line_from_points(points, inputs.mode == "Closed") }
         end,
         inputs = {
             P.strparam("points", "", true),
+            P.enum("mode", { "Open", "Closed" }, 0),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -202,7 +256,8 @@ local primitives = {
                     inputs.bottom_radius,
                     inputs.top_radius,
                     inputs.height,
-                    inputs.num_vertices
+                    inputs.num_vertices,
+                    inputs.caps ~= "None"
                 ),
             }
         end,
@@ -212,6 +267,7 @@ local primitives = {
             P.scalar("top_radius", { default = 0.0, min = 0.0 }),
             P.scalar("height", { default = 1.0, min = 0.0 }),
             P.scalar_int("num_vertices", { default = 8, min = 3, soft_max = 32 }),
+            P.enum("caps", { "N-Gon", "None" }, 0),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -227,7 +283,8 @@ local primitives = {
                     inputs.center,
                     inputs.radius,
                     inputs.height,
-                    inputs.num_vertices
+                    inputs.num_vertices,
+                    inputs.caps ~= "None"
                 ),
             }
         end,
@@ -236,6 +293,7 @@ local primitives = {
             P.scalar("radius", { default = 1.0, min = 0.0 }),
             P.scalar("height", { default = 1.0, min = 0.0 }),
             P.scalar_int("num_vertices", { default = 8, min = 3, soft_max = 32 }),
+            P.enum("caps", { "N-Gon", "None" }, 0),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -247,7 +305,12 @@ local primitives = {
         label = "Point Grid",
         op = function(inputs)
             return {
-                out_mesh = Primitives.grid(inputs.x, inputs.y, inputs.spacing_x, inputs.spacing_y),
+                out_mesh = Primitives.point_grid(
+                    inputs.x,
+                    inputs.y,
+                    inputs.spacing_x,
+                    inputs.spacing_y
+                ),
             }
         end,
         inputs = {
@@ -285,6 +348,33 @@ local primitives = {
         gizmos = { Gz.tweak_point("start_point"), Gz.tweak_point("end_point") },
         returns = "out_mesh",
     },
+    MakeHelix = {
+        label = "Helix",
+        op = function(inputs)
+            return {
+                out_mesh = Primitives.helix(
+                    inputs.turns,
+                    inputs.segments,
+                    inputs.height,
+                    inputs.radius,
+                    inputs.start_angle,
+                    inputs.direction == "Clockwise"
+                ),
+            }
+        end,
+        inputs = {
+            P.scalar("turns", { default = 3.0, min = 0.0, soft_max = 10.0 }),
+            P.scalar_int("segments", { default = 64, min = 1, soft_max = 256 }),
+            P.scalar("height", { default = 1.0, soft_min = 0.0, soft_max = 10.0 }),
+            P.scalar("radius", { default = 0.5, min = 0.0, soft_max = 10.0 }),
+            P.scalar("start_angle", { default = 0.0, soft_min = 0.0, soft_max = 360.0 }),
+            P.enum("direction", { "Counter-Clockwise", "Clockwise" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+    },
     MakeIcosahedron = {
         label = "Icosahedron",
         op = function(inputs)
@@ -312,6 +402,8 @@ local edit_ops = {
             P.mesh("in_mesh"),
             P.selection("edges"),
             P.scalar("amount", { default = 0.0, min = 0.0, soft_max = 1.0 }),
+            P.scalar_int("segments", { default = 1, min = 1, soft_max = 16 }),
+            P.scalar("profile", { default = 0.5, min = 0.0, max = 1.0 }),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -319,7 +411,7 @@ local edit_ops = {
         returns = "out_mesh",
         op = function(inputs)
             local out_mesh = inputs.in_mesh:clone()
-            Ops.bevel(inputs.edges, inputs.amount, out_mesh)
+            Ops.bevel(inputs.edges, inputs.amount, inputs.segments, inputs.profile, out_mesh)
             return { out_mesh = out_mesh }
         end,
     },
@@ -357,6 +449,73 @@ local edit_ops = {
             return { out_mesh = out_mesh }
         end,
     },
+    ExtrudeFacesIndividual = {
+        label = "Extrude Faces (Individual)",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.selection("faces"),
+            P.scalar("amount", { default = 0.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.extrude_individual(inputs.faces, inputs.amount, out_mesh)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    ExtrudeEdges = {
+        label = "Extrude Edges",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.selection("edges"),
+            P.v3("offset", vector(0, 1, 0)),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.extrude_edges(inputs.edges, inputs.offset, out_mesh)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    PokeFaces = {
+        label = "Poke Faces",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.selection("faces"),
+            P.scalar("offset", { default = 0.0, soft_min = -1.0, soft_max = 1.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.poke(inputs.faces, inputs.offset, out_mesh)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    FlipNormals = {
+        label = "Flip Normals",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.selection("faces"),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.flip_normals(inputs.faces, out_mesh)
+            return { out_mesh = out_mesh }
+        end,
+    },
     CollapseEdge = {
         label = "Collapse Edges",
         inputs = {
@@ -416,6 +575,8 @@ local edit_ops = {
         inputs = {
             P.mesh("mesh_a"),
             P.mesh("mesh_b"),
+            P.bool("weld", false),
+            P.scalar("weld_threshold", { default = 0.001, min = 0.0, soft_max = 0.1 }),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -423,7 +584,285 @@ local edit_ops = {
         returns = "out_mesh",
         op = function(inputs)
             local out_mesh = inputs.mesh_a:clone()
-            Ops.merge(out_mesh, inputs.mesh_b)
+            if inputs.weld then
+                Ops.merge_welded(out_mesh, inputs.mesh_b, inputs.weld_threshold)
+            else
+                Ops.merge(out_mesh, inputs.mesh_b)
+            end
+            return { out_mesh = out_mesh }
+        end,
+    },
+    MeshBoolean = {
+        label = "Boolean",
+        inputs = {
+            P.mesh("mesh_a"),
+            P.mesh("mesh_b"),
+            P.enum("mode", { "Union", "Intersection", "Difference" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.mesh_boolean(inputs.mesh_a, inputs.mesh_b, inputs.mode) }
+        end,
+    },
+    ConvexHull = {
+        label = "Convex Hull",
+        inputs = {
+            P.mesh("mesh"),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.convex_hull(inputs.mesh) }
+        end,
+    },
+    VoxelRemesh = {
+        label = "Voxel Remesh",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("voxel_size", { default = 0.05, min = 0.001, soft_max = 0.5 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.voxel_remesh(inputs.mesh, inputs.voxel_size) }
+        end,
+    },
+    Wireframe = {
+        label = "Wireframe",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("thickness", { default = 0.02, min = 0.0, soft_max = 0.2 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.wireframe(inputs.mesh, inputs.thickness) }
+        end,
+    },
+    Mirror = {
+        label = "Mirror",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("axis", vector(1, 0, 0)),
+            P.scalar("merge_threshold", { default = 0.001, min = 0.0, soft_max = 0.1 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.mirror(inputs.mesh, inputs.axis, inputs.merge_threshold) }
+        end,
+    },
+    FitToBox = {
+        label = "Fit To Box",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("min", vector(-1, -1, -1)),
+            P.v3("max", vector(1, 1, 1)),
+            P.bool("keep_aspect", true),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.fit_to_box(out_mesh, inputs.min, inputs.max, inputs.keep_aspect)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Symmetrize = {
+        label = "Symmetrize",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("axis", vector(1, 0, 0)),
+            P.enum("direction", { "PositiveToNegative", "NegativeToPositive" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.symmetrize(out_mesh, inputs.axis, inputs.direction)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Solidify = {
+        label = "Solidify",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("thickness", { default = 0.1, soft_min = 0.0, soft_max = 1.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.solidify(inputs.mesh, inputs.thickness) }
+        end,
+    },
+    MergeByDistance = {
+        label = "Merge By Distance",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.scalar("threshold", { default = 0.001, min = 0.0, soft_max = 0.1 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.merge_by_distance(out_mesh, inputs.threshold)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Triangulate = {
+        label = "Triangulate",
+        inputs = {
+            P.mesh("mesh"),
+            P.enum("strategy", { "EarClipping", "FanFromFirst" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.triangulate(inputs.mesh, inputs.strategy) }
+        end,
+    },
+    Decimate = {
+        label = "Decimate",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("ratio", { default = 0.5, min = 0.0, max = 1.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return { out_mesh = Ops.decimate(inputs.mesh, inputs.ratio) }
+        end,
+    },
+    Spin = {
+        label = "Spin",
+        inputs = {
+            P.mesh("profile"),
+            P.v3("axis", vector(0, 1, 0)),
+            P.v3("center", vector(0, 0, 0)),
+            P.scalar("angle", { default = 360.0, soft_min = 0.0, soft_max = 360.0 }),
+            P.scalar_int("steps", { default = 16, min = 1, soft_max = 64 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return {
+                out_mesh = Ops.spin(
+                    inputs.profile,
+                    inputs.axis,
+                    inputs.center,
+                    inputs.angle,
+                    inputs.steps
+                ),
+            }
+        end,
+    },
+    Twist = {
+        label = "Twist",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("axis", vector(0, 1, 0)),
+            P.scalar("amount", { default = 45.0, soft_min = -360.0, soft_max = 360.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.twist(out_mesh, inputs.axis, inputs.amount)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Taper = {
+        label = "Taper",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("axis", vector(0, 1, 0)),
+            P.scalar("factor", { default = 0.5, soft_min = 0.0, soft_max = 2.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.taper(out_mesh, inputs.axis, inputs.factor)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Bend = {
+        label = "Bend",
+        inputs = {
+            P.mesh("mesh"),
+            P.v3("axis", vector(0, 1, 0)),
+            P.scalar("angle", { default = 90.0, soft_min = -360.0, soft_max = 360.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.bend(out_mesh, inputs.axis, inputs.angle)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    Displace = {
+        label = "Displace",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("amount", { default = 0.1, soft_min = -1.0, soft_max = 1.0 }),
+            P.scalar("frequency", { default = 1.0, min = 0.0, soft_max = 10.0 }),
+            P.scalar_int("seed", { default = 0, min = 0, soft_max = 100 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.displace(out_mesh, inputs.amount, inputs.frequency, inputs.seed)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    ShrinkFatten = {
+        label = "Shrink/Fatten",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar("amount", { default = 0.1, soft_min = -1.0, soft_max = 1.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.shrink_fatten(out_mesh, inputs.amount)
             return { out_mesh = out_mesh }
         end,
     },
@@ -470,6 +909,23 @@ local edit_ops = {
             return { out_mesh = out_mesh }
         end,
     },
+    LoopCut = {
+        label = "Loop Cut",
+        inputs = {
+            P.mesh("in_mesh"),
+            P.selection("edges"),
+            P.scalar_int("num_cuts", { default = 1, min = 1, soft_max = 16 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.in_mesh:clone()
+            Ops.loop_cut(out_mesh, inputs.edges, inputs.num_cuts)
+            return { out_mesh = out_mesh }
+        end,
+    },
     CutFace = {
         label = "Cut Face",
         inputs = {
@@ -491,7 +947,9 @@ local edit_ops = {
         label = "Set Normals",
         inputs = {
             P.mesh("mesh"),
-            P.enum("normals", { "smooth", "flat" }, 0),
+            P.enum("normals", { "smooth", "flat", "auto" }, 0),
+            P.enum("weighting", { "Uniform", "Area", "Angle" }, 0),
+            P.scalar("auto_angle", { default = 30.0, min = 0.0, max = 180.0 }),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -500,7 +958,9 @@ local edit_ops = {
         op = function(inputs)
             local out_mesh = inputs.mesh:clone()
             if inputs.normals == "smooth" then
-                Ops.set_smooth_normals(out_mesh)
+                Ops.set_smooth_normals(out_mesh, inputs.weighting)
+            elseif inputs.normals == "auto" then
+                Ops.auto_smooth(out_mesh, inputs.auto_angle)
             else
                 Ops.set_flat_normals(out_mesh)
             end
@@ -545,10 +1005,62 @@ local edit_ops = {
             return { out_mesh = out_mesh }
         end,
     },
+    RecalculateNormals = {
+        label = "Recalculate Normals",
+        inputs = {
+            P.mesh("mesh"),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.recalculate_normals(out_mesh)
+            return { out_mesh = out_mesh }
+        end,
+    },
     SetFullRangeUVs = {
         label = "Set Full Range UVs",
         inputs = {
             P.mesh("mesh"),
+            P.enum("uv_channel", { "uv", "uv_lightmap" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.set_full_range_uvs(out_mesh, inputs.uv_channel)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    ProjectUVs = {
+        label = "Project UVs",
+        inputs = {
+            P.mesh("mesh"),
+            P.enum("mode", { "Planar", "Cylindrical", "Spherical" }, 0),
+            P.v3("axis", vector(0, 1, 0)),
+            P.v3("scale", vector(1, 1, 0)),
+            P.enum("uv_channel", { "uv", "uv_lightmap" }, 0),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.project_uvs(out_mesh, inputs.mode, inputs.axis, inputs.scale, inputs.uv_channel)
+            return { out_mesh = out_mesh }
+        end,
+    },
+    MarkSharp = {
+        label = "Mark Sharp",
+        inputs = {
+            P.mesh("mesh"),
+            P.selection("edges"),
+            P.bool("value", true),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -556,7 +1068,7 @@ local edit_ops = {
         returns = "out_mesh",
         op = function(inputs)
             local out_mesh = inputs.mesh:clone()
-            Ops.set_full_range_uvs(out_mesh)
+            Ops.mark_sharp(out_mesh, inputs.edges, inputs.value)
             return { out_mesh = out_mesh }
         end,
     },
@@ -577,6 +1089,23 @@ local edit_ops = {
             return { out_mesh = out_mesh }
         end,
     },
+    SetVertexColor = {
+        label = "Set Vertex Color",
+        inputs = {
+            P.mesh("mesh"),
+            P.selection("vertices"),
+            P.v3("color", vector(1, 1, 1)),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            local out_mesh = inputs.mesh:clone()
+            Ops.set_vertex_color(out_mesh, inputs.vertices, inputs.color)
+            return { out_mesh = out_mesh }
+        end,
+    },
     MakeGroup = {
         label = "Group",
         inputs = {
@@ -667,6 +1196,54 @@ local edit_ops = {
         },
         returns = "out_mesh",
     },
+    RadialArray = {
+        label = "Radial Array",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar_int("count", { default = 8, min = 1, soft_max = 64 }),
+            P.v3("axis", vector(0, 1, 0)),
+            P.v3("center", vector(0, 0, 0)),
+            P.scalar("angle", { default = 360.0, soft_min = 0.0, soft_max = 360.0 }),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return {
+                out_mesh = Ops.radial_array(
+                    inputs.mesh,
+                    inputs.count,
+                    inputs.axis,
+                    inputs.center,
+                    inputs.angle
+                ),
+            }
+        end,
+    },
+    LinearArray = {
+        label = "Linear Array",
+        inputs = {
+            P.mesh("mesh"),
+            P.scalar_int("count", { default = 4, min = 1, soft_max = 64 }),
+            P.v3("offset", vector(1, 0, 0)),
+            P.bool("relative", true),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            return {
+                out_mesh = Ops.linear_array(
+                    inputs.mesh,
+                    inputs.count,
+                    inputs.offset,
+                    inputs.relative
+                ),
+            }
+        end,
+    },
     ExtrudeAlongCurve = {
         label = "Extrude Along Curve",
         op = function(inputs)
@@ -674,7 +1251,9 @@ local edit_ops = {
                 out_mesh = Ops.extrude_along_curve(
                     inputs.backbone,
                     inputs.cross_section,
-                    inputs.flip
+                    inputs.flip,
+                    inputs.twist,
+                    inputs.scale_channel
                 ),
             }
         end,
@@ -682,6 +1261,8 @@ local edit_ops = {
             P.mesh("backbone"),
             P.mesh("cross_section"),
             P.scalar_int("flip", { default = 0.0, min = 0.0, soft_max = 4.0 }),
+            P.scalar("twist", { default = 0.0, soft_min = -360.0, soft_max = 360.0 }),
+            P.strparam("scale_channel", "size"),
         },
         outputs = {
             P.mesh("out_mesh"),
@@ -906,6 +1487,32 @@ local export = {
             HalfEdgeMesh.to_wavefront_obj(inputs.mesh, inputs.path)
         end,
     },
+    ExportGltf = {
+        label = "Export glTF",
+        inputs = {
+            P.mesh("mesh"),
+            P.file("path"),
+            P.enum("format", { "Binary (.glb)", "Text (.gltf)" }, 0),
+        },
+        outputs = {},
+        executable = true,
+        op = function(inputs)
+            Export.gltf(inputs.mesh, inputs.path, inputs.format == "Binary (.glb)")
+        end,
+    },
+    ExportStl = {
+        label = "Export STL",
+        inputs = {
+            P.mesh("mesh"),
+            P.file("path"),
+            P.enum("format", { "Binary", "ASCII" }, 0),
+        },
+        outputs = {},
+        executable = true,
+        op = function(inputs)
+            Export.stl(inputs.mesh, inputs.path, inputs.format == "Binary")
+        end,
+    },
     ImportObj = {
         label = "Import OBJ",
         inputs = {
@@ -967,6 +1574,102 @@ local misc = {
             }
         end,
     },
+    Switch = {
+        label = "Switch",
+        doc = [[
+            Outputs one of two meshes, depending on the condition. Only the
+            selected mesh is computed, the nodes for the other one don't run.
+        ]],
+        inputs = {
+            P.bool("condition", true),
+            P.mesh("if_true"),
+            P.mesh("if_false"),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        lazy_inputs = { "if_true", "if_false" },
+        select_lazy_inputs = function(inputs)
+            if inputs.condition then
+                return { "if_true" }
+            else
+                return { "if_false" }
+            end
+        end,
+        op = function(inputs)
+            if inputs.condition then
+                return { out_mesh = inputs.if_true }
+            else
+                return { out_mesh = inputs.if_false }
+            end
+        end,
+    },
+    Measure = {
+        label = "Measure",
+        doc = [[
+            Shows the distance between the `from` and `to` vertices in the
+            viewport. When `angle_to` is set, also shows the angle at `to`
+            between the segments towards `from` and `angle_to`. Selections with
+            more than one vertex are measured from their midpoint. Nothing is
+            shown while the `from` or `to` selections are empty.
+
+            The mesh is passed through unchanged.
+        ]],
+        inputs = {
+            P.mesh("mesh"),
+            P.selection("from"),
+            P.selection("to"),
+            P.selection("angle_to"),
+        },
+        outputs = {
+            P.mesh("out_mesh"),
+        },
+        returns = "out_mesh",
+        op = function(inputs)
+            if inputs.__gizmos_enabled ~= nil then
+                local midpoint = function(param)
+                    local vertices = inputs.mesh:resolve_vertex_selection_full(inputs[param])
+                    local sum = vector(0, 0, 0)
+                    for _, vertex in vertices do
+                        sum = sum + inputs.mesh:vertex_position(vertex)
+                    end
+                    if #vertices == 0 then
+                        return nil
+                    end
+                    return sum / #vertices
+                end
+                inputs.measure_from = midpoint("from")
+                inputs.measure_to = midpoint("to")
+                inputs.measure_angle_to = midpoint("angle_to")
+            end
+            return { out_mesh = inputs.mesh }
+        end,
+        gizmos = {
+            {
+                -- The measure gizmo is read-only, so it never changes params
+                update_params = function(inputs, _gizmo)
+                    return inputs
+                end,
+                update_gizmos = function(inputs, _gizmo, _outputs)
+                    if inputs.measure_from == nil or inputs.measure_to == nil then
+                        return nil
+                    elseif inputs.measure_angle_to ~= nil then
+                        return MeasureGizmo.angle(
+                            inputs.measure_from,
+                            inputs.measure_to,
+                            inputs.measure_angle_to
+                        )
+                    else
+                        return MeasureGizmo.distance(inputs.measure_from, inputs.measure_to)
+                    end
+                end,
+                affected_params = function()
+                    return nil
+                end,
+            },
+        },
+    },
 }
 
 NodeLibrary:addNodes(primitives)
@@ -14,7 +14,7 @@ use crate::{
         point_cloud_routine::PointCloudRoutine, wireframe_routine::WireframeRoutine,
     },
 };
-use blackjack_engine::lua_engine::LuaRuntime;
+use blackjack_engine::lua_engine::{lua_stdlib::library_folders_from_env, LogBuffer, LuaRuntime};
 use egui_wgpu::renderer::{RenderPass, ScreenDescriptor};
 use winit::window::Window;
 
@@ -37,6 +37,9 @@ pub struct RootViewport {
     offscreen_viewports: HashMap<OffscreenViewport, AppViewport>,
     inspector_tabs: InspectorTabs,
     diagnostics_open: bool,
+    /// The lines logged by Lua code, shown in the console panel.
+    console: LogBuffer,
+    console_open: bool,
     lua_runtime: LuaRuntime,
     mouse_captured_by_split: bool,
 }
@@ -77,6 +80,10 @@ pub mod inspector;
 /// highlighting support
 pub mod code_viewer;
 
+/// The maximum number of lines kept in the Lua console. Older lines are
+/// discarded.
+const MAX_CONSOLE_LINES: usize = 1000;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum OffscreenViewport {
     GraphEditor,
@@ -115,8 +122,13 @@ impl RootViewport {
         egui_winit_state.set_max_texture_side(renderer.limits.max_texture_dimension_2d as usize);
         egui_winit_state.set_pixels_per_point(scale_factor as f32);
 
-        // TODO: Hardcoded node libraries path. Read from cmd line?
-        let mut lua_runtime = LuaRuntime::initialize_with_std("./blackjack_lua/".into())
+        // The built-in node libraries come first, so that user libraries
+        // cannot shadow the core ones when using `require`.
+        let search_folders = std::iter::once("./blackjack_lua/".to_owned())
+            .chain(CLI_ARGS.library_path.iter().cloned())
+            .chain(library_folders_from_env())
+            .collect();
+        let mut lua_runtime = LuaRuntime::initialize_with_std(search_folders)
             .unwrap_or_else(|err| panic!("Init lua should not fail. {err}"));
         if !CLI_ARGS.disable_lua_watcher {
             lua_runtime
@@ -146,6 +158,8 @@ impl RootViewport {
             offscreen_viewports,
             inspector_tabs: InspectorTabs::new(),
             diagnostics_open: false,
+            console: LogBuffer::new(MAX_CONSOLE_LINES),
+            console_open: true,
             lua_runtime,
             mouse_captured_by_split: false,
         }
@@ -214,7 +228,9 @@ impl RootViewport {
             match self.lua_runtime.watch_for_changes() {
                 Ok(true) => {
                     if let Err(err) = self.graph_editor.on_node_definitions_update() {
-                        println!("Error while updating graph after Lua code reload: {err}.");
+                        self.console.push(format!(
+                            "Error while updating graph after Lua code reload: {err}."
+                        ));
                     }
 
                     // Reset gizmo state when code is reloaded. This helps
@@ -224,7 +240,8 @@ impl RootViewport {
                 }
                 Ok(false) => { /* Do nothing */ }
                 Err(err) => {
-                    println!("Error while reloading Lua code: {err}.");
+                    self.console
+                        .push(format!("Error while reloading Lua code: {err}."));
                 }
             }
         }
@@ -247,6 +264,12 @@ impl RootViewport {
             actions.push(menubar_action);
         }
 
+        // NOTE: The console is drawn before the central panel, so that the
+        // panel gets the remaining space.
+        self.console
+            .extend(self.lua_runtime.log_receiver.try_iter());
+        self.console_ui();
+
         egui::CentralPanel::default().show(&self.egui_context.clone(), |ui| {
             let mut split_tree = self.app_context.split_tree.clone();
 
@@ -296,6 +319,10 @@ impl RootViewport {
                 )?;
                 self.graph_editor.editor_state = editor_state;
                 self.graph_editor.custom_state = custom_state;
+                self.graph_editor.reset_undo_history();
+            }
+            AppRootAction::GroupSelected { as_loop } => {
+                self.graph_editor.group_selected(as_loop);
             }
         }
         Ok(())
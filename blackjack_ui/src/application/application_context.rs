@@ -9,7 +9,7 @@ use crate::prelude::*;
 use anyhow::Error;
 
 use blackjack_engine::graph::BjkGraph;
-use blackjack_engine::graph_interpreter::ExternalParameterValues;
+use blackjack_engine::graph_interpreter::{ExternalParameterValues, NodeOutputCache};
 use blackjack_engine::prelude::ChannelKeyType;
 use blackjack_engine::{
     lua_engine::{LuaRuntime, RenderableThing},
@@ -48,6 +48,9 @@ pub struct ApplicationContext {
     /// partition the state either horizontally or vertically. This separation
     /// is dynamic, very similar to Blender's UI model
     pub split_tree: SplitTree,
+    /// The outputs of the nodes from previous runs of the graph. The graph
+    /// runs every frame, but only nodes whose inputs changed run again.
+    pub output_cache: NodeOutputCache,
 }
 
 impl ApplicationContext {
@@ -57,6 +60,7 @@ impl ApplicationContext {
             current_selection: None,
             node_gizmo_states: gizmo_states,
             split_tree: SplitTree::default_tree(),
+            output_cache: NodeOutputCache::default(),
         }
     }
 
@@ -105,24 +109,37 @@ impl ApplicationContext {
         render_ctx: &mut RenderContext,
         viewport_settings: &Viewport3dSettings,
     ) -> Result<()> {
+        if let Err(err) = render_ctx.face_routine.set_custom_matcap(
+            &render_ctx.renderer,
+            viewport_settings.custom_matcap.as_deref(),
+        ) {
+            println!("Error: {err:?}");
+        }
+
         match self.renderable_thing.as_mut() {
             Some(RenderableThing::HalfEdgeMesh(mesh)) => {
                 // Base mesh
                 {
+                    let materials = viewport_settings.material_colors;
                     if let Some(VertexIndexBuffers {
                         positions,
                         normals,
+                        colors,
                         indices,
                     }) = match viewport_settings.face_mode {
                         FaceDrawMode::Real => {
                             if mesh.gen_config.smooth_normals {
-                                Some(mesh.generate_triangle_buffers_smooth(false)?)
+                                Some(mesh.generate_triangle_buffers_smooth(false, materials)?)
                             } else {
-                                Some(mesh.generate_triangle_buffers_flat(false)?)
+                                Some(mesh.generate_triangle_buffers_flat(false, materials)?)
                             }
                         }
-                        FaceDrawMode::Flat => Some(mesh.generate_triangle_buffers_flat(true)?),
-                        FaceDrawMode::Smooth => Some(mesh.generate_triangle_buffers_smooth(true)?),
+                        FaceDrawMode::Flat => {
+                            Some(mesh.generate_triangle_buffers_flat(true, materials)?)
+                        }
+                        FaceDrawMode::Smooth => {
+                            Some(mesh.generate_triangle_buffers_smooth(true, materials)?)
+                        }
                         FaceDrawMode::NoDraw => None,
                     } {
                         if !positions.is_empty() {
@@ -130,7 +147,9 @@ impl ApplicationContext {
                                 &render_ctx.renderer,
                                 &positions,
                                 &normals,
+                                &colors,
                                 &indices,
+                                !viewport_settings.backface_culling,
                             );
                         }
                     }
@@ -176,13 +195,32 @@ impl ApplicationContext {
                     }
                 }
 
+                // Vertex normals
+                if viewport_settings.render_normals {
+                    let LineBuffers { positions, colors } =
+                        mesh.generate_vertex_normal_buffers(viewport_settings.normals_length)?;
+                    if !positions.is_empty() {
+                        render_ctx.wireframe_routine.add_wireframe(
+                            &render_ctx.renderer.device,
+                            &positions,
+                            &colors,
+                        )
+                    }
+                }
+
                 // Vertices
                 {
-                    let PointBuffers { positions } = mesh.generate_point_buffers();
+                    let PointBuffers { positions, sizes } = mesh.generate_point_buffers();
+                    let sizes = sizes
+                        .iter()
+                        .map(|size| size * viewport_settings.point_size)
+                        .collect_vec();
                     if !positions.is_empty() {
-                        render_ctx
-                            .point_cloud_routine
-                            .add_point_cloud(&render_ctx.renderer.device, &positions);
+                        render_ctx.point_cloud_routine.add_point_cloud(
+                            &render_ctx.renderer.device,
+                            &positions,
+                            &sizes,
+                        );
                     }
                 }
             }
@@ -190,6 +228,7 @@ impl ApplicationContext {
                 let VertexIndexBuffers {
                     positions,
                     normals,
+                    colors,
                     indices,
                 } = heightmap.generate_triangle_buffers();
 
@@ -198,7 +237,9 @@ impl ApplicationContext {
                         &render_ctx.renderer,
                         &positions,
                         &normals,
+                        &colors,
                         &indices,
+                        !viewport_settings.backface_culling,
                     );
                 }
             }
@@ -250,13 +291,15 @@ impl ApplicationContext {
             let (bjk_graph, mapping, params) =
                 self.generate_bjk_graph(&editor_state.graph, custom_state)?;
             let gizmos = self.node_gizmo_states.to_bjk_data(&mapping);
-            let program_result = blackjack_engine::graph_interpreter::run_graph(
+            let program_result = blackjack_engine::graph_interpreter::run_graph_cached(
                 &lua_runtime.lua,
                 &bjk_graph,
                 mapping[active],
                 params,
                 &lua_runtime.node_definitions,
                 Some(gizmos),
+                &mut self.output_cache,
+                None,
             )?;
 
             self.renderable_thing = program_result.renderable;
@@ -307,6 +350,7 @@ impl ApplicationContext {
                 params,
                 &lua_runtime.node_definitions,
                 None,
+                None,
             )?;
         }
         Ok(())
@@ -18,7 +18,10 @@ use egui_node_graph::{Node, NodeId};
 use glam::Mat4;
 use slotmap::SecondaryMap;
 
-use crate::{graph::graph_interop::NodeMapping, prelude::graph::NodeData};
+use crate::{
+    app_window::gui_overlay::project_point, graph::graph_interop::NodeMapping,
+    prelude::graph::NodeData,
+};
 
 use super::viewport_3d::Viewport3d;
 
@@ -126,6 +129,54 @@ pub fn draw_gizmo_ui_viewport(
                 transform_gizmo.set_from_matrix(updated_matrix);
             }
         }
+        BlackjackGizmo::Measure(measure_gizmo) => {
+            if has_focus {
+                ui.allocate_ui_at_rect(viewport.viewport_rect().shrink(10.0), |ui| {
+                    gizmo_label(ui);
+                    ui.label(format!("Distance: {:.4}", measure_gizmo.length()));
+                    if let Some(angle) = measure_gizmo.angle_degrees() {
+                        ui.label(format!("Angle: {angle:.2}°"));
+                    }
+                });
+            }
+
+            let view_proj = viewport.view_proj_matrix();
+            let rect = viewport.viewport_rect();
+            let project = |p| project_point(&view_proj, rect, p);
+            let painter = ui.painter_at(rect);
+            let color = if has_focus {
+                egui::Color32::YELLOW
+            } else {
+                egui::Color32::from_rgba_unmultiplied(255, 255, 0, 150)
+            };
+            let stroke = egui::Stroke::new(2.0, color);
+            let text = |point: egui::Pos2, text: String| {
+                painter.text(
+                    point,
+                    egui::Align2::CENTER_BOTTOM,
+                    text,
+                    egui::FontId::default(),
+                    color,
+                );
+            };
+
+            let (a, b) = (project(measure_gizmo.a), project(measure_gizmo.b));
+            painter.line_segment([a, b], stroke);
+            painter.circle_filled(a, 3.0, color);
+            painter.circle_filled(b, 3.0, color);
+            text(a.lerp(b, 0.5), format!("{:.4}", measure_gizmo.length()));
+
+            if let (Some(c_pos), Some(angle)) = (measure_gizmo.c, measure_gizmo.angle_degrees()) {
+                let c = project(c_pos);
+                painter.line_segment([b, c], stroke);
+                painter.circle_filled(c, 3.0, color);
+                text(
+                    b.lerp(c, 0.5),
+                    format!("{:.4}", measure_gizmo.b.distance(c_pos)),
+                );
+                text(b, format!("{angle:.2}°"));
+            }
+        }
         BlackjackGizmo::None => {}
     }
 
@@ -4,10 +4,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::collections::{BTreeMap, VecDeque};
+
 use crate::{
     app_window::input::viewport_relative_position,
     prelude::{
-        graph::{data_type_to_input_param_kind, default_shown_inline, DataTypeUi, ValueTypeUi},
+        graph::{
+            data_type_to_input_param_kind, default_shown_inline, DataTypeUi, InputId, NodeId,
+            ValueTypeUi,
+        },
         *,
     },
 };
@@ -15,8 +20,10 @@ use blackjack_engine::graph::{
     serialization::SerializedBjkSnippet, BlackjackValue, DataType, NodeDefinitions,
 };
 use egui_wgpu::renderer::{RenderPass, ScreenDescriptor};
+use slotmap::SecondaryMap;
 
 use super::{blackjack_theme, gizmo_ui::UiNodeGizmoStates};
+use crate::graph::graph_interop;
 
 pub struct GraphEditor {
     pub editor_state: graph::GraphEditorState,
@@ -38,6 +45,190 @@ pub struct GraphEditor {
     pub pending_paste_operation: Option<SerializedBjkSnippet>,
     /// Allows ignoring the potentially unsafe paste confirmation dialog.
     pub skip_pending_paste_check: bool,
+    /// The recorded changes to the graph, for undo and redo.
+    pub undo_history: UndoHistory,
+}
+
+/// The maximum number of steps that can be undone.
+const MAX_UNDO_STEPS: usize = 100;
+
+/// A copy of the parts of the graph editor state that are restored on undo and
+/// redo.
+#[derive(Clone)]
+pub struct GraphSnapshot {
+    graph: graph::Graph,
+    node_positions: SecondaryMap<NodeId, egui::Pos2>,
+    node_order: Vec<NodeId>,
+    promoted_params: HashMap<InputId, String>,
+    selection_sets: BTreeMap<String, String>,
+}
+
+impl GraphSnapshot {
+    fn take(
+        editor_state: &graph::GraphEditorState,
+        custom_state: &graph::CustomGraphState,
+    ) -> Self {
+        Self {
+            graph: editor_state.graph.clone(),
+            node_positions: editor_state.node_positions.clone(),
+            node_order: editor_state.node_order.clone(),
+            promoted_params: custom_state.promoted_params.clone(),
+            selection_sets: custom_state.selection_sets.clone(),
+        }
+    }
+
+    fn restore(
+        self,
+        editor_state: &mut graph::GraphEditorState,
+        custom_state: &mut graph::CustomGraphState,
+    ) {
+        let GraphSnapshot {
+            graph,
+            node_positions,
+            node_order,
+            promoted_params,
+            selection_sets,
+        } = self;
+
+        for (node_id, _) in &editor_state.graph.nodes {
+            if !graph.nodes.contains_key(node_id) {
+                custom_state.gizmo_states.node_deleted(node_id);
+            }
+        }
+
+        // Moving nodes around is not recorded in the history, so nodes that
+        // are still present are left where they are.
+        editor_state.node_positions = node_positions
+            .into_iter()
+            .map(|(node_id, pos)| {
+                let pos = editor_state
+                    .node_positions
+                    .get(node_id)
+                    .copied()
+                    .unwrap_or(pos);
+                (node_id, pos)
+            })
+            .collect();
+        editor_state.node_order = node_order;
+        editor_state
+            .selected_nodes
+            .retain(|node_id| graph.nodes.contains_key(*node_id));
+        editor_state.connection_in_progress = None;
+        editor_state.graph = graph;
+
+        custom_state.promoted_params = promoted_params;
+        custom_state.selection_sets = selection_sets;
+        let graph = &editor_state.graph;
+        if custom_state
+            .active_node
+            .map_or(false, |n| !graph.nodes.contains_key(n))
+        {
+            custom_state.active_node = None;
+        }
+        if custom_state
+            .run_side_effect
+            .map_or(false, |n| !graph.nodes.contains_key(n))
+        {
+            custom_state.run_side_effect = None;
+        }
+    }
+}
+
+/// Records the changes made to the graph in the editor, so they can be undone
+/// and redone. Each step in the history is a snapshot of the graph, taken
+/// right after a change: Spawning or deleting nodes, changing connections or
+/// editing parameter values.
+pub struct UndoHistory {
+    /// The state of the graph after the last recorded change.
+    current: GraphSnapshot,
+    undo_stack: VecDeque<GraphSnapshot>,
+    redo_stack: Vec<GraphSnapshot>,
+}
+
+impl UndoHistory {
+    pub fn new(
+        editor_state: &graph::GraphEditorState,
+        custom_state: &graph::CustomGraphState,
+    ) -> Self {
+        Self {
+            current: GraphSnapshot::take(editor_state, custom_state),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    /// Records the current state of the graph as a new step in the history.
+    /// Any undone steps can no longer be redone after this.
+    pub fn record(
+        &mut self,
+        editor_state: &graph::GraphEditorState,
+        custom_state: &graph::CustomGraphState,
+    ) {
+        let previous = std::mem::replace(
+            &mut self.current,
+            GraphSnapshot::take(editor_state, custom_state),
+        );
+        self.undo_stack.push_back(previous);
+        if self.undo_stack.len() > MAX_UNDO_STEPS {
+            self.undo_stack.pop_front();
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Records a new step if any parameter value or selection set was edited
+    /// since the last recorded step. Value edits are not reported by the node
+    /// graph, so they are detected by comparing against the last snapshot.
+    pub fn record_value_edits(
+        &mut self,
+        editor_state: &graph::GraphEditorState,
+        custom_state: &graph::CustomGraphState,
+    ) {
+        let old_inputs = &self.current.graph.inputs;
+        let edited = editor_state.graph.inputs.iter().any(|(input_id, input)| {
+            old_inputs
+                .get(input_id)
+                .map_or(false, |old_input| old_input.value.0 != input.value.0)
+        });
+        if edited || self.current.selection_sets != custom_state.selection_sets {
+            self.record(editor_state, custom_state);
+        }
+    }
+
+    /// Restores the graph to the state before the last recorded change.
+    /// Returns false when there was nothing to undo.
+    pub fn undo(
+        &mut self,
+        editor_state: &mut graph::GraphEditorState,
+        custom_state: &mut graph::CustomGraphState,
+    ) -> bool {
+        // Make sure pending value edits can be undone too.
+        self.record_value_edits(editor_state, custom_state);
+        if let Some(previous) = self.undo_stack.pop_back() {
+            let current = std::mem::replace(&mut self.current, previous.clone());
+            self.redo_stack.push(current);
+            previous.restore(editor_state, custom_state);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Restores the graph to the state before the last undo. Returns false
+    /// when there was nothing to redo.
+    pub fn redo(
+        &mut self,
+        editor_state: &mut graph::GraphEditorState,
+        custom_state: &mut graph::CustomGraphState,
+    ) -> bool {
+        if let Some(next) = self.redo_stack.pop() {
+            let current = std::mem::replace(&mut self.current, next.clone());
+            self.undo_stack.push_back(current);
+            next.restore(editor_state, custom_state);
+            true
+        } else {
+            false
+        }
+    }
 }
 
 pub fn blackjack_graph_theme() -> egui::Visuals {
@@ -64,10 +255,14 @@ impl GraphEditor {
         egui_winit_state.set_max_texture_side(renderer.limits.max_texture_dimension_2d as usize);
         egui_winit_state.set_pixels_per_point(1.0);
 
+        // Set default zoom to the inverse of ui scale to preserve dpi
+        let editor_state = graph::GraphEditorState::new(1.0 / parent_scale);
+        let custom_state = graph::CustomGraphState::new(node_definitions, gizmo_states);
+        let undo_history = UndoHistory::new(&editor_state, &custom_state);
+
         Self {
-            // Set default zoom to the inverse of ui scale to preserve dpi
-            editor_state: graph::GraphEditorState::new(1.0 / parent_scale),
-            custom_state: graph::CustomGraphState::new(node_definitions, gizmo_states),
+            editor_state,
+            custom_state,
             egui_context,
             egui_winit_state,
             renderpass: RenderPass::new(&renderer.device, format, 1),
@@ -79,6 +274,66 @@ impl GraphEditor {
             previous_clipboard_contents: String::new(),
             pending_paste_operation: None,
             skip_pending_paste_check: false,
+            undo_history,
+        }
+    }
+
+    /// Discards the undo history. Used when the whole graph is replaced, for
+    /// instance after loading a file.
+    pub fn reset_undo_history(&mut self) {
+        self.undo_history = UndoHistory::new(&self.editor_state, &self.custom_state);
+    }
+
+    /// Handles the undo (Ctrl+Z) and redo (Ctrl+Shift+Z) shortcuts. Ignored
+    /// while a text field has focus, so it can handle its own shortcuts.
+    fn handle_undo_shortcuts(&mut self) {
+        if self.egui_context.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = {
+            let input = self.egui_context.input();
+            let pressed = input.key_pressed(egui::Key::Z) && input.modifiers.ctrl;
+            (
+                pressed && !input.modifiers.shift,
+                pressed && input.modifiers.shift,
+            )
+        };
+
+        let restored = if undo {
+            self.undo_history
+                .undo(&mut self.editor_state, &mut self.custom_state)
+        } else if redo {
+            self.undo_history
+                .redo(&mut self.editor_state, &mut self.custom_state)
+        } else {
+            false
+        };
+
+        // The restored graph may predate the last hot reload.
+        if restored {
+            if let Err(err) = self.on_node_definitions_update() {
+                println!("Error: Could not update restored graph: {err:?}");
+            }
+        }
+    }
+
+    /// Replaces the selected nodes with a subgraph node containing them, or a
+    /// loop node when `as_loop` is set. See `graph_interop::group_nodes`.
+    pub fn group_selected(&mut self, as_loop: bool) {
+        if self.editor_state.selected_nodes.is_empty() {
+            return;
+        }
+        let selected_nodes = self.editor_state.selected_nodes.clone();
+        match graph_interop::group_nodes(
+            &mut self.editor_state,
+            &mut self.custom_state,
+            &selected_nodes,
+            as_loop,
+        ) {
+            Ok(_) => self
+                .undo_history
+                .record(&self.editor_state, &self.custom_state),
+            Err(err) => println!("Error: Could not group nodes: {err:?}"),
         }
     }
 
@@ -218,6 +473,7 @@ impl GraphEditor {
         self.egui_context.begin_frame(egui_input);
 
         graph::draw_node_graph(self);
+        self.handle_undo_shortcuts();
 
         // Debug mouse pointer position
         // -- This is useful when mouse events are not being interpreted correctly.
@@ -386,6 +642,11 @@ impl GraphEditor {
         let mut delayed_ops = vec![];
 
         for (node_id, node) in &graph.nodes {
+            // Subgraph nodes don't have a node definition. Their parameters
+            // are set when the nodes are grouped.
+            if node.user_data.subgraph.is_some() {
+                continue;
+            }
             if let Some(node_def) = node_defs.node_def(&node.user_data.op_name) {
                 if node.label != node_def.label {
                     delayed_ops.push(DelayedOps::NodeLabelRenamed {
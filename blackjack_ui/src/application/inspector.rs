@@ -20,6 +20,7 @@ use slotmap::Key;
 pub enum InspectorTab {
     Properties,
     Spreadsheet,
+    SelectionSets,
     Debug,
 }
 
@@ -27,6 +28,7 @@ pub struct InspectorTabs {
     current_view: InspectorTab,
     properties: PropertiesTab,
     spreadsheet: SpreadsheetTab,
+    selection_sets: SelectionSetsTab,
     debug: DebugTab,
 }
 
@@ -39,6 +41,10 @@ impl InspectorTabs {
             },
             spreadsheet: SpreadsheetTab {
                 current_view: SpreadsheetViews::Vertices,
+                sort: None,
+            },
+            selection_sets: SelectionSetsTab {
+                new_set_name: String::new(),
             },
             debug: DebugTab {
                 mesh_element: ChannelKeyType::VertexId,
@@ -74,6 +80,17 @@ pub enum SpreadsheetViews {
 
 pub struct SpreadsheetTab {
     pub current_view: SpreadsheetViews,
+    /// When set, rows are sorted by the values of this column.
+    pub sort: Option<SpreadsheetSort>,
+}
+
+pub struct SpreadsheetSort {
+    pub column: String,
+    pub descending: bool,
+}
+
+pub struct SelectionSetsTab {
+    pub new_set_name: String,
 }
 
 pub struct DebugTab {
@@ -104,6 +121,11 @@ impl InspectorTabs {
                         InspectorTab::Spreadsheet,
                         "Spreadsheet",
                     );
+                    ui.selectable_value(
+                        &mut self.current_view,
+                        InspectorTab::SelectionSets,
+                        "Sets",
+                    );
                     ui.selectable_value(&mut self.current_view, InspectorTab::Debug, "Debug");
                 });
                 ui.separator();
@@ -111,6 +133,7 @@ impl InspectorTabs {
                 match self.current_view {
                     InspectorTab::Properties => self.properties.ui(ui, editor_state, custom_state),
                     InspectorTab::Spreadsheet => self.spreadsheet.ui(ui, Some(mesh)),
+                    InspectorTab::SelectionSets => self.selection_sets.ui(ui, custom_state),
                     InspectorTab::Debug => self.debug.ui(ui, Some(mesh)),
                 }
             }
@@ -173,12 +196,27 @@ impl PropertiesTab {
         self.maybe_show_new_promoted_modal(ui.ctx(), editor_state, custom_state);
 
         let graph = &mut editor_state.graph;
-        if !editor_state.selected_nodes.is_empty() {
+        // When no nodes are selected, the inspector shows the active node,
+        // which is the one currently displayed in the viewport.
+        let nodes = if !editor_state.selected_nodes.is_empty() {
+            editor_state
+                .selected_nodes
+                .iter_cpy()
+                .sorted()
+                .collect_vec()
+        } else {
+            custom_state
+                .active_node
+                .filter(|n| graph.nodes.contains_key(*n))
+                .into_iter()
+                .collect_vec()
+        };
+        if !nodes.is_empty() {
             Frame::default()
                 .inner_margin(egui::vec2(5.0, 0.0))
                 .show(ui, |ui| {
                     ScrollArea::both().show(ui, |ui| {
-                        for node in editor_state.selected_nodes.iter_cpy().sorted() {
+                        for node in nodes {
                             let mut ch_ui = ui.child_ui_with_id_source(
                                 ui.available_rect_before_wrap(),
                                 *ui.layout(),
@@ -286,7 +324,9 @@ impl SpreadsheetTab {
                 for vt in [
                     ChannelValueType::Vec3,
                     ChannelValueType::f32,
+                    ChannelValueType::i32,
                     ChannelValueType::bool,
+                    ChannelValueType::String,
                 ] {
                     if let Some(ch) = channel_introspect.get(&(kt, vt)) {
                         for (ch_name, ch_contents) in ch.iter() {
@@ -295,44 +335,138 @@ impl SpreadsheetTab {
                     }
                 }
 
+                let num_rows = match columns.first() {
+                    Some((_, contents)) => contents.len(),
+                    None => match self.current_view {
+                        SpreadsheetViews::Vertices => mesh.read_connectivity().num_vertices(),
+                        SpreadsheetViews::Halfedges => mesh.read_connectivity().num_halfedges(),
+                        SpreadsheetViews::Faces => mesh.read_connectivity().num_faces(),
+                    },
+                };
+
+                // The order in which rows are displayed. Rows keep their
+                // original index, so they can be referenced in selections.
+                let mut rows = (0..num_rows).collect_vec();
+                if let Some(sort) = &self.sort {
+                    if let Some((_, contents)) = columns.iter().find(|c| *c.0 == sort.column) {
+                        rows.sort_by(|a, b| compare_introspected(&contents[*a], &contents[*b]));
+                        if sort.descending {
+                            rows.reverse();
+                        }
+                    }
+                }
+
                 Grid::new("vertex-spreadsheet")
                     .striped(true)
-                    .num_columns(columns.len())
+                    .num_columns(columns.len() + 1)
                     .show(ui, |ui| {
-                        ui.label(" ");
-                        for c in &columns {
-                            ui.label(c.0);
+                        if ui
+                            .selectable_label(self.sort.is_none(), "#")
+                            .on_hover_text("Sort by index")
+                            .clicked()
+                        {
+                            self.sort = None;
+                        }
+                        for (ch_name, _) in &columns {
+                            let sorted = self.sort.as_ref().filter(|s| s.column == **ch_name);
+                            let label = match sorted {
+                                Some(SpreadsheetSort {
+                                    descending: true, ..
+                                }) => format!("{ch_name} ⏷"),
+                                Some(_) => format!("{ch_name} ⏶"),
+                                None => ch_name.to_string(),
+                            };
+                            if ui
+                                .selectable_label(sorted.is_some(), label)
+                                .on_hover_text("Click to sort by this column")
+                                .clicked()
+                            {
+                                self.sort = Some(SpreadsheetSort {
+                                    column: ch_name.to_string(),
+                                    descending: sorted.map(|s| !s.descending).unwrap_or(false),
+                                });
+                            }
                         }
                         ui.end_row();
 
-                        if !columns.is_empty() {
-                            for i in 0..columns[0].1.len() {
-                                ui.label(i.to_string());
-                                for c in &columns {
-                                    ui.monospace(c.1[i].clone() + " |");
-                                }
-                                ui.end_row();
-                            }
-                        } else {
-                            let count = match self.current_view {
-                                SpreadsheetViews::Vertices => {
-                                    mesh.read_connectivity().num_vertices()
-                                }
-                                SpreadsheetViews::Halfedges => {
-                                    mesh.read_connectivity().num_halfedges()
-                                }
-                                SpreadsheetViews::Faces => mesh.read_connectivity().num_faces(),
-                            };
-                            for i in 0..count {
-                                ui.label(i.to_string());
-                                ui.end_row();
+                        for i in rows {
+                            ui.label(i.to_string());
+                            for (_, contents) in &columns {
+                                ui.monospace(contents[i].clone() + " |");
                             }
+                            ui.end_row();
                         }
                     })
             });
         }
     }
 }
+
+/// Compares two values as shown in the spreadsheet. Numeric values (and
+/// vectors, component by component) are compared by their numeric value, and
+/// everything else is compared as text.
+fn compare_introspected(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse =
+        |s: &str| -> Option<Vec<f64>> { s.split_whitespace().map(|x| x.parse().ok()).collect() };
+    match (parse(a), parse(b)) {
+        (Some(a), Some(b)) => a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| a.total_cmp(b))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => a.cmp(b),
+    }
+}
+impl SelectionSetsTab {
+    fn ui(&mut self, ui: &mut Ui, custom_state: &mut CustomGraphState) {
+        ui.label("Named selections. Any selection parameter can use them as set(\"name\").");
+        ui.separator();
+
+        let mut defer_remove = None;
+        ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .max_height(ui.available_height() - 30.0)
+            .show(ui, |ui| {
+                Grid::new("selection-sets").num_columns(3).show(ui, |ui| {
+                    for (name, expr) in custom_state.selection_sets.iter_mut() {
+                        ui.label(name);
+                        let parsed = SelectionExpression::parse(expr);
+                        let mut text_edit = TextEdit::singleline(expr);
+                        if parsed.is_err() {
+                            text_edit = text_edit.text_color(Color32::RED);
+                        }
+                        let response = ui.add(text_edit);
+                        if let Err(err) = parsed {
+                            response.on_hover_text(err.to_string());
+                        }
+                        if ui.button("❌").on_hover_text("Delete this set").clicked() {
+                            defer_remove = Some(name.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if let Some(name) = defer_remove {
+            custom_state.selection_sets.remove(&name);
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_set_name);
+            let name = self.new_set_name.trim();
+            let valid = !name.is_empty()
+                && !name.contains('"')
+                && !custom_state.selection_sets.contains_key(name);
+            if ui.add_enabled(valid, Button::new("Add set")).clicked() {
+                custom_state
+                    .selection_sets
+                    .insert(name.to_string(), String::new());
+                self.new_set_name.clear();
+            }
+        });
+    }
+}
+
 impl DebugTab {
     fn ui(&mut self, ui: &mut Ui, mesh: Option<&HalfEdgeMesh>) {
         ui.horizontal(|ui| {
@@ -10,6 +10,7 @@ use std::path::PathBuf;
 pub enum AppRootAction {
     Save(PathBuf),
     Load(PathBuf),
+    GroupSelected { as_loop: bool },
 }
 
 impl RootViewport {
@@ -41,8 +42,29 @@ impl RootViewport {
                     ui.separator();
                     ui.add_enabled_ui(false, |ui| ui.button("Quit"));
                 });
+                ui.menu_button("Edit", |ui| {
+                    let has_selection = !self.graph_editor.editor_state.selected_nodes.is_empty();
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Group Selected (Ctrl+G)"))
+                        .clicked()
+                    {
+                        action = Some(AppRootAction::GroupSelected { as_loop: false });
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Group Selected as Loop (Ctrl+Shift+G)"),
+                        )
+                        .clicked()
+                    {
+                        action = Some(AppRootAction::GroupSelected { as_loop: true });
+                        ui.close_menu();
+                    }
+                });
                 ui.menu_button("Window", |ui| {
                     ui.checkbox(&mut self.diagnostics_open, "Diagnostics");
+                    ui.checkbox(&mut self.console_open, "Console");
                 });
             });
         });
@@ -58,6 +80,34 @@ impl RootViewport {
             });
     }
 
+    /// Shows the lines logged by Lua code in a panel at the bottom of the
+    /// window.
+    pub fn console_ui(&mut self) {
+        if !self.console_open {
+            return;
+        }
+        egui::TopBottomPanel::bottom("lua_console")
+            .resizable(true)
+            .default_height(120.0)
+            .show(&self.egui_context, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Console");
+                    if ui.button("Clear").clicked() {
+                        self.console.clear();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in self.console.lines() {
+                            ui.monospace(line);
+                        }
+                    });
+            });
+    }
+
     pub fn show_leaf(ui: &mut egui::Ui, payload: &mut Self, name: &str) {
         // TODO: These names here are hard-coded in the creation of the
         // SplitTree. We should be using some kind of identifier instead
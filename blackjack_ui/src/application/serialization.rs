@@ -74,7 +74,8 @@ pub fn load(
     gizmo_states: &UiNodeGizmoStates,
 ) -> Result<(GraphEditorState, CustomGraphState)> {
     let serialized = SerializedBjkGraph::load_from_file(&path)?;
-    let (runtime, ui_data, id_idx_mappings) = serialized.into_runtime()?;
+    let (mut runtime, ui_data, id_idx_mappings) = serialized.into_runtime()?;
+    runtime.fill_missing_inputs(node_definitions);
 
     if ui_data.is_none() {
         bail!(
@@ -145,6 +146,7 @@ pub fn load(
         node_definitions: node_definitions.share(),
         gizmo_states: gizmo_states.share(),
         promoted_params,
+        selection_sets: runtime.graph.selection_sets.clone(),
     };
 
     Ok((editor_state, custom_state))
@@ -219,6 +221,9 @@ pub fn from_clipboard(
         node_definitions: _,
         promoted_params: _,
         gizmo_states: _,
+        // Snippets don't carry selection sets. Pasted nodes can reference the
+        // sets of the graph they're pasted into.
+        selection_sets: _,
     } = custom_state;
     let GraphEditorState {
         // This is updated by `append_snippet_to_existing_ui_graph`
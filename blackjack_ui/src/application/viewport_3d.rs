@@ -4,6 +4,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::path::PathBuf;
+
 use blackjack_engine::lua_engine::RenderableThing;
 use winit::event::MouseButton;
 
@@ -57,9 +59,24 @@ pub enum TextOverlayMode {
 
 pub struct Viewport3dSettings {
     pub render_vertices: bool,
+    /// The size of vertices, in pixels. Scaled by the `size` channel of the
+    /// mesh, when present.
+    pub point_size: f32,
+    /// Draws a line along the normal of each vertex.
+    pub render_normals: bool,
+    /// The length of the lines drawn when `render_normals` is set.
+    pub normals_length: f32,
     pub matcap: usize,
+    /// An image file to use as matcap, instead of the bundled ones.
+    pub custom_matcap: Option<PathBuf>,
     pub edge_mode: EdgeDrawMode,
     pub face_mode: FaceDrawMode,
+    /// When set, faces are only visible from their front side. Otherwise,
+    /// they're drawn double-sided.
+    pub backface_culling: bool,
+    /// Tints faces with a different color for each material index, as set
+    /// by the `material` face channel.
+    pub material_colors: bool,
     pub overlay_mode: TextOverlayMode,
 }
 
@@ -119,9 +136,15 @@ impl Viewport3d {
             settings: Viewport3dSettings {
                 edge_mode: EdgeDrawMode::FullEdge,
                 face_mode: FaceDrawMode::Real,
+                backface_culling: true,
+                material_colors: true,
                 overlay_mode: TextOverlayMode::NoDraw,
                 render_vertices: true,
+                point_size: 5.0,
+                render_normals: false,
+                normals_length: 0.2,
                 matcap: 0,
+                custom_matcap: None,
             },
             view_proj_matrix: Mat4::default(),
             view_matrix: Mat4::default(),
@@ -279,6 +302,25 @@ impl Viewport3d {
                     ui.horizontal(|ui| {
                         ui.label("Vertices:");
                         ui.checkbox(&mut self.settings.render_vertices, "");
+                        ui.add_enabled(
+                            self.settings.render_vertices,
+                            egui::DragValue::new(&mut self.settings.point_size)
+                                .speed(0.1)
+                                .clamp_range(1.0..=50.0)
+                                .prefix("size: "),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Normals:");
+                        ui.checkbox(&mut self.settings.render_normals, "");
+                        ui.add_enabled(
+                            self.settings.render_normals,
+                            egui::DragValue::new(&mut self.settings.normals_length)
+                                .speed(0.01)
+                                .clamp_range(0.0..=f32::INFINITY)
+                                .prefix("length: "),
+                        );
                     });
 
                     ui.horizontal(|ui| {
@@ -305,6 +347,17 @@ impl Viewport3d {
                         );
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("Backfaces:");
+                        ui.selectable_value(&mut self.settings.backface_culling, true, "Culled");
+                        ui.selectable_value(&mut self.settings.backface_culling, false, "Visible");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Material colors:");
+                        ui.checkbox(&mut self.settings.material_colors, "");
+                    });
+
                     ui.horizontal(|ui| {
                         ui.label("Matcap:");
                         if ui.button("<").clicked() {
@@ -319,6 +372,29 @@ impl Viewport3d {
                         }
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("Custom Matcap:");
+                        if ui.button("Open…").clicked() {
+                            let file_location = rfd::FileDialog::new()
+                                .add_filter("PNG Image", &["png"])
+                                .pick_file();
+                            if let Some(path) = file_location {
+                                self.settings.custom_matcap = Some(path);
+                            }
+                        }
+                        if let Some(path) = &self.settings.custom_matcap {
+                            ui.label(path.display().to_string());
+                        }
+                        if self.settings.custom_matcap.is_some()
+                            && ui
+                                .button("✖")
+                                .on_hover_text("Go back to the bundled matcaps")
+                                .clicked()
+                        {
+                            self.settings.custom_matcap = None;
+                        }
+                    });
+
                     ui.horizontal(|ui| {
                         ui.label("Text Overlay:");
                         ui.selectable_value(
@@ -353,6 +429,38 @@ impl Viewport3d {
                         );
                     });
                 });
+
+                // Shading can also be changed from the popup, but it's here
+                // too so both modes can be compared with a single click.
+                ui.separator();
+                ui.label("Shading:");
+                const RECOMPUTED: &str = "Recompute the normals, for display only";
+                for (mode, label, hover_text) in [
+                    (
+                        FaceDrawMode::Real,
+                        "Mesh",
+                        "Use the normals computed by the graph",
+                    ),
+                    (FaceDrawMode::Flat, "Flat", RECOMPUTED),
+                    (FaceDrawMode::Smooth, "Smooth", RECOMPUTED),
+                ] {
+                    ui.selectable_value(&mut self.settings.face_mode, mode, label)
+                        .on_hover_text(hover_text);
+                }
+
+                ui.separator();
+                let mut wireframe = self.settings.edge_mode != EdgeDrawMode::NoDraw;
+                if ui
+                    .checkbox(&mut wireframe, "Wireframe")
+                    .on_hover_text("Draw the edges of the mesh over its faces")
+                    .changed()
+                {
+                    self.settings.edge_mode = if wireframe {
+                        EdgeDrawMode::FullEdge
+                    } else {
+                        EdgeDrawMode::NoDraw
+                    };
+                }
             });
             offscreen_viewport.show(ui, ui.available_size());
         });
@@ -404,6 +512,10 @@ impl Viewport3d {
         self.projection_matrix
     }
 
+    pub fn view_proj_matrix(&self) -> Mat4 {
+        self.view_proj_matrix
+    }
+
     pub fn viewport_rect(&self) -> egui::Rect {
         self.viewport_rect
     }
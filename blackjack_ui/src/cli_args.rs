@@ -22,6 +22,12 @@ pub struct Args {
     /// and the Lua code will be loaded once at startup.
     #[arg(long)]
     pub disable_lua_watcher: bool,
+
+    /// Adds a folder to load Lua node libraries from, in addition to the
+    /// built-in ones. Can be given multiple times. Extra folders can also be
+    /// set using the BLACKJACK_LIBRARY_PATH environment variable.
+    #[arg(long)]
+    pub library_path: Vec<String>,
 }
 
 /// CLI args are stored in a lazy static variable so they're accessible from
@@ -8,15 +8,17 @@ use std::ops::Index;
 
 use super::node_graph::{
     data_type_to_input_param_kind, default_shown_inline, CustomGraphState, DataTypeUi, Graph,
-    NodeData, ValueTypeUi,
+    GraphEditorState, NodeData, ValueTypeUi,
 };
 
 use crate::prelude::*;
 use blackjack_engine::{
     graph::{
-        BjkGraph, BjkNode, BjkNodeId, BjkSnippet, BlackjackValue, DependencyKind, NodeDefinitions,
+        loop_iterations_def, BjkGraph, BjkNode, BjkNodeId, BjkSnippet, BjkSubgraph, BlackjackValue,
+        DataType, DependencyKind, NodeDefinitions, LOOP_ITERATIONS_INPUT, SUBGRAPH_OP_NAME,
     },
     graph_interpreter::{ExternalParameter, ExternalParameterValues},
+    sync::RefCounted,
 };
 use egui_node_graph::{InputId, NodeId, OutputId};
 use slotmap::SecondaryMap;
@@ -58,12 +60,21 @@ pub fn ui_graph_to_blackjack_graph(
     let mut output_names = SecondaryMap::<OutputId, &str>::new();
 
     for (node_id, node) in &graph.nodes {
-        let node_def = custom_state
-            .node_definitions
-            .node_def(&node.user_data.op_name)
-            .ok_or_else(|| anyhow!("Node definition not found for {}", &node.user_data.op_name))?;
-
-        let bjk_id = bjk_graph.add_node(node.user_data.op_name.clone(), node_def.returns.clone());
+        let returns = if let Some(subgraph) = &node.user_data.subgraph {
+            subgraph.return_value()
+        } else {
+            custom_state
+                .node_definitions
+                .node_def(&node.user_data.op_name)
+                .ok_or_else(|| {
+                    anyhow!("Node definition not found for {}", &node.user_data.op_name)
+                })?
+                .returns
+                .clone()
+        };
+
+        let bjk_id = bjk_graph.add_node(node.user_data.op_name.clone(), returns);
+        bjk_graph.nodes[bjk_id].subgraph = node.user_data.subgraph.clone();
         mapping.insert(node_id, bjk_id);
 
         for (input_name, input_id) in &node.inputs {
@@ -92,10 +103,20 @@ pub fn ui_graph_to_blackjack_graph(
     }
 
     bjk_graph.default_node = custom_state.active_node.map(|x| mapping[x]);
+    bjk_graph.selection_sets = custom_state.selection_sets.clone();
 
     Ok((bjk_graph, mapping))
 }
 
+/// The label shown for a subgraph node.
+fn subgraph_label(subgraph: &BjkSubgraph) -> &'static str {
+    if subgraph.feedback.is_some() {
+        "Loop"
+    } else {
+        SUBGRAPH_OP_NAME
+    }
+}
+
 pub fn add_ui_node_from_bjk_node(
     graph: &mut Graph,
     bjk_node_id: BjkNodeId,
@@ -104,13 +125,16 @@ pub fn add_ui_node_from_bjk_node(
     node_definitions: &NodeDefinitions,
 ) {
     let new_id = graph.add_node(
-        if let Some(node_def) = node_definitions.node_def(&bjk_node.op_name) {
+        if let Some(subgraph) = &bjk_node.subgraph {
+            subgraph_label(subgraph).into()
+        } else if let Some(node_def) = node_definitions.node_def(&bjk_node.op_name) {
             node_def.label.clone()
         } else {
             "⚠ Unknown".into()
         },
         NodeData {
             op_name: bjk_node.op_name.clone(),
+            subgraph: bjk_node.subgraph.clone(),
         },
         |_, _| { /* Params added later */ },
     );
@@ -143,14 +167,8 @@ pub fn set_inputs_outputs_from_bjk_node(
                         }
                     }
                     // Otherwise, try to get it from the node definition's default value
-                    if let Some(node_def) = node_definitions.node_def(&bjk_node.op_name) {
-                        if let Some(def) = node_def
-                            .inputs
-                            .iter()
-                            .find(|input| input.name == bjk_input.name)
-                        {
-                            return def.default_value();
-                        }
+                    if let Some(def) = bjk_node.input_def(&bjk_input.name, node_definitions) {
+                        return def.default_value();
                     }
                     // If all else fails, return the default for the datatype.
                     bjk_input.data_type.default_value()
@@ -208,6 +226,8 @@ pub fn blackjack_graph_to_ui_graph(
     let BjkGraph {
         nodes: bjk_nodes,
         default_node: _,
+        // Selection sets are stored in the `CustomGraphState`
+        selection_sets: _,
     } = bjk_graph;
 
     // Fill in the nodes in a first pass
@@ -321,3 +341,133 @@ pub fn set_parameters_from_external_values(
     }
     Ok(())
 }
+
+/// Replaces the given `nodes` with a single subgraph node containing them.
+/// Connections between the grouped nodes and the rest of the graph are kept,
+/// and now go through the inputs and outputs of the subgraph node. Returns the
+/// id of the new node. When the active node is grouped, the new node becomes
+/// active only if it returns the same value.
+///
+/// When `as_loop` is set, the subgraph node is a loop, which runs the grouped
+/// nodes several times. See `BjkSubgraph::make_loop`.
+pub fn group_nodes(
+    editor_state: &mut GraphEditorState,
+    custom_state: &mut CustomGraphState,
+    nodes: &[NodeId],
+    as_loop: bool,
+) -> Result<NodeId> {
+    let (bjk_graph, mapping) = ui_graph_to_blackjack_graph(&editor_state.graph, custom_state)?;
+    let external_param_values = extract_graph_params(&editor_state.graph, &bjk_graph, &mapping)?;
+    let (mut subgraph, id_map) = BjkSubgraph::from_nodes(
+        &bjk_graph,
+        &external_param_values,
+        &nodes.iter().map(|n| mapping[*n]).collect_vec(),
+    )?;
+    if as_loop {
+        subgraph.make_loop()?;
+    }
+
+    // Maps the nodes inside the subgraph back to the grouped nodes.
+    let mut inner_to_ui = SecondaryMap::<BjkNodeId, NodeId>::new();
+    for node_id in nodes {
+        inner_to_ui.insert(id_map[mapping[*node_id]], *node_id);
+    }
+
+    let subgraph = RefCounted::new(subgraph);
+    let graph = &mut editor_state.graph;
+    let group_id = graph.add_node(
+        subgraph_label(&subgraph).into(),
+        NodeData {
+            op_name: SUBGRAPH_OP_NAME.into(),
+            subgraph: Some(subgraph.clone()),
+        },
+        |_, _| { /* Params added below */ },
+    );
+
+    let mut new_connections = vec![];
+    for port in &subgraph.inputs {
+        let inner_input = graph[inner_to_ui[port.node]].get_input(&port.param_name)?;
+        let input_id = graph.add_input_param(
+            group_id,
+            port.name.clone(),
+            DataTypeUi(port.data_type),
+            graph[inner_input].value.clone(),
+            data_type_to_input_param_kind(port.data_type),
+            default_shown_inline(),
+        );
+        if let Some(output_id) = graph.connection(inner_input) {
+            new_connections.push((output_id, input_id));
+        }
+    }
+    if subgraph.feedback.is_some() {
+        graph.add_input_param(
+            group_id,
+            LOOP_ITERATIONS_INPUT.into(),
+            DataTypeUi(DataType::Scalar),
+            ValueTypeUi(loop_iterations_def().default_value()),
+            data_type_to_input_param_kind(DataType::Scalar),
+            default_shown_inline(),
+        );
+    }
+    for port in &subgraph.outputs {
+        let inner_output = graph[inner_to_ui[port.node]].get_output(&port.param_name)?;
+        let output_id =
+            graph.add_output_param(group_id, port.name.clone(), DataTypeUi(port.data_type));
+        for (input_id, connected) in &graph.connections {
+            if *connected == inner_output && !nodes.contains(&graph[input_id].node) {
+                new_connections.push((output_id, input_id));
+            }
+        }
+    }
+
+    // The rest of the graph may have been connected to the grouped nodes, so
+    // they're removed before adding the new connections.
+    let group_pos = nodes
+        .iter()
+        .filter_map(|n| editor_state.node_positions.get(*n))
+        .fold(None, |acc: Option<egui::Pos2>, pos| {
+            Some(acc.map_or(*pos, |acc| acc.min(*pos)))
+        })
+        .unwrap_or(egui::Pos2::ZERO);
+    for node_id in nodes {
+        editor_state.graph.remove_node(*node_id);
+        editor_state.node_positions.remove(*node_id);
+        custom_state.gizmo_states.node_deleted(*node_id);
+    }
+    editor_state.node_order.retain(|n| !nodes.contains(n));
+    let graph = &mut editor_state.graph;
+    for (output_id, input_id) in new_connections {
+        graph.add_connection(output_id, input_id);
+    }
+    custom_state
+        .promoted_params
+        .retain(|input_id, _| graph.inputs.contains_key(*input_id));
+
+    editor_state.node_positions.insert(group_id, group_pos);
+    editor_state.node_order.push(group_id);
+    editor_state.selected_nodes = vec![group_id];
+
+    if let Some(active) = custom_state.active_node.filter(|n| nodes.contains(n)) {
+        // The subgraph node only displays the same result as the active node
+        // when the output it returns is the return value of the active node.
+        // Otherwise, there's no active node left.
+        let active = mapping[active];
+        let exposes_active = subgraph.return_value().map_or(false, |name| {
+            subgraph.outputs.iter().any(|port| {
+                port.name == name
+                    && port.node == id_map[active]
+                    && bjk_graph.nodes[active].return_value.as_deref()
+                        == Some(port.param_name.as_str())
+            })
+        });
+        custom_state.active_node = exposes_active.then_some(group_id);
+    }
+    if custom_state
+        .run_side_effect
+        .map_or(false, |n| nodes.contains(&n))
+    {
+        custom_state.run_side_effect = None;
+    }
+
+    Ok(group_id)
+}
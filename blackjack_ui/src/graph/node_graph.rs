@@ -5,16 +5,21 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 use crate::application::gizmo_ui::UiNodeGizmoStates;
 use crate::application::graph_editor::GraphEditor;
 use crate::application::serialization;
 use crate::custom_widgets::smart_dragvalue::SmartDragValue;
+use crate::graph::graph_interop;
 use crate::{application::code_viewer::code_edit_ui, prelude::*};
 use blackjack_engine::graph::serialization::SerializedBjkSnippet;
 use blackjack_engine::{
-    graph::{BlackjackValue, DataType, FilePathMode, InputValueConfig, NodeDefinitions},
+    graph::{
+        BjkSubgraph, BlackjackValue, DataType, FilePathMode, InputValueConfig, NodeDefinitions,
+    },
     prelude::selection::SelectionExpression,
+    sync::RefCounted,
 };
 use egui::RichText;
 use egui_node_graph::{
@@ -60,6 +65,10 @@ pub struct CustomGraphState {
     pub promoted_params: HashMap<InputId, String>,
 
     pub gizmo_states: UiNodeGizmoStates,
+
+    /// The named selections of the graph, which selection parameters can
+    /// reference as `set("name")`.
+    pub selection_sets: BTreeMap<String, String>,
 }
 
 impl CustomGraphState {
@@ -70,6 +79,7 @@ impl CustomGraphState {
             active_node: None,
             promoted_params: HashMap::default(),
             gizmo_states,
+            selection_sets: BTreeMap::default(),
         }
     }
 }
@@ -85,6 +95,7 @@ impl DataTypeTrait<CustomGraphState> for DataTypeUi {
             DataType::Scalar => color_from_hex("#4ecdc4").unwrap(),
             DataType::Selection => color_from_hex("#f7fff7").unwrap(),
             DataType::String => color_from_hex("#ffe66d").unwrap(),
+            DataType::Bool => color_from_hex("#ff6b6b").unwrap(),
         }
     }
 
@@ -96,6 +107,7 @@ impl DataTypeTrait<CustomGraphState> for DataTypeUi {
             DataType::Mesh => "mesh",
             DataType::HeightMap => "heightmap",
             DataType::String => "string",
+            DataType::Bool => "bool",
         })
     }
 }
@@ -106,6 +118,8 @@ impl UserResponseTrait for CustomNodeResponse {}
 #[derive(Clone)]
 pub struct NodeData {
     pub op_name: String,
+    /// For subgraph nodes, the graph embedded in the node.
+    pub subgraph: Option<RefCounted<BjkSubgraph>>,
 }
 impl NodeDataTrait for NodeData {
     type Response = CustomNodeResponse;
@@ -123,15 +137,17 @@ impl NodeDataTrait for NodeData {
     where
         Self::Response: egui_node_graph::UserResponseTrait,
     {
-        let node_def = user_state
-            .node_definitions
-            .node_def(&graph[node_id].user_data.op_name);
-        if node_def.is_none() {
+        let node_data = &graph[node_id].user_data;
+        let (has_gizmo, executable) = if node_data.subgraph.is_some() {
+            // Subgraph nodes don't have a node definition.
+            (false, false)
+        } else if let Some(node_def) = user_state.node_definitions.node_def(&node_data.op_name) {
+            (node_def.has_gizmo, node_def.executable)
+        } else {
             ui.label("⚠ no node definition")
                 .on_hover_text("This node is referencing a node definition that doesn't exist.");
             return Default::default();
-        }
-        let node_def = node_def.unwrap();
+        };
 
         let mut responses = Vec::new();
         ui.horizontal(|ui| {
@@ -159,7 +175,7 @@ impl NodeDataTrait for NodeData {
                         }
                     }
                 }
-                if node_def.has_gizmo {
+                if has_gizmo {
                     if user_state.gizmo_states.is_node_locked(node_id) {
                         let button =
                             egui::Button::new(RichText::new("↺ Gizmo").color(egui::Color32::BLACK))
@@ -174,7 +190,7 @@ impl NodeDataTrait for NodeData {
                     }
                 }
                 // Show 'Run' button for executable nodes
-                if node_def.executable && ui.button("⛭ Run").clicked() {
+                if executable && ui.button("⛭ Run").clicked() {
                     responses.push(NodeResponse::User(CustomNodeResponse::RunNodeSideEffect(
                         node_id,
                     )));
@@ -197,6 +213,7 @@ pub fn draw_node_graph(graph_editor: &mut GraphEditor) {
         previous_clipboard_contents,
         pending_paste_operation,
         skip_pending_paste_check,
+        undo_history,
         ..
     } = graph_editor;
     egui::CentralPanel::default().show(ctx, |ui| {
@@ -214,9 +231,16 @@ pub fn draw_node_graph(graph_editor: &mut GraphEditor) {
         // scroll wheel events.
         *mouse_over_node_finder = responses.cursor_in_finder;
 
+        let mut graph_changed = false;
         for response in responses.node_responses {
             match response {
+                NodeResponse::CreatedNode(_)
+                | NodeResponse::ConnectEventEnded { .. }
+                | NodeResponse::DisconnectEvent { .. } => {
+                    graph_changed = true;
+                }
                 NodeResponse::DeleteNodeFull { node_id, .. } => {
+                    graph_changed = true;
                     if custom_state.active_node == Some(node_id) {
                         custom_state.active_node = None;
 
@@ -290,6 +314,19 @@ pub fn draw_node_graph(graph_editor: &mut GraphEditor) {
             }
         }
 
+        if ui.input().key_pressed(egui::Key::G)
+            && ui.input().modifiers.ctrl
+            && !editor_state.selected_nodes.is_empty()
+        {
+            // Ctrl+Shift+G groups the nodes into a loop.
+            let as_loop = ui.input().modifiers.shift;
+            let selected_nodes = editor_state.selected_nodes.clone();
+            match graph_interop::group_nodes(editor_state, custom_state, &selected_nodes, as_loop) {
+                Ok(_) => graph_changed = true,
+                Err(err) => println!("Error: Could not group nodes: {err:?}"),
+            }
+        }
+
         let input = ui.input();
         let cursor_pos = ui.input().pointer.hover_pos().unwrap_or(egui::Pos2::ZERO);
         let mut do_paste = |snippet: SerializedBjkSnippet| {
@@ -298,6 +335,7 @@ pub fn draw_node_graph(graph_editor: &mut GraphEditor) {
             {
                 println!("Error: Could not paste clipboard data: {err:?}")
             }
+            graph_changed = true;
         };
 
         if let Some(paste_contents) = input.events.iter().find_map(|ev| match ev {
@@ -349,6 +387,18 @@ Pasted nodes can potentially run code, but only when you activate them.
         if clear_pending_paste {
             *pending_paste_operation = None;
         }
+
+        if graph_changed {
+            undo_history.record(editor_state, custom_state);
+        } else {
+            // Parameter edits are recorded once the user is done with them,
+            // so dragging a value or typing some text is a single step.
+            // NOTE: Separate statements, to release the input lock first.
+            let pointer_down = ui.input().pointer.any_down();
+            if !pointer_down && !ui.ctx().wants_keyboard_input() {
+                undo_history.record_value_edits(editor_state, custom_state);
+            }
+        }
     });
 }
 
@@ -371,6 +421,7 @@ pub fn data_type_to_input_param_kind(data_type: DataType) -> InputParamKind {
         DataType::Mesh => InputParamKind::ConnectionOnly,
         DataType::HeightMap => InputParamKind::ConnectionOnly,
         DataType::String => InputParamKind::ConnectionOrConstant,
+        DataType::Bool => InputParamKind::ConnectionOrConstant,
     }
 }
 
@@ -411,6 +462,7 @@ impl NodeTemplateTrait for NodeOpName {
         );
         NodeData {
             op_name: node_def.op_name.clone(),
+            subgraph: None,
         }
     }
 
@@ -470,10 +522,15 @@ impl WidgetValueTrait for ValueTypeUi {
         const INT_DRAG_SPEEDS: &[f64] = &[100.0, 10.0, 1.0];
         const INT_DRAG_LABELS: &[&str] = &["100", "10", "1"];
 
-        let node_def = user_state.node_definitions.node_def(&node_data.op_name);
-        let input_def = node_def
-            .as_deref()
-            .and_then(|d| d.inputs.iter().find(|i| i.name == param_name));
+        // The inputs of subgraph nodes use the definition of the input they
+        // are exposed from.
+        let input_def = match &node_data.subgraph {
+            Some(subgraph) => subgraph.input_def(param_name, &user_state.node_definitions),
+            None => user_state
+                .node_definitions
+                .node_def(&node_data.op_name)
+                .and_then(|d| d.inputs.iter().find(|i| i.name == param_name).cloned()),
+        };
 
         // This may happen on rare occasions when the nodes are reloaded and a
         // parameter that previously existed now doesn't anymore.
@@ -484,6 +541,8 @@ impl WidgetValueTrait for ValueTypeUi {
         }
         let input_def = input_def.unwrap();
 
+        // Set when the widget switches between a value and an expression.
+        let mut new_value = None;
         match (&mut self.0, &input_def.config) {
             (BlackjackValue::Vector(vector), InputValueConfig::Vector { .. }) => {
                 ui.label(param_name);
@@ -519,6 +578,7 @@ impl WidgetValueTrait for ValueTypeUi {
                     ..
                 },
             ) => {
+                let current = *value;
                 let is_int = *num_decimals == Some(0);
                 let drag_speeds = if is_int {
                     INT_DRAG_SPEEDS
@@ -545,7 +605,28 @@ impl WidgetValueTrait for ValueTypeUi {
 
                 ui.horizontal(|ui| {
                     ui.label(param_name);
-                    ui.add(drag_value)
+                    ui.add(drag_value);
+                    if ui
+                        .small_button("ƒ")
+                        .on_hover_text("Use an expression")
+                        .clicked()
+                    {
+                        new_value = Some(BlackjackValue::Expression(current.to_string()));
+                    }
+                });
+            }
+            (BlackjackValue::Expression(expr), InputValueConfig::Scalar { .. }) => {
+                ui.horizontal(|ui| {
+                    ui.label(param_name);
+                    ui.text_edit_singleline(expr).on_hover_text(
+                        "An expression, which can use the other inputs of this node. \
+                         For example: radius * 4",
+                    );
+                    if ui.small_button("ƒ").on_hover_text("Use a value").clicked() {
+                        new_value = Some(BlackjackValue::Scalar(
+                            expr.trim().parse().unwrap_or_default(),
+                        ));
+                    }
                 });
             }
             (BlackjackValue::String(string), InputValueConfig::Enum { values, .. }) => {
@@ -605,6 +686,9 @@ impl WidgetValueTrait for ValueTypeUi {
                     *selection = SelectionExpression::parse(text).ok();
                 }
             }
+            (BlackjackValue::Bool(value), InputValueConfig::Bool { .. }) => {
+                ui.checkbox(value, param_name);
+            }
             (BlackjackValue::None, InputValueConfig::None) => {
                 ui.label(param_name);
             }
@@ -612,6 +696,9 @@ impl WidgetValueTrait for ValueTypeUi {
                 panic!("Invalid combination {a:?} {b:?}")
             }
         }
+        if let Some(new_value) = new_value {
+            self.0 = new_value;
+        }
 
         Vec::new()
     }
@@ -74,7 +74,8 @@ pub fn blackjack_viewport_rendergraph<'node>(
     });
 
     use crate::application::viewport_3d::EdgeDrawMode::*;
-    if matches!(settings.edge_mode, FullEdge | HalfEdge) {
+    // Vertex normals are drawn as lines by the wireframe routine too.
+    if matches!(settings.edge_mode, FullEdge | HalfEdge) || settings.render_normals {
         routines.wireframe.add_to_graph(graph, &state);
     }
     if settings.render_vertices {
@@ -7,12 +7,13 @@
 pub fn primitive_state(
     topology: wgpu::PrimitiveTopology,
     front_face: wgpu::FrontFace,
+    cull_mode: Option<wgpu::Face>,
 ) -> wgpu::PrimitiveState {
     wgpu::PrimitiveState {
         topology,
         strip_index_format: None,
         front_face,
-        cull_mode: Some(wgpu::Face::Back),
+        cull_mode,
         unclipped_depth: false,
         polygon_mode: wgpu::PolygonMode::Fill,
         conservative: false,
@@ -4,6 +4,7 @@
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
 };
 
 struct FragmentOutput {
@@ -15,6 +16,8 @@ var<storage> positions: Vec3Array;
 @group(1) @binding(1)
 var<storage> normals: Vec3Array;
 @group(1) @binding(2)
+var<storage> colors: Vec3Array;
+@group(1) @binding(3)
 var matcap: texture_2d<f32>;
 
 @vertex
@@ -23,10 +26,12 @@ fn vs_main(
 ) -> VertexOutput {
     let position = unpack_v3(positions.inner[vertex_idx]);
     let normal = unpack_v3(normals.inner[vertex_idx]);
+    let color = unpack_v3(colors.inner[vertex_idx]);
 
     var output : VertexOutput;
     output.clip_position = uniforms.view_proj * vec4<f32>(position, 1.0);
     output.normal = normalize(normal);
+    output.color = color;
     return output;
 }
 
@@ -37,7 +42,8 @@ fn fs_main(input: VertexOutput) -> FragmentOutput {
     let muv = (uniforms.view * vec4<f32>(normalize(input.normal), 0.0)).xy;
     let muv = muv * 0.5 + vec2<f32>(0.5, 0.5);
 
-    out.color = textureSample(matcap, primary_sampler, vec2<f32>(muv.x, 1.0 - muv.y));
+    let matcap_color = textureSample(matcap, primary_sampler, vec2<f32>(muv.x, 1.0 - muv.y));
+    out.color = vec4<f32>(matcap_color.rgb * input.color, matcap_color.a);
 
     return out;
 }
@@ -4,9 +4,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::{application::viewport_3d::Viewport3dSettings, prelude::r3};
+use anyhow::{Context, Result};
 use glam::{Vec3, Vec4};
 
 use rend3::{
@@ -24,8 +26,8 @@ use super::{
     viewport_3d_routine::{DrawType, RoutineLayout, Viewport3dRoutine},
 };
 
-/// The number of matcap materials loaded in the routine. TODO: Matcaps should
-/// be eventually extendible at runtime.
+/// The number of matcap materials bundled with the routine. Users can load an
+/// additional matcap from an image file, see `FaceRoutine::set_custom_matcap`.
 pub const NUM_MATCAPS: usize = 6;
 
 /// Represents the buffers to draw a base mesh. Unlike other structures using
@@ -35,17 +37,20 @@ pub struct MeshFacesLayout {
     indices: Buffer,
     positions: Buffer,
     normals: Buffer,
+    colors: Buffer,
     matcaps: Arc<Vec<TextureHandle>>,
+    /// When set, used instead of the matcap selected in the settings.
+    custom_matcap: Option<TextureHandle>,
     num_indices: usize,
 }
 
-const BASE_MESH_NUM_BUFFERS: usize = 2;
+const BASE_MESH_NUM_BUFFERS: usize = 3;
 const BASE_MESH_NUM_TEXTURES: usize = 1;
 impl RoutineLayout<BASE_MESH_NUM_BUFFERS, BASE_MESH_NUM_TEXTURES> for MeshFacesLayout {
     type Settings = Viewport3dSettings;
 
     fn get_wgpu_buffers(&self, _settings: &Viewport3dSettings) -> [&Buffer; BASE_MESH_NUM_BUFFERS] {
-        [&self.positions, &self.normals]
+        [&self.positions, &self.normals, &self.colors]
     }
 
     fn get_wgpu_textures<'a>(
@@ -53,7 +58,11 @@ impl RoutineLayout<BASE_MESH_NUM_BUFFERS, BASE_MESH_NUM_TEXTURES> for MeshFacesL
         texture_manager: &'a TextureManager,
         settings: &Viewport3dSettings,
     ) -> [&'a TextureView; BASE_MESH_NUM_TEXTURES] {
-        [texture_manager.get_view(self.matcaps[settings.matcap % NUM_MATCAPS].get_raw())]
+        let matcap = match &self.custom_matcap {
+            Some(custom_matcap) => custom_matcap,
+            None => &self.matcaps[settings.matcap % NUM_MATCAPS],
+        };
+        [texture_manager.get_view(matcap.get_raw())]
     }
 
     fn get_wgpu_uniforms(&self, _settings: &Self::Settings) -> [&Buffer; 0] {
@@ -115,10 +124,28 @@ impl RoutineLayout<OVERLAY_NUM_BUFFERS, 0, OVERLAY_NUM_UNIFORMS> for FaceOverlay
     }
 }
 
+/// Uploads a matcap image to the GPU.
+fn add_matcap_texture(renderer: &r3::Renderer, image: image::RgbaImage) -> TextureHandle {
+    renderer.add_texture_2d(Texture {
+        label: None,
+        data: image.to_vec(),
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        size: glam::UVec2::new(image.width(), image.height()),
+        mip_count: rend3::types::MipmapCount::Maximum,
+        mip_source: rend3::types::MipmapSource::Generated,
+    })
+}
+
 pub struct FaceRoutine {
     matcaps: Arc<Vec<TextureHandle>>,
+    /// The path of the custom matcap, and its texture. The texture is `None`
+    /// when the image could not be loaded.
+    custom_matcap: Option<(PathBuf, Option<TextureHandle>)>,
     base_mesh_routine:
         Viewport3dRoutine<MeshFacesLayout, BASE_MESH_NUM_BUFFERS, BASE_MESH_NUM_TEXTURES>,
+    /// Same as `base_mesh_routine`, but back faces are drawn too.
+    double_sided_base_mesh_routine:
+        Viewport3dRoutine<MeshFacesLayout, BASE_MESH_NUM_BUFFERS, BASE_MESH_NUM_TEXTURES>,
     face_overlay_routine:
         Viewport3dRoutine<FaceOverlayLayout, OVERLAY_NUM_BUFFERS, 0, OVERLAY_NUM_UNIFORMS>,
 }
@@ -140,14 +167,7 @@ impl FaceRoutine {
                 .expect(concat!("loading texture ", $image))
                 .to_rgba8();
 
-                matcaps.push(renderer.add_texture_2d(Texture {
-                    label: None,
-                    data: image.to_vec(),
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    size: glam::UVec2::new(image.width(), image.height()),
-                    mip_count: rend3::types::MipmapCount::Maximum,
-                    mip_source: rend3::types::MipmapSource::Generated,
-                }));
+                matcaps.push(add_matcap_texture(renderer, image));
             };
         }
 
@@ -160,6 +180,7 @@ impl FaceRoutine {
 
         Self {
             matcaps: Arc::new(matcaps),
+            custom_matcap: None,
             base_mesh_routine: Viewport3dRoutine::new(
                 "base mesh",
                 &renderer.device,
@@ -167,6 +188,16 @@ impl FaceRoutine {
                 shader_manager.get("face_draw"),
                 PrimitiveTopology::TriangleList,
                 FrontFace::Cw,
+                Some(Face::Back),
+            ),
+            double_sided_base_mesh_routine: Viewport3dRoutine::new(
+                "double sided base mesh",
+                &renderer.device,
+                base,
+                shader_manager.get("face_draw"),
+                PrimitiveTopology::TriangleList,
+                FrontFace::Cw,
+                None,
             ),
             face_overlay_routine: Viewport3dRoutine::new(
                 "face overlay",
@@ -175,20 +206,27 @@ impl FaceRoutine {
                 shader_manager.get("face_overlay_draw"),
                 PrimitiveTopology::TriangleList,
                 FrontFace::Cw,
+                Some(Face::Back),
             ),
         }
     }
 
+    /// Adds a mesh to be drawn. When `double_sided` is set, faces are visible
+    /// from both sides. Otherwise, faces looking away from the camera are
+    /// culled, which makes faces with flipped normals stand out.
     pub fn add_base_mesh(
         &mut self,
         renderer: &r3::Renderer,
         positions: &[Vec3],
         normals: &[Vec3],
+        colors: &[Vec3],
         indices: &[u32],
+        double_sided: bool,
     ) {
         let num_indices = indices.len();
 
         assert_eq!(positions.len(), normals.len());
+        assert_eq!(positions.len(), colors.len());
 
         let positions = renderer.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
@@ -200,21 +238,59 @@ impl FaceRoutine {
             contents: bytemuck::cast_slice(normals),
             usage: BufferUsages::STORAGE,
         });
+        let colors = renderer.device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(colors),
+            usage: BufferUsages::STORAGE,
+        });
         let indices = renderer.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(indices),
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        self.base_mesh_routine.layouts.push(MeshFacesLayout {
+        let routine = if double_sided {
+            &mut self.double_sided_base_mesh_routine
+        } else {
+            &mut self.base_mesh_routine
+        };
+        routine.layouts.push(MeshFacesLayout {
             positions,
             normals,
+            colors,
             indices,
             matcaps: self.matcaps.clone(),
+            custom_matcap: self
+                .custom_matcap
+                .as_ref()
+                .and_then(|(_, texture)| texture.clone()),
             num_indices,
         });
     }
 
+    /// Sets the image file used as matcap for the meshes added after this
+    /// call, or goes back to the bundled matcaps when `path` is `None`. The
+    /// image is only loaded when the path changes. When loading fails, the
+    /// error is returned once and the bundled matcaps are used instead.
+    pub fn set_custom_matcap(
+        &mut self,
+        renderer: &r3::Renderer,
+        path: Option<&Path>,
+    ) -> Result<()> {
+        let current_path = self.custom_matcap.as_ref().map(|(p, _)| p.as_path());
+        if current_path == path {
+            return Ok(());
+        }
+        self.custom_matcap = None;
+        if let Some(path) = path {
+            let texture =
+                image::open(path).map(|image| add_matcap_texture(renderer, image.to_rgba8()));
+            self.custom_matcap = Some((path.to_owned(), texture.as_ref().ok().cloned()));
+            texture.with_context(|| format!("Could not load matcap from {}", path.display()))?;
+        }
+        Ok(())
+    }
+
     pub fn add_overlay_mesh(
         &mut self,
         renderer: &r3::Renderer,
@@ -260,6 +336,7 @@ impl FaceRoutine {
 
     pub fn clear(&mut self) {
         self.base_mesh_routine.clear();
+        self.double_sided_base_mesh_routine.clear();
         self.face_overlay_routine.clear();
     }
 
@@ -272,6 +349,8 @@ impl FaceRoutine {
     ) {
         self.base_mesh_routine
             .add_to_graph(graph, state, settings, &[]);
+        self.double_sided_base_mesh_routine
+            .add_to_graph(graph, state, settings, &[]);
         self.face_overlay_routine
             .add_to_graph(graph, state, &(), &[id_map]);
     }
@@ -13,6 +13,9 @@ struct FragmentOutput {
 @group(1) @binding(0)
 var<storage> point_cloud: Vec3Array;
 
+@group(1) @binding(1)
+var<storage> sizes: F32Array;
+
 var<private> screen_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>( 
     vec2<f32>(0.0, 1.0),
     vec2<f32>(-1.0, 0.0),
@@ -37,7 +40,7 @@ fn vs_main(
     // Get the offset for the current vertex in the quad
     let screen_quad_vertex = screen_quad[vertex_idx];
     let pixel_size = vec2<f32>(1.0 / f32(uniforms.resolution.x), 1.0 / f32(uniforms.resolution.y));
-    let point_size = pixel_size * 5.0;
+    let point_size = pixel_size * sizes.inner[instance_idx];
     let vertex_offset = screen_quad_vertex * point_size;
 
     // The final position is the clip space position for the point, plus the
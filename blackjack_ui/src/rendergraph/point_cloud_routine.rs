@@ -21,15 +21,17 @@ use super::{
 
 pub struct PointCloudLayout {
     buffer: Buffer,
+    /// Contains len f32 elements, with the size of each point in pixels.
+    sizes: Buffer,
     len: usize,
 }
 
-const NUM_BUFFERS: usize = 1;
+const NUM_BUFFERS: usize = 2;
 
 impl RoutineLayout<NUM_BUFFERS> for PointCloudLayout {
     type Settings = ();
     fn get_wgpu_buffers(&self, _settings: &()) -> [&Buffer; NUM_BUFFERS] {
-        [&self.buffer]
+        [&self.buffer, &self.sizes]
     }
 
     fn get_wgpu_textures<'a>(
@@ -66,18 +68,31 @@ impl PointCloudRoutine {
                 shader_manager.get("point_cloud_draw"),
                 PrimitiveTopology::TriangleList,
                 FrontFace::Ccw,
+                Some(Face::Back),
             ),
         }
     }
 
-    pub fn add_point_cloud(&mut self, device: &Device, points: &[Vec3]) {
+    /// Adds a point cloud to be drawn. There must be one size, in pixels, for
+    /// each point.
+    pub fn add_point_cloud(&mut self, device: &Device, points: &[Vec3], sizes: &[f32]) {
+        assert!(
+            points.len() == sizes.len(),
+            "There must be exactly one size per point in a point cloud"
+        );
         let buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(points),
             usage: BufferUsages::STORAGE,
         });
+        let sizes = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(sizes),
+            usage: BufferUsages::STORAGE,
+        });
         self.inner.layouts.push(PointCloudLayout {
             buffer,
+            sizes,
             len: points.len(),
         });
     }
@@ -31,6 +31,10 @@ struct U32Array {
     inner: array<u32>,
 };
 
+struct F32Array {
+    inner: array<f32>,
+};
+
 struct ColorArray {
     // Unlike vec3, vec4 has the same stride and alignment of 16, so we don't
     // need a Packed version
@@ -103,6 +103,7 @@ impl<
         shader: &Shader,
         topology: PrimitiveTopology,
         front_face: FrontFace,
+        cull_mode: Option<Face>,
     ) -> Self {
         let bgl = {
             let mut builder = BindGroupLayoutBuilder::new();
@@ -152,7 +153,7 @@ impl<
             label: Some(&format!("{name} render pipeline")),
             layout: Some(&pipeline_layout),
             vertex: shader.to_vertex_state(&[]),
-            primitive: common::primitive_state(topology, front_face),
+            primitive: common::primitive_state(topology, front_face, cull_mode),
             depth_stencil: Some(common::depth_stencil(true)),
             multisample: MultisampleState::default(),
             fragment: Some(shader.get_fragment_state()),
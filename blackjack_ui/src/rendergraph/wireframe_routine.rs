@@ -68,6 +68,7 @@ impl WireframeRoutine {
                 shader_manager.get("edge_wireframe_draw"),
                 PrimitiveTopology::LineList,
                 FrontFace::Ccw,
+                Some(Face::Back),
             ),
         }
     }
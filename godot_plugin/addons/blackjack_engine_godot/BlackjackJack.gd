@@ -9,6 +9,7 @@ extends Spatial
 
 signal error_occurred(err_str)
 signal clear_error(err_str)
+signal mesh_updated
 
 var BlackjackApi = preload("res://addons/blackjack_engine_godot/BlackjackApi.gdns").new()
 var BlackjackPropertiesTweaker = preload("res://addons/blackjack_engine_godot/BlackjackPropertiesTweaker.tscn")
@@ -19,6 +20,9 @@ var BlackjackPropertiesTweaker = preload("res://addons/blackjack_engine_godot/Bl
 # These get saved with the scene
 var jack_resource : Resource setget set_jack, get_jack
 var show_gui : bool = false setget set_show_gui
+# When set, the jack is generated in a background thread. The mesh is replaced
+# once the generation finishes, without blocking the game.
+var async_update : bool = false
 var overriden_params : Dictionary = {}
 var materials : Array = []
 
@@ -106,17 +110,34 @@ func start():
     add_child(child_mesh)
 
 func _process(delta):
-    if needs_update:
+    if async_update:
+        if needs_update:
+            needs_update = false
+            BlackjackApi.update_jack_async(jack_id)
+        var results = BlackjackApi.poll_jack(jack_id, materials)
+        if results != null:
+            handle_update_results(results)
+    elif needs_update:
         needs_update = false
-        var results = BlackjackApi.update_jack(jack_id, materials)
-        if results != null and results.has("Ok"):
-            child_mesh.mesh = results.Ok
-            emit_signal("clear_error")
-        elif results != null and results.has("Err"):
-            emit_signal("error_occurred", str(results.Err))
-        else:
-            push_error("Blackjack encountered an unexpected error")
-            emit_signal("error_occurred", "Blackjack encountered an unexpected error")
+        handle_update_results(BlackjackApi.update_jack(jack_id, materials))
+
+func handle_update_results(results):
+    if results != null and results.has("Ok"):
+        child_mesh.mesh = results.Ok
+        emit_signal("clear_error")
+        emit_signal("mesh_updated")
+    elif results != null and results.has("Err"):
+        emit_signal("error_occurred", str(results.Err))
+    else:
+        push_error("Blackjack encountered an unexpected error")
+        emit_signal("error_occurred", "Blackjack encountered an unexpected error")
+
+# Returns a collision Shape for the last generated mesh, or null if the jack
+# has not been generated yet. When convex is true, a ConvexPolygonShape is
+# returned, which can be used with dynamic bodies.
+func make_collision(convex = false):
+    if jack_id != null:
+        return BlackjackApi.make_collision(jack_id, convex)
 
 func is_class(other): return other == "BlackjackJack" or .is_class(other)
 func get_class(): return "BlackjackJack"
@@ -131,6 +152,10 @@ func _get_property_list():
             name = "show_gui",
             type = TYPE_BOOL,
         },
+        {
+            name = "async_update",
+            type = TYPE_BOOL,
+        },
         {
             name = "overriden_params",
             type = TYPE_DICTIONARY,
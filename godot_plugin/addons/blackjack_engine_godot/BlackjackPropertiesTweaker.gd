@@ -59,6 +59,12 @@ func _ready():
             "Selection":
                 control = preload("SelectionProp.tscn").instance()
                 control.init(prop.label, prop.val)
+            "Bool":
+                control = preload("BoolProp.tscn").instance()
+                control.init(prop.label, prop.val)
+            "Enum":
+                control = preload("EnumProp.tscn").instance()
+                control.init(prop.label, prop.val, prop.values)
         control.connect("on_changed", self, "on_property_changed", [prop.addr])
         property_controls.push_back(control)
 
@@ -0,0 +1,20 @@
+# Copyright (C) 2023 setzer22 and contributors
+#
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+tool
+extends HBoxContainer
+
+signal on_changed(value)
+
+func init(label: String, value: bool):
+    $Label.text = label
+    $CheckBox.pressed = value
+
+func _on_CheckBox_toggled(button_pressed):
+    emit_signal("on_changed", button_pressed)
+
+func set_value_externally(val):
+    $CheckBox.pressed = val
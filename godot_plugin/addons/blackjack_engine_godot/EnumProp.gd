@@ -0,0 +1,25 @@
+# Copyright (C) 2023 setzer22 and contributors
+#
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+tool
+extends HBoxContainer
+
+signal on_changed(value)
+
+func init(label: String, value: String, values: Array):
+    $Label.text = label
+    $OptionButton.clear()
+    for v in values:
+        $OptionButton.add_item(v)
+    set_value_externally(value)
+
+func _on_OptionButton_item_selected(index):
+    emit_signal("on_changed", $OptionButton.get_item_text(index))
+
+func set_value_externally(val):
+    for i in range($OptionButton.get_item_count()):
+        if $OptionButton.get_item_text(i) == val:
+            $OptionButton.select(i)